@@ -1,7 +1,5 @@
 #[cfg(target_os = "windows")]
 extern crate winres;
-#[cfg(target_os = "windows")]
-extern crate winapi;
 
 // only build for windows
 #[cfg(target_os = "windows")]
@@ -13,9 +11,9 @@ fn main() {
         let mut res = winres::WindowsResource::new();
         res.set_icon("icon.ico")
            .set_language(
-               winapi::um::winnt::MAKELANGID(
-                   winapi::um::winnt::LANG_ENGLISH,
-                   winapi::um::winnt::SUBLANG_ENGLISH_US
+               winres::language::make_lang_id(
+                   winres::language::LANG_ENGLISH,
+                   winres::language::SUBLANG_ENGLISH_US
                 )
             )
            .set_manifest_file("manifest.xml");
@@ -0,0 +1,81 @@
+//! Minimal writer for GNU `ar` archives
+//!
+//! The GNU toolchain links the compiled resource from a static library. Writing the
+//! archive directly avoids depending on `ar.exe`, which is missing on some minimal
+//! MinGW installations. The members are written with a zero timestamp and owner,
+//! so the archive is reproducible.
+
+use std::io;
+use std::io::prelude::*;
+
+/// Write an archive with `members`, given as file name and content
+///
+/// The archive starts with an empty symbol index `/`, which is what `ar rs` writes for
+/// objects without global symbols like the output of `windres`. GNU `ld` refuses
+/// archives without any index.
+pub fn write_archive<W: Write>(w: &mut W, members: &[(&str, &[u8])]) -> io::Result<()> {
+    w.write_all(b"!<arch>\n")?;
+    // the symbol index has a big-endian count of symbols, their offsets and the names
+    writeln!(w, "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`", "/", 0, 0, 0, 0, 4)?;
+    write_data(w, &[0; 4])?;
+
+    // names that do not fit into the header go to the long name table `//`
+    let mut long_names = Vec::new();
+    let mut names = Vec::new();
+    for &(name, _) in members {
+        if name.len() < 16 {
+            names.push(format!("{}/", name));
+        } else {
+            names.push(format!("/{}", long_names.len()));
+            long_names.extend_from_slice(name.as_bytes());
+            long_names.extend_from_slice(b"/\n");
+        }
+    }
+    if !long_names.is_empty() {
+        // the long name table has no timestamp, owner or mode
+        writeln!(w, "{:<48}{:<10}`", "//", long_names.len())?;
+        write_data(w, &long_names)?;
+    }
+    for (name, &(_, data)) in names.iter().zip(members) {
+        write_member(w, name, data)?;
+    }
+    Ok(())
+}
+
+fn write_member<W: Write>(w: &mut W, name: &str, data: &[u8]) -> io::Result<()> {
+    writeln!(w, "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`", name, 0, 0, 0, 644, data.len())?;
+    write_data(w, data)
+}
+
+fn write_data<W: Write>(w: &mut W, data: &[u8]) -> io::Result<()> {
+    w.write_all(data)?;
+    // members are aligned to two bytes
+    if data.len() % 2 == 1 {
+        w.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_archive;
+
+    #[test]
+    fn archive_layout() {
+        let mut archive = vec![];
+        write_archive(&mut archive, &[("resource.o", b"abc"), ("resource_my_package.o", b"de")])
+            .expect("write archive");
+        let expected = [
+            &b"!<arch>\n"[..],
+            b"/               0           0     0     0       4         `\n",
+            b"\0\0\0\0",
+            b"//                                              23        `\n",
+            b"resource_my_package.o/\n\n",
+            b"resource.o/     0           0     0     644     3         `\n",
+            b"abc\n",
+            b"/0              0           0     0     644     2         `\n",
+            b"de",
+        ].concat();
+        assert_eq!(String::from_utf8_lossy(&archive), String::from_utf8_lossy(&expected));
+    }
+}
@@ -0,0 +1,190 @@
+//! Self diagnostics for bug reports
+//!
+//! [`doctor()`] runs the same discovery as [`WindowsResource::compile()`] step by step
+//! and records the outcome of every step in a [`Report`], instead of stopping at the
+//! first error. Printing the report is usually the quickest way to find out why a
+//! resource does not compile on a particular machine.
+//!
+//! ```rust
+//! let report = winres::doctor();
+//! println!("{}", report);
+//! ```
+//!
+//! [`doctor()`]: fn.doctor.html
+//! [`WindowsResource::compile()`]: ../struct.WindowsResource.html#method.compile
+//! [`Report`]: struct.Report.html
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+use sdk;
+use mingw;
+use {default_toolchain, get_sdk_tools, ProcessEnv, Toolchain};
+
+/// Outcome of a single check
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum CheckStatus {
+    Ok,
+    /// Not required for every setup, e.g. `OUT_DIR` outside of a build script
+    Warning,
+    Failed,
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match *self {
+            CheckStatus::Ok => "ok",
+            CheckStatus::Warning => "warning",
+            CheckStatus::Failed => "FAILED",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A single prerequisite checked by [`doctor()`]
+///
+/// [`doctor()`]: fn.doctor.html
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    /// What was found, or why the check failed
+    pub detail: String,
+}
+
+/// Result of [`doctor()`]
+///
+/// [`doctor()`]: fn.doctor.html
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    /// Whether no check failed
+    pub fn is_ok(&self) -> bool {
+        self.checks.iter().all(|check| check.status != CheckStatus::Failed)
+    }
+
+    fn push<S: Into<String>>(&mut self, name: &'static str, status: CheckStatus, detail: S) {
+        self.checks.push(Check { name, status, detail: detail.into() });
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            write!(f, "[{}] {}", check.status, check.name)?;
+            if !check.detail.is_empty() {
+                write!(f, ": {}", check.detail.trim_end().replace('\n', "\n    "))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check the prerequisites for compiling resources
+///
+/// The checks are the target architecture, the toolkit (registry access, installed
+/// Windows SDKs and a runnable `rc.exe` for MSVC, `windres` for GNU) and
+/// whether `OUT_DIR` is writable.
+pub fn doctor() -> Report {
+    let mut report = Report::default();
+    let target = env::var("TARGET").unwrap_or_else(|_| "<not set>".to_string());
+    match sdk::Arch::arch_for_target() {
+        Some(arch) => report.push("target arch", CheckStatus::Ok, format!("{} ({})", arch, target)),
+        None => report.push("target arch", CheckStatus::Failed, format!("unsupported ({})", target)),
+    }
+
+    match default_toolchain(&ProcessEnv) {
+        Toolchain::Msvc => check_msvc(&mut report),
+        Toolchain::Gnu => {
+            let arch = sdk::Arch::arch_for_target().unwrap_or(sdk::Arch::X64);
+            match mingw::find_windres(arch) {
+                Some(tool) => check_runnable(&mut report, "windres", &tool.path.display().to_string(), &["--version"]),
+                None => {
+                    let searched = mingw::install_prefixes(arch).iter()
+                        .map(|dir| dir.display().to_string())
+                        .collect::<Vec<_>>();
+                    report.push("windres", CheckStatus::Failed,
+                                format!("not found in PATH, {}", searched.join(", ")));
+                }
+            }
+        }
+        Toolchain::LlvmRc => check_runnable(&mut report, "llvm-rc", "llvm-rc", &["/?"]),
+    }
+
+    match env::var_os("OUT_DIR") {
+        Some(dir) => {
+            let dir = PathBuf::from(dir);
+            let probe = dir.join("winres-doctor.tmp");
+            match fs::write(&probe, b"") {
+                Ok(()) => {
+                    let _ = fs::remove_file(&probe);
+                    report.push("OUT_DIR writable", CheckStatus::Ok, dir.display().to_string());
+                }
+                Err(e) => report.push("OUT_DIR writable", CheckStatus::Failed, e.to_string()),
+            }
+        }
+        None => report.push("OUT_DIR writable", CheckStatus::Warning, "OUT_DIR is not set, not running in a build script"),
+    }
+    report
+}
+
+fn check_msvc(report: &mut Report) {
+    match sdk::InstalledRoots::discover() {
+        Ok(roots) => {
+            let detail = roots.kits_roots.iter()
+                .map(|(_, path)| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            report.push("registry", CheckStatus::Ok, detail);
+        }
+        Err(e) => {
+            report.push("registry", CheckStatus::Failed, e.to_string());
+            return;
+        }
+    }
+
+    match get_sdk_tools(&ProcessEnv, None) {
+        Ok(tools) => {
+            let versions = tools.iter().map(|tool| tool.sdk_version.as_str()).collect::<Vec<_>>();
+            report.push("SDK versions", CheckStatus::Ok, versions.join(", "));
+            let rc = tools[0].path.display().to_string();
+            check_runnable(report, "rc.exe", &rc, &["/?"]);
+        }
+        Err(e) => report.push("SDK versions", CheckStatus::Failed, e.to_string()),
+    }
+}
+
+/// Check that `program` can be started; the exit code is not checked as not every
+/// tool has a side effect free way to succeed
+fn check_runnable(report: &mut Report, name: &'static str, program: &str, args: &[&str]) {
+    match process::Command::new(program).args(args).output() {
+        Ok(_) => report.push(name, CheckStatus::Ok, program),
+        Err(e) => report.push(name, CheckStatus::Failed, format!("{}: {}", program, e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Report, CheckStatus};
+
+    #[test]
+    fn report_display() {
+        let mut report = Report::default();
+        report.push("registry", CheckStatus::Ok, "C:\\Program Files (x86)\\Windows Kits\\10\\");
+        report.push("OUT_DIR writable", CheckStatus::Warning, "");
+        assert!(report.is_ok());
+        report.push("rc.exe", CheckStatus::Failed, "rc.exe: not found\nsecond line");
+        assert!(!report.is_ok());
+        assert_eq!(report.to_string(),
+                   "[ok] registry: C:\\Program Files (x86)\\Windows Kits\\10\\\n\
+                    [warning] OUT_DIR writable\n\
+                    [FAILED] rc.exe: rc.exe: not found\n    second line\n");
+    }
+}
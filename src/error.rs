@@ -0,0 +1,233 @@
+//! The error type of winres
+//!
+//! All fallible functions of the crate return [`Error`], which describes the failed step,
+//! like a missing environment variable, a resource compiler that could not be found or
+//! failed, or invalid metadata.
+//!
+//! [`Error`]: enum.Error.html
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Errors returned by winres
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing a file or running a tool failed
+    Io(io::Error),
+    /// Neither `OUT_DIR` nor an output directory is set
+    OutDirNotSet,
+    /// An environment variable cargo sets for build scripts is missing
+    EnvVarNotSet(String),
+    /// No resource compiler was found in the searched directories
+    SdkNotFound { searched: Vec<PathBuf> },
+    /// The requested Windows SDK version is not installed
+    SdkVersionNotFound { version: String, installed: Vec<String> },
+    /// The target architecture is not supported by the toolkit
+    UnsupportedArch(String),
+    /// Resources can only be compiled with the MSVC or GNU toolchain
+    UnsupportedToolchain,
+    /// An external tool returned an error, `command` is the executed command line
+    ToolFailed { tool: String, command: String, exit_code: Option<i32>, stderr: String },
+    /// An external tool ran longer than the timeout and was killed
+    ToolTimedOut { command: String, timeout: Duration },
+    /// A version string could not be parsed
+    InvalidVersionString(String),
+    /// A build number is not a number between 0 and 65535
+    InvalidBuildNumber(String),
+    /// A language tag has no known `LANGID`
+    UnknownLanguage(String),
+    /// The icon file does not exist
+    IconNotFound(PathBuf),
+    /// The icon file is not a valid `.ico` file
+    InvalidIcon { path: PathBuf, message: String },
+    /// The manifest file does not exist
+    ManifestNotFound(PathBuf),
+    /// The custom resource file does not exist
+    ResourceFileNotFound(PathBuf),
+    /// The configuration file of `from_config_file()` does not exist
+    ConfigFileNotFound(PathBuf),
+    /// A resource name or type is not a valid identifier
+    InvalidResourceName(String),
+    /// The selected icon variant was not added
+    UnknownIconVariant(String),
+    /// A `${name}` placeholder has no value, neither from a metadata provider nor from
+    /// the environment
+    UndefinedPlaceholder { name: String, value: String },
+    /// The embedded files are larger than the configured budget
+    SizeBudgetExceeded { size: u64, budget: u64 },
+    /// A `Cargo.toml` file is not valid TOML
+    InvalidCargoToml { path: PathBuf, message: String },
+    /// The `package.metadata.winres` section is malformed, in strict mode
+    InvalidMetadata(Vec<String>),
+    /// The string properties have problems, in strict mode
+    InvalidProperties(Vec<String>),
+    /// A resource file could not be parsed by `from_rc_file()`
+    InvalidRcFile { path: PathBuf, message: String },
+    /// A configuration file could not be parsed by `from_config_file()`
+    InvalidConfigFile { path: PathBuf, message: String },
+    /// A `.res` file is malformed
+    InvalidResFile { path: PathBuf, message: String },
+    /// A compiled resource or binary lacks expected resources, see the `verify` module
+    VerificationFailed(Vec<String>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Error::Io(ref e) => write!(f, "{}", e),
+            Error::OutDirNotSet =>
+                write!(f, "OUT_DIR is not set, winres is not running in a cargo build script; \
+                           use set_output_directory() to compile the resource elsewhere"),
+            Error::EnvVarNotSet(ref name) =>
+                write!(f, "environment variable {} is not set, winres has to run in a build script", name),
+            Error::SdkNotFound { ref searched } => {
+                write!(f, "no resource compiler found")?;
+                if !searched.is_empty() {
+                    write!(f, ", searched:")?;
+                    for path in searched {
+                        write!(f, "\n  {}", path.display())?;
+                    }
+                }
+                Ok(())
+            }
+            Error::SdkVersionNotFound { ref version, ref installed } => {
+                write!(f, "Windows SDK {} not found", version)?;
+                if !installed.is_empty() {
+                    write!(f, ", installed: {}", installed.join(", "))?;
+                }
+                Ok(())
+            }
+            Error::UnsupportedArch(ref arch) => write!(f, "unsupported target arch {}", arch),
+            Error::UnsupportedToolchain =>
+                write!(f, "Can only compile resource file when target_env is \"gnu\" or \"msvc\""),
+            Error::ToolFailed { ref tool, ref command, exit_code, ref stderr } => {
+                write!(f, "{} failed", tool)?;
+                if let Some(code) = exit_code {
+                    write!(f, " with exit code {}", code)?;
+                }
+                if !stderr.is_empty() {
+                    write!(f, ":\n{}", stderr.trim_end())?;
+                }
+                if !command.is_empty() {
+                    write!(f, "\ncommand line: {}", command)?;
+                }
+                Ok(())
+            }
+            Error::ToolTimedOut { ref command, timeout } =>
+                write!(f, "{} did not finish within {:?} and was killed", command, timeout),
+            Error::InvalidVersionString(ref message) => write!(f, "{}", message),
+            Error::InvalidBuildNumber(ref message) => write!(f, "{}", message),
+            Error::UnknownLanguage(ref tag) => write!(f, "unknown language {}", tag),
+            Error::IconNotFound(ref path) => write!(f, "icon {} not found", path.display()),
+            Error::InvalidIcon { ref path, ref message } =>
+                write!(f, "icon {} is invalid: {}", path.display(), message),
+            Error::ManifestNotFound(ref path) => write!(f, "manifest file {} not found", path.display()),
+            Error::ResourceFileNotFound(ref path) => write!(f, "resource file {} not found", path.display()),
+            Error::ConfigFileNotFound(ref path) => write!(f, "configuration file {} not found", path.display()),
+            Error::InvalidResourceName(ref name) =>
+                write!(f, "invalid resource name {:?}, use ASCII letters, digits and underscores", name),
+            Error::UnknownIconVariant(ref variant) => write!(f, "unknown icon variant {}", variant),
+            Error::UndefinedPlaceholder { ref name, ref value } =>
+                write!(f, "no value for placeholder ${{{}}} in \"{}\", it is not provided and the \
+                           environment variable {} is not set", name, value, name),
+            Error::SizeBudgetExceeded { size, budget } =>
+                write!(f, "embedded resources are {} bytes, exceeding the budget of {} bytes", size, budget),
+            Error::InvalidCargoToml { ref path, ref message } => write!(f, "{}: {}", path.display(), message),
+            Error::InvalidMetadata(ref warnings) => write!(f, "invalid metadata: {}", warnings.join("; ")),
+            Error::InvalidProperties(ref problems) => write!(f, "invalid properties: {}", problems.join("; ")),
+            Error::InvalidRcFile { ref path, ref message } => write!(f, "{}: {}", path.display(), message),
+            Error::InvalidConfigFile { ref path, ref message } =>
+                write!(f, "{} is not a valid configuration file: {}", path.display(), message),
+            Error::InvalidResFile { ref path, ref message } =>
+                write!(f, "{} is not a valid .res file: {}", path.display(), message),
+            Error::VerificationFailed(ref problems) =>
+                write!(f, "verification failed: {}", problems.join("; ")),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// Allows using `?` on winres results in functions returning `io::Result`
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Io(e) => e,
+            Error::OutDirNotSet | Error::IconNotFound(_) | Error::ManifestNotFound(_)
+            | Error::ResourceFileNotFound(_) | Error::ConfigFileNotFound(_) =>
+                io::Error::new(io::ErrorKind::NotFound, e.to_string()),
+            _ => io::Error::new(io::ErrorKind::Other, e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Error;
+    use std::error::Error as StdError;
+    use std::io;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    #[test]
+    fn display() {
+        let e = Error::SdkNotFound { searched: vec![PathBuf::from("C:/kits/bin/x64"), PathBuf::from("C:/old/bin")] };
+        assert_eq!(e.to_string(), "no resource compiler found, searched:\n  C:/kits/bin/x64\n  C:/old/bin");
+        assert_eq!(Error::SdkNotFound { searched: vec![] }.to_string(), "no resource compiler found");
+        let e = Error::SdkVersionNotFound { version: "10.0.1".to_string(), installed: vec!["10.0.2".to_string()] };
+        assert_eq!(e.to_string(), "Windows SDK 10.0.1 not found, installed: 10.0.2");
+        let e = Error::ToolFailed {
+            tool: "rc.exe".to_string(),
+            command: "rc.exe /fo out.res in.rc".to_string(),
+            exit_code: Some(2),
+            stderr: "in.rc(3) : error RC2135 : file not found\n".to_string(),
+        };
+        assert_eq!(e.to_string(), "rc.exe failed with exit code 2:\nin.rc(3) : error RC2135 : file not found\n\
+                                   command line: rc.exe /fo out.res in.rc");
+        let e = Error::ToolFailed { tool: "windres".to_string(), command: String::new(), exit_code: None,
+                                    stderr: String::new() };
+        assert_eq!(e.to_string(), "windres failed");
+        let e = Error::ToolTimedOut { command: "rc.exe".to_string(), timeout: Duration::from_secs(5) };
+        assert_eq!(e.to_string(), "rc.exe did not finish within 5s and was killed");
+        assert_eq!(Error::EnvVarNotSet("OUT_DIR".to_string()).to_string(),
+                   "environment variable OUT_DIR is not set, winres has to run in a build script");
+        assert_eq!(Error::IconNotFound(PathBuf::from("app.ico")).to_string(), "icon app.ico not found");
+        assert_eq!(Error::UndefinedPlaceholder { name: "GIT_HASH".to_string(), value: "${GIT_HASH}".to_string() }
+                       .to_string(),
+                   "no value for placeholder ${GIT_HASH} in \"${GIT_HASH}\", it is not provided and the \
+                    environment variable GIT_HASH is not set");
+        assert_eq!(Error::InvalidMetadata(vec!["a".to_string(), "b".to_string()]).to_string(),
+                   "invalid metadata: a; b");
+    }
+
+    #[test]
+    fn io_conversion() {
+        let e = Error::from(io::Error::new(io::ErrorKind::PermissionDenied, "denied"));
+        assert!(matches!(e, Error::Io(_)));
+        assert_eq!(e.source().map(|source| source.to_string()), Some("denied".to_string()));
+        assert!(Error::OutDirNotSet.source().is_none());
+
+        let e: io::Error = Error::Io(io::Error::new(io::ErrorKind::PermissionDenied, "denied")).into();
+        assert_eq!(e.kind(), io::ErrorKind::PermissionDenied);
+        let e: io::Error = Error::IconNotFound(PathBuf::from("app.ico")).into();
+        assert_eq!((e.kind(), e.to_string()), (io::ErrorKind::NotFound, "icon app.ico not found".to_string()));
+        let e: io::Error = Error::UnsupportedToolchain.into();
+        assert_eq!(e.kind(), io::ErrorKind::Other);
+    }
+}
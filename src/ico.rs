@@ -0,0 +1,220 @@
+//! Reading `.ico` files
+//!
+//! An icon file is a directory of images in several sizes and color depths, each stored
+//! as PNG or as device independent bitmap. [`parse()`] reads the directory and
+//! [`warnings()`] checks it for the variants Windows Explorer needs to render the icon
+//! sharply: a 256×256 PNG image and 32 bit images with alpha channel.
+//!
+//! With the `png-icons` feature, [`from_pngs()`] assembles an icon file from PNG images.
+//!
+//! [`parse()`]: fn.parse.html
+//! [`warnings()`]: fn.warnings.html
+//! [`from_pngs()`]: fn.from_pngs.html
+
+use std::fmt;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// An image of an icon file
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct IconImage {
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// Bits per pixel, `32` for images with alpha channel
+    pub bit_count: u16,
+    /// The image is PNG compressed instead of a bitmap
+    pub png: bool,
+}
+
+/// The problem of a file that is not a valid icon
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct InvalidIcon(String);
+
+impl fmt::Display for InvalidIcon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Read the images of the icon file `data`
+pub fn parse(data: &[u8]) -> Result<Vec<IconImage>, InvalidIcon> {
+    let invalid = |message: &str| InvalidIcon(message.to_string());
+    if data.len() < 6 || read_u16(data, 0) != 0 {
+        return Err(invalid("not an icon file"));
+    }
+    if read_u16(data, 2) != 1 {
+        return Err(invalid("not an icon file, cursors are not supported"));
+    }
+    let count = read_u16(data, 4) as usize;
+    if count == 0 {
+        return Err(invalid("the icon has no images"));
+    }
+    if data.len() < 6 + count * 16 {
+        return Err(invalid("the image directory is truncated"));
+    }
+    let mut images = vec![];
+    for entry in data[6..6 + count * 16].chunks(16) {
+        let size = read_u32(entry, 8) as usize;
+        let offset = read_u32(entry, 12) as usize;
+        let image = match offset.checked_add(size) {
+            Some(end) if end <= data.len() => &data[offset..end],
+            _ => return Err(invalid("an image lies outside of the file")),
+        };
+        // a width or height of 0 means 256 pixels
+        let dimension = |b: u8| if b == 0 { 256 } else { u32::from(b) };
+        let png = image.starts_with(PNG_SIGNATURE);
+        let bit_count = if png {
+            png_bit_count(image).ok_or_else(|| invalid("a PNG image is truncated"))?
+        } else {
+            match read_u16(entry, 6) {
+                0 if image.len() >= 16 => read_u16(image, 14),
+                bit_count => bit_count,
+            }
+        };
+        images.push(IconImage {
+            width: dimension(entry[0]),
+            height: dimension(entry[1]),
+            bit_count,
+            png,
+        });
+    }
+    Ok(images)
+}
+
+/// Missing image variants of an icon, each as a sentence
+pub fn warnings(images: &[IconImage]) -> Vec<String> {
+    let mut warnings = vec![];
+    if !images.iter().any(|image| image.width == 256 && image.height == 256 && image.png) {
+        warnings.push("has no 256x256 PNG image, Windows Explorer scales up a smaller one".to_string());
+    }
+    if !images.iter().any(|image| image.bit_count == 32) {
+        warnings.push("has no 32 bit image with alpha channel, edges are drawn jagged".to_string());
+    }
+    warnings
+}
+
+/// Assemble an icon file from PNG images
+///
+/// The images are sorted by size, the largest supported size is 256×256. Windows Vista
+/// and newer read PNG images in icons, so they are stored as they are.
+#[cfg(feature = "png-icons")]
+pub fn from_pngs(pngs: &[Vec<u8>]) -> Result<Vec<u8>, InvalidIcon> {
+    let mut images = vec![];
+    for png in pngs {
+        if !png.starts_with(PNG_SIGNATURE) || png.len() < 26 || &png[12..16] != b"IHDR" {
+            return Err(InvalidIcon("not a PNG image".to_string()));
+        }
+        let width = u32::from_be_bytes([png[16], png[17], png[18], png[19]]);
+        let height = u32::from_be_bytes([png[20], png[21], png[22], png[23]]);
+        if width == 0 || width > 256 || height == 0 || height > 256 {
+            return Err(InvalidIcon(format!("a {}x{} image is not a valid icon size", width, height)));
+        }
+        images.push((width, height, png_bit_count(png).unwrap_or(32), png));
+    }
+    if images.is_empty() {
+        return Err(InvalidIcon("the icon has no images".to_string()));
+    }
+    images.sort_by_key(|&(width, height, _, _)| (width, height));
+
+    let mut file = vec![0, 0, 1, 0];
+    file.extend_from_slice(&(images.len() as u16).to_le_bytes());
+    let mut offset = 6 + images.len() * 16;
+    for &(width, height, bit_count, png) in images.iter() {
+        // 256 is stored as 0
+        file.extend_from_slice(&[width as u8, height as u8, 0, 0, 1, 0]);
+        file.extend_from_slice(&bit_count.to_le_bytes());
+        file.extend_from_slice(&(png.len() as u32).to_le_bytes());
+        file.extend_from_slice(&(offset as u32).to_le_bytes());
+        offset += png.len();
+    }
+    for &(_, _, _, png) in images.iter() {
+        file.extend_from_slice(png);
+    }
+    Ok(file)
+}
+
+/// Bits per pixel of a PNG image, from the `IHDR` chunk
+fn png_bit_count(png: &[u8]) -> Option<u16> {
+    // signature, chunk length and type, width, height
+    let depth = u16::from(*png.get(24)?);
+    let channels = match *png.get(25)? {
+        2 => 3,
+        4 => 2,
+        6 => 4,
+        _ => 1,
+    };
+    Some(depth * channels)
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, warnings, IconImage};
+
+    /// An icon file with one image per `(width, bit_count, data)`
+    fn icon(images: &[(u8, u16, &[u8])]) -> Vec<u8> {
+        let mut file = vec![0, 0, 1, 0, images.len() as u8, 0];
+        let mut offset = 6 + images.len() * 16;
+        for &(width, bit_count, data) in images {
+            file.extend_from_slice(&[width, width, 0, 0, 1, 0]);
+            file.extend_from_slice(&bit_count.to_le_bytes());
+            file.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            file.extend_from_slice(&(offset as u32).to_le_bytes());
+            offset += data.len();
+        }
+        for &(_, _, data) in images {
+            file.extend_from_slice(data);
+        }
+        file
+    }
+
+    #[test]
+    fn parse_icon() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR\0\0\x01\0\0\0\x01\0".to_vec();
+        png.extend_from_slice(&[8, 6, 0, 0, 0]);
+        let file = icon(&[(16, 8, &[0; 40]), (0, 0, &png)]);
+        let images = parse(&file).expect("parse icon");
+        assert_eq!(images, [
+            IconImage { width: 16, height: 16, bit_count: 8, png: false },
+            IconImage { width: 256, height: 256, bit_count: 32, png: true },
+        ]);
+        assert!(warnings(&images).is_empty());
+        assert_eq!(warnings(&images[..1]).len(), 2);
+
+        assert!(parse(b"[package]").is_err());
+        assert!(parse(&[0, 0, 2, 0, 1, 0]).is_err());
+        let mut truncated = file.clone();
+        truncated.truncate(60);
+        assert!(parse(&truncated).is_err());
+    }
+
+    #[cfg(feature = "png-icons")]
+    #[test]
+    fn icon_from_pngs() {
+        let png = |size: u8| {
+            let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+            let size = if size == 0 { 256 } else { u32::from(size) };
+            png.extend_from_slice(&size.to_be_bytes());
+            png.extend_from_slice(&size.to_be_bytes());
+            png.extend_from_slice(&[8, 6, 0, 0, 0]);
+            png
+        };
+        let file = super::from_pngs(&[png(0), png(16)]).expect("assemble icon");
+        let images = parse(&file).expect("parse icon");
+        assert_eq!(images, [
+            IconImage { width: 16, height: 16, bit_count: 32, png: true },
+            IconImage { width: 256, height: 256, bit_count: 32, png: true },
+        ]);
+        assert!(super::from_pngs(&[]).is_err());
+        assert!(super::from_pngs(&[b"GIF89a".to_vec()]).is_err());
+    }
+}
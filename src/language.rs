@@ -0,0 +1,295 @@
+//! Language identifiers without a `winapi` dependency
+//!
+//! A `LANGID` combines a primary language with a sublanguage, see [`make_lang_id()`].
+//! The most common ones are available as constants, like [`EN_US`], and
+//! [`from_tag()`] maps language tags like `"de-DE"` to them. [`resolve_tag()`] also
+//! accepts tags it does not know exactly, like `"de-LU"`, and picks a region for bare
+//! languages like `"en"`.
+//!
+//! ```rust
+//! use winres::language::{make_lang_id, LANG_ENGLISH, SUBLANG_ENGLISH_US, EN_US};
+//!
+//! assert_eq!(make_lang_id(LANG_ENGLISH, SUBLANG_ENGLISH_US), EN_US);
+//! assert_eq!(winres::language::from_tag("en-US"), Some(EN_US));
+//! assert_eq!(winres::language::resolve_tag("en"), Some(EN_US));
+//! ```
+//!
+//! [`make_lang_id()`]: fn.make_lang_id.html
+//! [`EN_US`]: constant.EN_US.html
+//! [`from_tag()`]: fn.from_tag.html
+//! [`resolve_tag()`]: fn.resolve_tag.html
+
+pub const LANG_NEUTRAL: u16 = 0x00;
+pub const LANG_CATALAN: u16 = 0x03;
+pub const LANG_CHINESE: u16 = 0x04;
+pub const LANG_CZECH: u16 = 0x05;
+pub const LANG_DANISH: u16 = 0x06;
+pub const LANG_GERMAN: u16 = 0x07;
+pub const LANG_GREEK: u16 = 0x08;
+pub const LANG_ENGLISH: u16 = 0x09;
+pub const LANG_SPANISH: u16 = 0x0a;
+pub const LANG_FINNISH: u16 = 0x0b;
+pub const LANG_FRENCH: u16 = 0x0c;
+pub const LANG_HUNGARIAN: u16 = 0x0e;
+pub const LANG_ITALIAN: u16 = 0x10;
+pub const LANG_JAPANESE: u16 = 0x11;
+pub const LANG_KOREAN: u16 = 0x12;
+pub const LANG_DUTCH: u16 = 0x13;
+pub const LANG_NORWEGIAN: u16 = 0x14;
+pub const LANG_POLISH: u16 = 0x15;
+pub const LANG_PORTUGUESE: u16 = 0x16;
+pub const LANG_RUSSIAN: u16 = 0x19;
+pub const LANG_SWEDISH: u16 = 0x1d;
+pub const LANG_TURKISH: u16 = 0x1f;
+pub const LANG_UKRAINIAN: u16 = 0x22;
+pub const LANG_BASQUE: u16 = 0x2d;
+
+pub const SUBLANG_NEUTRAL: u16 = 0x00;
+pub const SUBLANG_DEFAULT: u16 = 0x01;
+pub const SUBLANG_CHINESE_TRADITIONAL: u16 = 0x01;
+pub const SUBLANG_CHINESE_SIMPLIFIED: u16 = 0x02;
+pub const SUBLANG_ENGLISH_US: u16 = 0x01;
+pub const SUBLANG_ENGLISH_UK: u16 = 0x02;
+pub const SUBLANG_FRENCH: u16 = 0x01;
+pub const SUBLANG_FRENCH_CANADIAN: u16 = 0x03;
+pub const SUBLANG_GERMAN: u16 = 0x01;
+pub const SUBLANG_GERMAN_SWISS: u16 = 0x02;
+pub const SUBLANG_GERMAN_AUSTRIAN: u16 = 0x03;
+pub const SUBLANG_ITALIAN: u16 = 0x01;
+pub const SUBLANG_PORTUGUESE_BRAZILIAN: u16 = 0x01;
+pub const SUBLANG_PORTUGUESE: u16 = 0x02;
+pub const SUBLANG_SPANISH_MEXICAN: u16 = 0x02;
+pub const SUBLANG_SPANISH_MODERN: u16 = 0x03;
+
+pub const NEUTRAL: u16 = 0x0000;
+pub const EN_US: u16 = 0x0409;
+pub const EN_GB: u16 = 0x0809;
+pub const DE_DE: u16 = 0x0407;
+pub const DE_AT: u16 = 0x0c07;
+pub const DE_CH: u16 = 0x0807;
+pub const FR_FR: u16 = 0x040c;
+pub const FR_CA: u16 = 0x0c0c;
+pub const ES_ES: u16 = 0x0c0a;
+pub const ES_MX: u16 = 0x080a;
+pub const IT_IT: u16 = 0x0410;
+pub const JA_JP: u16 = 0x0411;
+pub const KO_KR: u16 = 0x0412;
+pub const NL_NL: u16 = 0x0413;
+pub const PL_PL: u16 = 0x0415;
+pub const PT_BR: u16 = 0x0416;
+pub const PT_PT: u16 = 0x0816;
+pub const RU_RU: u16 = 0x0419;
+pub const SV_SE: u16 = 0x041d;
+pub const ZH_CN: u16 = 0x0804;
+pub const ZH_TW: u16 = 0x0404;
+
+/// Combine a primary language and a sublanguage, like the `MAKELANGID` macro
+pub fn make_lang_id(lang: u16, sublang: u16) -> u16 {
+    (sublang << 10) | lang
+}
+
+/// The primary language of `lang_id`
+pub fn primary_lang_id(lang_id: u16) -> u16 {
+    lang_id & 0x3ff
+}
+
+/// The sublanguage of `lang_id`
+pub fn sub_lang_id(lang_id: u16) -> u16 {
+    lang_id >> 10
+}
+
+/// Map a language tag like `"en-US"` or `"de"` to a `LANGID`
+///
+/// The tag is case insensitive and `_` may be used instead of `-`.
+/// Returns `None` for tags without a known `LANGID`.
+pub fn from_tag(tag: &str) -> Option<u16> {
+    let id = match tag.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+        "neutral" => NEUTRAL,
+        "ca" => LANG_CATALAN,
+        "cs" | "cs-cz" => 0x0405,
+        "da" | "da-dk" => 0x0406,
+        "el" | "el-gr" => 0x0408,
+        "en" => LANG_ENGLISH,
+        "en-us" => EN_US,
+        "en-gb" => EN_GB,
+        "de" => LANG_GERMAN,
+        "de-de" => DE_DE,
+        "de-at" => DE_AT,
+        "de-ch" => DE_CH,
+        "es" => LANG_SPANISH,
+        "es-es" => ES_ES,
+        "es-mx" => ES_MX,
+        "eu" => LANG_BASQUE,
+        "fi" | "fi-fi" => 0x040b,
+        "fr" => LANG_FRENCH,
+        "fr-fr" => FR_FR,
+        "fr-ca" => FR_CA,
+        "hu" | "hu-hu" => 0x040e,
+        "it" => LANG_ITALIAN,
+        "it-it" => IT_IT,
+        "ja" | "ja-jp" => JA_JP,
+        "ko" | "ko-kr" => KO_KR,
+        "nb" | "nb-no" => 0x0414,
+        "nl" => LANG_DUTCH,
+        "nl-nl" => NL_NL,
+        "pl" | "pl-pl" => PL_PL,
+        "pt" => LANG_PORTUGUESE,
+        "pt-br" => PT_BR,
+        "pt-pt" => PT_PT,
+        "ru" | "ru-ru" => RU_RU,
+        "sv" | "sv-se" => SV_SE,
+        "tr" | "tr-tr" => 0x041f,
+        "uk" | "uk-ua" => 0x0422,
+        "zh" => LANG_CHINESE,
+        "zh-cn" | "zh-hans" => ZH_CN,
+        "zh-tw" | "zh-hant" => ZH_TW,
+        _ => return None,
+    };
+    Some(id)
+}
+
+/// Map a language tag to a `LANGID` with a sublanguage, falling back sensibly
+///
+/// Unlike [`from_tag()`], a bare language like `"pt"` resolves to the default sublanguage
+/// of Windows, here [`PT_BR`]. Unknown subtags, like the script of `"zh-Hans-CN"`, the
+/// region of `"de-LU"` or private use subtags, are dropped from the end until the tag is
+/// known. Returns `None` if not even the language is known.
+///
+/// [`from_tag()`]: fn.from_tag.html
+/// [`PT_BR`]: constant.PT_BR.html
+pub fn resolve_tag(tag: &str) -> Option<u16> {
+    let tag = tag.trim().replace('_', "-");
+    let mut subtags: Vec<&str> = tag.split('-').collect();
+    while !subtags.is_empty() {
+        if let Some(id) = from_tag(&subtags.join("-")) {
+            return Some(default_sublanguage(id));
+        }
+        subtags.pop();
+    }
+    None
+}
+
+/// `lang_id` with the default sublanguage, if it is only a primary language
+fn default_sublanguage(lang_id: u16) -> u16 {
+    if lang_id == NEUTRAL || sub_lang_id(lang_id) != SUBLANG_NEUTRAL {
+        return lang_id;
+    }
+    match lang_id {
+        // SUBLANG_DEFAULT is Taiwan and the traditional sort order
+        LANG_CHINESE => ZH_CN,
+        LANG_SPANISH => ES_ES,
+        primary => make_lang_id(primary, SUBLANG_DEFAULT),
+    }
+}
+
+/// A language as `LANGID` and as language tag
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub struct Language {
+    /// The `LANGID`
+    pub id: u16,
+    /// The language tag, like `"pt-BR"` or `"pt"`, `None` if the `LANGID` has no known tag
+    pub tag: Option<&'static str>,
+}
+
+impl Language {
+    /// The language of `lang_id`, with the tag of [`to_tag()`], or the tag of the primary
+    /// language for `LANGID`s without a sublanguage
+    ///
+    /// [`to_tag()`]: fn.to_tag.html
+    pub fn from_id(lang_id: u16) -> Language {
+        let primary_tag = match lang_id {
+            LANG_CATALAN => Some("ca"),
+            LANG_CHINESE => Some("zh"),
+            LANG_ENGLISH => Some("en"),
+            LANG_GERMAN => Some("de"),
+            LANG_SPANISH => Some("es"),
+            LANG_BASQUE => Some("eu"),
+            LANG_FRENCH => Some("fr"),
+            LANG_ITALIAN => Some("it"),
+            LANG_DUTCH => Some("nl"),
+            LANG_PORTUGUESE => Some("pt"),
+            _ => None,
+        };
+        Language { id: lang_id, tag: to_tag(lang_id).or(primary_tag) }
+    }
+}
+
+/// The language tag of a `LANGID` with a region, like `"de-DE"` for [`DE_DE`]
+///
+/// Returns `None` for `LANGID`s without a known tag, and for primary languages
+/// without a region.
+///
+/// [`DE_DE`]: constant.DE_DE.html
+pub fn to_tag(lang_id: u16) -> Option<&'static str> {
+    let tag = match lang_id {
+        0x0405 => "cs-CZ",
+        0x0406 => "da-DK",
+        0x0408 => "el-GR",
+        EN_US => "en-US",
+        EN_GB => "en-GB",
+        DE_DE => "de-DE",
+        DE_AT => "de-AT",
+        DE_CH => "de-CH",
+        ES_ES => "es-ES",
+        ES_MX => "es-MX",
+        0x040b => "fi-FI",
+        FR_FR => "fr-FR",
+        FR_CA => "fr-CA",
+        0x040e => "hu-HU",
+        IT_IT => "it-IT",
+        JA_JP => "ja-JP",
+        KO_KR => "ko-KR",
+        0x0414 => "nb-NO",
+        NL_NL => "nl-NL",
+        PL_PL => "pl-PL",
+        PT_BR => "pt-BR",
+        PT_PT => "pt-PT",
+        RU_RU => "ru-RU",
+        SV_SE => "sv-SE",
+        0x041f => "tr-TR",
+        0x0422 => "uk-UA",
+        ZH_CN => "zh-CN",
+        ZH_TW => "zh-TW",
+        _ => return None,
+    };
+    Some(tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lang_ids() {
+        assert_eq!(make_lang_id(LANG_GERMAN, SUBLANG_GERMAN_AUSTRIAN), DE_AT);
+        assert_eq!(make_lang_id(LANG_CHINESE, SUBLANG_CHINESE_SIMPLIFIED), ZH_CN);
+        assert_eq!(make_lang_id(LANG_PORTUGUESE, SUBLANG_PORTUGUESE), PT_PT);
+        assert_eq!(primary_lang_id(ES_MX), LANG_SPANISH);
+        assert_eq!(sub_lang_id(ES_MX), SUBLANG_SPANISH_MEXICAN);
+        assert_eq!(from_tag("pt-BR"), Some(PT_BR));
+        assert_eq!(from_tag("zh_Hans"), Some(ZH_CN));
+        assert_eq!(from_tag("xx-YY"), None);
+        assert_eq!(to_tag(DE_AT), Some("de-AT"));
+        assert_eq!(to_tag(LANG_GERMAN), None);
+        assert_eq!(from_tag(to_tag(ZH_TW).unwrap()), Some(ZH_TW));
+    }
+
+    #[test]
+    fn resolve_tags() {
+        assert_eq!(resolve_tag("pt-BR"), Some(PT_BR));
+        assert_eq!(resolve_tag("en"), Some(EN_US));
+        assert_eq!(resolve_tag("pt"), Some(PT_BR));
+        assert_eq!(resolve_tag("es"), Some(ES_ES));
+        assert_eq!(resolve_tag("zh"), Some(ZH_CN));
+        assert_eq!(resolve_tag("ca"), Some(0x0403));
+        assert_eq!(resolve_tag("de-LU"), Some(DE_DE));
+        assert_eq!(resolve_tag("zh-Hant-TW"), Some(ZH_TW));
+        assert_eq!(resolve_tag("en-GB-x-custom"), Some(EN_GB));
+        assert_eq!(resolve_tag("neutral"), Some(NEUTRAL));
+        assert_eq!(resolve_tag("xx-YY"), None);
+        assert_eq!(resolve_tag(""), None);
+        assert_eq!(Language::from_id(PT_BR), Language { id: PT_BR, tag: Some("pt-BR") });
+        assert_eq!(Language::from_id(LANG_PORTUGUESE).tag, Some("pt"));
+        assert_eq!(Language::from_id(0x0418).tag, None);
+    }
+}
@@ -30,36 +30,81 @@
 //!
 //! We try to guess some sensible default values from Cargo's build time environement variables
 //! This is described in [`WindowsResource::new()`]. Furthermore we have to know where to find the
-//! resource compiler for the MSVC Toolkit. This can be done by looking up a registry key but
-//! for MinGW this has to be done manually.
+//! resource compiler for the MSVC Toolkit. This can be done by looking up a registry key, for
+//! MinGW we search the usual install locations.
 //!
 //! The following paths are the hardcoded defaults:
 //! MSVC the last registry key at
-//! `HKLM\SOFTWARE\Microsoft\Windows Kits\Installed Roots`, for MinGW we try our luck by simply
-//! using the `%PATH%` environment variable.
+//! `HKLM\SOFTWARE\Microsoft\Windows Kits\Installed Roots`, for MinGW the `%PATH%` environment
+//! variable followed by the install prefixes of MSYS2 and other distributions, see the
+//! [`mingw`] module.
 //!
 //! Note that the toolkit bitness as to match the one from the current Rust compiler. If you are
 //! using Rust GNU 64-bit you have to use MinGW64. For MSVC this is simpler as (recent) Windows
 //! SDK always installs both versions on a 64-bit system.
 //!
 //! [`WindowsResorce::compile()`]: struct.WindowsResource.html#method.compile
+//! [`mingw`]: mingw/index.html
 //! [`WindowsResource::new()`]: struct.WindowsResource.html#method.new
 
 use std::env;
+use std::ffi::{OsStr, OsString};
 use std::path::{PathBuf, Path};
 use std::process;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::io;
 use std::io::prelude::*;
 use std::fs;
-use std::error::Error;
+use std::fmt;
+use std::sync::Mutex;
+use std::cell::{OnceCell, RefCell};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+#[cfg(feature = "cargo-metadata")]
 extern crate toml;
+#[cfg(feature = "json-config")]
+extern crate serde_json;
+#[cfg(all(windows, feature = "registry"))]
+extern crate windows_sys;
 
 pub mod sdk;
+pub mod manifest;
+pub mod rc_errors;
+pub mod language;
+pub mod mingw;
+pub mod doctor;
+pub mod ico;
+pub mod res;
+pub mod testing;
+pub mod verify;
+mod error;
+mod archive;
+mod rc_parser;
+#[cfg(all(windows, feature = "registry"))]
+mod registry;
+
+pub use error::Error;
+pub use doctor::doctor;
+
+/// Name of the `RCDATA` resource added by [`WindowsResource::embed_build_info()`]
+///
+/// [`WindowsResource::embed_build_info()`]: struct.WindowsResource.html#method.embed_build_info
+pub const BUILD_INFO_RESOURCE: &str = "WINRES_BUILD_INFO";
+
+/// Environment variables with tool paths that take precedence over the discovery, e.g. set
+/// in a CI image
+///
+/// `WINRES_TOOLKIT_PATH` is a directory with the tools. If one of them is set,
+/// `cargo:rerun-if-env-changed` is printed for all of them. Since cargo then no longer reruns
+/// the build script when any file of the package changes, `cargo:rerun-if-changed` is printed
+/// as well for the input files of the resource, `Cargo.toml` and `build.rs`.
+pub const TOOL_ENV_VARS: [&str; 4] = ["WINRES_RC_PATH", "WINRES_WINDRES_PATH", "WINRES_AR_PATH", "WINRES_TOOLKIT_PATH"];
 
 /// Version info field names
-#[derive(PartialEq, Eq, Hash, Debug)]
+///
+/// The fields are written to the resource script in the order of this enum.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Copy, Clone)]
 pub enum VersionInfo {
     /// The version value consists of four 16 bit words, e.g.,
     /// `MAJOR << 48 | MINOR << 32 | PATCH << 16 | RELEASE`
@@ -80,20 +125,722 @@ pub enum VersionInfo {
     FILEFLAGS,
 }
 
+impl VersionInfo {
+    /// The field with the name `name`, ignoring case
+    fn from_name(name: &str) -> Option<VersionInfo> {
+        Some(match name.to_ascii_uppercase().as_str() {
+            "FILEVERSION" => VersionInfo::FILEVERSION,
+            "PRODUCTVERSION" => VersionInfo::PRODUCTVERSION,
+            "FILEOS" => VersionInfo::FILEOS,
+            "FILETYPE" => VersionInfo::FILETYPE,
+            "FILESUBTYPE" => VersionInfo::FILESUBTYPE,
+            "FILEFLAGSMASK" => VersionInfo::FILEFLAGSMASK,
+            "FILEFLAGS" => VersionInfo::FILEFLAGS,
+            _ => return None,
+        })
+    }
+}
+
+/// A value of [`VersionInfo::FILEOS`], one of the `VOS_*` constants of `verrsrc.h`
+///
+/// ```rust
+/// # use winres::{FileOs, VersionInfo};
+/// # let mut res = winres::WindowsResource::new();
+/// res.set_file_os(FileOs::NT_WINDOWS32);
+/// res.set_version_info(VersionInfo::FILEOS, FileOs::NT_WINDOWS32.into());
+/// ```
+///
+/// [`VersionInfo::FILEOS`]: enum.VersionInfo.html#variant.FILEOS
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub struct FileOs(u32);
+
+impl FileOs {
+    /// `VOS_UNKNOWN`
+    pub const UNKNOWN: FileOs = FileOs(0x0);
+    /// `VOS_DOS`
+    pub const DOS: FileOs = FileOs(0x10000);
+    /// `VOS_NT`
+    pub const NT: FileOs = FileOs(0x40000);
+    /// `VOS__WINDOWS32`
+    pub const WINDOWS32: FileOs = FileOs(0x4);
+    /// `VOS_DOS_WINDOWS32`
+    pub const DOS_WINDOWS32: FileOs = FileOs(0x10004);
+    /// `VOS_NT_WINDOWS32`, the default, also for 64-bit targets
+    pub const NT_WINDOWS32: FileOs = FileOs(0x40004);
+}
+
+impl From<FileOs> for u32 {
+    fn from(file_os: FileOs) -> u32 {
+        file_os.0
+    }
+}
+
+impl From<FileOs> for u64 {
+    fn from(file_os: FileOs) -> u64 {
+        u64::from(file_os.0)
+    }
+}
+
+/// Values for [`VersionInfo::FILETYPE`], the `VFT_*` constants of `verrsrc.h`
+///
+/// [`VersionInfo::FILETYPE`]: enum.VersionInfo.html#variant.FILETYPE
+pub enum FileType {}
+
+impl FileType {
+    /// `VFT_UNKNOWN`
+    pub const UNKNOWN: u64 = 0x0;
+    /// `VFT_APP`
+    pub const APP: u64 = 0x1;
+    /// `VFT_DLL`
+    pub const DLL: u64 = 0x2;
+    /// `VFT_DRV`
+    pub const DRV: u64 = 0x3;
+    /// `VFT_FONT`
+    pub const FONT: u64 = 0x4;
+    /// `VFT_VXD`
+    pub const VXD: u64 = 0x5;
+    /// `VFT_STATIC_LIB`
+    pub const STATIC_LIB: u64 = 0x7;
+}
+
+/// Bits for [`VersionInfo::FILEFLAGS`], the `VS_FF_*` constants of `verrsrc.h`
+///
+/// The flags are combined with `|`:
+///
+/// ```rust
+/// # use winres::{FileFlags, VersionInfo};
+/// # let mut res = winres::WindowsResource::new();
+/// res.set_version_info(VersionInfo::FILEFLAGS, FileFlags::DEBUG | FileFlags::PRERELEASE);
+/// ```
+///
+/// [`VersionInfo::FILEFLAGS`]: enum.VersionInfo.html#variant.FILEFLAGS
+pub enum FileFlags {}
+
+impl FileFlags {
+    /// `VS_FF_DEBUG`
+    pub const DEBUG: u64 = 0x1;
+    /// `VS_FF_PRERELEASE`
+    pub const PRERELEASE: u64 = 0x2;
+    /// `VS_FF_PATCHED`
+    pub const PATCHED: u64 = 0x4;
+    /// `VS_FF_PRIVATEBUILD`, requires the `"PrivateBuild"` property
+    pub const PRIVATEBUILD: u64 = 0x8;
+    /// `VS_FF_INFOINFERRED`
+    pub const INFOINFERRED: u64 = 0x10;
+    /// `VS_FF_SPECIALBUILD`, requires the `"SpecialBuild"` property
+    pub const SPECIALBUILD: u64 = 0x20;
+    /// `VS_FFI_FILEFLAGSMASK`, all of the above, the default for `FILEFLAGSMASK`
+    pub const MASK: u64 = 0x3F;
+}
+
+/// Source of values for `${name}` placeholders in properties
+///
+/// Placeholders are resolved when the resource file is written, so values can be provided
+/// by other build tools, e.g. the git or CI data collected by `vergen`.
+///
+/// A `HashMap<String, String>` can be used as a simple provider.
+pub trait MetadataProvider: fmt::Debug {
+    /// The value for the placeholder `${key}`, if this provider knows it
+    fn get(&self, key: &str) -> Option<String>;
+}
+
+impl MetadataProvider for HashMap<String, String> {
+    fn get(&self, key: &str) -> Option<String> {
+        HashMap::get(self, key).cloned()
+    }
+}
+
+/// Source of the environment variables cargo sets for build scripts
+///
+/// By default the variables of the process are used, see [`ProcessEnv`]. Another source
+/// makes the builder usable outside of cargo and in hermetic tests, see
+/// [`WindowsResource::new_with_env()`]. A `HashMap<String, String>` can be used as a
+/// simple source.
+///
+/// The source is also used by the Windows SDK discovery, its cache in `OUT_DIR` and the
+/// name of the linked library. Only the tools themselves run with the variables of the
+/// process.
+///
+/// [`ProcessEnv`]: struct.ProcessEnv.html
+/// [`WindowsResource::new_with_env()`]: struct.WindowsResource.html#method.new_with_env
+pub trait EnvSource: fmt::Debug {
+    /// The value of the variable `name`, if it is set
+    fn var(&self, name: &str) -> Option<String>;
+
+    /// All variables, used to find the enabled `CARGO_FEATURE_*` features and the
+    /// `DEP_*` variables of dependencies
+    fn vars(&self) -> Vec<(String, String)>;
+}
+
+/// The environment variables of the process
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Default)]
+pub struct ProcessEnv;
+
+impl EnvSource for ProcessEnv {
+    fn var(&self, name: &str) -> Option<String> {
+        env::var(name).ok()
+    }
+
+    fn vars(&self) -> Vec<(String, String)> {
+        env::vars().collect()
+    }
+}
+
+impl EnvSource for HashMap<String, String> {
+    fn var(&self, name: &str) -> Option<String> {
+        self.get(name).cloned()
+    }
+
+    fn vars(&self) -> Vec<(String, String)> {
+        self.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+    }
+}
+
+/// Hook to rewrite paths before they are written to the resource file or passed to tools
+///
+/// This is needed in build sandboxes where the paths seen by the build script are not
+/// valid for the Windows tools, e.g. for WSL interop or remote execution.
+/// See [`PathPrefixMap`] for a simple resolver.
+///
+/// [`PathPrefixMap`]: struct.PathPrefixMap.html
+pub trait PathResolver: fmt::Debug {
+    /// The path the tools should use for `path`
+    fn resolve(&self, path: &Path) -> PathBuf;
+}
+
+/// Path resolver replacing a path prefix
+///
+/// ```rust
+/// use std::path::{Path, PathBuf};
+/// use winres::{PathPrefixMap, PathResolver};
+///
+/// let wsl = PathPrefixMap::new("/home/user", r"\\wsl$\Ubuntu\home\user");
+/// assert_eq!(wsl.resolve(Path::new("/home/user/app/icon.ico")),
+///            PathBuf::from(r"\\wsl$\Ubuntu\home\user\app\icon.ico"));
+/// ```
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct PathPrefixMap {
+    from: PathBuf,
+    to: String,
+}
+
+impl PathPrefixMap {
+    /// Map paths starting with `from` to `to`
+    ///
+    /// `to` is kept as string, so it can use a different path separator than the host.
+    pub fn new<P: AsRef<Path>>(from: P, to: &str) -> Self {
+        PathPrefixMap { from: from.as_ref().to_path_buf(), to: to.to_string() }
+    }
+}
+
+impl PathResolver for PathPrefixMap {
+    fn resolve(&self, path: &Path) -> PathBuf {
+        match path.strip_prefix(&self.from) {
+            Ok(rest) => {
+                // use the separator of the mapped prefix for the rest of the path
+                let separator = if self.to.contains('\\') { "\\" } else { "/" };
+                let mut mapped = self.to.trim_end_matches(separator).to_string();
+                for component in rest.components() {
+                    mapped.push_str(separator);
+                    mapped.push_str(&component.as_os_str().to_string_lossy());
+                }
+                PathBuf::from(mapped)
+            }
+            Err(_) => path.to_path_buf(),
+        }
+    }
+}
+
+/// Hook run after the resource was compiled, e.g. to sign or verify artifacts
+///
+/// The hook gets the builder, so it can use [`WindowsResource::run_signtool()`] with the
+/// discovered Windows SDK, and the compiled resource. An error of the hook fails the
+/// compilation. See [`WindowsResource::add_post_compile_hook()`].
+///
+/// [`WindowsResource::run_signtool()`]: struct.WindowsResource.html#method.run_signtool
+/// [`WindowsResource::add_post_compile_hook()`]: struct.WindowsResource.html#method.add_post_compile_hook
+pub trait PostCompileHook: fmt::Debug {
+    /// Run after `compiled`, the `.res`, `.lib` or `.o` file, was written
+    fn after_compile(&self, res: &WindowsResource, compiled: &Path) -> Result<(), Error>;
+}
+
+/// How the pre-release part of the cargo version, e.g. `rc.4` in `1.2.3-rc.4`,
+/// is mapped into the version info
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum PrereleasePolicy {
+    /// Ignore the pre-release part, this is the default
+    Ignore,
+    /// Use the trailing number of the pre-release part as fourth version component
+    Revision,
+    /// Like `Revision`, and also set `VS_FF_PRERELEASE (0x2)` in `FILEFLAGS`
+    /// for pre-release versions
+    RevisionAndFlag,
+}
+
+/// What happens if the embedded files exceed the budget of
+/// [`WindowsResource::set_size_budget()`]
+///
+/// [`WindowsResource::set_size_budget()`]: struct.WindowsResource.html#method.set_size_budget
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum SizeBudgetPolicy {
+    /// Print a cargo warning and compile anyway
+    Warn,
+    /// Fail with `Error::SizeBudgetExceeded`
+    Fail,
+}
+
+/// Kind of binary the resource is linked into, see
+/// [`WindowsResource::set_binary_type()`]
+///
+/// [`WindowsResource::set_binary_type()`]: struct.WindowsResource.html#method.set_binary_type
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum BinaryType {
+    /// An executable, `VFT_APP`
+    Exe,
+    /// A dynamic library, `VFT_DLL`
+    Dll,
+    /// A driver, `VFT_DRV` with the `FILESUBTYPE` of the kind of driver, e.g.
+    /// `BinaryType::Driver(DriverSubtype::System)` for a kernel mode driver
+    Driver(DriverSubtype),
+}
+
+/// Kind of driver, the `VFT2_DRV_*` constants of `verrsrc.h` used as `FILESUBTYPE`
+/// of [`BinaryType::Driver`]
+///
+/// [`BinaryType::Driver`]: enum.BinaryType.html#variant.Driver
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum DriverSubtype {
+    /// `VFT2_DRV_PRINTER`
+    Printer = 0x1,
+    /// `VFT2_DRV_KEYBOARD`
+    Keyboard = 0x2,
+    /// `VFT2_DRV_LANGUAGE`
+    Language = 0x3,
+    /// `VFT2_DRV_DISPLAY`
+    Display = 0x4,
+    /// `VFT2_DRV_MOUSE`
+    Mouse = 0x5,
+    /// `VFT2_DRV_NETWORK`
+    Network = 0x6,
+    /// `VFT2_DRV_SYSTEM`, e.g. a kernel mode driver or file system filter
+    System = 0x7,
+    /// `VFT2_DRV_INSTALLABLE`
+    Installable = 0x8,
+    /// `VFT2_DRV_SOUND`
+    Sound = 0x9,
+    /// `VFT2_DRV_COMM`
+    Comm = 0xa,
+    /// `VFT2_DRV_INPUTMETHOD`
+    InputMethod = 0xb,
+    /// `VFT2_DRV_VERSIONED_PRINTER`
+    VersionedPrinter = 0xc,
+}
+
+impl BinaryType {
+    /// The `FILETYPE` of the version info
+    pub fn file_type(self) -> u64 {
+        match self {
+            BinaryType::Exe => FileType::APP,
+            BinaryType::Dll => FileType::DLL,
+            BinaryType::Driver(_) => FileType::DRV,
+        }
+    }
+
+    /// The `FILESUBTYPE` of the version info
+    pub fn file_subtype(self) -> u64 {
+        match self {
+            BinaryType::Exe | BinaryType::Dll => 0x0,
+            BinaryType::Driver(subtype) => subtype as u64,
+        }
+    }
+
+    /// The `FILEOS` of the version info, `VOS_NT_WINDOWS32` for every kind of binary
+    pub fn file_os(self) -> FileOs {
+        FileOs::NT_WINDOWS32
+    }
+
+    /// The id of the manifest resource, `CREATEPROCESS_MANIFEST_RESOURCE_ID (1)` for
+    /// executables and `ISOLATIONAWARE_MANIFEST_RESOURCE_ID (2)` otherwise
+    pub fn manifest_resource_id(self) -> u16 {
+        match self {
+            BinaryType::Exe => 1,
+            BinaryType::Dll | BinaryType::Driver(_) => 2,
+        }
+    }
+}
+
+/// A bundle of settings for a common kind of application, see
+/// [`WindowsResource::preset()`]
+///
+/// [`WindowsResource::preset()`]: struct.WindowsResource.html#method.preset
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum Preset {
+    /// A windowed application: [`BinaryType::Exe`] with a manifest enabling visual
+    /// styles, per monitor DPI awareness (v2) and `asInvoker`
+    ///
+    /// [`BinaryType::Exe`]: enum.BinaryType.html#variant.Exe
+    GuiApp,
+    /// A console application: [`BinaryType::Exe`] with an `asInvoker` manifest
+    ///
+    /// [`BinaryType::Exe`]: enum.BinaryType.html#variant.Exe
+    ConsoleApp,
+    /// A Windows service: [`BinaryType::Exe`] with an `asInvoker` manifest without UI
+    /// access, as services run under their own account and cannot show an elevation prompt
+    ///
+    /// The manifest also declares Windows 10 support, so the service is not run in a
+    /// compatibility mode that reports an older Windows version, and long path awareness,
+    /// as services work on paths configured by others. There are no visual styles or DPI
+    /// settings, since services have no windows.
+    ///
+    /// [`BinaryType::Exe`]: enum.BinaryType.html#variant.Exe
+    Service,
+    /// A dynamic library: [`BinaryType::Dll`], so `FILETYPE` is `VFT_DLL` and the
+    /// manifest gets the id `ISOLATIONAWARE_MANIFEST_RESOURCE_ID (2)`
+    ///
+    /// [`BinaryType::Dll`]: enum.BinaryType.html#variant.Dll
+    Dll,
+}
+
+/// Where the manifest is placed, see [`WindowsResource::set_manifest_mode()`]
+///
+/// [`WindowsResource::set_manifest_mode()`]: struct.WindowsResource.html#method.set_manifest_mode
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum ManifestMode {
+    /// Embed the manifest as resource 24
+    Embedded,
+    /// Write the manifest to a file beside the binary, e.g. `app.exe.manifest`, instead
+    /// of embedding it
+    External,
+    /// Embed the manifest and write it to a file
+    Both,
+}
+
+/// A compiled resource and the `cargo:` directives linking it, see
+/// [`WindowsResource::build()`] and [`WindowsResource::compile_artifacts()`]
+///
+/// [`WindowsResource::build()`]: struct.WindowsResource.html#method.build
+/// [`WindowsResource::compile_artifacts()`]: struct.WindowsResource.html#method.compile_artifacts
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct CompilationOutput {
+    /// The compiled resource script, the generated one or the one of
+    /// [`WindowsResource::set_resource_file()`]
+    ///
+    /// [`WindowsResource::set_resource_file()`]: struct.WindowsResource.html#method.set_resource_file
+    pub script: PathBuf,
+    /// The compiled `.lib`, `.res` or `.o` file
+    pub compiled: PathBuf,
+    /// The toolchain that compiled the resource
+    pub toolchain: Toolchain,
+    /// The resource compiler that was run, e.g. the `rc.exe` of the Windows SDK
+    pub tool: PathBuf,
+    /// The directives a build script prints, e.g. `cargo:rustc-link-lib=dylib=resource`
+    /// and `cargo:warning=...`
+    pub directives: Vec<String>,
+}
+
+impl CompilationOutput {
+    /// Print the directives for cargo, like [`WindowsResource::compile()`]
+    ///
+    /// [`WindowsResource::compile()`]: struct.WindowsResource.html#method.compile
+    pub fn print_directives(&self) {
+        print_directives(&self.directives);
+    }
+}
+
+/// How the compiled resource is passed to the linker, see
+/// [`WindowsResource::set_link_mode()`]
+///
+/// [`WindowsResource::set_link_mode()`]: struct.WindowsResource.html#method.set_link_mode
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum LinkMode {
+    /// Link the resource as library `resource` with `cargo:rustc-link-lib`
+    Library,
+    /// Pass the compiled `.res` file, or the COFF object for GNU, to the linker with
+    /// `cargo:rustc-link-arg`
+    ///
+    /// This avoids the library name and needs cargo 1.50 or newer. For GNU no static
+    /// library is created, so `ar` is not needed.
+    LinkArg,
+}
+
+/// A kind of cargo target the resource is linked into, see
+/// [`WindowsResource::apply_to()`]
+///
+/// [`WindowsResource::apply_to()`]: struct.WindowsResource.html#method.apply_to
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum TargetKind {
+    /// The binaries, with `cargo:rustc-link-arg-bins`
+    Bins,
+    /// The examples, with `cargo:rustc-link-arg-examples`
+    Examples,
+    /// The test binaries, with `cargo:rustc-link-arg-tests`
+    Tests,
+    /// The benchmarks, with `cargo:rustc-link-arg-benches`
+    Benches,
+    /// The `cdylib` of the package, with `cargo:rustc-link-arg-cdylib`
+    Cdylib,
+}
+
+impl TargetKind {
+    /// The suffix of the `cargo:rustc-link-arg-*` instruction for this kind of target
+    fn directive_suffix(self) -> &'static str {
+        match self {
+            TargetKind::Bins => "bins",
+            TargetKind::Examples => "examples",
+            TargetKind::Tests => "tests",
+            TargetKind::Benches => "benches",
+            TargetKind::Cdylib => "cdylib",
+        }
+    }
+}
+
+/// How much winres reports as `cargo:warning` lines, see
+/// [`WindowsResource::set_verbosity()`]
+///
+/// [`WindowsResource::set_verbosity()`]: struct.WindowsResource.html#method.set_verbosity
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum Verbosity {
+    /// Report only the warnings, the tool output is part of the returned error
+    Quiet,
+    /// Report the output of a tool if it fails
+    Normal,
+    /// Report the output of every tool, the executed command lines and how the
+    /// resource compiler was selected
+    Verbose,
+}
+
+/// The id of a resource in the resource script, a number or a name
+///
+/// Names consist of ASCII letters, digits and underscores and must not start with a
+/// digit. The resource compiler stores them in upper case. Strings that are numbers,
+/// like `"101"`, convert to numeric ids, as they do in resource scripts.
+///
+/// ```rust
+/// use winres::ResourceId;
+///
+/// assert_eq!(ResourceId::from(101), ResourceId::Number(101));
+/// assert_eq!(ResourceId::from("101"), ResourceId::Number(101));
+/// assert_eq!(ResourceId::from("APP"), ResourceId::Name("APP".to_string()));
+/// assert_eq!(ResourceId::from(&"APP".to_string()), ResourceId::Name("APP".to_string()));
+/// ```
+#[derive(PartialEq, Eq, Debug, Clone, Hash, PartialOrd, Ord)]
+pub enum ResourceId {
+    /// A numeric id, e.g. for `MAKEINTRESOURCE(101)`
+    Number(u16),
+    /// A named id, e.g. for `FindResource(hinstance, "CONFIG", ..)`
+    Name(String),
+}
+
+impl From<u16> for ResourceId {
+    fn from(id: u16) -> Self {
+        ResourceId::Number(id)
+    }
+}
+
+impl<'a> From<&'a str> for ResourceId {
+    fn from(name: &'a str) -> Self {
+        match name.parse() {
+            Ok(id) => ResourceId::Number(id),
+            Err(_) => ResourceId::Name(name.to_string()),
+        }
+    }
+}
+
+impl From<String> for ResourceId {
+    fn from(name: String) -> Self {
+        match name.parse() {
+            Ok(id) => ResourceId::Number(id),
+            Err(_) => ResourceId::Name(name),
+        }
+    }
+}
+
+impl<'a> From<&'a String> for ResourceId {
+    fn from(name: &'a String) -> Self {
+        ResourceId::from(name.as_str())
+    }
+}
+
+impl ResourceId {
+    /// The numeric id, `None` for names
+    pub fn number(&self) -> Option<u16> {
+        match *self {
+            ResourceId::Number(id) => Some(id),
+            ResourceId::Name(_) => None,
+        }
+    }
+
+    /// The id as written to the resource script, an error for names that are not valid
+    fn to_rc(&self) -> Result<String, Error> {
+        if let ResourceId::Name(ref name) = *self {
+            check_resource_name(name, false)?;
+        }
+        Ok(self.to_string())
+    }
+}
+
+impl fmt::Display for ResourceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ResourceId::Number(id) => write!(f, "{}", id),
+            ResourceId::Name(ref name) => f.write_str(name),
+        }
+    }
+}
+
+/// Encoding of the written resource scripts, see [`WindowsResource::set_rc_encoding()`]
+///
+/// [`WindowsResource::set_rc_encoding()`]: struct.WindowsResource.html#method.set_rc_encoding
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum RcEncoding {
+    /// UTF-8, declared with `#pragma code_page(65001)`
+    Utf8,
+    /// UTF-16LE with byte order mark, for `rc.exe` versions that mishandle the UTF-8 code page
+    ///
+    /// windres and llvm-rc cannot read UTF-16 scripts.
+    Utf16Le,
+}
+
+/// Resource compiler toolchain
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum Toolchain {
+    /// `rc.exe` from the Windows SDK
+    Msvc,
+    /// `windres.exe` and `ar.exe` from MinGW
+    Gnu,
+    /// `llvm-rc` from LLVM, the `.res` file is linked like the one of `rc.exe`
+    LlvmRc,
+}
+
 #[derive(Debug)]
 pub struct WindowsResource {
-    tool: sdk::Tool,
-    properties: HashMap<String, String>,
-    version_info: HashMap<VersionInfo, u64>,
-    rc_file: Option<String>,
-    icon_id: Option<String>,
-    icon: Option<String>,
+    env: Box<dyn EnvSource>,
+    target: Option<String>,
+    package_name: String,
+    /// Discovered on first use, see [`tool()`](#method.tool)
+    tool: OnceCell<sdk::Tool>,
+    /// The log and tool output as `cargo:warning` lines, see [`log()`](#method.log)
+    messages: RefCell<Vec<String>>,
+    /// Set by `default()` if `try_new()` failed, compiling then returns its error
+    init_failed: bool,
+    properties: BTreeMap<String, String>,
+    default_properties: BTreeMap<String, String>,
+    version_info: BTreeMap<VersionInfo, u64>,
+    default_version_info: BTreeMap<VersionInfo, u64>,
+    binary_type: BinaryType,
+    manifest_resource_id: Option<ResourceId>,
+    rc_file: Option<PathBuf>,
+    rc_template: Option<PathBuf>,
+    rc_copy: Option<PathBuf>,
+    icon_id: Option<ResourceId>,
+    icon: Option<PathBuf>,
+    icon_variants: Vec<(String, PathBuf)>,
+    icons: Vec<(ResourceId, PathBuf)>,
+    bitmaps: Vec<(ResourceId, PathBuf)>,
+    rcdata: Vec<(ResourceId, PathBuf)>,
+    custom_resources: Vec<(String, ResourceId, PathBuf)>,
+    strings: Vec<(String, u16, String)>,
+    localized_strings: Vec<(u16, u16, String)>,
+    resources_rs: bool,
+    validate_icons: bool,
+    icon_variant: Option<String>,
+    fonts: Vec<PathBuf>,
+    build_info: bool,
+    typelibs: Vec<(u16, PathBuf)>,
+    include_rcs: Vec<PathBuf>,
+    include_dirs: Vec<PathBuf>,
+    defines: Vec<(String, String)>,
+    toolkit_args: Vec<String>,
+    rc_content: String,
+    metadata_providers: Vec<Box<dyn MetadataProvider>>,
+    sdk_fallback: bool,
+    export_metadata: bool,
+    export_build_env: bool,
+    language: u16,
+    default_language: u16,
+    var_file_info: bool,
+    skip_version_info: bool,
+    charset: u16,
+    translations: Vec<(u16, u16)>,
+    localized: Vec<(u16, BTreeMap<String, String>)>,
+    manifest: Option<String>,
+    manifest_builder: Option<manifest::Manifest>,
+    manifest_file: Option<PathBuf>,
+    merge_manifests: Vec<PathBuf>,
+    res_files: Vec<PathBuf>,
+    output_directory: Option<PathBuf>,
+    standalone: bool,
+    verbosity: Verbosity,
+    tool_timeout: Option<Duration>,
+    tool_wrapper: Vec<OsString>,
+    manifest_dir: PathBuf,
+    #[cfg(feature = "cargo-metadata")]
+    metadata_file: Option<PathBuf>,
+    metadata_warnings: Vec<String>,
+    strict: bool,
+    warnings: bool,
+    dry_run: bool,
+    package_include: bool,
+    package_include_dir: Option<PathBuf>,
+    windres_path: Option<PathBuf>,
+    rc_path: Option<PathBuf>,
+    ar_path: Option<PathBuf>,
+    linker_path: Option<PathBuf>,
+    llvm_rc_path: Option<PathBuf>,
+    kits_root: Option<PathBuf>,
+    toolchain: Option<Toolchain>,
+    size_budget: Option<(u64, SizeBudgetPolicy)>,
+    link_mode: LinkMode,
+    target_kinds: Vec<TargetKind>,
+    manifest_mode: ManifestMode,
+    external_manifest_path: Option<PathBuf>,
+    rc_encoding: RcEncoding,
+    output_name: Option<String>,
+    path_resolver: Option<Box<dyn PathResolver>>,
+    post_compile_hooks: Vec<Box<dyn PostCompileHook>>,
+}
+
+/// The settings [`WindowsResource::compile_for_bin()`] may change for one binary, restored
+/// afterwards
+///
+/// [`WindowsResource::compile_for_bin()`]: struct.WindowsResource.html#method.compile_for_bin
+struct BinSettings {
+    properties: BTreeMap<String, String>,
+    default_properties: BTreeMap<String, String>,
+    version_info: BTreeMap<VersionInfo, u64>,
+    icon: Option<PathBuf>,
+    icons: Vec<(ResourceId, PathBuf)>,
+    strings: Vec<(String, u16, String)>,
     language: u16,
+    localized: Vec<(u16, BTreeMap<String, String>)>,
     manifest: Option<String>,
-    manifest_file: Option<String>,
-    output_directory: String,
-    windres_path: Option<String>,
-    ar_path: Option<String>,
+    manifest_builder: Option<manifest::Manifest>,
+    manifest_file: Option<PathBuf>,
+    metadata_warnings: Vec<String>,
+}
+
+impl Default for WindowsResource {
+    /// The resource of [`WindowsResource::try_new()`]
+    ///
+    /// If that fails, e.g. outside of a build script, the resource starts out without the
+    /// values of cargo, and compiling it returns the error of `try_new()`.
+    ///
+    /// [`WindowsResource::try_new()`]: struct.WindowsResource.html#method.try_new
+    fn default() -> Self {
+        WindowsResource::try_new().unwrap_or_else(|_| {
+            let env = [("CARGO_PKG_NAME", ""), ("CARGO_PKG_VERSION", "0.0.0"), ("CARGO_PKG_VERSION_MAJOR", "0"),
+                       ("CARGO_PKG_VERSION_MINOR", "0"), ("CARGO_PKG_VERSION_PATCH", "0"),
+                       ("CARGO_PKG_DESCRIPTION", ""), ("CARGO_MANIFEST_DIR", ".")]
+                .iter()
+                .map(|&(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<_, _>>();
+            let mut res = WindowsResource::from_env(Box::new(env), false)
+                .expect("the fallback cargo variables are complete");
+            res.env = Box::new(ProcessEnv);
+            res.init_failed = true;
+            res
+        })
+    }
 }
 
 impl WindowsResource {
@@ -109,13 +856,35 @@ impl WindowsResource {
     /// | `"ProductVersion"`   | `package.version`            |
     /// | `"ProductName"`      | `package.name`               |
     /// | `"FileDescription"`  | `package.description`        |
+    /// | `"CompanyName"`      | `package.authors`            |
+    /// | `"LegalCopyright"`   | `Copyright © <year> <package.authors>` |
+    /// | `"Comments"`         | `package.homepage`, or `package.repository` |
+    /// | `"OriginalFilename"` | `<package.name>.exe`, or `.dll` for libraries |
+    ///
+    /// The authors are written without their email addresses, and the year is the current
+    /// one, or the one of `SOURCE_DATE_EPOCH` for reproducible builds. Empty values are left out.
+    /// With [`for_bin()`], `OriginalFilename` and `InternalName` are derived from the binary name.
     ///
     /// Furthermore if a section `package.metadata.winres` exists
-    /// in `Cargo.toml` it will be parsed. Values in this section take precedence
-    /// over the values provided natively by cargo. Only the string table
-    /// of the version struct can be set this way.
-    /// Additionally, the language field is set to neutral (i.e. `0`)
-    /// and no icon is set. These settings have to be done programmatically.
+    /// in `Cargo.toml` it will be parsed, unless the default `cargo-metadata` feature is
+    /// disabled. Values in this section take precedence
+    /// over the values provided natively by cargo. Besides the string table, a few
+    /// keys configure other settings:
+    ///
+    /// | Key                  | Setting                                        |
+    /// |----------------------|------------------------------------------------|
+    /// | `icon`               | [`set_icon()`]                                 |
+    /// | `language`           | [`set_language()`], a number or a language tag |
+    /// | `manifest-file`      | [`set_manifest_file()`]                        |
+    /// | `version-info`       | a table of [`set_version_info()`] fields       |
+    /// | `lang.<language>`    | a table of [`set_localized()`] properties      |
+    ///
+    /// Otherwise the language field is set to neutral (i.e. `0`) and no icon is set.
+    ///
+    /// The default language can be overridden without code changes by setting the
+    /// `WINRES_LANG` environment variable, either to a numeric language id
+    /// (e.g. `0x0407`) or to a language tag like `de-DE`. An unknown language is reported
+    /// as metadata warning, see [`metadata_warnings()`], and the language stays neutral.
     ///
     /// `Cargo.toml` files have to be written in UTF-8, so we support all valid UTF-8 strings
     /// provided.
@@ -126,10 +895,19 @@ impl WindowsResource {
     /// OriginalFilename = "testing.exe"
     /// FileDescription = "⛄❤☕"
     /// LegalCopyright = "Copyright © 2016"
+    /// icon = "assets/app.ico"
+    /// language = 0x0409
+    ///
+    /// [package.metadata.winres.version-info]
+    /// FILEFLAGS = 0x8
+    ///
+    /// [package.metadata.winres.lang.de-DE]
+    /// FileDescription = "Rechner"
     /// ```
     ///
     /// The version info struct is set to some values
-    /// sensible for creating an executable file.
+    /// sensible for creating an executable file. If the package only builds a `cdylib`
+    /// or `dylib`, the file type is a DLL instead, see [`set_binary_type()`].
     ///
     /// | Property             | Cargo / Values               |
     /// |----------------------|------------------------------|
@@ -141,55 +919,396 @@ impl WindowsResource {
     /// | `FILEFLAGSMASK`      | `VS_FFI_FILEFLAGSMASK (0x3F)`|
     /// | `FILEFLAGS`          | `0x0`                        |
     ///
+    /// # Panics
+    ///
+    /// Panics if [`try_new()`] fails, e.g. outside of a build script.
+    ///
+    /// [`try_new()`]: #method.try_new
+    /// [`for_bin()`]: #method.for_bin
+    /// [`set_binary_type()`]: #method.set_binary_type
+    /// [`set_icon()`]: #method.set_icon
+    /// [`set_language()`]: #method.set_language
+    /// [`set_manifest_file()`]: #method.set_manifest_file
+    /// [`set_version_info()`]: #method.set_version_info
+    /// [`set_localized()`]: #method.set_localized
+    /// [`metadata_warnings()`]: #method.metadata_warnings
     pub fn new() -> Self {
-        let mut props: HashMap<String, String> = HashMap::new();
-        let mut ver: HashMap<VersionInfo, u64> = HashMap::new();
+        match WindowsResource::try_new() {
+            Ok(res) => res,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Create a new resource like [`new()`], but return an error instead of panicking
+    ///
+    /// Fails if an environment variable set by cargo is missing, a component of the package
+    /// version exceeds `65535`, or `Cargo.toml` cannot be read. The resource compiler is only looked up when it is needed, so a resource
+    /// file can be written without an installed toolkit.
+    ///
+    /// [`new()`]: #method.new
+    pub fn try_new() -> Result<Self, Error> {
+        WindowsResource::from_env(Box::new(ProcessEnv), true)
+    }
+
+    /// Create a new resource like [`try_new()`], reading the variables cargo sets for build
+    /// scripts from `env` instead of the process
+    ///
+    /// This allows to use the builder outside of a build script, e.g. in packaging tools,
+    /// and to test build logic without changing the environment of the process:
+    ///
+    /// ```rust,no_run
+    /// use std::collections::HashMap;
+    ///
+    /// let env = [("CARGO_PKG_NAME", "calc"), ("CARGO_PKG_VERSION", "1.2.3"),
+    ///            ("CARGO_PKG_VERSION_MAJOR", "1"), ("CARGO_PKG_VERSION_MINOR", "2"),
+    ///            ("CARGO_PKG_VERSION_PATCH", "3"), ("CARGO_PKG_DESCRIPTION", "Calculator"),
+    ///            ("CARGO_MANIFEST_DIR", "/src/calc"), ("OUT_DIR", "/tmp/calc")]
+    ///     .iter()
+    ///     .map(|&(k, v)| (k.to_string(), v.to_string()))
+    ///     .collect::<HashMap<_, _>>();
+    /// let res = winres::WindowsResource::new_with_env(env).unwrap();
+    /// assert_eq!(res.get("ProductName"), Some("calc"));
+    /// ```
+    ///
+    /// `Cargo.toml` is read from `CARGO_MANIFEST_DIR` of `env`.
+    ///
+    /// [`try_new()`]: #method.try_new
+    pub fn new_with_env<E: EnvSource + 'static>(env: E) -> Result<Self, Error> {
+        WindowsResource::from_env(Box::new(env), true)
+    }
+
+    /// Create a new resource like [`new()`], but without reading `Cargo.toml`
+    ///
+    /// Only the environment variables set by cargo are used, the `package.metadata.winres`
+    /// section is ignored and the binary type is not detected. This is useful in workspaces
+    /// with generated manifests, or to read the metadata from another file with
+    /// [`load_metadata_from()`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if an environment variable set by cargo is missing.
+    ///
+    /// [`new()`]: #method.new
+    /// [`load_metadata_from()`]: #method.load_metadata_from
+    pub fn new_without_metadata() -> Self {
+        match WindowsResource::from_env(Box::new(ProcessEnv), false) {
+            Ok(res) => res,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Create a new resource like [`try_new()`], seeded from an existing resource file
+    ///
+    /// The common statements of a hand-written `.rc` file are read into the builder, so a
+    /// project migrating to winres can change values programmatically:
+    ///
+    /// * `LANGUAGE` and the fixed statements of `VERSIONINFO`, like `FILEVERSION`
+    /// * the `StringFileInfo` values, the first table with [`set()`], the others with
+    ///   [`set_localized()`]
+    /// * `ICON`, `BITMAP`, `RCDATA` and the `RT_MANIFEST` file
+    /// * `STRINGTABLE` entries, named `IDS_<id>`
+    ///
+    /// Relative file names are resolved against the directory of `path`. Other resources,
+    /// like dialogs and menus, are skipped and reported as metadata warnings, see
+    /// [`metadata_warnings()`]. Only `#define`s with a number are understood, included
+    /// headers are not read.
+    ///
+    /// [`try_new()`]: #method.try_new
+    /// [`set()`]: #method.set
+    /// [`set_localized()`]: #method.set_localized
+    /// [`metadata_warnings()`]: #method.metadata_warnings
+    pub fn from_rc_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let source = match fs::read_to_string(path) {
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Err(Error::ResourceFileNotFound(path.to_path_buf())),
+            source => source?,
+        };
+        let script = rc_parser::parse(&source)
+            .map_err(|message| Error::InvalidRcFile { path: path.to_path_buf(), message })?;
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+        let mut res = WindowsResource::try_new()?;
+        if let Some(language) = script.language {
+            res.set_language(language);
+        }
+        for (key, value) in script.fixed {
+            match VersionInfo::from_name(&key) {
+                Some(field) => {
+                    res.set_version_info(field, value);
+                }
+                None => res.metadata_warnings.push(format!("{}: {} is not a version info field", path.display(), key)),
+            }
+        }
+        for (i, (language, charset, values)) in script.string_tables.into_iter().enumerate() {
+            if i == 0 {
+                res.set_language(language).set_charset(charset);
+            }
+            for (name, value) in values {
+                if i == 0 {
+                    res.set(&name, &value);
+                } else {
+                    res.set_localized(&name, language, &value);
+                }
+            }
+        }
+        for (i, (id, icon)) in script.icons.into_iter().enumerate() {
+            if i == 0 {
+                res.set_icon_with_id(dir.join(icon), id);
+            } else {
+                res.add_icon(dir.join(icon), id);
+            }
+        }
+        for (id, bitmap) in script.bitmaps {
+            res.add_bitmap(dir.join(bitmap), id);
+        }
+        for (id, data) in script.rcdata {
+            res.add_rcdata(dir.join(data), id);
+        }
+        if let Some(manifest) = script.manifest {
+            res.set_manifest_file(dir.join(manifest));
+        }
+        for (id, value) in script.strings {
+            res.add_string(&format!("IDS_{}", id), id, &value);
+        }
+        for skipped in script.skipped {
+            res.metadata_warnings.push(format!("{}: {}", path.display(), skipped));
+        }
+        Ok(res)
+    }
+
+    /// Create a resource from a standalone configuration file, like `winres.toml`
+    ///
+    /// The file has the keys of the `package.metadata.winres` section of `Cargo.toml`,
+    /// see [`new()`], so resource metadata can be edited without touching the build script.
+    /// Files ending in `.json` are read as JSON with the `json-config` feature, all others
+    /// as TOML. JSON has no equivalent of `null` in TOML, so it is rejected. Relative file names
+    /// are resolved against the directory of the configuration file. A few more keys are
+    /// understood, also in `Cargo.toml`:
+    ///
+    /// | Key                  | Setting                                                    |
+    /// |----------------------|------------------------------------------------------------|
+    /// | `icons`              | a table of [`add_icon()`] ids and files                    |
+    /// | `strings`            | a table of [`add_string()`] names with `id` and `value`    |
+    /// | `manifest`           | `execution-level`, `ui-access`, `dpi-awareness`, `visual-styles`, `long-path-aware`, `active-code-page-utf8`, `segment-heap`, `gdi-scaling`, `disable-theming` and `printer-driver-isolation` |
+    ///
+    /// ```toml
+    /// # winres.toml
+    /// FileDescription = "Calculator"
+    /// icon = "assets/calc.ico"
+    /// language = "en-US"
+    ///
+    /// [version-info]
+    /// FILEVERSION = "1.2.3.4"
+    ///
+    /// [lang.de-DE]
+    /// FileDescription = "Rechner"
+    ///
+    /// [icons]
+    /// 101 = "assets/document.ico"
+    ///
+    /// [strings]
+    /// IDS_GREETING = { id = 1, value = "Hello" }
+    ///
+    /// [manifest]
+    /// dpi-awareness = "permonitorv2"
+    /// visual-styles = true
+    /// ```
+    ///
+    /// The resource starts out like one of [`try_new()`], so `Cargo.toml` is read as well,
+    /// the configuration file takes precedence. Unknown keys and malformed values are
+    /// reported as metadata warnings, see [`metadata_warnings()`].
+    /// Only available with the `cargo-metadata` feature.
+    ///
+    /// [`new()`]: #method.new
+    /// [`try_new()`]: #method.try_new
+    /// [`add_icon()`]: #method.add_icon
+    /// [`add_string()`]: #method.add_string
+    /// [`metadata_warnings()`]: #method.metadata_warnings
+    #[cfg(feature = "cargo-metadata")]
+    pub fn from_config_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let source = match fs::read_to_string(path) {
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Err(Error::ConfigFileNotFound(path.to_path_buf())),
+            source => source?,
+        };
+        let config = if path.extension().map(|ext| ext.eq_ignore_ascii_case("json")).unwrap_or(false) {
+            parse_json_config(&source)
+        } else {
+            source.parse::<toml::Value>().map_err(|e| e.to_string())
+        }.map_err(|message| Error::InvalidConfigFile { path: path.to_path_buf(), message })?;
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+        let mut res = WindowsResource::try_new()?;
+        res.apply_metadata_section(&config, &path.display().to_string(), dir, true)?;
+        Ok(res)
+    }
+
+    /// Create a new resource from the cargo variables of `env`, reading `Cargo.toml` if `metadata`
+    fn from_env(env: Box<dyn EnvSource>, metadata: bool) -> Result<Self, Error> {
+        let mut props: BTreeMap<String, String> = BTreeMap::new();
+        let mut ver: BTreeMap<VersionInfo, u64> = BTreeMap::new();
 
-        props.insert("FileVersion".to_string(),
-                     env::var("CARGO_PKG_VERSION").expect("env").to_string());
-        props.insert("ProductVersion".to_string(),
-                     env::var("CARGO_PKG_VERSION").expect("env").to_string());
-        props.insert("ProductName".to_string(),
-                     env::var("CARGO_PKG_NAME").expect("env").to_string());
-        props.insert("FileDescription".to_string(),
-                     env::var("CARGO_PKG_DESCRIPTION").expect("env").to_string());
+        props.insert("FileVersion".to_string(), cargo_env(&*env, "CARGO_PKG_VERSION")?);
+        props.insert("ProductVersion".to_string(), cargo_env(&*env, "CARGO_PKG_VERSION")?);
+        props.insert("ProductName".to_string(), cargo_env(&*env, "CARGO_PKG_NAME")?);
+        props.insert("FileDescription".to_string(), cargo_env(&*env, "CARGO_PKG_DESCRIPTION")?);
+        let authors = cargo_authors(&*env);
+        if !authors.is_empty() {
+            props.insert("CompanyName".to_string(), authors.clone());
+            props.insert("LegalCopyright".to_string(), format!("Copyright © {} {}", current_year(&*env), authors));
+        }
+        let homepage = ["CARGO_PKG_HOMEPAGE", "CARGO_PKG_REPOSITORY"].iter()
+            .filter_map(|name| env.var(name))
+            .find(|url| !url.is_empty());
+        if let Some(homepage) = homepage {
+            props.insert("Comments".to_string(), homepage);
+        }
 
-        parse_cargo_toml(&mut props).expect("parse toml");
+        let manifest_dir = PathBuf::from(cargo_env(&*env, "CARGO_MANIFEST_DIR")?);
+        #[cfg(feature = "cargo-metadata")]
+        let metadata_file = if metadata { Some(manifest_dir.join("Cargo.toml")) } else { None };
 
         let mut version = 0 as u64;
-        version |= env::var("CARGO_PKG_VERSION_MAJOR").expect("env").parse().unwrap_or(0) << 48;
-        version |= env::var("CARGO_PKG_VERSION_MINOR").expect("env").parse().unwrap_or(0) << 32;
-        version |= env::var("CARGO_PKG_VERSION_PATCH").expect("env").parse().unwrap_or(0) << 16;
-        // version |= env::var("CARGO_PKG_VERSION_PRE").unwrap().parse().unwrap_or(0);
+        version |= u64::from(cargo_version_component(&*env, "CARGO_PKG_VERSION_MAJOR")?) << 48;
+        version |= u64::from(cargo_version_component(&*env, "CARGO_PKG_VERSION_MINOR")?) << 32;
+        version |= u64::from(cargo_version_component(&*env, "CARGO_PKG_VERSION_PATCH")?) << 16;
+        // the pre-release part is mapped by set_prerelease_policy()
         ver.insert(VersionInfo::FILEVERSION, version);
         ver.insert(VersionInfo::PRODUCTVERSION, version);
-        ver.insert(VersionInfo::FILEOS, 0x00040004);
-        ver.insert(VersionInfo::FILETYPE, 1);
-        ver.insert(VersionInfo::FILESUBTYPE, 0);
-        ver.insert(VersionInfo::FILEFLAGSMASK, 0x3F);
+        ver.insert(VersionInfo::FILEOS, FileOs::NT_WINDOWS32.into());
+        let binary_type = if metadata { detect_binary_type(&manifest_dir) } else { BinaryType::Exe };
+        props.insert("OriginalFilename".to_string(), original_filename(&cargo_env(&*env, "CARGO_PKG_NAME")?, binary_type));
+        ver.insert(VersionInfo::FILETYPE, binary_type.file_type());
+        ver.insert(VersionInfo::FILESUBTYPE, binary_type.file_subtype());
+        ver.insert(VersionInfo::FILEFLAGSMASK, FileFlags::MASK);
         ver.insert(VersionInfo::FILEFLAGS, 0);
 
-        let tool = if cfg!(target_env = "msvc") {
-            get_sdk().expect("get_sdk")
-        } else if cfg!(target_os = "windows") {
-            unimplemented!()
-        } else {
-            unimplemented!()
+        // an unknown WINRES_LANG is reported with the metadata warnings
+        let (language, language_warning) = match default_language(&*env) {
+            Ok(language) => (language, None),
+            Err(warning) => (0, Some(warning)),
         };
 
-        WindowsResource {
-            tool: tool,
+        let mut res = WindowsResource {
+            target: env.var("TARGET"),
+            package_name: cargo_env(&*env, "CARGO_PKG_NAME")?,
+            tool: OnceCell::new(),
+            messages: RefCell::new(vec![]),
+            init_failed: false,
+            default_properties: props.clone(),
             properties: props,
+            default_version_info: ver.clone(),
             version_info: ver,
+            binary_type,
+            manifest_resource_id: None,
             rc_file: None,
+            rc_template: None,
+            rc_copy: None,
             icon_id: None,
             icon: None,
-            language: 0,
+            icon_variants: Vec::new(),
+            icons: Vec::new(),
+            bitmaps: Vec::new(),
+            rcdata: Vec::new(),
+            custom_resources: Vec::new(),
+            strings: Vec::new(),
+            localized_strings: Vec::new(),
+            resources_rs: false,
+            validate_icons: true,
+            icon_variant: env.var("WINRES_ICON_VARIANT"),
+            fonts: Vec::new(),
+            build_info: false,
+            typelibs: Vec::new(),
+            include_rcs: Vec::new(),
+            include_dirs: Vec::new(),
+            defines: Vec::new(),
+            toolkit_args: Vec::new(),
+            rc_content: String::new(),
+            metadata_providers: Vec::new(),
+            sdk_fallback: false,
+            export_metadata: false,
+            export_build_env: false,
+            language,
+            default_language: language,
+            var_file_info: true,
+            skip_version_info: false,
+            charset: 0x04b0,
+            translations: Vec::new(),
+            localized: Vec::new(),
             manifest: None,
+            manifest_builder: None,
             manifest_file: None,
-            output_directory: env::var("OUT_DIR").unwrap_or(".".to_string()),
+            merge_manifests: Vec::new(),
+            res_files: Vec::new(),
+            output_directory: env.var("OUT_DIR").map(PathBuf::from),
+            standalone: false,
+            verbosity: Verbosity::Normal,
+            tool_timeout: None,
+            tool_wrapper: vec![],
+            manifest_dir,
+            #[cfg(feature = "cargo-metadata")]
+            metadata_file,
+            metadata_warnings: language_warning.into_iter().collect(),
+            strict: false,
+            warnings: true,
+            dry_run: false,
+            package_include: true,
+            package_include_dir: None,
             windres_path: None,
+            rc_path: None,
             ar_path: None,
+            linker_path: None,
+            llvm_rc_path: None,
+            kits_root: None,
+            toolchain: None,
+            size_budget: None,
+            link_mode: LinkMode::Library,
+            target_kinds: Vec::new(),
+            manifest_mode: ManifestMode::Embedded,
+            external_manifest_path: None,
+            rc_encoding: RcEncoding::Utf8,
+            output_name: None,
+            path_resolver: None,
+            post_compile_hooks: Vec::new(),
+            env,
+        };
+        res.apply_tool_env();
+        res.apply_package_metadata()?;
+        if let Some(bin) = res.env.var("CARGO_BIN_NAME") {
+            res.for_bin(&bin);
+        }
+        Ok(res)
+    }
+
+    /// Take the tool paths of the [`TOOL_ENV_VARS`] that are set, before any discovery
+    ///
+    /// `WINRES_TOOLKIT_PATH` is a directory with `rc.exe`, or `windres` and `ar` for GNU.
+    /// The variables for a single tool take precedence over it.
+    ///
+    /// [`TOOL_ENV_VARS`]: constant.TOOL_ENV_VARS.html
+    fn apply_tool_env(&mut self) {
+        let paths = {
+            let var = |name: &str| self.env.var(name).filter(|value| !value.trim().is_empty()).map(PathBuf::from);
+            [var("WINRES_TOOLKIT_PATH"), var("WINRES_RC_PATH"), var("WINRES_WINDRES_PATH"), var("WINRES_AR_PATH")]
+        };
+        let [toolkit, rc, windres, ar] = paths;
+        if let Some(dir) = toolkit {
+            let arch = self.env.var("CARGO_CFG_TARGET_ARCH")
+                .and_then(|arch| sdk::Arch::from_target_arch(&arch))
+                .unwrap_or(sdk::Arch::X64);
+            let exe = if cfg!(windows) { ".exe" } else { "" };
+            self.rc_path = Some(dir.join("rc.exe"));
+            self.windres_path = Some(mingw::windres_in(&dir, arch).unwrap_or_else(|| dir.join(format!("windres{}", exe))));
+            self.ar_path = Some(dir.join(format!("ar{}", exe))).filter(|ar| ar.is_file());
+        }
+        if rc.is_some() {
+            self.rc_path = rc;
+        }
+        if windres.is_some() {
+            self.windres_path = windres;
+        }
+        if ar.is_some() {
+            self.ar_path = ar;
         }
     }
 
@@ -203,7 +1322,7 @@ impl WindowsResource {
     ///  - `"ProductName"`
     ///  - `"OriginalFilename"`
     ///  - `"LegalCopyright"`
-    ///  - `"LegalTrademark"`
+    ///  - `"LegalTrademarks"`
     ///  - `"CompanyName"`
     ///  - `"Comments"`
     ///  - `"InternalName"`
@@ -220,51 +1339,284 @@ impl WindowsResource {
         self
     }
 
-    /// Set the correct tool.
+    /// Configure the resource in a chain of `&mut self` setters and return it by value
     ///
-    /// For the GNU toolkit this has to be the path where MinGW
-    /// put `windres.exe` and `ar.exe`. This could be something like:
-    /// `"C:\Program Files\mingw-w64\x86_64-5.3.0-win32-seh-rt_v4-rev0\mingw64\bin"`
+    /// The builder can then be created and compiled in a single expression, e.g. in a
+    /// helper function shared by several build scripts:
     ///
-    /// For MSVC the Windows SDK has to be installed. It comes with the resource compiler
-    /// `rc.exe`. This should be set to the root directory of the Windows SDK, e.g.,
-    /// `"C:\Program Files (x86)\Windows Kits\10"`
-    /// or, if multiple 10 versions are installed,
-    /// set it directly to the corret bin directory
-    /// `"C:\Program Files (x86)\Windows Kits\10\bin\10.0.14393.0\x64"`
+    /// ```rust,no_run
+    /// fn resource(description: &str) -> winres::WindowsResource {
+    ///     winres::WindowsResource::default()
+    ///         .with(|res| res.set("FileDescription", description).set_language(0x0409))
+    /// }
     ///
-    /// If it is left unset, it will look up a path in the registry,
-    /// i.e. `HKLM\SOFTWARE\Microsoft\Windows Kits\Installed Roots`
-    pub fn set_tool<'a>(&mut self, tool: sdk::Tool) -> &mut Self {
-        self.tool = tool;
+    /// resource("My tool").icon("tool.ico").compile().unwrap();
+    /// ```
+    pub fn with<F>(mut self, configure: F) -> Self
+        where F: FnOnce(&mut Self) -> &mut Self
+    {
+        configure(&mut self);
         self
     }
 
-    /// Set the user interface language of the file
+    /// Set a string property like [`set()`], by value for chaining
     ///
-    /// # Example
+    /// [`set()`]: #method.set
+    pub fn property(mut self, name: &str, value: &str) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    /// Set the icon like [`set_icon()`], by value for chaining
     ///
-    /// ```
-    /// extern crate winapi;
-    /// extern crate winres;
-    /// # use std::io;
-    /// fn main() {
-    ///   if cfg!(target_os = "windows") {
-    ///     let mut res = winres::WindowsResource::new();
-    /// #   res.set_output_directory(".");
-    ///     res.set_language(winapi::um::winnt::MAKELANGID(
-    ///         winapi::um::winnt::LANG_ENGLISH,
-    ///         winapi::um::winnt::SUBLANG_ENGLISH_US
-    ///     ));
+    /// [`set_icon()`]: #method.set_icon
+    pub fn icon<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.set_icon(path);
+        self
+    }
+
+    /// Set the manifest file like [`set_manifest_file()`], by value for chaining
+    ///
+    /// [`set_manifest_file()`]: #method.set_manifest_file
+    pub fn manifest_file<P: AsRef<Path>>(mut self, file: P) -> Self {
+        self.set_manifest_file(file);
+        self
+    }
+
+    /// Set the name of the binary the resource is compiled for, e.g. `"my-tool"`
+    ///
+    /// `OriginalFilename` then defaults to `my-tool.exe` instead of the package name,
+    /// and `InternalName` to `my-tool`, unless they are set by [`set()`] or in `Cargo.toml`.
+    /// The name is detected from `CARGO_BIN_NAME` when cargo sets it for build scripts.
+    /// [`compile_for_bin()`] uses the name of its binary the same way.
+    ///
+    /// [`set()`]: #method.set
+    /// [`compile_for_bin()`]: #method.compile_for_bin
+    pub fn for_bin(&mut self, name: &str) -> &mut Self {
+        let defaults = [("OriginalFilename", original_filename(name, self.binary_type)),
+                        ("InternalName", name.to_string())];
+        for (property, value) in defaults.iter() {
+            // properties that still have their default value were not set by the user
+            if self.properties.get(*property) == self.default_properties.get(*property) {
+                self.properties.insert(property.to_string(), value.clone());
+                self.default_properties.insert(property.to_string(), value.clone());
+            }
+        }
+        self
+    }
+
+    /// The value of a string property, as set by cargo, `Cargo.toml` or [`set()`]
+    ///
+    /// Placeholders are not expanded yet.
+    ///
+    /// [`set()`]: #method.set
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.properties.get(name).map(String::as_str)
+    }
+
+    /// Remove a string property, so it is not written to the resource file
+    ///
+    /// Returns the previous value.
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        self.properties.remove(name)
+    }
+
+    /// The string properties, sorted by name
+    pub fn properties(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.properties.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Set a string property for another language
+    ///
+    /// Each language gets its own `StringFileInfo` block, containing the properties
+    /// set with [`set()`] overridden by the localized values. The languages are also added
+    /// to the `Translation` value, after the language of the file, see [`translations()`].
+    ///
+    /// ```rust,no_run
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set("ProductName", "Calculator")
+    ///    .set_localized("ProductName", 0x0407, "Rechner")
+    ///    .set_localized("ProductName", 0x0411, "電卓");
+    /// ```
+    ///
+    /// [`set()`]: #method.set
+    /// [`translations()`]: #method.translations
+    pub fn set_localized(&mut self, name: &str, language: u16, value: &str) -> &mut Self {
+        match self.localized.iter().position(|&(lang, _)| lang == language) {
+            Some(index) => {
+                self.localized[index].1.insert(name.to_string(), value.to_string());
+            }
+            None => {
+                let mut properties = BTreeMap::new();
+                properties.insert(name.to_string(), value.to_string());
+                self.localized.push((language, properties));
+            }
+        }
+        self
+    }
+
+    /// Add a provider for `${name}` placeholders in property values
+    ///
+    /// Providers are asked in the order they were added. Placeholders no provider knows
+    /// are replaced by the environment variable of that name, so values set with [`set()`]
+    /// or in `Cargo.toml` can use build metadata from CI, e.g.
+    /// `LegalCopyright = "© ${BUILD_YEAR} ACME"`. [`compile()`] prints
+    /// `cargo:rerun-if-env-changed=BUILD_YEAR`, so the resource is rebuilt when the variable
+    /// changes, and `cargo:rerun-if-changed` for the input files of the resource, as cargo
+    /// then no longer reruns the build script on any change of the package. Writing the
+    /// resource file fails with [`Error::UndefinedPlaceholder`] if a placeholder cannot be
+    /// resolved.
+    ///
+    /// Placeholder names consist of ASCII letters, digits, `_`, `.` and `-`. Write `$${` for a
+    /// literal `${`; a `${` that is not followed by a name and `}` is kept as it is.
+    ///
+    /// ```rust,no_run
+    /// use std::collections::HashMap;
+    ///
+    /// let mut git = HashMap::new();
+    /// git.insert("GIT_SHA".to_string(), "4f3a2b1".to_string());
+    ///
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set("Comments", "built from ${GIT_SHA}")
+    ///    .add_metadata_provider(git);
+    /// ```
+    ///
+    /// [`set()`]: #method.set
+    /// [`compile()`]: #method.compile
+    /// [`Error::UndefinedPlaceholder`]: enum.Error.html#variant.UndefinedPlaceholder
+    pub fn add_metadata_provider<P: MetadataProvider + 'static>(&mut self, provider: P) -> &mut Self {
+        self.metadata_providers.push(Box::new(provider));
+        self
+    }
+
+    /// Set a hook to rewrite paths before they are written to the resource file or
+    /// passed to the resource compiler
+    ///
+    /// ```rust,no_run
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_path_resolver(winres::PathPrefixMap::new("/home/user", r"\\wsl$\Ubuntu\home\user"));
+    /// ```
+    pub fn set_path_resolver<R: PathResolver + 'static>(&mut self, resolver: R) -> &mut Self {
+        self.path_resolver = Some(Box::new(resolver));
+        self
+    }
+
+    /// Add a hook run after the resource was compiled, before the link directives are added
+    ///
+    /// Hooks run in the order they were added, with [`compile()`] and [`build()`]. A build
+    /// pipeline can use them to Authenticode-sign binaries produced by the build script,
+    /// like satellite DLLs, with the `signtool.exe` of the same Windows SDK:
+    ///
+    /// ```rust,no_run
+    /// use std::path::Path;
+    /// use winres::{Error, PostCompileHook, WindowsResource};
+    ///
+    /// #[derive(Debug)]
+    /// struct Sign;
+    ///
+    /// impl PostCompileHook for Sign {
+    ///     fn after_compile(&self, res: &WindowsResource, _compiled: &Path) -> Result<(), Error> {
+    ///         for dll in res.compile_satellite_dlls("myapp")? {
+    ///             res.run_signtool(&["sign".as_ref(), "/a".as_ref(), "/fd".as_ref(), "SHA256".as_ref(),
+    ///                                dll.as_os_str()])?;
+    ///         }
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let mut res = WindowsResource::new();
+    /// res.add_post_compile_hook(Sign);
+    /// res.compile().unwrap();
+    /// ```
+    ///
+    /// [`compile()`]: #method.compile
+    /// [`build()`]: #method.build
+    pub fn add_post_compile_hook<H: PostCompileHook + 'static>(&mut self, hook: H) -> &mut Self {
+        self.post_compile_hooks.push(Box::new(hook));
+        self
+    }
+
+    /// `path` rewritten by the path resolver, as string for the resource file or a tool
+    fn resolve_path<P: AsRef<Path>>(&self, path: P) -> String {
+        self.resolve_path_os(path).to_string_lossy().into_owned()
+    }
+
+    /// `path` rewritten by the path resolver, as argument for a tool
+    fn resolve_path_os<P: AsRef<Path>>(&self, path: P) -> OsString {
+        match self.path_resolver {
+            Some(ref resolver) => resolver.resolve(path.as_ref()).into_os_string(),
+            None => path.as_ref().as_os_str().to_os_string(),
+        }
+    }
+
+    /// Resolve the `${name}` placeholders in `value` with the metadata providers, falling
+    /// back to the environment variables
+    fn resolve_placeholders(&self, value: &str) -> Result<String, Error> {
+        expand_placeholders(value, |key| {
+            self.metadata_providers.iter().filter_map(|p| p.get(key)).next()
+                .or_else(|| self.env.var(key))
+        }).map_err(|name| Error::UndefinedPlaceholder { name, value: value.to_string() })
+    }
+
+    /// The environment variables the `${name}` placeholders of the properties fall back to,
+    /// because no metadata provider knows them
+    fn placeholder_env_vars(&self) -> BTreeSet<String> {
+        let names = RefCell::new(BTreeSet::new());
+        let localized = self.localized.iter().flat_map(|(_, properties)| properties.values());
+        for value in self.properties.values().chain(localized) {
+            let _ = expand_placeholders(value, |key| {
+                if self.metadata_providers.iter().all(|p| p.get(key).is_none()) {
+                    names.borrow_mut().insert(key.to_string());
+                }
+                Some(String::new())
+            });
+        }
+        names.into_inner()
+    }
+
+    /// Set the correct tool.
+    ///
+    /// For the GNU toolkit this has to be the path where MinGW
+    /// put `windres.exe` and `ar.exe`. This could be something like:
+    /// `"C:\Program Files\mingw-w64\x86_64-5.3.0-win32-seh-rt_v4-rev0\mingw64\bin"`
+    ///
+    /// For MSVC the Windows SDK has to be installed. It comes with the resource compiler
+    /// `rc.exe`. This should be set to the root directory of the Windows SDK, e.g.,
+    /// `"C:\Program Files (x86)\Windows Kits\10"`
+    /// or, if multiple 10 versions are installed,
+    /// set it directly to the corret bin directory
+    /// `"C:\Program Files (x86)\Windows Kits\10\bin\10.0.14393.0\x64"`
+    ///
+    /// If it is left unset, it will look up a path in the registry,
+    /// i.e. `HKLM\SOFTWARE\Microsoft\Windows Kits\Installed Roots`
+    pub fn set_tool<'a>(&mut self, tool: sdk::Tool) -> &mut Self {
+        self.tool = OnceCell::from(tool);
+        self
+    }
+
+    /// Set the user interface language of the file
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate winres;
+    /// # use std::io;
+    /// use winres::language::{make_lang_id, LANG_ENGLISH, SUBLANG_ENGLISH_US};
+    ///
+    /// fn main() {
+    ///   if cfg!(target_os = "windows") {
+    ///     let mut res = winres::WindowsResource::new();
+    /// #   res.set_output_directory(".");
+    ///     res.set_language(make_lang_id(LANG_ENGLISH, SUBLANG_ENGLISH_US));
     ///     res.compile().expect("compile");
     ///   }
     /// }
     /// ```
-    /// For possible values look at the `winapi::um::winnt` constants, specifically those
-    /// starting with `LANG_` and `SUBLANG_`.
+    /// For possible values look at the constants of the [`language`] module, specifically
+    /// those starting with `LANG_` and `SUBLANG_`, or use [`set_language_tag()`].
     ///
-    /// [`MAKELANGID`]: https://docs.rs/winapi/0.3/x86_64-pc-windows-msvc/winapi/um/winnt/fn.MAKELANGID.html
-    /// [`winapi::um::winnt`]: https://docs.rs/winapi/0.3/x86_64-pc-windows-msvc/winapi/um/winnt/index.html#constants
+    /// [`language`]: language/index.html
+    /// [`set_language_tag()`]: #method.set_language_tag
     ///
     /// # Table
     /// Sometimes it is just simpler to specify the numeric constant directly
@@ -292,358 +1644,6187 @@ impl WindowsResource {
         self
     }
 
-    /// Set an icon filename
+    /// Set the user interface language of the file by a language tag like `"pt-BR"`
     ///
-    /// This icon need to be in `ico` format. The filename can be absolute
-    /// or relative to the projects root.
-    pub fn set_icon<'a>(&mut self, path: &'a str) -> &mut Self {
-        self.icon = Some(path.to_string());
-        self
+    /// A tag without region like `"en"` sets the default sublanguage, and unknown regions
+    /// fall back to it, see [`language::resolve_tag()`]. Returns an error if the language
+    /// is not known.
+    ///
+    /// [`language::resolve_tag()`]: language/fn.resolve_tag.html
+    pub fn set_language_tag(&mut self, tag: &str) -> Result<&mut Self, Error> {
+        let language = language::resolve_tag(tag).ok_or_else(|| Error::UnknownLanguage(tag.to_string()))?;
+        Ok(self.set_language(language))
     }
 
-    /// Set an icon filename and icon id
+    /// The user interface language of the file, as `LANGID` and language tag
     ///
-    /// This icon need to be in `ico` format. The filename can be absolute
-    /// or relative to the projects root.
-    pub fn set_icon_with_id<'a>(&mut self, path: &'a str, icon_id: &'a str) -> &mut Self {
-        self.icon = Some(path.to_string());
-        self.icon_id = Some(icon_id.to_string());
-        self
+    /// ```rust,no_run
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_language_tag("en").unwrap();
+    /// assert_eq!(res.language().id, 0x0409);
+    /// assert_eq!(res.language().tag, Some("en-US"));
+    /// ```
+    pub fn language(&self) -> language::Language {
+        language::Language::from_id(self.language)
     }
 
-    /// Set a version info struct property
-    /// Currently we only support numeric values; you have to look them up.
-    pub fn set_version_info(&mut self, field: VersionInfo, value: u64) -> &mut Self {
-        self.version_info.insert(field, value);
+    /// Add a language and charset pair to the `Translation` value of the `VarFileInfo` block
+    ///
+    /// Pairs are written in the order they were added, followed by the languages without
+    /// an explicit pair, see [`translations()`]. Each pair also gets a `StringFileInfo`
+    /// block, with the localized properties of its language.
+    ///
+    /// [`translations()`]: #method.translations
+    pub fn add_translation(&mut self, language: u16, charset: u16) -> &mut Self {
+        self.translations.push((language, charset));
         self
     }
 
-    /// Set the embedded manifest file
+    /// Replace the language and charset pairs of the `Translation` value
     ///
-    /// # Example
+    /// The pairs are written as given, followed by the languages without an explicit pair.
+    /// An empty list restores the default, see [`translations()`].
     ///
-    /// The following manifest will brand the exe as requesting administrator privileges.
-    /// Thus, everytime it is executed, a Windows UAC dialog will appear.
+    /// ```rust,no_run
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_language(0x0409)
+    ///    .set_translation(&[(0x0409, 0x04b0), (0x0407, 0x04e4)]);
+    /// ```
+    ///
+    /// [`translations()`]: #method.translations
+    pub fn set_translation(&mut self, translations: &[(u16, u16)]) -> &mut Self {
+        self.translations = translations.to_vec();
+        self
+    }
+
+    /// The language and charset pairs of the version info
+    ///
+    /// These are the pairs of [`add_translation()`], followed by the language of the file
+    /// and the languages of [`set_localized()`] that have no explicit pair, each with the
+    /// charset of [`set_charset()`]. Every pair is written both as a `StringFileInfo` block
+    /// and as an entry of the `Translation` value, so the two always match.
     ///
     /// ```rust
     /// let mut res = winres::WindowsResource::new();
-    /// res.set_manifest(r#"
-    /// <assembly xmlns="urn:schemas-microsoft-com:asm.v1" manifestVersion="1.0">
-    /// <trustInfo xmlns="urn:schemas-microsoft-com:asm.v3">
-    ///     <security>
-    ///         <requestedPrivileges>
-    ///             <requestedExecutionLevel level="requireAdministrator" uiAccess="false" />
-    ///         </requestedPrivileges>
-    ///     </security>
-    /// </trustInfo>
-    /// </assembly>
-    /// "#);
+    /// res.set_language(0x0409)
+    ///    .set_localized("ProductName", 0x0407, "Rechner")
+    ///    .add_translation(0x0407, 0x04e4);
+    /// assert_eq!(res.translations(), [(0x0407, 0x04e4), (0x0409, 0x04b0)]);
+    /// let rc = res.to_rc_string().unwrap();
+    /// assert!(rc.contains("BLOCK \"040704e4\""));
+    /// assert!(rc.contains("VALUE \"Translation\", 0x407, 0x04e4, 0x409, 0x04b0"));
     /// ```
-    pub fn set_manifest<'a>(&mut self, manifest: &'a str) -> &mut Self {
-        self.manifest_file = None;
-        self.manifest = Some(manifest.to_string());
-        self
+    ///
+    /// [`add_translation()`]: #method.add_translation
+    /// [`set_localized()`]: #method.set_localized
+    /// [`set_charset()`]: #method.set_charset
+    pub fn translations(&self) -> Vec<(u16, u16)> {
+        let mut translations: Vec<(u16, u16)> = vec![];
+        for &pair in self.translations.iter() {
+            if !translations.contains(&pair) {
+                translations.push(pair);
+            }
+        }
+        for language in self.string_file_info_languages() {
+            if !translations.iter().any(|&(lang, _)| lang == language) {
+                translations.push((language, self.charset));
+            }
+        }
+        translations
     }
 
-    /// Some as [`set_manifest()`] but a filename can be provided and
-    /// file is included by the resource compieler itself.
-    /// This method works the same way as [`set_icon()`]
+    /// Set the charset of the `StringFileInfo` blocks
     ///
-    /// [`set_manifest()`]: #method.set_manifest
-    /// [`set_icon()`]: #method.set_icon
-    pub fn set_manifest_file<'a>(&mut self, file: &'a str) -> &mut Self {
-        self.manifest_file = Some(file.to_string());
-        self.manifest = None;
+    /// The default is the Unicode charset `0x04b0` (1200). The charset is part of the
+    /// block names, e.g. `040904b0`, and of the default `Translation` value.
+    /// Some installers expect a specific pair like `0x0409, 0x04e4` (Windows-1252).
+    pub fn set_charset(&mut self, charset: u16) -> &mut Self {
+        self.charset = charset;
         self
     }
 
-    /// Set the path to the windres executable.
-    pub fn set_windres_path(&mut self, path: &str) -> &mut Self {
-        self.windres_path = Some(path.to_string());
+    /// Enable or disable writing the `VarFileInfo` block
+    ///
+    /// The block is written by default. Some legacy tools expect it to be absent.
+    pub fn set_var_file_info(&mut self, enable: bool) -> &mut Self {
+        self.var_file_info = enable;
         self
     }
 
-    /// Set the path to the ar executable.
-    pub fn set_ar_path(&mut self, path: &str) -> &mut Self {
-        self.ar_path = Some(path.to_string());
+    /// Leave the `VERSIONINFO` block out of the resource script
+    ///
+    /// Useful to embed only an icon or a manifest, e.g. into test helper binaries, without
+    /// filling their file properties with the cargo defaults. The block is also left out
+    /// if no version info field and no property is set. This does not affect
+    /// [`write_version_info_file()`], which writes nothing but the version info.
+    ///
+    /// ```rust
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_manifest("<assembly/>").skip_version_info(true);
+    /// assert!(!res.to_rc_string().unwrap().contains("VERSIONINFO"));
+    /// ```
+    ///
+    /// [`write_version_info_file()`]: #method.write_version_info_file
+    pub fn skip_version_info(&mut self, skip: bool) -> &mut Self {
+        self.skip_version_info = skip;
         self
     }
 
-    /// Write a resource file with the set values
-    pub fn write_resource_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
-        let mut f = try!(fs::File::create(path));
-        // we don't need to include this, we use constants instead of macro names
-        // try!(write!(f, "#include <winver.h>\n"));
-
-        // use UTF8 as an encoding
-        // this makes it easier since in rust all string are UTF8
-        writeln!(f, "#pragma code_page(65001)")?;
-        writeln!(f, "1 VERSIONINFO")?;
-        for (k, v) in self.version_info.iter() {
-            match *k {
-                VersionInfo::FILEVERSION |
-                VersionInfo::PRODUCTVERSION => {
-                    writeln!(f,
-                                  "{:?} {}, {}, {}, {}",
-                                  k,
-                                  (*v >> 48) as u16,
-                                  (*v >> 32) as u16,
-                                  (*v >> 16) as u16,
-                                  *v as u16)?
-                }
-                _ => writeln!(f, "{:?} {:#x}", k, v)?,
-            };
-        }
-        writeln!(f, "{{\nBLOCK \"StringFileInfo\"")?;
-        writeln!(f, "{{\nBLOCK \"{:04x}04b0\"\n{{", self.language)?;
-        for (k, v) in self.properties.iter() {
-            if !v.is_empty() {
-                writeln!(f, "VALUE \"{}\", \"{}\"",
-                         escape_string(k), escape_string(v))?;
-            }
-        }
-        writeln!(f, "}}\n}}")?;
-
-        writeln!(f, "BLOCK \"VarFileInfo\" {{")?;
-        writeln!(f, "VALUE \"Translation\", {:#x}, 0x04b0", self.language)?;
-        writeln!(f, "}}\n}}")?;
-        if let Some(ref icon) = self.icon {
-            let name_id = self.icon_id.as_ref().map(String::as_str).unwrap_or("1");
-            writeln!(f, "{} ICON \"{}\"", escape_string(name_id), escape_string(icon))?;
-        }
-        if let Some(e) = self.version_info.get(&VersionInfo::FILETYPE) {
-            if let Some(manf) = self.manifest.as_ref() {
-                writeln!(f, "{} 24", e)?;
-                writeln!(f, "{{")?;
-                for line in manf.lines() {
-                    writeln!(f, "\"{}\"", escape_string(line.trim()))?;
-                }
-                writeln!(f, "}}")?;
-            } else if let Some(manf) = self.manifest_file.as_ref() {
-                writeln!(f, "{} 24 \"{}\"", e, escape_string(manf))?;
-            }
-        }
-        Ok(())
+    /// Set an icon filename
+    ///
+    /// This icon need to be in `ico` format. The filename can be absolute
+    /// or relative to the projects root. Compiling fails with [`Error::IconNotFound`]
+    /// if the file does not exist.
+    ///
+    /// [`Error::IconNotFound`]: enum.Error.html#variant.IconNotFound
+    pub fn set_icon<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.icon = Some(path.as_ref().to_path_buf());
+        self
     }
 
-    /// Set a path to an already existing resource file.
+    /// Set an icon filename and icon id, a number like `1` or a name like `"APP"`
     ///
-    /// We will neither modify this file nor parse its contents. This function
-    /// simply replaces the internaly generated resource file that is passed to
-    /// the compiler. You can use this function to write a resource file yourself.
-    pub fn set_resource_file<'a>(&mut self, path: &'a str) -> &mut Self {
-        self.rc_file = Some(path.to_string());
+    /// This icon need to be in `ico` format. The filename can be absolute
+    /// or relative to the projects root.
+    pub fn set_icon_with_id<P: AsRef<Path>, I: Into<ResourceId>>(&mut self, path: P, icon_id: I) -> &mut Self {
+        self.icon = Some(path.as_ref().to_path_buf());
+        self.icon_id = Some(icon_id.into());
         self
     }
 
-    /// Override the output directoy.
+    /// Add an icon variant, e.g. for visually distinct nightly or beta builds
     ///
-    /// As a default, we use `%OUT_DIR%` set by cargo, but it may be necessary to override the
-    /// the setting.
-    pub fn set_output_directory<'a>(&mut self, path: &'a str) -> &mut Self {
-        self.output_directory = path.to_string();
-        self
-    }
-
-    #[cfg(target_env = "gnu")]
-    fn compile_with_toolkit<'a>(&self, input: &'a str, output_dir: &'a str) -> io::Result<()> {
-        let output = PathBuf::from(output_dir).join("resource.o");
-        let input = PathBuf::from(input);
-        let windres_path = self.windres_path.as_ref().map_or("windres.exe", String::as_str);
-        let status = process::Command::new(windres_path)
-            .current_dir(&self.toolkit_path)
-            .arg(format!("-I{}", env::var("CARGO_MANIFEST_DIR").expect("env")))
-            .arg(format!("{}", input.display()))
-            .arg(format!("{}", output.display()))
-            .status()?;
-        if !status.success() {
-            return Err(io::Error::new(io::ErrorKind::Other, "Could not compile resource file"));
-        }
-
-        let libname = PathBuf::from(output_dir).join("libresource.a");
-        let ar_path = self.ar_path.as_ref().map_or("ar.exe", String::as_str);
-        let status = process::Command::new(ar_path)
-            .current_dir(&self.toolkit_path)
-            .arg("rsc")
-            .arg(format!("{}", libname.display()))
-            .arg(format!("{}", output.display()))
-            .status()?;
-        if !status.success() {
-            return Err(io::Error::new(io::ErrorKind::Other,
-                                      "Could not create static library for resource file"));
-        }
-
-        println!("cargo:rustc-link-search=native={}", output_dir);
-        println!("cargo:rustc-link-lib=static={}", "resource");
-
-        Ok(())
+    /// The variant replaces the icon set by [`set_icon()`] when it is selected by
+    /// [`set_icon_variant()`] or the `WINRES_ICON_VARIANT` environment variable.
+    /// The filename can be absolute or relative to the projects root.
+    ///
+    /// ```rust,no_run
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_icon("icon.ico")
+    ///    .add_icon_variant("nightly", "icon-nightly.ico")
+    ///    .add_icon_variant("beta", "icon-beta.ico");
+    /// ```
+    ///
+    /// [`set_icon()`]: #method.set_icon
+    /// [`set_icon_variant()`]: #method.set_icon_variant
+    pub fn add_icon_variant<P: AsRef<Path>>(&mut self, name: &str, path: P) -> &mut Self {
+        self.icon_variants.push((name.to_string(), path.as_ref().to_path_buf()));
+        self
     }
 
-    /// Run the resource compiler
+    /// Assemble the icon from PNG images, e.g. `&["icon-16.png", "icon-32.png", "icon-256.png"]`
     ///
-    /// This function generates a resource file from the settings or
-    /// uses an existing resource file and passes it to the resource compiler
-    /// of your toolkit.
+    /// The images are written to `icon.ico` in the output directory, which is used like
+    /// [`set_icon()`]. The filenames can be absolute or relative to the projects root.
+    /// Only available with the `png-icons` feature.
     ///
-    /// Further more we will print the correct statements for
-    /// `cargo:rustc-link-lib=` and `cargo:rustc-link-search` on the console,
-    /// so that the cargo build script can link the compiled resource file.
-    pub fn compile(&self) -> io::Result<()> {
-        let output = PathBuf::from(&self.output_directory);
-        let rc = output.join("resource.rc");
-        if self.rc_file.is_none() {
-            self.write_resource_file(&rc)?;
+    /// [`set_icon()`]: #method.set_icon
+    #[cfg(feature = "png-icons")]
+    pub fn icon_from_pngs<P: AsRef<Path>>(&mut self, pngs: &[P]) -> Result<&mut Self, Error> {
+        let mut images = vec![];
+        for png in pngs {
+            images.push(fs::read(self.manifest_dir.join(png))?);
         }
-        let rc = if let Some(s) = self.rc_file.as_ref() {
-            s.clone()
-        } else {
-            rc.to_str().ok_or_else(|| io::Error::new(io::ErrorKind::Other, "utf8 decode"))?.to_string()
-        };
-        self.compile_with_toolkit(rc.as_str(), &self.output_directory)?;
+        let icon = self.output_dir()?.join("icon.ico");
+        let file = ico::from_pngs(&images)
+            .map_err(|e| Error::InvalidIcon { path: icon.clone(), message: e.to_string() })?;
+        fs::write(&icon, file)?;
+        Ok(self.set_icon(icon))
+    }
 
-        Ok(())
+    /// Add another icon with a numeric id, e.g. for `LoadIcon(hinstance, MAKEINTRESOURCE(101))`
+    ///
+    /// The filename can be absolute or relative to the projects root. Use
+    /// [`write_resource_ids_rs()`] to share the ids with the application code.
+    ///
+    /// [`write_resource_ids_rs()`]: #method.write_resource_ids_rs
+    pub fn add_icon_with_numeric_id<P: AsRef<Path>>(&mut self, path: P, id: u16) -> &mut Self {
+        self.add_icon(path, id)
     }
 
-    pub fn tool_path<'a>(&'a self) -> io::Result<&'a Path> {
-        Ok(&self.tool.path)
+    /// Add another icon with a numeric or named id, e.g. `101` or `"DOCUMENT"`
+    ///
+    /// The filename can be absolute or relative to the projects root.
+    pub fn add_icon<P: AsRef<Path>, I: Into<ResourceId>>(&mut self, path: P, id: I) -> &mut Self {
+        self.icons.push((id.into(), path.as_ref().to_path_buf()));
+        self
     }
 
-    pub fn include_dirs<'a>(&'a self) -> Vec<&Path> {
-        self.tool.include_dirs.values().map(PathBuf::as_path).collect()
+    /// Add a bitmap as `BITMAP` resource, with a numeric or named id
+    ///
+    /// The filename can be absolute or relative to the projects root.
+    pub fn add_bitmap<P: AsRef<Path>, I: Into<ResourceId>>(&mut self, path: P, id: I) -> &mut Self {
+        self.bitmaps.push((id.into(), path.as_ref().to_path_buf()));
+        self
     }
 
-    #[cfg(target_env = "msvc")]
-    fn compile_with_toolkit<'a>(&self, input: &'a str, output_dir: &'a str) -> io::Result<()> {
-        let rc_exe = self.tool_path()?;
+    /// Add a file as `RCDATA` resource, with a numeric or named id, e.g. a default configuration
+    ///
+    /// The filename can be absolute or relative to the projects root.
+    pub fn add_rcdata<P: AsRef<Path>, I: Into<ResourceId>>(&mut self, path: P, id: I) -> &mut Self {
+        self.rcdata.push((id.into(), path.as_ref().to_path_buf()));
+        self
+    }
 
-        let output = PathBuf::from(output_dir).join("resource.lib");
-        let input = PathBuf::from(input);
+    /// Add a file as resource of a type winres does not model, e.g. presets or game data
+    ///
+    /// `type_name` is a name like `"MYTYPE"` or a number above 255 like `"300"`, it must not
+    /// be one of the predefined types like `RCDATA`. The file is written as
+    /// `CONFIG MYTYPE "file.bin"` and loaded with `FindResource(hinstance, "CONFIG", "MYTYPE")`.
+    /// The filename can be absolute or relative to the projects root.
+    ///
+    /// ```rust,no_run
+    /// let mut res = winres::WindowsResource::new();
+    /// res.add_custom_resource("PRESET", "DEFAULT", "presets/default.bin")
+    ///    .add_custom_resource("PRESET", 2, "presets/warm.bin");
+    /// ```
+    pub fn add_custom_resource<I: Into<ResourceId>, P: AsRef<Path>>(&mut self, type_name: &str, id: I, path: P)
+                                                                   -> &mut Self {
+        self.custom_resources.push((type_name.to_string(), id.into(), path.as_ref().to_path_buf()));
+        self
+    }
 
-        let mut args = vec![];
-        args.push(format!("/I{}", env::var("CARGO_MANIFEST_DIR").expect("env")));
-        
-        for inc in self.include_dirs() {
-            args.push(format!("/I{}", inc.to_str().ok_or_else(||
-                    io::Error::new(io::ErrorKind::Other, "unicode serialisation"))?));
-        }
+    /// Add a string to the `STRINGTABLE`, for `LoadString(hinstance, id, ..)`
+    ///
+    /// `name` is the name of the id in [`write_resource_ids_rs()`], e.g. `"IDS_GREETING"`.
+    ///
+    /// [`write_resource_ids_rs()`]: #method.write_resource_ids_rs
+    pub fn add_string(&mut self, name: &str, id: u16, value: &str) -> &mut Self {
+        self.strings.push((name.to_string(), id, value.to_string()));
+        self
+    }
 
-        args.push(format!("/fo{}", output.display()));
-        args.push(format!("{}", input.display()));
+    /// Set the value of the string `id` of [`add_string()`] for `language`
+    ///
+    /// The localized strings are compiled into the satellite DLLs of
+    /// [`compile_satellite_dlls()`], the resource of the package keeps the values of
+    /// [`add_string()`].
+    ///
+    /// ```rust
+    /// let mut res = winres::WindowsResource::new();
+    /// res.add_string("IDS_GREETING", 1, "Hello")
+    ///    .set_localized_string(1, 0x0407, "Hallo");
+    /// ```
+    ///
+    /// [`add_string()`]: #method.add_string
+    /// [`compile_satellite_dlls()`]: #method.compile_satellite_dlls
+    pub fn set_localized_string(&mut self, id: u16, language: u16, value: &str) -> &mut Self {
+        self.localized_strings.retain(|&(known, lang, _)| known != id || lang != language);
+        self.localized_strings.push((id, language, value.to_string()));
+        self
+    }
 
-        let status = process::Command::new(rc_exe)
-            .args(&args)
-            .output()?;
-        
-        println!("RC Output:\n{}\n------", String::from_utf8_lossy(&status.stdout));
-        println!("RC Error:\n{}\n------", String::from_utf8_lossy(&status.stderr));
-        if !status.status.success() {
-            return Err(io::Error::new(io::ErrorKind::Other, "Could not compile resource file"));
+    /// The named numeric resource ids, for [`write_resource_ids_rs()`]
+    ///
+    /// File resources are named by their type followed by the file name, e.g. `ICON_APP` for
+    /// `app.ico`. The id is appended if two files have the same name. Resources with named
+    /// ids are left out.
+    ///
+    /// [`write_resource_ids_rs()`]: #method.write_resource_ids_rs
+    fn resource_ids(&self) -> Result<Vec<(String, u16)>, Error> {
+        let mut files = vec![];
+        if let (Some(icon), Some(id)) = (self.selected_icon()?, self.icon_id.as_ref().map_or(Some(1), ResourceId::number)) {
+            files.push(("ICON", id, icon));
+        }
+        for &(kind, resources) in [("ICON", &self.icons), ("BITMAP", &self.bitmaps), ("RCDATA", &self.rcdata)].iter() {
+            files.extend(resources.iter().filter_map(|(id, file)| id.number().map(|id| (kind, id, file.as_path()))));
         }
+        files.extend(self.custom_resources.iter()
+            .filter_map(|(kind, id, file)| id.number().map(|id| (kind.as_str(), id, file.as_path()))));
+        files.extend(self.fonts.iter().enumerate().map(|(i, font)| ("FONT", i as u16 + 1, font.as_path())));
+        files.extend(self.typelibs.iter().map(|(id, typelib)| ("TYPELIB", *id, typelib.as_path())));
+        let mut ids: Vec<(String, u16)> = vec![];
+        for (kind, id, file) in files {
+            let stem = file.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+            let mut name = format!("{}_{}", constant_name(kind), constant_name(&stem));
+            if ids.iter().any(|(other, _)| *other == name) {
+                name = format!("{}_{}", name, id);
+            }
+            ids.push((name, id));
+        }
+        ids.extend(self.strings.iter().map(|&(ref name, id, _)| (constant_name(name), id)));
+        Ok(ids)
+    }
 
-        println!("cargo:rustc-link-search=native={}", output_dir);
-        println!("cargo:rustc-link-lib=dylib={}", "resource");
+    /// Write a Rust module with a `pub const` for each numeric resource id
+    ///
+    /// The file is meant to be included by the application, so the ids are not
+    /// duplicated by hand:
+    ///
+    /// ```rust,no_run
+    /// // build.rs
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_icon("app.ico")
+    ///    .add_icon_with_numeric_id("document.ico", 101);
+    /// let out_dir = std::env::var("OUT_DIR").unwrap();
+    /// res.write_resource_ids_rs(std::path::Path::new(&out_dir).join("resource_ids.rs")).unwrap();
+    /// res.compile().unwrap();
+    /// ```
+    ///
+    /// ```text
+    /// // main.rs
+    /// include!(concat!(env!("OUT_DIR"), "/resource_ids.rs"));
+    /// // pub const ICON_APP: u16 = 1;
+    /// // pub const ICON_DOCUMENT: u16 = 101;
+    /// ```
+    pub fn write_resource_ids_rs<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let mut f = io::BufWriter::new(fs::File::create(path)?);
+        writeln!(f, "// Resource ids generated by winres")?;
+        for (name, id) in self.resource_ids()? {
+            writeln!(f, "pub const {}: u16 = {};", name, id)?;
+        }
+        f.flush()?;
         Ok(())
     }
 
-    #[cfg(not(any(target_env = "gnu", target_env = "msvc")))]
-    fn compile_with_toolkit<'a>(&self, _input: &'a str, _output_dir: &'a str) -> io::Result<()> {
-        Err(io::Error::new(io::ErrorKind::Other, "Can only compile resource file when target_env is \"gnu\" or \"msvc\""))
+    /// Write a Rust module with the resource ids of [`write_resource_ids_rs()`] and the
+    /// string properties of the version info
+    ///
+    /// The properties are `&str` constants named in upper snake case, e.g. `PRODUCT_NAME`
+    /// for `"ProductName"`, with the placeholders resolved.
+    ///
+    /// [`write_resource_ids_rs()`]: #method.write_resource_ids_rs
+    pub fn write_resources_rs<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let mut f = io::BufWriter::new(fs::File::create(path)?);
+        writeln!(f, "// Resource ids and version info generated by winres")?;
+        for (name, id) in self.resource_ids()? {
+            writeln!(f, "pub const {}: u16 = {};", name, id)?;
+        }
+        for (name, value) in self.properties.iter() {
+            writeln!(f, "pub const {}: &str = {:?};", constant_name(&snake_case(name)),
+                     self.resolve_placeholders(value)?)?;
+        }
+        f.flush()?;
+        Ok(())
     }
-}
 
-/// Find a Windows SDK
-fn get_sdk() -> io::Result<sdk::Tool> {
-    // use the reg command, so we don't need a winapi dependency
-    let system = sdk::System::new()?;
-    let env_version = env::var("WindowsSDKVersion").ok();
-    let arch = sdk::Arch::arch_for_cfg_target()
-        .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "unsupported target arch"))?;
-    let tools =  system.sdks.iter().filter_map(|sdk| sdk.tool("rc.exe", arch)).collect::<Vec<_>>();
+    /// Write `resources.rs` with [`write_resources_rs()`] to the output directory when compiling
+    ///
+    /// The application includes it with
+    /// `include!(concat!(env!("OUT_DIR"), "/resources.rs"));`.
+    ///
+    /// [`write_resources_rs()`]: #method.write_resources_rs
+    pub fn set_resources_rs(&mut self, enable: bool) -> &mut Self {
+        self.resources_rs = enable;
+        self
+    }
 
-    let max_version = tools.iter().max_by(|a,b| a.sdk_version.cmp(&b.sdk_version));
+    /// Check the icon before compiling
+    ///
+    /// This is enabled by default: compiling fails with [`Error::InvalidIcon`] if the icon
+    /// is not a valid `.ico` file, and emits a cargo warning if it has no 256×256 PNG image
+    /// or no 32 bit image with alpha channel, see [`ico::warnings()`].
+    ///
+    /// [`Error::InvalidIcon`]: enum.Error.html#variant.InvalidIcon
+    /// [`ico::warnings()`]: ico/fn.warnings.html
+    pub fn set_validate_icons(&mut self, validate: bool) -> &mut Self {
+        self.validate_icons = validate;
+        self
+    }
 
-    let tool = tools.iter().find(|tool| {
-        env_version.as_ref().map(|ev| ev == &tool.sdk_version).unwrap_or(false)
-    }).or_else(|| max_version);
+    /// Select the icon variant added with [`add_icon_variant()`]
+    ///
+    /// This overrides the `WINRES_ICON_VARIANT` environment variable.
+    /// Compiling fails if no variant with this name was added.
+    ///
+    /// [`add_icon_variant()`]: #method.add_icon_variant
+    pub fn set_icon_variant(&mut self, name: &str) -> &mut Self {
+        self.icon_variant = Some(name.to_string());
+        self
+    }
 
-    tool.ok_or_else(|| {
-        io::Error::new(io::ErrorKind::Other, format!("no rc.exe tool found for arch {} in {:?}", arch, system.installed_roots))
-    }).map(std::borrow::ToOwned::to_owned)
-}
+    /// The icon file of the selected variant, or the default icon
+    fn selected_icon(&self) -> Result<Option<&Path>, Error> {
+        match self.icon_variant {
+            Some(ref variant) => self.icon_variants.iter()
+                .find(|(name, _)| name == variant)
+                .map(|(_, path)| Some(path.as_path()))
+                .ok_or_else(|| Error::UnknownIconVariant(variant.clone())),
+            None => Ok(self.icon.as_deref()),
+        }
+    }
 
-fn parse_cargo_toml(props: &mut HashMap<String, String>) -> io::Result<()> {
-    let cargo = Path::new(&env::var("CARGO_MANIFEST_DIR").expect("env")).join("Cargo.toml");
-    let mut f = fs::File::open(cargo)?;
-    let mut cargo_toml = String::new();
-    f.read_to_string(&mut cargo_toml)?;
-    if let Ok(ml) = cargo_toml.parse::<toml::Value>() {
-        if let Some(pkg) = ml.get("package") {
-            if let Some(pkg) = pkg.get("metadata") {
-                if let Some(pkg) = pkg.get("winres") {
-                    if let Some(pkg) = pkg.as_table() {
-                        for (k, v) in pkg {
-                            // println!("{} {}", k ,v);
-                            if let Some(v) = v.as_str() {
-                                props.insert(k.clone(), v.to_string());
-                            } else {
-                                println!("package.metadata.winres.{} is not a string", k);
-                            }
-                        }
-                    } else {
-                        println!("package.metadata.winres is not a table");
-                    }
-                } else {
-                    println!("package.metadata.winres does not exist");
+    /// Add a font file to be embedded as a `FONT` resource
+    ///
+    /// The file can be a `.fnt` or `.ttf` font. Fonts are numbered in the order
+    /// they were added, starting at `1`. The filename can be absolute
+    /// or relative to the projects root.
+    pub fn add_font<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.fonts.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Embed information about the build as `RCDATA` resource
+    ///
+    /// The resource is named [`BUILD_INFO_RESOURCE`] and contains a TOML document with the
+    /// package name and version, the target, profile and enabled features, and the packages
+    /// locked in `Cargo.lock`, if one is found. This allows supply-chain inspection of
+    /// shipped binaries.
+    ///
+    /// [`BUILD_INFO_RESOURCE`]: constant.BUILD_INFO_RESOURCE.html
+    #[cfg(feature = "cargo-metadata")]
+    pub fn embed_build_info(&mut self) -> &mut Self {
+        self.build_info = true;
+        self
+    }
+
+    /// Write the build information for [`embed_build_info()`] to the output directory
+    ///
+    /// [`embed_build_info()`]: #method.embed_build_info
+    #[cfg(feature = "cargo-metadata")]
+    fn write_build_info(&self) -> Result<PathBuf, Error> {
+        let mut package = toml::value::Table::new();
+        for &(key, var) in &[("name", "CARGO_PKG_NAME"), ("version", "CARGO_PKG_VERSION")] {
+            if let Some(value) = self.env.var(var) {
+                package.insert(key.to_string(), toml::Value::String(value));
+            }
+        }
+
+        let mut features = self.env.vars().into_iter()
+            .filter_map(|(k, _)| k.strip_prefix("CARGO_FEATURE_").map(|f| f.to_lowercase().replace('_', "-")))
+            .collect::<Vec<_>>();
+        features.sort();
+        let mut build = toml::value::Table::new();
+        for &(key, var) in &[("target", "TARGET"), ("profile", "PROFILE")] {
+            if let Some(value) = self.env.var(var) {
+                build.insert(key.to_string(), toml::Value::String(value));
+            }
+        }
+        build.insert("features".to_string(),
+                     toml::Value::Array(features.into_iter().map(toml::Value::String).collect()));
+
+        let mut info = toml::value::Table::new();
+        info.insert("package".to_string(), toml::Value::Table(package));
+        info.insert("build".to_string(), toml::Value::Table(build));
+        // Cargo.lock lives in the workspace root, which may be any ancestor
+        let lock = self.manifest_dir.ancestors().map(|dir| dir.join("Cargo.lock")).find(|lock| lock.exists());
+        if let Some(lock) = lock {
+            let mut content = String::new();
+            fs::File::open(&lock)?.read_to_string(&mut content)?;
+            if let Ok(toml::Value::Table(mut lock)) = content.parse::<toml::Value>() {
+                if let Some(packages) = lock.remove("package") {
+                    info.insert("dependencies".to_string(), packages);
                 }
-            } else {
-                println!("package.metadata does not exist");
             }
-        } else {
-            println!("package does not exist");
         }
-    } else {
-        println!("TOML parsing error")
+
+        let path = self.output_dir()?.join("build-info.toml");
+        let content = toml::to_string(&toml::Value::Table(info))
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        fs::File::create(&path)?.write_all(content.as_bytes())?;
+        Ok(path)
     }
-    Ok(())
-}
 
-pub(crate) fn escape_string(string: &str) -> String {
-    let mut escaped = String::new();
-    for chr in string.chars() {
-        // In quoted RC strings, double-quotes are escaped by using two
-        // consecutive double-quotes.  Other characters are escaped in the
-        // usual C way using backslashes.
-        match chr {
-            '"' => escaped.push_str("\"\""),
-            '\'' => escaped.push_str("\\'"),
-            '\\' => escaped.push_str("\\\\"),
-            '\n' => escaped.push_str("\\n"),
-            '\t' => escaped.push_str("\\t"),
-            '\r' => escaped.push_str("\\r"),
-            _ => escaped.push(chr),
+    /// Limit the total size of the embedded files
+    ///
+    /// The icon, fonts, type libraries, build info and manifest file are counted, so an
+    /// accidentally embedded large asset is caught at build time. Depending on `policy`,
+    /// exceeding `max_bytes` prints a warning or fails [`compile()`].
+    ///
+    /// [`compile()`]: #method.compile
+    pub fn set_size_budget(&mut self, max_bytes: u64, policy: SizeBudgetPolicy) -> &mut Self {
+        self.size_budget = Some((max_bytes, policy));
+        self
+    }
+
+    /// Total size of the files embedded by the resource file, missing files are skipped
+    ///
+    /// The build info has to be written already.
+    fn embedded_size(&self, merged_manifest: Option<&Path>) -> Result<u64, Error> {
+        let mut files = vec![];
+        if let Some(icon) = self.selected_icon()? {
+            files.push(self.manifest_dir.join(icon));
+        }
+        files.extend(self.icons.iter().map(|(_, icon)| self.manifest_dir.join(icon)));
+        files.extend(self.bitmaps.iter().chain(self.rcdata.iter()).map(|(_, file)| self.manifest_dir.join(file)));
+        files.extend(self.custom_resources.iter().map(|(_, _, file)| self.manifest_dir.join(file)));
+        files.extend(self.fonts.iter().map(|font| self.manifest_dir.join(font)));
+        files.extend(self.typelibs.iter().map(|(_, typelib)| self.manifest_dir.join(typelib)));
+        if self.build_info {
+            files.push(self.output_dir()?.join("build-info.toml"));
+        }
+        let manifest = merged_manifest.map(Path::to_path_buf)
+            .or_else(|| self.manifest_file.as_ref().map(|manifest| self.manifest_dir.join(manifest)));
+        if self.manifest_mode != ManifestMode::External {
+            files.extend(manifest);
+        }
+        Ok(files.iter().filter_map(|file| fs::metadata(file).ok()).map(|m| m.len()).sum())
+    }
+
+    /// The files the resource is compiled from, with `Cargo.toml` and `build.rs` of the package
+    ///
+    /// These are printed as `cargo:rerun-if-changed` once another `rerun-if` directive turned
+    /// off the default of rerunning the build script on any change of the package. Files that
+    /// don't exist are left out, since cargo would rerun the build script on every build.
+    fn input_files(&self) -> Result<Vec<PathBuf>, Error> {
+        let mut files = vec![self.manifest_dir.join("Cargo.toml"), self.manifest_dir.join("build.rs")];
+        #[cfg(feature = "cargo-metadata")]
+        files.extend(self.metadata_file.iter().cloned());
+        files.extend(self.rc_file.iter().chain(self.rc_template.iter()).map(|rc| self.manifest_dir.join(rc)));
+        files.extend(self.res_files.iter().map(|res| self.manifest_dir.join(res)));
+        files.extend(self.selected_icon()?.map(|icon| self.manifest_dir.join(icon)));
+        files.extend(self.icons.iter().map(|(_, icon)| self.manifest_dir.join(icon)));
+        files.extend(self.bitmaps.iter().chain(self.rcdata.iter()).map(|(_, file)| self.manifest_dir.join(file)));
+        files.extend(self.custom_resources.iter().map(|(_, _, file)| self.manifest_dir.join(file)));
+        files.extend(self.fonts.iter().map(|font| self.manifest_dir.join(font)));
+        files.extend(self.typelibs.iter().map(|(_, typelib)| self.manifest_dir.join(typelib)));
+        files.extend(self.include_rcs.iter().map(|rc| self.manifest_dir.join(rc)));
+        files.extend(self.manifest_file.iter().chain(self.merge_manifests.iter()).map(|m| self.manifest_dir.join(m)));
+        let mut inputs: Vec<PathBuf> = vec![];
+        for file in files {
+            if file.exists() && !inputs.contains(&file) {
+                inputs.push(file);
+            }
+        }
+        Ok(inputs)
+    }
+
+    /// Check that the icon, the manifest file and the custom resource file can be read
+    ///
+    /// Relative paths are resolved against the package directory, which is also the
+    /// working directory of build scripts.
+    fn check_input_files(&self, directives: &mut Vec<String>) -> Result<(), Error> {
+        fn check(path: PathBuf, not_found: fn(PathBuf) -> Error) -> Result<(), Error> {
+            match fs::File::open(&path) {
+                Ok(_) => Ok(()),
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => Err(not_found(path)),
+                Err(e) => Err(Error::Io(e)),
+            }
+        }
+        for res in self.res_files.iter() {
+            check(self.manifest_dir.join(res), Error::ResourceFileNotFound)?;
+        }
+        if let Some(rc) = self.rc_file.as_ref().or(self.rc_template.as_ref()) {
+            // the builder settings are not used with a custom resource file
+            return check(self.manifest_dir.join(rc), Error::ResourceFileNotFound);
+        }
+        let icons = self.selected_icon()?.into_iter().chain(self.icons.iter().map(|(_, icon)| icon.as_path()));
+        for icon in icons {
+            let icon = self.manifest_dir.join(icon);
+            check(icon.clone(), Error::IconNotFound)?;
+            if self.validate_icons {
+                let images = ico::parse(&fs::read(&icon)?)
+                    .map_err(|e| Error::InvalidIcon { path: icon.clone(), message: e.to_string() })?;
+                for warning in ico::warnings(&images) {
+                    self.warn(directives, &format!("icon {} {}", icon.display(), warning));
+                }
+            }
+        }
+        if let Some(ref manifest) = self.manifest_file {
+            check(self.manifest_dir.join(manifest), Error::ManifestNotFound)?;
+        }
+        for manifest in self.merge_manifests.iter() {
+            check(self.manifest_dir.join(manifest), Error::ManifestNotFound)?;
+        }
+        Ok(())
+    }
+
+    /// Check the embedded files against the budget of [`set_size_budget()`]
+    ///
+    /// [`set_size_budget()`]: #method.set_size_budget
+    fn check_size_budget(&self, merged_manifest: Option<&Path>, directives: &mut Vec<String>) -> Result<(), Error> {
+        if let Some((budget, policy)) = self.size_budget {
+            let size = self.embedded_size(merged_manifest)?;
+            if size > budget {
+                match policy {
+                    SizeBudgetPolicy::Warn =>
+                        self.warn(directives, &format!("embedded resources are {} bytes, exceeding the budget of {} bytes",
+                                                       size, budget)),
+                    SizeBudgetPolicy::Fail => return Err(Error::SizeBudgetExceeded { size, budget }),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a type library to be embedded as a `TYPELIB` resource
+    ///
+    /// COM servers usually embed the `.tlb` produced by `midl` with id `1`, so that
+    /// `LoadTypeLib` can find it in the module. The filename can be absolute
+    /// or relative to the projects root.
+    pub fn add_typelib<P: AsRef<Path>>(&mut self, id: u16, path: P) -> &mut Self {
+        self.typelibs.push((id, path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Include an existing resource script, e.g. with dialogs or menus, into the generated one
+    ///
+    /// The file is included with `#include` after the generated resources, and its
+    /// directory is passed to the resource compiler as include path, so headers next to
+    /// it like `resource.h` are found. The filename can be absolute or relative to the
+    /// projects root.
+    pub fn add_include_rc<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.include_rcs.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Add an include directory for the resource compiler
+    ///
+    /// Files referenced from the resource script, like icons, manifests or headers of a
+    /// custom resource file, are also looked up in it. The path can be absolute or
+    /// relative to the projects root.
+    pub fn add_include_dir<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.include_dirs.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Define a preprocessor macro for the resource script
+    ///
+    /// The macro is passed to the resource compiler (`/d` for `rc.exe`, `-D` for `windres`
+    /// and `llvm-rc`) and written as `#define` at the top of the generated script, so
+    /// included resource scripts can use it for conditional resources. An empty `value`
+    /// defines the macro without a value.
+    ///
+    /// ```rust,no_run
+    /// let mut res = winres::WindowsResource::new();
+    /// if std::env::var("PROFILE").unwrap() == "debug" {
+    ///     res.define("DEBUG_ICON", "1");
+    /// }
+    /// ```
+    pub fn define(&mut self, name: &str, value: &str) -> &mut Self {
+        self.defines.retain(|(defined, _)| defined != name);
+        self.defines.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// The macros of [`define()`] as command line arguments, e.g. `NAME=VALUE`
+    ///
+    /// [`define()`]: #method.define
+    fn define_args(&self) -> Vec<String> {
+        self.defines.iter()
+            .map(|(name, value)| if value.is_empty() { name.clone() } else { format!("{}={}", name, value) })
+            .collect()
+    }
+
+    /// Pass an additional argument to the resource compiler
+    ///
+    /// The argument is passed as is to `rc.exe`, `windres` or `llvm-rc`, whichever
+    /// compiles the resource, before the input and output files. Use it for options
+    /// winres does not model, e.g. `/n` or `--use-temp-file`.
+    pub fn add_toolkit_arg(&mut self, arg: &str) -> &mut Self {
+        self.toolkit_args.push(arg.to_string());
+        self
+    }
+
+    /// Append raw statements to the generated resource script
+    ///
+    /// Use this for resources winres does not model, like dialogs, menus or custom
+    /// resource types. The content is written after the generated resources, so it must
+    /// not redefine them. It can be called multiple times; each call starts a new line.
+    ///
+    /// ```rust,no_run
+    /// let mut res = winres::WindowsResource::new();
+    /// res.append_rc_content(r#"101 RCDATA "assets/config.json""#);
+    /// ```
+    pub fn append_rc_content(&mut self, content: &str) -> &mut Self {
+        self.rc_content.push_str(content);
+        if !content.ends_with('\n') {
+            self.rc_content.push('\n');
+        }
+        self
+    }
+
+    /// Set the kind of binary the resource is linked into
+    ///
+    /// This sets `FILETYPE` and `FILESUBTYPE` of the version info and the id of the manifest
+    /// resource, so drivers only need to pick their [`DriverSubtype`]. `FILEOS` is set too,
+    /// unless it was changed, e.g. with [`set_file_os()`]:
+    ///
+    /// ```rust
+    /// use winres::{BinaryType, DriverSubtype};
+    ///
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_binary_type(BinaryType::Driver(DriverSubtype::Network));
+    /// ```
+    ///
+    /// By default it is [`BinaryType::Dll`] if the package only builds a
+    /// `cdylib` or `dylib`, and [`BinaryType::Exe`] otherwise.
+    ///
+    /// [`BinaryType::Dll`]: enum.BinaryType.html#variant.Dll
+    /// [`BinaryType::Exe`]: enum.BinaryType.html#variant.Exe
+    /// [`DriverSubtype`]: enum.DriverSubtype.html
+    /// [`set_file_os()`]: #method.set_file_os
+    pub fn set_binary_type(&mut self, binary_type: BinaryType) -> &mut Self {
+        self.binary_type = binary_type;
+        if self.version_info.get(&VersionInfo::FILEOS) == self.default_version_info.get(&VersionInfo::FILEOS) {
+            self.version_info.insert(VersionInfo::FILEOS, binary_type.file_os().into());
+        }
+        self.version_info.insert(VersionInfo::FILETYPE, binary_type.file_type());
+        self.version_info.insert(VersionInfo::FILESUBTYPE, binary_type.file_subtype());
+        self
+    }
+
+    /// Set the `FILEOS` of the version info to one of the [`FileOs`] values
+    ///
+    /// Windows defines no value for 64-bit binaries, so Windows' own x64 and ARM64 binaries
+    /// declare `VOS_NT_WINDOWS32` as well, which is the default for every target. The
+    /// binary type of [`set_binary_type()`] resets it.
+    ///
+    /// ```rust
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_file_os(winres::FileOs::NT);
+    /// ```
+    ///
+    /// [`FileOs`]: struct.FileOs.html
+    /// [`set_binary_type()`]: #method.set_binary_type
+    pub fn set_file_os(&mut self, file_os: FileOs) -> &mut Self {
+        self.version_info.insert(VersionInfo::FILEOS, file_os.into());
+        self
+    }
+
+    /// Apply the settings of a [`Preset`] for a common kind of application
+    ///
+    /// This sets the [`set_binary_type()`] and uses the manifest helpers, so the settings
+    /// can be adjusted afterwards:
+    ///
+    /// ```rust
+    /// use winres::Preset;
+    ///
+    /// let mut res = winres::WindowsResource::new();
+    /// res.preset(Preset::GuiApp)
+    ///     .set_long_path_aware(true);
+    /// ```
+    ///
+    /// A manifest set by [`set_manifest()`] or [`set_manifest_file()`] is replaced, except
+    /// for [`Preset::Dll`], which does not touch the manifest.
+    ///
+    /// [`Preset`]: enum.Preset.html
+    /// [`Preset::Dll`]: enum.Preset.html#variant.Dll
+    /// [`set_binary_type()`]: #method.set_binary_type
+    /// [`set_manifest()`]: #method.set_manifest
+    /// [`set_manifest_file()`]: #method.set_manifest_file
+    pub fn preset(&mut self, preset: Preset) -> &mut Self {
+        match preset {
+            Preset::GuiApp => {
+                self.set_binary_type(BinaryType::Exe)
+                    .set_requested_execution_level(manifest::ExecutionLevel::AsInvoker, false)
+                    .set_dpi_awareness(manifest::DpiAwareness::PerMonitorV2)
+                    .enable_visual_styles();
+            }
+            Preset::ConsoleApp => {
+                self.set_binary_type(BinaryType::Exe)
+                    .set_requested_execution_level(manifest::ExecutionLevel::AsInvoker, false);
+            }
+            Preset::Service => {
+                self.set_binary_type(BinaryType::Exe)
+                    .set_requested_execution_level(manifest::ExecutionLevel::AsInvoker, false)
+                    .set_supported_os(&[manifest::SupportedOs::Windows10])
+                    .set_long_path_aware(true);
+            }
+            Preset::Dll => {
+                self.set_binary_type(BinaryType::Dll);
+            }
+        }
+        self
+    }
+
+    /// Set the id of the manifest resource
+    ///
+    /// The default depends on the [`set_binary_type()`], `CREATEPROCESS_MANIFEST_RESOURCE_ID (1)`
+    /// for executables and `ISOLATIONAWARE_MANIFEST_RESOURCE_ID (2)` for DLLs. It does not
+    /// change with the `FILETYPE` of the version info.
+    ///
+    /// [`set_binary_type()`]: #method.set_binary_type
+    pub fn set_manifest_resource_id<I: Into<ResourceId>>(&mut self, id: I) -> &mut Self {
+        self.manifest_resource_id = Some(id.into());
+        self
+    }
+
+    /// Set whether the manifest is embedded, written to a file beside the binary, or both
+    ///
+    /// Some deployments need `app.exe.manifest` next to the executable instead of the
+    /// embedded resource. With [`ManifestMode::External`] the manifest is not embedded, and
+    /// the generated or merged manifest, or the manifest file, is written to
+    /// [`set_external_manifest_path()`].
+    ///
+    /// ```rust,no_run
+    /// use winres::ManifestMode;
+    ///
+    /// let mut res = winres::WindowsResource::new();
+    /// res.enable_visual_styles()
+    ///     .set_manifest_mode(ManifestMode::External);
+    /// ```
+    ///
+    /// [`ManifestMode::External`]: enum.ManifestMode.html#variant.External
+    /// [`set_external_manifest_path()`]: #method.set_external_manifest_path
+    pub fn set_manifest_mode(&mut self, mode: ManifestMode) -> &mut Self {
+        self.manifest_mode = mode;
+        self
+    }
+
+    /// Set the path of the manifest file of [`ManifestMode::External`] and [`ManifestMode::Both`]
+    ///
+    /// By default it is the `OriginalFilename` with `.manifest` appended, in the profile
+    /// directory of the target directory, e.g. `target/release/app.exe.manifest`, where
+    /// cargo places the binary.
+    ///
+    /// [`ManifestMode::External`]: enum.ManifestMode.html#variant.External
+    /// [`ManifestMode::Both`]: enum.ManifestMode.html#variant.Both
+    pub fn set_external_manifest_path<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.external_manifest_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// The path of the external manifest file, see [`set_external_manifest_path()`]
+    ///
+    /// [`set_external_manifest_path()`]: #method.set_external_manifest_path
+    fn external_manifest_path(&self) -> Result<PathBuf, Error> {
+        if let Some(ref path) = self.external_manifest_path {
+            return Ok(self.manifest_dir.join(path));
+        }
+        // OUT_DIR is <target>/<profile>/build/<package>-<hash>/out
+        let dir = match self.env.var("OUT_DIR") {
+            Some(out) => Path::new(&out).ancestors().nth(3).map(Path::to_path_buf),
+            None => None,
+        };
+        let dir = match dir {
+            Some(dir) => dir,
+            None => self.output_dir()?,
         };
+        let name = self.properties.get("OriginalFilename").cloned()
+            .unwrap_or_else(|| original_filename(&self.package_name, self.binary_type));
+        Ok(dir.join(format!("{}.manifest", name)))
     }
-    escaped
-}
 
-#[cfg(test)]
-mod tests {
-    use super::escape_string;
-    use super::get_sdk;
+    /// Write the manifest to [`external_manifest_path()`], preferring `merged_manifest`
+    ///
+    /// [`external_manifest_path()`]: #method.external_manifest_path
+    fn write_external_manifest(&self, merged_manifest: Option<&Path>) -> Result<(), Error> {
+        let xml = match (merged_manifest, self.manifest_xml(), self.manifest_file.as_ref()) {
+            (Some(merged), _, _) => fs::read(merged)?,
+            (None, Some(xml), _) => xml.into_bytes(),
+            (None, None, Some(file)) => fs::read(self.manifest_dir.join(file))?,
+            (None, None, None) => return Ok(()),
+        };
+        let path = self.external_manifest_path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, xml)?;
+        Ok(())
+    }
 
-    #[test]
-    fn string_escaping() {
-        assert_eq!(&escape_string(""), "");
-        assert_eq!(&escape_string("foo"), "foo");
-        assert_eq!(&escape_string("\"Hello\""), "\"\"Hello\"\"");
-        assert_eq!(&escape_string("C:\\Program Files\\Foobar"),
-                   "C:\\\\Program Files\\\\Foobar");
+    /// Set a version info struct property
+    /// Currently we only support numeric values, see [`FileOs`], [`FileType`] and
+    /// [`FileFlags`] for the common ones.
+    ///
+    /// [`FileOs`]: struct.FileOs.html
+    /// [`FileType`]: enum.FileType.html
+    /// [`FileFlags`]: enum.FileFlags.html
+    pub fn set_version_info(&mut self, field: VersionInfo, value: u64) -> &mut Self {
+        self.version_info.insert(field, value);
+        self
+    }
+
+    /// The value of a version info struct property
+    pub fn get_version_info(&self, field: VersionInfo) -> Option<u64> {
+        self.version_info.get(&field).cloned()
+    }
+
+    /// Remove a version info struct property, so it is not written to the resource file
+    ///
+    /// Returns the previous value.
+    pub fn remove_version_info(&mut self, field: VersionInfo) -> Option<u64> {
+        self.version_info.remove(&field)
+    }
+
+    /// The version info struct properties, in the order they are written
+    pub fn version_info(&self) -> impl Iterator<Item = (VersionInfo, u64)> + '_ {
+        self.version_info.iter().map(|(&k, &v)| (k, v))
+    }
+
+    /// Set `FILEVERSION` and `PRODUCTVERSION` from their four components
+    ///
+    /// By default they are the `MAJOR.MINOR.PATCH` version of the package with a zero
+    /// build number. The `"FileVersion"` and `"ProductVersion"` strings are not changed,
+    /// see [`set_file_version_str()`] to set both.
+    ///
+    /// ```rust
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_version_components(1, 2, 3, 4512);
+    /// ```
+    ///
+    /// [`set_file_version_str()`]: #method.set_file_version_str
+    pub fn set_version_components(&mut self, major: u16, minor: u16, patch: u16, build: u16) -> &mut Self {
+        let packed = u64::from(major) << 48 | u64::from(minor) << 32 | u64::from(patch) << 16 | u64::from(build);
+        self.version_info.insert(VersionInfo::FILEVERSION, packed);
+        self.version_info.insert(VersionInfo::PRODUCTVERSION, packed);
+        self
+    }
+
+    /// Set the file version from a dotted string, e.g. `"1.2.3.4"`
+    ///
+    /// The version needs two to four numeric components, each at most `65535`; missing
+    /// components are zero. Both `FILEVERSION` and the `"FileVersion"` string are set.
+    pub fn set_file_version_str(&mut self, version: &str) -> Result<&mut Self, Error> {
+        let packed = parse_version_string(version).map_err(Error::InvalidVersionString)?;
+        self.version_info.insert(VersionInfo::FILEVERSION, packed);
+        self.properties.insert("FileVersion".to_string(), version.to_string());
+        Ok(self)
+    }
+
+    /// Set the product version from a dotted string, e.g. `"1.2.3.4"`
+    ///
+    /// This works like [`set_file_version_str()`] for `PRODUCTVERSION`
+    /// and the `"ProductVersion"` string.
+    ///
+    /// [`set_file_version_str()`]: #method.set_file_version_str
+    pub fn set_product_version_str(&mut self, version: &str) -> Result<&mut Self, Error> {
+        let packed = parse_version_string(version).map_err(Error::InvalidVersionString)?;
+        self.version_info.insert(VersionInfo::PRODUCTVERSION, packed);
+        self.properties.insert("ProductVersion".to_string(), version.to_string());
+        Ok(self)
+    }
+
+    /// Set `VS_FF_DEBUG` in `FILEFLAGS` for non-release builds
+    ///
+    /// With `true` the cargo `PROFILE` (or `DEBUG` outside of cargo profiles) decides:
+    /// debug builds get [`FileFlags::DEBUG`] in `FILEFLAGS` and `FILEFLAGSMASK`, release builds
+    /// get it cleared from `FILEFLAGS`. Other flags, like [`FileFlags::PRERELEASE`], are kept.
+    /// This is what hand-written resource files do with `#ifdef _DEBUG`.
+    ///
+    /// [`FileFlags::DEBUG`]: enum.FileFlags.html#associatedconstant.DEBUG
+    /// [`FileFlags::PRERELEASE`]: enum.FileFlags.html#associatedconstant.PRERELEASE
+    pub fn set_fileflags_from_profile(&mut self, enable: bool) -> &mut Self {
+        if !enable {
+            return self;
+        }
+        if is_debug_build(self.env.var("PROFILE").as_deref(), self.env.var("DEBUG").as_deref()) {
+            let mask = self.version_info.get(&VersionInfo::FILEFLAGSMASK).cloned().unwrap_or(0);
+            self.version_info.insert(VersionInfo::FILEFLAGSMASK, mask | FileFlags::DEBUG);
+            let flags = self.version_info.get(&VersionInfo::FILEFLAGS).cloned().unwrap_or(0);
+            self.version_info.insert(VersionInfo::FILEFLAGS, flags | FileFlags::DEBUG);
+        } else if let Some(flags) = self.version_info.get_mut(&VersionInfo::FILEFLAGS) {
+            *flags &= !FileFlags::DEBUG;
+        }
+        self
+    }
+
+    /// Map the pre-release part of the cargo version into the version info
+    ///
+    /// For a version like `1.2.3-rc.4`, `PrereleasePolicy::Revision` sets `FILEVERSION`
+    /// and `PRODUCTVERSION`, and the `"FileVersion"` and `"ProductVersion"` strings, to
+    /// `1.2.3.4`. A pre-release without a trailing number maps to `0`, and an error is
+    /// returned if the number exceeds `65535`.
+    pub fn set_prerelease_policy(&mut self, policy: PrereleasePolicy) -> Result<&mut Self, Error> {
+        let pre = self.env.var("CARGO_PKG_VERSION_PRE").unwrap_or_default();
+        if policy == PrereleasePolicy::Ignore || pre.is_empty() {
+            return Ok(self);
+        }
+        let revision = prerelease_revision(&pre).map_err(Error::InvalidVersionString)? as u64;
+        for &(field, name) in [(VersionInfo::FILEVERSION, "FileVersion"), (VersionInfo::PRODUCTVERSION, "ProductVersion")].iter() {
+            let version = self.version_info.get(&field).cloned().unwrap_or(0) & !0xffff | revision;
+            self.version_info.insert(field, version);
+            self.properties.insert(name.to_string(), format!("{}.{}.{}.{}",
+                (version >> 48) as u16, (version >> 32) as u16, (version >> 16) as u16, version as u16));
+        }
+        if policy == PrereleasePolicy::RevisionAndFlag {
+            let flags = self.version_info.get(&VersionInfo::FILEFLAGS).cloned().unwrap_or(0);
+            self.version_info.insert(VersionInfo::FILEFLAGS, flags | FileFlags::PRERELEASE);
+        }
+        Ok(self)
+    }
+
+    /// Set the build number, i.e. the fourth component of the file version
+    ///
+    /// This updates both `FILEVERSION` and the `"FileVersion"` string.
+    pub fn set_build_number(&mut self, build: u16) -> &mut Self {
+        let version = self.version_info.get(&VersionInfo::FILEVERSION).cloned().unwrap_or(0);
+        let version = version & !0xffff | build as u64;
+        self.version_info.insert(VersionInfo::FILEVERSION, version);
+        self.properties.insert("FileVersion".to_string(), format!("{}.{}.{}.{}",
+            (version >> 48) as u16, (version >> 32) as u16, (version >> 16) as u16, version as u16));
+        self
+    }
+
+    /// Set the build number from the first of the environment variables `vars` that is set
+    ///
+    /// This is meant for CI systems, e.g. `&["GITHUB_RUN_NUMBER", "BUILD_NUMBER"]`.
+    /// The version is unchanged if none of the variables is set, and an error is returned
+    /// if the value is not a number between 0 and 65535.
+    pub fn set_build_number_from_env(&mut self, vars: &[&str]) -> Result<&mut Self, Error> {
+        if let Some((var, value)) = vars.iter().filter_map(|var| self.env.var(var).map(|v| (var, v))).next() {
+            let build = value.trim().parse::<u16>().map_err(|_| Error::InvalidBuildNumber(
+                format!("{}={} is not a build number between 0 and 65535", var, value)))?;
+            self.set_build_number(build);
+        }
+        Ok(self)
+    }
+
+    /// Set the build number to the number of commits in the git repository of the package
+    ///
+    /// This runs `git rev-list --count HEAD` in the package directory.
+    pub fn set_build_number_from_git_commit_count(&mut self) -> Result<&mut Self, Error> {
+        let build = self.reported(|| self.git_commit_count())?;
+        Ok(self.set_build_number(build))
+    }
+
+    /// The number of commits in the git repository of the package
+    fn git_commit_count(&self) -> Result<u16, Error> {
+        let mut git = process::Command::new("git");
+        git.args(["rev-list", "--count", "HEAD"]).current_dir(&self.manifest_dir);
+        let output = self.capture_output(&mut git)?;
+        if !output.status.success() {
+            return Err(tool_failed("git", &command_line(&git), &output));
+        }
+        let count = String::from_utf8_lossy(&output.stdout);
+        count.trim().parse::<u16>().map_err(|_| Error::InvalidBuildNumber(
+            format!("git commit count {} is not a build number between 0 and 65535", count.trim())))
+    }
+
+    /// Set the embedded manifest file
+    ///
+    /// # Example
+    ///
+    /// The following manifest will brand the exe as requesting administrator privileges.
+    /// Thus, everytime it is executed, a Windows UAC dialog will appear.
+    ///
+    /// ```rust
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_manifest(r#"
+    /// <assembly xmlns="urn:schemas-microsoft-com:asm.v1" manifestVersion="1.0">
+    /// <trustInfo xmlns="urn:schemas-microsoft-com:asm.v3">
+    ///     <security>
+    ///         <requestedPrivileges>
+    ///             <requestedExecutionLevel level="requireAdministrator" uiAccess="false" />
+    ///         </requestedPrivileges>
+    ///     </security>
+    /// </trustInfo>
+    /// </assembly>
+    /// "#);
+    /// ```
+    pub fn set_manifest<'a>(&mut self, manifest: &'a str) -> &mut Self {
+        self.manifest_file = None;
+        self.manifest_builder = None;
+        self.manifest = Some(manifest.to_string());
+        self
+    }
+
+    /// Some as [`set_manifest()`] but a filename can be provided and
+    /// file is included by the resource compieler itself.
+    /// This method works the same way as [`set_icon()`]
+    ///
+    /// [`set_manifest()`]: #method.set_manifest
+    /// [`set_icon()`]: #method.set_icon
+    pub fn set_manifest_file<P: AsRef<Path>>(&mut self, file: P) -> &mut Self {
+        self.manifest_file = Some(file.as_ref().to_path_buf());
+        self.manifest = None;
+        self.manifest_builder = None;
+        self
+    }
+
+    /// Set the embedded manifest from a [`Manifest`] builder
+    ///
+    /// This replaces a manifest set by [`set_manifest()`] or [`set_manifest_file()`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use winres::manifest::{Manifest, ExecutionLevel};
+    ///
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_manifest_builder(Manifest::new()
+    ///     .requested_execution_level(ExecutionLevel::RequireAdministrator));
+    /// ```
+    ///
+    /// [`Manifest`]: manifest/struct.Manifest.html
+    /// [`set_manifest()`]: #method.set_manifest
+    /// [`set_manifest_file()`]: #method.set_manifest_file
+    pub fn set_manifest_builder(&mut self, manifest: &manifest::Manifest) -> &mut Self {
+        self.manifest = None;
+        self.manifest_file = None;
+        self.manifest_builder = Some(manifest.clone());
+        self
+    }
+
+    /// The manifest builder used by the manifest helpers, created if necessary
+    ///
+    /// The helpers, like [`set_supported_os()`], cannot extend a manifest set by
+    /// [`set_manifest()`] or [`set_manifest_file()`], also from `Cargo.toml`. That manifest is
+    /// replaced, which is reported as metadata warning, see [`metadata_warnings()`], and is an
+    /// error in strict mode. Build the whole manifest with [`set_manifest_builder()`] instead,
+    /// or merge the file with [`merge_manifest_file()`].
+    ///
+    /// [`set_supported_os()`]: #method.set_supported_os
+    /// [`set_manifest()`]: #method.set_manifest
+    /// [`set_manifest_file()`]: #method.set_manifest_file
+    /// [`metadata_warnings()`]: #method.metadata_warnings
+    /// [`set_manifest_builder()`]: #method.set_manifest_builder
+    /// [`merge_manifest_file()`]: #method.merge_manifest_file
+    pub fn manifest_builder_mut(&mut self) -> &mut manifest::Manifest {
+        if self.manifest.take().is_some() {
+            self.metadata_warnings.push("the manifest of set_manifest() is replaced by the manifest settings".to_string());
+        }
+        if let Some(file) = self.manifest_file.take() {
+            self.metadata_warnings.push(format!("the manifest file {} is replaced by the manifest settings", file.display()));
+        }
+        self.manifest_builder.get_or_insert_with(manifest::Manifest::new)
+    }
+
+    /// Declare the operating systems the application supports in the manifest
+    ///
+    /// This generates the `compatibility` section with the matching `supportedOS` GUIDs
+    /// and composes with the other manifest helpers.
+    /// A manifest set by [`set_manifest()`] or [`set_manifest_file()`] is replaced, see
+    /// [`manifest_builder_mut()`].
+    ///
+    /// ```rust,no_run
+    /// use winres::manifest::SupportedOs;
+    ///
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_supported_os(&[SupportedOs::Windows8_1, SupportedOs::Windows10]);
+    /// ```
+    ///
+    /// [`set_manifest()`]: #method.set_manifest
+    /// [`set_manifest_file()`]: #method.set_manifest_file
+    /// [`manifest_builder_mut()`]: #method.manifest_builder_mut
+    pub fn set_supported_os(&mut self, os: &[manifest::SupportedOs]) -> &mut Self {
+        self.manifest_builder_mut().supported_os(os);
+        self
+    }
+
+    /// Set the requested execution level for UAC in the manifest
+    ///
+    /// This generates the `trustInfo` section and composes with the other manifest helpers.
+    ///
+    /// ```rust,no_run
+    /// use winres::manifest::ExecutionLevel;
+    ///
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_requested_execution_level(ExecutionLevel::RequireAdministrator, false);
+    /// ```
+    pub fn set_requested_execution_level(&mut self, level: manifest::ExecutionLevel, ui_access: bool)
+                                         -> &mut Self {
+        self.manifest_builder_mut()
+            .requested_execution_level(level)
+            .ui_access(ui_access);
+        self
+    }
+
+    /// Set the DPI awareness in the manifest
+    ///
+    /// Both the legacy `dpiAware` and the newer `dpiAwareness` elements are written,
+    /// so the setting is honored by all Windows versions. This composes with the other
+    /// manifest helpers.
+    pub fn set_dpi_awareness(&mut self, awareness: manifest::DpiAwareness) -> &mut Self {
+        self.manifest_builder_mut().dpi_awareness(awareness);
+        self
+    }
+
+    /// Enable visual styles for common controls
+    ///
+    /// This adds the dependency on version 6 of `Microsoft.Windows.Common-Controls`
+    /// to the manifest and composes with the other manifest helpers.
+    pub fn enable_visual_styles(&mut self) -> &mut Self {
+        self.manifest_builder_mut().common_controls_v6();
+        self
+    }
+
+    /// Set the identity of the application in the manifest
+    ///
+    /// By default a manifest generated by the manifest helpers gets an identity with the
+    /// package name, the four part `FILEVERSION` of the version info and the processor
+    /// architecture of the target, so it stays in sync with the `VERSIONINFO`. This
+    /// replaces it and composes with the other manifest helpers.
+    ///
+    /// ```rust
+    /// use winres::manifest::AssemblyIdentity;
+    ///
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_assembly_identity(AssemblyIdentity::new("Contoso.Calculator", "1.0.0.0"));
+    /// ```
+    pub fn set_assembly_identity(&mut self, identity: manifest::AssemblyIdentity) -> &mut Self {
+        self.manifest_builder_mut().assembly_identity(identity);
+        self
+    }
+
+    /// Declare a file with COM classes in the manifest for registration free activation
+    ///
+    /// See [`ComFile`] for an example. This composes with the other manifest helpers.
+    ///
+    /// [`ComFile`]: manifest/struct.ComFile.html
+    pub fn add_com_file(&mut self, file: &manifest::ComFile) -> &mut Self {
+        self.manifest_builder_mut().com_file(file);
+        self
+    }
+
+    /// Declare a dependency on a side-by-side assembly in the manifest, see
+    /// [`Manifest::add_dependent_assembly()`]
+    ///
+    /// This composes with the other manifest helpers.
+    ///
+    /// [`Manifest::add_dependent_assembly()`]: manifest/struct.Manifest.html#method.add_dependent_assembly
+    pub fn add_dependent_assembly(&mut self, name: &str, version: &str, public_key_token: Option<&str>,
+                                  processor_architecture: &str) -> &mut Self {
+        self.manifest_builder_mut().add_dependent_assembly(name, version, public_key_token, processor_architecture);
+        self
+    }
+
+    /// Allow paths longer than `MAX_PATH` in the manifest
+    ///
+    /// This composes with the other manifest helpers.
+    /// A manifest set by [`set_manifest()`] or [`set_manifest_file()`] is replaced, see
+    /// [`manifest_builder_mut()`].
+    ///
+    /// [`set_manifest()`]: #method.set_manifest
+    /// [`set_manifest_file()`]: #method.set_manifest_file
+    /// [`manifest_builder_mut()`]: #method.manifest_builder_mut
+    pub fn set_long_path_aware(&mut self, enable: bool) -> &mut Self {
+        self.manifest_builder_mut().long_path_aware(enable);
+        self
+    }
+
+    /// Use UTF-8 as active code page of the process in the manifest
+    ///
+    /// This composes with the other manifest helpers.
+    /// A manifest set by [`set_manifest()`] or [`set_manifest_file()`] is replaced, see
+    /// [`manifest_builder_mut()`].
+    ///
+    /// [`set_manifest()`]: #method.set_manifest
+    /// [`set_manifest_file()`]: #method.set_manifest_file
+    /// [`manifest_builder_mut()`]: #method.manifest_builder_mut
+    pub fn set_active_code_page_utf8(&mut self, enable: bool) -> &mut Self {
+        self.manifest_builder_mut().active_code_page_utf8(enable);
+        self
+    }
+
+    /// Use the segment heap instead of the NT heap in the manifest
+    ///
+    /// This composes with the other manifest helpers.
+    pub fn set_segment_heap(&mut self, enable: bool) -> &mut Self {
+        self.manifest_builder_mut().segment_heap(enable);
+        self
+    }
+
+    /// Enable GDI scaling on high DPI monitors in the manifest
+    ///
+    /// This composes with the other manifest helpers.
+    pub fn set_gdi_scaling(&mut self, enable: bool) -> &mut Self {
+        self.manifest_builder_mut().gdi_scaling(enable);
+        self
+    }
+
+    /// Turn off visual styles for the windows of the application in the manifest
+    ///
+    /// This composes with the other manifest helpers.
+    pub fn set_disable_theming(&mut self, disable: bool) -> &mut Self {
+        self.manifest_builder_mut().disable_theming(disable);
+        self
+    }
+
+    /// Load printer drivers in a separate process in the manifest
+    ///
+    /// This composes with the other manifest helpers.
+    pub fn set_printer_driver_isolation(&mut self, enable: bool) -> &mut Self {
+        self.manifest_builder_mut().printer_driver_isolation(enable);
+        self
+    }
+
+    /// Add a manifest fragment to be merged into the embedded manifest
+    ///
+    /// This can be called multiple times. The fragments are merged with the manifest set by
+    /// [`set_manifest()`], [`set_manifest_file()`] or [`set_manifest_builder()`] using
+    /// `mt.exe` from the Windows SDK, so this is only supported by the MSVC toolkit.
+    /// The filename can be absolute or relative to the projects root.
+    ///
+    /// [`set_manifest()`]: #method.set_manifest
+    /// [`set_manifest_file()`]: #method.set_manifest_file
+    /// [`set_manifest_builder()`]: #method.set_manifest_builder
+    pub fn merge_manifest_file<P: AsRef<Path>>(&mut self, file: P) -> &mut Self {
+        self.merge_manifests.push(file.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the path to the windres executable.
+    ///
+    /// By default it is taken from `WINRES_WINDRES_PATH` or `WINRES_TOOLKIT_PATH`, see
+    /// [`TOOL_ENV_VARS`], or searched with [`mingw::find_windres()`].
+    ///
+    /// [`TOOL_ENV_VARS`]: constant.TOOL_ENV_VARS.html
+    /// [`mingw::find_windres()`]: mingw/fn.find_windres.html
+    pub fn set_windres_path<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.windres_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the path to the rc.exe executable of the MSVC toolchain
+    ///
+    /// The installed Windows SDKs are not searched then, see [`sdk::rc_tool()`] for the
+    /// include dirs that are used. By default it is taken from `WINRES_RC_PATH` or
+    /// `WINRES_TOOLKIT_PATH`, see [`TOOL_ENV_VARS`].
+    ///
+    /// ```rust,no_run
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_rc_path(r"C:\toolchains\sdk\bin\10.0.22621.0\x64\rc.exe");
+    /// ```
+    ///
+    /// [`sdk::rc_tool()`]: sdk/fn.rc_tool.html
+    /// [`TOOL_ENV_VARS`]: constant.TOOL_ENV_VARS.html
+    pub fn set_rc_path<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.rc_path = Some(path.as_ref().to_path_buf());
+        self.tool = OnceCell::new();
+        self
+    }
+
+    /// Set the path to the ar executable.
+    ///
+    /// Without it, the static library for the GNU toolchain is written directly,
+    /// so `ar.exe` is not needed. By default it is taken from `WINRES_AR_PATH` or
+    /// `WINRES_TOOLKIT_PATH`, see [`TOOL_ENV_VARS`].
+    ///
+    /// [`TOOL_ENV_VARS`]: constant.TOOL_ENV_VARS.html
+    pub fn set_ar_path<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.ar_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the path to the linker of [`compile_satellite_dlls()`]
+    ///
+    /// By default it is `link.exe` for MSVC, `lld-link` for llvm-rc and the linker of rustc
+    /// for GNU: `RUSTC_LINKER` if cargo sets it, otherwise `gcc`, or the target prefixed
+    /// `gcc` like `x86_64-w64-mingw32-gcc` for cross builds.
+    ///
+    /// [`compile_satellite_dlls()`]: #method.compile_satellite_dlls
+    pub fn set_linker_path<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.linker_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the encoding of the resource scripts written by winres
+    ///
+    /// The default is [`RcEncoding::Utf8`]. `rc.exe` of some older Windows SDKs ignores
+    /// `#pragma code_page(65001)` and corrupts non-ASCII strings like `©` or CJK product
+    /// names, [`RcEncoding::Utf16Le`] avoids this. [`to_rc_string()`] always returns the
+    /// UTF-8 script.
+    ///
+    /// [`RcEncoding::Utf8`]: enum.RcEncoding.html#variant.Utf8
+    /// [`RcEncoding::Utf16Le`]: enum.RcEncoding.html#variant.Utf16Le
+    /// [`to_rc_string()`]: #method.to_rc_string
+    pub fn set_rc_encoding(&mut self, encoding: RcEncoding) -> &mut Self {
+        self.rc_encoding = encoding;
+        self
+    }
+
+    /// Escape `string` for a quoted string of the resource script, see [`toolchain()`]
+    ///
+    /// [`toolchain()`]: #method.toolchain
+    fn escape(&self, string: &str) -> String {
+        escape_string(string, self.toolchain())
+    }
+
+    /// Create the resource script `path` with the content written by `write`, in the
+    /// encoding of [`set_rc_encoding()`]
+    ///
+    /// [`set_rc_encoding()`]: #method.set_rc_encoding
+    fn write_script<F>(&self, path: &Path, write: F) -> Result<(), Error>
+        where F: FnOnce(&mut Vec<u8>) -> Result<(), Error> {
+        let mut script = Vec::new();
+        write(&mut script)?;
+        if self.rc_encoding == RcEncoding::Utf16Le {
+            let script = String::from_utf8(script).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            // the code page only applies to 8 bit scripts
+            let script: String = script.lines()
+                .filter(|line| *line != "#pragma code_page(65001)")
+                .map(|line| format!("{}\r\n", line))
+                .collect();
+            let mut utf16 = vec![0xff, 0xfe];
+            utf16.extend(script.encode_utf16().flat_map(u16::to_le_bytes));
+            fs::write(path, utf16)?;
+        } else {
+            fs::write(path, script)?;
+        }
+        Ok(())
+    }
+
+    /// Write a resource file with the set values
+    ///
+    /// The output is reproducible: the version info fields are written in the order of
+    /// [`VersionInfo`], and the string values sorted by name.
+    ///
+    /// [`VersionInfo`]: enum.VersionInfo.html
+    pub fn write_resource_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        self.write_resource_file_with_manifest(path, None)
+    }
+
+    /// Write the resource script with the set values to `w`, see [`to_rc_string()`]
+    ///
+    /// [`to_rc_string()`]: #method.to_rc_string
+    pub fn write_resource<W: Write>(&self, w: &mut W) -> Result<(), Error> {
+        self.write_resource_with_manifest(w, None)
+    }
+
+    /// The resource script with the set values, as written by [`write_resource_file()`]
+    ///
+    /// Useful for snapshot tests of the generated resources, or to post-process the
+    /// script before compiling it.
+    ///
+    /// ```rust
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set("ProductName", "My Tool");
+    /// let rc = res.to_rc_string().unwrap();
+    /// assert!(rc.contains("VALUE \"ProductName\", \"My Tool\""));
+    /// ```
+    ///
+    /// [`write_resource_file()`]: #method.write_resource_file
+    pub fn to_rc_string(&self) -> Result<String, Error> {
+        let mut rc = Vec::new();
+        self.write_resource(&mut rc)?;
+        Ok(String::from_utf8(rc).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?)
+    }
+
+    /// Write a resource file with only the version info
+    ///
+    /// Icons, fonts and the manifest are left out, so the file can be compiled for
+    /// companion binaries built outside of cargo, like installer stubs, which then
+    /// report the same metadata as the main executable.
+    pub fn write_version_info_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        self.write_script(path.as_ref(), |f| {
+            writeln!(f, "#pragma code_page(65001)")?;
+            self.write_version_info(f, &self.translations())
+        })
+    }
+
+    /// Write the resource file, embedding `merged_manifest` instead of the configured manifest
+    fn write_resource_file_with_manifest<P: AsRef<Path>>(&self, path: P, merged_manifest: Option<&Path>)
+                                                         -> Result<(), Error> {
+        self.write_script(path.as_ref(), |f| self.write_resource_with_manifest(f, merged_manifest))
+    }
+
+    fn write_resource_with_manifest<W: Write>(&self, f: &mut W, merged_manifest: Option<&Path>)
+                                              -> Result<(), Error> {
+        if let Some(ref template) = self.rc_template {
+            return self.write_template(f, template);
+        }
+        // we don't need to include this, we use constants instead of macro names
+        // try!(write!(f, "#include <winver.h>\n"));
+
+        // use UTF8 as an encoding
+        // this makes it easier since in rust all string are UTF8
+        writeln!(f, "#pragma code_page(65001)")?;
+        for (name, value) in self.defines.iter() {
+            writeln!(f, "{}", format!("#define {} {}", name, value).trim_end())?;
+        }
+        if self.has_version_info() {
+            self.write_version_info(f, &self.translations())?;
+        }
+        self.write_shared_resources(f, merged_manifest)
+    }
+
+    /// Whether the resource script gets a `VERSIONINFO` block, see [`skip_version_info()`]
+    ///
+    /// [`skip_version_info()`]: #method.skip_version_info
+    fn has_version_info(&self) -> bool {
+        !self.skip_version_info
+            && (!self.version_info.is_empty() || self.properties.values().any(|v| !v.is_empty()))
+    }
+
+    /// Write the VERSIONINFO block with a `StringFileInfo` block and a `Translation` entry
+    /// for each of the language and charset pairs `translations`
+    fn write_version_info<W: Write>(&self, f: &mut W, translations: &[(u16, u16)]) -> Result<(), Error> {
+        writeln!(f, "1 VERSIONINFO")?;
+        for (k, v) in self.version_info.iter() {
+            match *k {
+                VersionInfo::FILEVERSION |
+                VersionInfo::PRODUCTVERSION => {
+                    writeln!(f,
+                                  "{:?} {}, {}, {}, {}",
+                                  k,
+                                  (*v >> 48) as u16,
+                                  (*v >> 32) as u16,
+                                  (*v >> 16) as u16,
+                                  *v as u16)?
+                }
+                _ => writeln!(f, "{:?} {:#x}", k, v)?,
+            };
+        }
+        writeln!(f, "{{\nBLOCK \"StringFileInfo\"\n{{")?;
+        for &(language, charset) in translations {
+            let mut properties = self.properties.clone();
+            if let Some((_, localized)) = self.localized.iter().find(|&&(lang, _)| lang == language) {
+                properties.extend(localized.iter().map(|(k, v)| (k.clone(), v.clone())));
+            }
+            writeln!(f, "BLOCK \"{:04x}{:04x}\"\n{{", language, charset)?;
+            for (k, v) in properties.iter() {
+                if !v.is_empty() {
+                    writeln!(f, "VALUE \"{}\", \"{}\"",
+                             self.escape(k), self.escape(&self.resolve_placeholders(v)?))?;
+                }
+            }
+            writeln!(f, "}}")?;
+        }
+        writeln!(f, "}}")?;
+
+        if self.var_file_info {
+            writeln!(f, "BLOCK \"VarFileInfo\" {{")?;
+            write!(f, "VALUE \"Translation\"")?;
+            for &(lang, charset) in translations {
+                write!(f, ", {:#x}, {:#06x}", lang, charset)?;
+            }
+            writeln!(f, "\n}}")?;
+        }
+        writeln!(f, "}}")?;
+        Ok(())
+    }
+
+    /// Write the resources that do not depend on the language, i.e. icons, fonts,
+    /// type libraries and the manifest
+    fn write_shared_resources<W: Write>(&self, f: &mut W, merged_manifest: Option<&Path>)
+                                        -> Result<(), Error> {
+        if let Some(icon) = self.selected_icon()? {
+            let name_id = self.icon_id.as_ref().map_or(Ok("1".to_string()), ResourceId::to_rc)?;
+            writeln!(f, "{} ICON \"{}\"", name_id, self.escape(&self.resolve_path(icon)))?;
+        }
+        for (id, icon) in self.icons.iter() {
+            writeln!(f, "{} ICON \"{}\"", id.to_rc()?, self.escape(&self.resolve_path(icon)))?;
+        }
+        for (id, bitmap) in self.bitmaps.iter() {
+            writeln!(f, "{} BITMAP \"{}\"", id.to_rc()?, self.escape(&self.resolve_path(bitmap)))?;
+        }
+        for (id, data) in self.rcdata.iter() {
+            writeln!(f, "{} RCDATA \"{}\"", id.to_rc()?, self.escape(&self.resolve_path(data)))?;
+        }
+        for (kind, id, data) in self.custom_resources.iter() {
+            check_resource_name(kind, true)?;
+            writeln!(f, "{} {} \"{}\"", id.to_rc()?, kind, self.escape(&self.resolve_path(data)))?;
+        }
+        if !self.strings.is_empty() {
+            writeln!(f, "STRINGTABLE")?;
+            writeln!(f, "{{")?;
+            for &(_, id, ref value) in self.strings.iter() {
+                writeln!(f, "{} \"{}\"", id, self.escape(value))?;
+            }
+            writeln!(f, "}}")?;
+        }
+        for (i, font) in self.fonts.iter().enumerate() {
+            writeln!(f, "{} FONT \"{}\"", i + 1, self.escape(&self.resolve_path(font)))?;
+        }
+        #[cfg(feature = "cargo-metadata")]
+        if self.build_info {
+            let build_info = self.write_build_info()?;
+            writeln!(f, "{} RCDATA \"{}\"", BUILD_INFO_RESOURCE,
+                     self.escape(&self.resolve_path(&build_info)))?;
+        }
+        for &(id, ref typelib) in self.typelibs.iter() {
+            writeln!(f, "{} TYPELIB \"{}\"", id, self.escape(&self.resolve_path(typelib)))?;
+        }
+        if self.manifest_mode != ManifestMode::External {
+            let e = match self.manifest_resource_id {
+                Some(ref id) => id.to_rc()?,
+                None => self.binary_type.manifest_resource_id().to_string(),
+            };
+            if let Some(manf) = merged_manifest {
+                writeln!(f, "{} 24 \"{}\"", e, self.escape(&self.resolve_path(manf)))?;
+            } else if let Some(manf) = self.manifest_xml().as_ref() {
+                writeln!(f, "{} 24", e)?;
+                writeln!(f, "{{")?;
+                for line in manf.lines() {
+                    writeln!(f, "\"{}\"", self.escape(line.trim()))?;
+                }
+                writeln!(f, "}}")?;
+            } else if let Some(manf) = self.manifest_file.as_ref() {
+                writeln!(f, "{} 24 \"{}\"", e, self.escape(&self.resolve_path(manf)))?;
+            }
+        }
+        for rc in self.include_rcs.iter() {
+            writeln!(f, "#include \"{}\"", self.escape(&self.resolve_path(self.manifest_dir.join(rc))))?;
+        }
+        f.write_all(self.rc_content.as_bytes())?;
+        Ok(())
+    }
+
+    /// The manifest set by [`set_manifest()`] or [`set_manifest_builder()`] as XML
+    ///
+    /// [`set_manifest()`]: #method.set_manifest
+    /// [`set_manifest_builder()`]: #method.set_manifest_builder
+    fn manifest_xml(&self) -> Option<String> {
+        self.manifest.clone()
+            .or_else(|| self.manifest_builder.as_ref().map(|builder| {
+                if builder.get_assembly_identity().is_some() {
+                    return builder.to_xml();
+                }
+                let mut builder = builder.clone();
+                builder.assembly_identity(self.assembly_identity());
+                builder.to_xml()
+            }))
+    }
+
+    /// The identity of a generated manifest without [`set_assembly_identity()`]: the package
+    /// name, the `FILEVERSION` and the processor architecture of the target
+    ///
+    /// [`set_assembly_identity()`]: #method.set_assembly_identity
+    fn assembly_identity(&self) -> manifest::AssemblyIdentity {
+        let version = self.version_info.get(&VersionInfo::FILEVERSION).cloned().unwrap_or(0);
+        let version = format!("{}.{}.{}.{}", (version >> 48) as u16, (version >> 32) as u16, (version >> 16) as u16,
+                              version as u16);
+        let mut identity = manifest::AssemblyIdentity::new(&self.package_name, &version);
+        identity.processor_architecture = Some(processor_architecture(self.target.as_deref()).to_string());
+        identity
+    }
+
+    /// Merge the manifest with the files added by [`merge_manifest_file()`] using `mt.exe`
+    ///
+    /// Returns the path of the merged manifest.
+    ///
+    /// [`merge_manifest_file()`]: #method.merge_manifest_file
+    fn merge_manifests(&self) -> Result<PathBuf, Error> {
+        let output = self.output_dir()?;
+        let mut inputs = vec![];
+        if let Some(xml) = self.manifest_xml() {
+            let path = output.join("resource.manifest");
+            fs::File::create(&path)?.write_all(xml.as_bytes())?;
+            inputs.push(path);
+        } else if let Some(file) = self.manifest_file.as_ref() {
+            inputs.push(self.manifest_dir.join(file));
+        }
+        inputs.extend(self.merge_manifests.iter().map(|file| self.manifest_dir.join(file)));
+
+        let merged = output.join("merged.manifest");
+        let mt = self.tool()?.bin_dir.join("mt.exe");
+        self.run_tool("mt.exe", process::Command::new(&mt)
+            .arg("-nologo")
+            .arg("-manifest")
+            .args(&inputs)
+            .arg(format!("-out:{}", merged.display())))?;
+        Ok(merged)
+    }
+
+    /// `signtool.exe` of the Windows SDK, found like `rc.exe`, see [`set_kits_root()`]
+    ///
+    /// The tool runs on this machine, independent of the target arch.
+    ///
+    /// [`set_kits_root()`]: #method.set_kits_root
+    pub fn signtool(&self) -> Result<sdk::Tool, Error> {
+        let (system, arch) = sdk_system(&*self.env, self.kits_root.as_deref())?;
+        let host = sdk::Arch::host().unwrap_or(arch);
+        system.find_signtool(host).ok_or_else(|| Error::SdkNotFound { searched: runnable_bin_dirs(&system, host) })
+    }
+
+    /// Run [`signtool()`] with `args`, e.g. `["verify", "/pa", "app.dll"]`
+    ///
+    /// The tool is run like the resource compiler, with the timeout of [`set_tool_timeout()`],
+    /// and fails with [`Error::ToolFailed`] if it returns an error.
+    ///
+    /// [`signtool()`]: #method.signtool
+    /// [`set_tool_timeout()`]: #method.set_tool_timeout
+    /// [`Error::ToolFailed`]: enum.Error.html#variant.ToolFailed
+    pub fn run_signtool<I, S>(&self, args: I) -> Result<(), Error>
+        where I: IntoIterator<Item = S>, S: AsRef<OsStr> {
+        let mut command = self.signtool()?.command();
+        command.args(args);
+        self.reported(|| self.run_tool("signtool.exe", &mut command).map(|_| ()))
+    }
+
+    /// Set a path to an already existing resource file.
+    ///
+    /// We will neither modify this file nor parse its contents. This function
+    /// simply replaces the internaly generated resource file that is passed to
+    /// the compiler. You can use this function to write a resource file yourself.
+    ///
+    /// Properties, version info, icons, fonts, manifests and the language set on the builder
+    /// are not part of a custom resource file; [`compile()`] emits a cargo warning if any of
+    /// them are set. Values that cargo or `WINRES_LANG` provide by default don't count.
+    ///
+    /// [`compile()`]: #method.compile
+    pub fn set_resource_file<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.rc_file = Some(path.as_ref().to_path_buf());
+        self.rc_template = None;
+        self
+    }
+
+    /// Use a hand written resource script with `@NAME@` variables instead of the generated one
+    ///
+    /// The template, e.g. `app.rc.in`, is read relative to the project's root and written
+    /// with the variables filled in to the output directory before it is compiled. This
+    /// replaces [`set_resource_file()`] and vice versa. The variables are
+    ///
+    /// * the string properties, e.g. `@ProductName@`, escaped for a string literal,
+    /// * the version info fields, e.g. `@FILEVERSION@` as `1, 2, 3, 0` or `@FILEFLAGS@` as `0x0`,
+    /// * `@LANGUAGE@`, the language id of the file, e.g. `0x409`.
+    ///
+    /// Writing the resource file fails with [`Error::InvalidRcFile`] for other variables. An
+    /// `@` without a variable name, e.g. in an e-mail address, is kept as is. Icons, fonts
+    /// and the other resources set on the builder are not part of the template.
+    ///
+    /// ```rust
+    /// # use std::{env, fs};
+    /// let dir = env::temp_dir();
+    /// fs::write(dir.join("winres-doc.rc.in"),
+    ///           "1 VERSIONINFO\nFILEVERSION @FILEVERSION@\n{\nVALUE \"ProductName\", \"@ProductName@\"\n}\n")
+    ///     .unwrap();
+    ///
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set("ProductName", "My \"Tool\"")
+    ///    .set_version_info(winres::VersionInfo::FILEVERSION, 0x0001000200030000)
+    ///    .set_resource_template(dir.join("winres-doc.rc.in"));
+    /// let rc = res.to_rc_string().unwrap();
+    /// assert!(rc.contains("FILEVERSION 1, 2, 3, 0\n"));
+    /// assert!(rc.contains(r#"VALUE "ProductName", "My ""Tool""""#));
+    /// ```
+    ///
+    /// [`set_resource_file()`]: #method.set_resource_file
+    /// [`Error::InvalidRcFile`]: enum.Error.html#variant.InvalidRcFile
+    pub fn set_resource_template<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.rc_template = Some(path.as_ref().to_path_buf());
+        self.rc_file = None;
+        self
+    }
+
+    /// Also write the compiled resource script to `path`, e.g. to review or diff it
+    ///
+    /// The script in the output directory is hard to find, since cargo names it after a
+    /// hash. The filename can be absolute or relative to the projects root, missing
+    /// directories are created. The copy is written before the script is compiled, so it
+    /// is there if the compilation fails, and only if its contents changed. It is the
+    /// script passed to the compiler, i.e. the custom resource file or the filled in
+    /// template if one is set.
+    ///
+    /// ```rust,no_run
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set("ProductName", "My Tool")
+    ///    .emit_rc_copy("target/resource.rc");
+    /// res.compile().unwrap();
+    /// ```
+    pub fn emit_rc_copy<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.rc_copy = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Write the copy of `script` set with [`emit_rc_copy()`], unless it is up to date
+    ///
+    /// [`emit_rc_copy()`]: #method.emit_rc_copy
+    fn write_rc_copy(&self, script: &Path) -> Result<(), Error> {
+        let path = match self.rc_copy {
+            Some(ref path) => self.manifest_dir.join(path),
+            None => return Ok(()),
+        };
+        let contents = fs::read(script)?;
+        if fs::read(&path).ok().as_ref() == Some(&contents) {
+            return Ok(());
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, contents)?;
+        Ok(())
+    }
+
+    /// The values of the `@NAME@` variables of [`set_resource_template()`]
+    ///
+    /// [`set_resource_template()`]: #method.set_resource_template
+    fn template_variables(&self) -> Result<BTreeMap<String, String>, Error> {
+        let mut variables = BTreeMap::new();
+        for (k, v) in self.properties.iter() {
+            variables.insert(k.clone(), self.escape(&self.resolve_placeholders(v)?));
+        }
+        for (k, v) in self.version_info.iter() {
+            let value = match *k {
+                VersionInfo::FILEVERSION | VersionInfo::PRODUCTVERSION =>
+                    format!("{}, {}, {}, {}", (*v >> 48) as u16, (*v >> 32) as u16, (*v >> 16) as u16, *v as u16),
+                _ => format!("{:#x}", v),
+            };
+            variables.insert(format!("{:?}", k), value);
+        }
+        variables.insert("LANGUAGE".to_string(), format!("{:#x}", self.language));
+        Ok(variables)
+    }
+
+    /// Write the template of [`set_resource_template()`] with the variables filled in
+    ///
+    /// [`set_resource_template()`]: #method.set_resource_template
+    fn write_template<W: Write>(&self, f: &mut W, template: &Path) -> Result<(), Error> {
+        let path = self.manifest_dir.join(template);
+        let source = match fs::read_to_string(&path) {
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Err(Error::ResourceFileNotFound(path)),
+            source => source?,
+        };
+        let variables = self.template_variables()?;
+        let script = expand_template_variables(&source, |name| variables.get(name).cloned())
+            .map_err(|name| Error::InvalidRcFile { path: path.clone(), message: format!("no value for @{}@", name) })?;
+        f.write_all(script.as_bytes())?;
+        Ok(())
+    }
+
+    /// Merge the resources of a compiled `.res` file, e.g. from a vendor SDK, into the
+    /// compiled resource
+    ///
+    /// The filename can be absolute or relative to the projects root. The resources are
+    /// appended as they are, so their ids must not clash with the generated ones.
+    /// This also works with a custom resource file, see [`set_resource_file()`].
+    ///
+    /// [`set_resource_file()`]: #method.set_resource_file
+    pub fn add_res_file<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.res_files.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Append the resources of the [`add_res_file()`] files to the compiled `.res` file `compiled`
+    ///
+    /// [`add_res_file()`]: #method.add_res_file
+    fn append_res_files(&self, compiled: &Path) -> Result<(), Error> {
+        if self.res_files.is_empty() {
+            return Ok(());
+        }
+        let mut f = fs::OpenOptions::new().append(true).open(compiled)?;
+        for path in self.res_files.iter() {
+            let path = self.manifest_dir.join(path);
+            let data = fs::read(&path)?;
+            res::from_bytes(&data).map_err(|e| Error::InvalidResFile { path: path.clone(), message: e.to_string() })?;
+            f.write_all(res::without_marker(&data))?;
+        }
+        Ok(())
+    }
+
+    /// Compile `rc` to the `.res` file `res` with windres and append the [`add_res_file()`] files
+    ///
+    /// windres only converts whole `.res` files to objects, so the merged `.res` file is
+    /// compiled to the object instead of `rc`.
+    ///
+    /// [`add_res_file()`]: #method.add_res_file
+    fn merge_res_files_with_gnu(&self, rc: &Path, res: &Path) -> Result<(), Error> {
+        self.run_command(&self.compiler_command_line(Toolchain::Gnu, rc, res)?)?;
+        self.append_res_files(res)
+    }
+
+    /// Settings on the builder that are not used when a custom resource file is set
+    ///
+    /// Properties, version info and language only count if they differ from the defaults of
+    /// cargo and `WINRES_LANG`.
+    fn ignored_settings(&self) -> Vec<&'static str> {
+        let mut ignored = vec![];
+        if self.properties != self.default_properties {
+            ignored.push("properties");
+        }
+        if self.version_info != self.default_version_info {
+            ignored.push("version info");
+        }
+        if self.icon.is_some() || !self.icons.is_empty() {
+            ignored.push("icon");
+        }
+        if !self.fonts.is_empty() {
+            ignored.push("fonts");
+        }
+        if !self.typelibs.is_empty() {
+            ignored.push("type libraries");
+        }
+        if !self.bitmaps.is_empty() || !self.rcdata.is_empty() || !self.custom_resources.is_empty()
+            || !self.strings.is_empty() {
+            ignored.push("bitmaps, data and strings");
+        }
+        if !self.include_rcs.is_empty() {
+            ignored.push("included resource scripts");
+        }
+        if !self.rc_content.is_empty() {
+            ignored.push("appended resource script content");
+        }
+        if self.manifest.is_some() || self.manifest_file.is_some() || self.manifest_builder.is_some()
+            || !self.merge_manifests.is_empty() {
+            ignored.push("manifest");
+        }
+        if self.language != self.default_language {
+            ignored.push("language");
+        }
+        ignored
+    }
+
+    /// Use the package in `path` instead of `CARGO_MANIFEST_DIR`
+    ///
+    /// The name, version and description of the package as well as the
+    /// `package.metadata.winres` section are read from its `Cargo.toml`, replacing the values
+    /// of the current package. The package name is also used for the assembly identity of the
+    /// manifest and the default `OriginalFilename`, the binary type is detected again unless it
+    /// was set, and a default `InternalName` of the current binary is dropped. The directory is also passed as include path to the resource
+    /// compiler, so relative icon and manifest paths are resolved against it.
+    ///
+    /// This is useful to generate resources for a sibling package or a generated project,
+    /// e.g. an installer stub. Only available with the `cargo-metadata` feature.
+    #[cfg(feature = "cargo-metadata")]
+    pub fn set_manifest_dir<P: AsRef<Path>>(&mut self, path: P) -> Result<&mut Self, Error> {
+        let manifest_dir = path.as_ref().to_path_buf();
+        let ml = read_cargo_toml(&manifest_dir)?;
+        if let Some(pkg) = ml.get("package") {
+            if let Some(name) = pkg.get("name").and_then(toml::Value::as_str) {
+                self.set_default_property("ProductName", name);
+                // the binary defaults of the current package don't apply
+                let fields = [VersionInfo::FILETYPE, VersionInfo::FILESUBTYPE, VersionInfo::FILEOS];
+                if fields.iter().all(|field| self.version_info.get(field) == self.default_version_info.get(field)) {
+                    self.set_binary_type(detect_binary_type(&manifest_dir));
+                    for field in fields.iter() {
+                        self.default_version_info.insert(*field, self.version_info[field]);
+                    }
+                }
+                if self.properties.contains_key("InternalName")
+                    && self.properties.get("InternalName") == self.default_properties.get("InternalName") {
+                    self.properties.remove("InternalName");
+                    self.default_properties.remove("InternalName");
+                }
+                if self.properties.get("OriginalFilename") == self.default_properties.get("OriginalFilename") {
+                    self.set_default_property("OriginalFilename", &original_filename(name, self.binary_type));
+                }
+                self.package_name = name.to_string();
+            }
+            if let Some(description) = pkg.get("description").and_then(toml::Value::as_str) {
+                self.set_default_property("FileDescription", description);
+            }
+            if let Some(version) = pkg.get("version").and_then(toml::Value::as_str) {
+                self.set_default_property("FileVersion", version);
+                self.set_default_property("ProductVersion", version);
+                let version = parse_semver(version).map_err(Error::InvalidVersionString)?;
+                for &field in [VersionInfo::FILEVERSION, VersionInfo::PRODUCTVERSION].iter() {
+                    self.version_info.insert(field, version);
+                    self.default_version_info.insert(field, version);
+                }
+            }
+        }
+        self.metadata_file = Some(manifest_dir.join("Cargo.toml"));
+        self.metadata_warnings = default_language(&*self.env).err().into_iter().collect();
+        self.manifest_dir = manifest_dir;
+        self.apply_package_metadata()?;
+        Ok(self)
+    }
+
+    /// Set a property derived from the package, which doesn't count as set by the user
+    #[cfg(feature = "cargo-metadata")]
+    fn set_default_property(&mut self, name: &str, value: &str) {
+        self.properties.insert(name.to_string(), value.to_string());
+        self.default_properties.insert(name.to_string(), value.to_string());
+    }
+
+    /// Turn the warnings about `package.metadata.winres` into an error and validate the properties
+    ///
+    /// Malformed metadata, like a value of the wrong type or an unknown setting, is ignored
+    /// and reported as `cargo:warning` when the resource is compiled, see [`set_warnings()`].
+    /// In strict mode,
+    /// compiling fails with [`Error::InvalidMetadata`] instead, and with
+    /// [`Error::InvalidProperties`] if [`validate_properties()`] finds a problem.
+    ///
+    /// [`Error::InvalidMetadata`]: enum.Error.html#variant.InvalidMetadata
+    /// [`Error::InvalidProperties`]: enum.Error.html#variant.InvalidProperties
+    /// [`validate_properties()`]: #method.validate_properties
+    /// [`set_warnings()`]: #method.set_warnings
+    pub fn set_strict(&mut self, strict: bool) -> &mut Self {
+        self.strict = strict;
+        self
+    }
+
+    /// The problems found in `package.metadata.winres` and `WINRES_LANG`, and manifests
+    /// replaced by the manifest helpers, see [`set_strict()`]
+    ///
+    /// [`set_strict()`]: #method.set_strict
+    pub fn metadata_warnings(&self) -> &[String] {
+        &self.metadata_warnings
+    }
+
+    /// Add the metadata warnings to `directives`, or fail in strict mode
+    fn report_metadata_warnings(&self, directives: &mut Vec<String>) -> Result<(), Error> {
+        if self.strict && !self.metadata_warnings.is_empty() {
+            return Err(Error::InvalidMetadata(self.metadata_warnings.clone()));
+        }
+        for warning in self.metadata_warnings.iter() {
+            self.warn(directives, warning);
+        }
+        let problems = self.validate_properties();
+        if self.strict && !problems.is_empty() {
+            return Err(Error::InvalidProperties(problems));
+        }
+        // the properties are only written to the generated resource script
+        if self.rc_file.is_none() && self.rc_template.is_none() && self.has_version_info() {
+            for problem in problems.iter() {
+                self.warn(directives, problem);
+            }
+            for name in self.skipped_properties() {
+                self.warn(directives, &format!("property \"{}\" is empty and left out of the version info", name));
+            }
+        }
+        Ok(())
+    }
+
+    /// The names of the empty properties that are not written, except the required ones
+    /// reported by [`validate_properties()`]
+    ///
+    /// [`validate_properties()`]: #method.validate_properties
+    fn skipped_properties(&self) -> Vec<&str> {
+        let mut skipped = vec![];
+        let blocks = Some(&self.properties).into_iter().chain(self.localized.iter().map(|(_, properties)| properties));
+        for (name, value) in blocks.flat_map(|properties| properties.iter()) {
+            if value.is_empty() && !REQUIRED_PROPERTIES.contains(&name.as_str()) && !skipped.contains(&name.as_str()) {
+                skipped.push(name.as_str());
+            }
+        }
+        skipped
+    }
+
+    /// Report `message` as `cargo:warning`, unless disabled with [`set_warnings()`]
+    ///
+    /// [`set_warnings()`]: #method.set_warnings
+    fn warn(&self, directives: &mut Vec<String>, message: &str) {
+        if self.warnings {
+            // cargo reads one instruction per line
+            directives.push(format!("cargo:warning={}", message.replace(['\r', '\n'], " ")));
+        }
+    }
+
+    /// Enable or disable the `cargo:warning` directives
+    ///
+    /// Problems that do not stop the compilation are reported as warnings by default, so
+    /// they show up in the output of `cargo build`: malformed metadata, properties found by
+    /// [`validate_properties()`], empty properties that are left out, settings ignored with a
+    /// custom resource file, icons with odd images and Windows SDK versions that are not
+    /// numeric. Errors are returned regardless of this setting, see [`set_strict()`].
+    ///
+    /// [`validate_properties()`]: #method.validate_properties
+    /// [`set_strict()`]: #method.set_strict
+    pub fn set_warnings(&mut self, enable: bool) -> &mut Self {
+        self.warnings = enable;
+        self
+    }
+
+    /// Only show what compiling the resource would do, without running the resource compiler
+    ///
+    /// [`compile()`] still writes the resource script and resolves the toolchain and the
+    /// Windows SDK, so their errors are reported, but prints the command lines it would run
+    /// as `cargo:warning`s, and the directives it would print as well, e.g.
+    /// `warning: dry run: cargo:rustc-link-lib=dylib=resource`. This is handy to debug the
+    /// choice of the SDK or architecture. [`compile_artifacts()`] and [`build()`] return the
+    /// same warnings, so no link directive points to a library that was not produced, and
+    /// [`compile_to_res()`] only reports the command lines. Post compile hooks are not run
+    /// and `.res` files are not merged.
+    ///
+    /// [`compile()`]: #method.compile
+    /// [`compile_artifacts()`]: #method.compile_artifacts
+    /// [`build()`]: #method.build
+    /// [`compile_to_res()`]: #method.compile_to_res
+    pub fn set_dry_run(&mut self, dry_run: bool) -> &mut Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Check the string properties for common mistakes
+    ///
+    /// Returns a description of each problem found:
+    ///
+    ///  - a name that looks like a misspelled standard name, e.g. `"LegalTrademark"`
+    ///    instead of `"LegalTrademarks"`
+    ///  - a missing or empty value of a property Windows requires, like `"FileDescription"`,
+    ///    unless it is the default from the package, e.g. without a `description`
+    ///  - `"PrivateBuild"` or `"SpecialBuild"` without the matching [`FileFlags`] bit
+    ///  - a value too long for the 16 bit length of a `String` structure
+    ///
+    /// In strict mode, see [`set_strict()`], compiling fails if there are problems,
+    /// otherwise they are reported as `cargo:warning`.
+    ///
+    /// [`FileFlags`]: enum.FileFlags.html
+    /// [`set_strict()`]: #method.set_strict
+    pub fn validate_properties(&self) -> Vec<String> {
+        let mut problems = vec![];
+        let flags = self.version_info.get(&VersionInfo::FILEFLAGS).cloned().unwrap_or(0);
+        let blocks = Some((self.language, &self.properties, true)).into_iter()
+            .chain(self.localized.iter().map(|&(lang, ref properties)| (lang, properties, false)));
+        for (language, properties, main) in blocks {
+            let block = if language == self.language { String::new() } else { format!(" ({:04x})", language) };
+            for &name in REQUIRED_PROPERTIES.iter() {
+                let value = properties.get(name);
+                // a value from the package, like an empty description or no authors, is not the
+                // user's mistake, and the localized blocks inherit the missing properties
+                if main && self.default_properties.get(name) == value || !main && value.is_none() {
+                    continue;
+                }
+                match value {
+                    None => problems.push(format!("property \"{}\"{} is required but missing", name, block)),
+                    Some(value) if value.is_empty() => {
+                        problems.push(format!("property \"{}\"{} is required but empty", name, block))
+                    }
+                    Some(_) => {}
+                }
+            }
+            for (name, value) in properties.iter() {
+                if let Some(known) = misspelled_property(name) {
+                    problems.push(format!("property \"{}\"{} looks like a misspelling of \"{}\"", name, block, known));
+                }
+                // wLength of the String structure counts the header, the key and the value in bytes
+                let size = 6 + (name.encode_utf16().count() + 1) * 2 + 2 + (value.encode_utf16().count() + 1) * 2;
+                if size > 0xffff {
+                    problems.push(format!("property \"{}\"{} is too long", name, block));
+                }
+            }
+            for &(name, flag) in &[("PrivateBuild", FileFlags::PRIVATEBUILD), ("SpecialBuild", FileFlags::SPECIALBUILD)] {
+                if properties.contains_key(name) && flags & flag == 0 {
+                    problems.push(format!("property \"{}\"{} is set without VS_FF_{} in FILEFLAGS",
+                                          name, block, name.to_ascii_uppercase()));
+                }
+            }
+        }
+        problems
+    }
+
+    /// Read the `package.metadata.winres` section from another manifest
+    ///
+    /// `path` is a `Cargo.toml` or a directory containing one. Unlike [`set_manifest_dir()`],
+    /// only the metadata section is read, the package name and version as well as the
+    /// include path are kept. The section is also used for the overrides of
+    /// [`compile_for_bin()`]. Errors if the file cannot be read or is not valid TOML.
+    ///
+    /// [`set_manifest_dir()`]: #method.set_manifest_dir
+    /// [`compile_for_bin()`]: #method.compile_for_bin
+    #[cfg(feature = "cargo-metadata")]
+    pub fn load_metadata_from<P: AsRef<Path>>(&mut self, path: P) -> Result<&mut Self, Error> {
+        let path = path.as_ref();
+        let cargo = if path.is_dir() { path.join("Cargo.toml") } else { path.to_path_buf() };
+        read_toml_file(&cargo)?;
+        self.metadata_file = Some(cargo);
+        if let Some(winres) = self.package_metadata()? {
+            self.apply_metadata_section(&winres, "package.metadata.winres", Path::new(""), true)?;
+        }
+        Ok(self)
+    }
+
+    /// Apply the `package.metadata.winres` section
+    #[cfg(feature = "cargo-metadata")]
+    fn apply_package_metadata(&mut self) -> Result<(), Error> {
+        if let Some(Err(Error::InvalidCargoToml { ref path, ref message })) =
+            self.metadata_file.as_ref().map(|file| read_toml_file(file)) {
+            self.metadata_warnings.push(format!("{} is not valid TOML: {}", path.display(), message));
+        }
+        match self.package_metadata()? {
+            Some(winres) => self.apply_metadata_section(&winres, "package.metadata.winres", Path::new(""), true),
+            None => Ok(()),
+        }
+    }
+
+    /// Without the `cargo-metadata` feature, `Cargo.toml` is not read
+    #[cfg(not(feature = "cargo-metadata"))]
+    fn apply_package_metadata(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// The `package.metadata.winres` section of the package
+    #[cfg(feature = "cargo-metadata")]
+    fn package_metadata(&self) -> Result<Option<toml::Value>, Error> {
+        let file = match self.metadata_file {
+            Some(ref file) => file,
+            None => return Ok(None),
+        };
+        let ml = match read_toml_file(file) {
+            Ok(ml) => ml,
+            Err(Error::InvalidCargoToml { .. }) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(ml.get("package").and_then(|pkg| pkg.get("metadata")).and_then(|m| m.get("winres")).cloned())
+    }
+
+    /// Apply a metadata section named `section`, with its string properties if `properties`
+    #[cfg(feature = "cargo-metadata")]
+    fn apply_metadata_section(&mut self, winres: &toml::Value, section: &str, base: &Path, properties: bool)
+                              -> Result<(), Error> {
+        if winres.as_table().is_none() {
+            self.metadata_warnings.push(format!("{} is not a table", section));
+            return Ok(());
+        }
+        if properties {
+            if let Some(table) = winres.as_table() {
+                for (k, v) in table {
+                    if METADATA_SETTINGS.contains(&k.as_str()) {
+                        continue;
+                    }
+                    // string properties are capitalized, like "FileDescription"
+                    if k.starts_with(|c: char| c.is_ascii_lowercase()) {
+                        self.metadata_warnings.push(format!("{}.{} is not a known setting, it is written as a \
+                                                             string property", section, k));
+                    }
+                    match v.as_str() {
+                        Some(v) => {
+                            self.properties.insert(k.clone(), v.to_string());
+                        }
+                        None => self.metadata_warnings.push(format!("{}.{} is not a string", section, k)),
+                    }
+                }
+            }
+        }
+        match winres.get("icon") {
+            Some(toml::Value::String(icon)) => {
+                self.set_icon(base.join(icon));
+            }
+            Some(_) => self.metadata_warnings.push(format!("{}.icon is not a string", section)),
+            None => {}
+        }
+        match winres.get("icons") {
+            Some(toml::Value::Table(icons)) => {
+                for (id, icon) in icons {
+                    match icon.as_str() {
+                        Some(icon) => {
+                            self.add_icon(base.join(icon), id.as_str());
+                        }
+                        None => self.metadata_warnings.push(format!("{}.icons.{} is not a string", section, id)),
+                    }
+                }
+            }
+            Some(_) => self.metadata_warnings.push(format!("{}.icons is not a table", section)),
+            None => {}
+        }
+        match winres.get("strings") {
+            Some(toml::Value::Table(strings)) => {
+                for (name, string) in strings {
+                    let id = string.get("id").and_then(toml::Value::as_integer).filter(|id| (0..=0xffff).contains(id));
+                    match (id, string.get("value").and_then(toml::Value::as_str)) {
+                        (Some(id), Some(value)) => {
+                            self.add_string(name, id as u16, value);
+                        }
+                        _ => self.metadata_warnings.push(format!("{}.strings.{} needs a numeric id and a string value",
+                                                                 section, name)),
+                    }
+                }
+            }
+            Some(_) => self.metadata_warnings.push(format!("{}.strings is not a table", section)),
+            None => {}
+        }
+        match winres.get("manifest-file") {
+            Some(toml::Value::String(file)) => {
+                self.set_manifest_file(base.join(file));
+            }
+            Some(_) => self.metadata_warnings.push(format!("{}.manifest-file is not a string", section)),
+            None => {}
+        }
+        match winres.get("manifest") {
+            Some(toml::Value::Table(manifest)) => self.apply_manifest_section(manifest, section),
+            Some(_) => self.metadata_warnings.push(format!("{}.manifest is not a table", section)),
+            None => {}
+        }
+        // WINRES_LANG overrides the language of Cargo.toml
+        if let (Some(lang), None) = (winres.get("language"), self.env.var("WINRES_LANG")) {
+            let language = match *lang {
+                toml::Value::Integer(lang) if (0..=0xffff).contains(&lang) => Some(lang as u16),
+                toml::Value::String(ref lang) => parse_language(lang),
+                _ => None,
+            };
+            match language {
+                Some(language) => {
+                    self.set_language(language);
+                }
+                None => self.metadata_warnings.push(format!("{}.language is not a known language", section)),
+            }
+        }
+        match winres.get("lang") {
+            Some(toml::Value::Table(languages)) => {
+                for (lang, table) in languages {
+                    let language = match parse_language(lang) {
+                        Some(language) => language,
+                        None => {
+                            self.metadata_warnings.push(format!("{}.lang.{} is not a known language", section, lang));
+                            continue;
+                        }
+                    };
+                    let table = match table.as_table() {
+                        Some(table) => table,
+                        None => {
+                            self.metadata_warnings.push(format!("{}.lang.{} is not a table", section, lang));
+                            continue;
+                        }
+                    };
+                    for (k, v) in table {
+                        match v.as_str() {
+                            Some(v) => {
+                                self.set_localized(k, language, v);
+                            }
+                            None => self.metadata_warnings.push(format!("{}.lang.{}.{} is not a string", section, lang, k)),
+                        }
+                    }
+                }
+            }
+            Some(_) => self.metadata_warnings.push(format!("{}.lang is not a table", section)),
+            None => {}
+        }
+        let table = match winres.get("version-info") {
+            Some(toml::Value::Table(table)) => table,
+            Some(_) => {
+                self.metadata_warnings.push(format!("{}.version-info is not a table", section));
+                return Ok(());
+            }
+            None => return Ok(()),
+        };
+        for (k, v) in table {
+            let field = match VersionInfo::from_name(k) {
+                Some(field) => field,
+                None => {
+                    self.metadata_warnings.push(format!("{}.version-info.{} is not a version info field", section, k));
+                    continue;
+                }
+            };
+            match *v {
+                toml::Value::Integer(value) if value >= 0 => {
+                    self.set_version_info(field, value as u64);
+                }
+                toml::Value::String(ref version) if field == VersionInfo::FILEVERSION
+                    || field == VersionInfo::PRODUCTVERSION => {
+                    let packed = parse_version_string(version).map_err(Error::InvalidVersionString)?;
+                    self.set_version_info(field, packed);
+                }
+                _ => self.metadata_warnings.push(format!("{}.version-info.{} is not a number", section, k)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply the `manifest` table of a metadata section to the manifest builder
+    #[cfg(feature = "cargo-metadata")]
+    fn apply_manifest_section(&mut self, manifest: &toml::value::Table, section: &str) {
+        for (k, v) in manifest {
+            let warning = match (k.as_str(), v) {
+                ("execution-level", toml::Value::String(level)) => {
+                    let level = match level.as_str() {
+                        "asInvoker" => manifest::ExecutionLevel::AsInvoker,
+                        "highestAvailable" => manifest::ExecutionLevel::HighestAvailable,
+                        "requireAdministrator" => manifest::ExecutionLevel::RequireAdministrator,
+                        _ => {
+                            self.metadata_warnings.push(format!("{}.manifest.execution-level is not asInvoker, \
+                                                                 highestAvailable or requireAdministrator", section));
+                            continue;
+                        }
+                    };
+                    let ui_access = manifest.get("ui-access").and_then(toml::Value::as_bool).unwrap_or(false);
+                    self.set_requested_execution_level(level, ui_access);
+                    None
+                }
+                ("ui-access", &toml::Value::Boolean(_)) => None,
+                ("dpi-awareness", toml::Value::String(awareness)) => {
+                    let awareness = match awareness.to_ascii_lowercase().as_str() {
+                        "unaware" => manifest::DpiAwareness::Unaware,
+                        "system" => manifest::DpiAwareness::System,
+                        "permonitor" => manifest::DpiAwareness::PerMonitor,
+                        "permonitorv2" => manifest::DpiAwareness::PerMonitorV2,
+                        _ => {
+                            self.metadata_warnings.push(format!("{}.manifest.dpi-awareness is not unaware, system, \
+                                                                 permonitor or permonitorv2", section));
+                            continue;
+                        }
+                    };
+                    self.set_dpi_awareness(awareness);
+                    None
+                }
+                ("visual-styles", &toml::Value::Boolean(enable)) => {
+                    if enable {
+                        self.enable_visual_styles();
+                    }
+                    None
+                }
+                ("long-path-aware", &toml::Value::Boolean(enable)) => {
+                    self.set_long_path_aware(enable);
+                    None
+                }
+                ("active-code-page-utf8", &toml::Value::Boolean(enable)) => {
+                    self.set_active_code_page_utf8(enable);
+                    None
+                }
+                ("segment-heap", &toml::Value::Boolean(enable)) => {
+                    self.set_segment_heap(enable);
+                    None
+                }
+                ("gdi-scaling", &toml::Value::Boolean(enable)) => {
+                    self.set_gdi_scaling(enable);
+                    None
+                }
+                ("disable-theming", &toml::Value::Boolean(disable)) => {
+                    self.set_disable_theming(disable);
+                    None
+                }
+                ("printer-driver-isolation", &toml::Value::Boolean(enable)) => {
+                    self.set_printer_driver_isolation(enable);
+                    None
+                }
+                ("execution-level", _) | ("dpi-awareness", _) => Some("is not a string"),
+                ("ui-access", _) | ("visual-styles", _) | ("long-path-aware", _) | ("active-code-page-utf8", _)
+                | ("segment-heap", _) | ("gdi-scaling", _) | ("disable-theming", _)
+                | ("printer-driver-isolation", _) => Some("is not a boolean"),
+                _ => Some("is not a known manifest setting"),
+            };
+            if let Some(warning) = warning {
+                self.metadata_warnings.push(format!("{}.manifest.{} {}", section, k, warning));
+            }
+        }
+    }
+
+    /// Override the output directoy.
+    ///
+    /// As a default, we use `%OUT_DIR%` set by cargo, but it may be necessary to override the
+    /// the setting.
+    pub fn set_output_directory<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.output_directory = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Compile the resource outside of a cargo build script, e.g. from a packaging tool
+    ///
+    /// Without an output directory set by cargo's `OUT_DIR` or [`set_output_directory()`],
+    /// compiling fails with [`Error::OutDirNotSet`] before anything is written, so the
+    /// output doesn't end up in the current directory by accident; this also applies in
+    /// standalone mode. In standalone mode, or if `OUT_DIR` is not set, [`compile()`] and
+    /// [`compile_to_res()`] do not print the `cargo:` directives, which only a build script
+    /// could pass to cargo, but print the warnings to stderr. The directives are still
+    /// returned by [`compile_artifacts()`] and [`build()`].
+    ///
+    /// [`Error::OutDirNotSet`]: enum.Error.html#variant.OutDirNotSet
+    /// [`compile()`]: #method.compile
+    /// [`compile_to_res()`]: #method.compile_to_res
+    /// [`compile_artifacts()`]: #method.compile_artifacts
+    /// [`build()`]: #method.build
+    /// [`set_output_directory()`]: #method.set_output_directory
+    pub fn set_standalone(&mut self, standalone: bool) -> &mut Self {
+        self.standalone = standalone;
+        self
+    }
+
+    /// Report the output of the resource compiler and the other tools even if they succeed
+    ///
+    /// This is [`set_verbosity()`] with [`Verbosity::Verbose`], or [`Verbosity::Normal`]
+    /// for `false`.
+    ///
+    /// [`set_verbosity()`]: #method.set_verbosity
+    /// [`Verbosity::Verbose`]: enum.Verbosity.html#variant.Verbose
+    /// [`Verbosity::Normal`]: enum.Verbosity.html#variant.Normal
+    pub fn set_verbose(&mut self, verbose: bool) -> &mut Self {
+        self.set_verbosity(if verbose { Verbosity::Verbose } else { Verbosity::Normal })
+    }
+
+    /// Set how much is reported besides the warnings
+    ///
+    /// By default the output of a tool is only reported if it fails; it is also part of the
+    /// returned [`Error::ToolFailed`]. The messages are `cargo:warning` lines, printed like
+    /// the other directives, or returned in [`CompilationOutput::directives`] by [`build()`].
+    /// Nothing is written to stdout outside of the directives.
+    ///
+    /// [`Error::ToolFailed`]: enum.Error.html#variant.ToolFailed
+    /// [`CompilationOutput::directives`]: struct.CompilationOutput.html#structfield.directives
+    /// [`build()`]: #method.build
+    pub fn set_verbosity(&mut self, verbosity: Verbosity) -> &mut Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// Kill the resource compiler and the other tools if they run longer than `timeout`
+    ///
+    /// A hung tool then fails the build with [`Error::ToolTimedOut`] instead of stalling
+    /// it. By default there is no timeout.
+    ///
+    /// [`Error::ToolTimedOut`]: enum.Error.html#variant.ToolTimedOut
+    pub fn set_tool_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.tool_timeout = Some(timeout);
+        self
+    }
+
+    /// Run the resource compiler through `wrapper`, a program followed by its arguments
+    ///
+    /// The command line of the resource compiler is appended to the wrapper, for every
+    /// toolchain. This runs `rc.exe` under Wine for cross builds, or through a distributed
+    /// build tool. An empty wrapper runs the resource compiler directly.
+    ///
+    /// ```rust,no_run
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_tool_wrapper(["wine"]);
+    /// ```
+    pub fn set_tool_wrapper<I, S>(&mut self, wrapper: I) -> &mut Self
+        where I: IntoIterator<Item = S>, S: AsRef<OsStr> {
+        self.tool_wrapper = wrapper.into_iter().map(|arg| arg.as_ref().to_os_string()).collect();
+        self
+    }
+
+    /// `command` run through the [`set_tool_wrapper()`] wrapper
+    ///
+    /// [`set_tool_wrapper()`]: #method.set_tool_wrapper
+    fn wrapped(&self, command: Vec<OsString>) -> Vec<OsString> {
+        self.tool_wrapper.iter().cloned().chain(command).collect()
+    }
+
+    /// Report `message` as a warning in verbose mode
+    ///
+    /// The messages are collected and added to the directives by [`take_messages()`].
+    ///
+    /// [`take_messages()`]: #method.take_messages
+    fn log(&self, message: &str) {
+        if self.verbosity == Verbosity::Verbose {
+            self.messages.borrow_mut().push(format!("cargo:warning=winres: {}", message));
+        }
+    }
+
+    /// Report the captured output of `tool` as warnings if it failed or in verbose mode,
+    /// see [`log()`]
+    ///
+    /// [`log()`]: #method.log
+    fn log_tool_output(&self, tool: &str, output: &process::Output) {
+        let report = match self.verbosity {
+            Verbosity::Quiet => false,
+            Verbosity::Normal => !output.status.success(),
+            Verbosity::Verbose => true,
+        };
+        if report {
+            let mut messages = self.messages.borrow_mut();
+            for stream in [&output.stdout, &output.stderr] {
+                for line in String::from_utf8_lossy(stream).lines().filter(|line| !line.trim().is_empty()) {
+                    messages.push(format!("cargo:warning={}: {}", tool, line));
+                }
+            }
+        }
+    }
+
+    /// Move the messages of [`log()`] to `directives`
+    ///
+    /// [`log()`]: #method.log
+    fn take_messages(&self, directives: &mut Vec<String>) {
+        directives.append(&mut self.messages.borrow_mut());
+    }
+
+    /// Run `f` and print the messages of [`log()`] like [`compile_to_res()`] prints its
+    /// warnings, also if `f` fails
+    ///
+    /// [`log()`]: #method.log
+    /// [`compile_to_res()`]: #method.compile_to_res
+    fn reported<T, F: FnOnce() -> Result<T, Error>>(&self, f: F) -> Result<T, Error> {
+        let result = f();
+        let mut directives = vec![];
+        self.take_messages(&mut directives);
+        self.emit_directives(&directives);
+        result
+    }
+
+    /// Run `command` and capture its output, failing if `tool` returns an error
+    fn run_tool(&self, tool: &str, command: &mut process::Command) -> Result<process::Output, Error> {
+        let output = self.capture_output(command)?;
+        self.log_tool_output(tool, &output);
+        if !output.status.success() {
+            return Err(tool_failed(tool, &command_line(command), &output));
+        }
+        Ok(output)
+    }
+
+    /// Run `command` and capture its output, killing it after the timeout of
+    /// [`set_tool_timeout()`]
+    ///
+    /// [`set_tool_timeout()`]: #method.set_tool_timeout
+    fn capture_output(&self, command: &mut process::Command) -> Result<process::Output, Error> {
+        self.log(&format!("running {}", command_line(command)));
+        let timeout = match self.tool_timeout {
+            Some(timeout) => timeout,
+            None => return Ok(command.output()?),
+        };
+        let mut child = command.stdin(process::Stdio::null())
+            .stdout(process::Stdio::piped())
+            .stderr(process::Stdio::piped())
+            .spawn()?;
+        // the pipes are drained while waiting, so a tool with much output doesn't block
+        let stdout = child.stdout.take().map(read_in_thread);
+        let stderr = child.stderr.take().map(read_in_thread);
+        let start = Instant::now();
+        let mut timed_out = false;
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if start.elapsed() >= timeout {
+                let _ = child.kill();
+                timed_out = true;
+                break child.wait()?;
+            }
+            thread::sleep(Duration::from_millis(10));
+        };
+        if timed_out {
+            // the pipes close with the killed tool, unless a process it started still holds
+            // them, so the readers are joined if they finish within a grace period
+            let grace = Instant::now();
+            for reader in stdout.into_iter().chain(stderr) {
+                while !reader.is_finished() && grace.elapsed() < Duration::from_secs(1) {
+                    thread::sleep(Duration::from_millis(10));
+                }
+                if reader.is_finished() {
+                    let _ = reader.join();
+                }
+            }
+            return Err(Error::ToolTimedOut { command: command_line(command), timeout });
+        }
+        let collect = |reader: Option<thread::JoinHandle<Vec<u8>>>| {
+            reader.and_then(|reader| reader.join().ok()).unwrap_or_default()
+        };
+        Ok(process::Output { status, stdout: collect(stdout), stderr: collect(stderr) })
+    }
+
+    /// Run `command`, given as program followed by its arguments, like [`run_tool()`]
+    ///
+    /// [`run_tool()`]: #method.run_tool
+    fn run_command(&self, command: &[OsString]) -> Result<process::Output, Error> {
+        self.run_process(&mut self.prepare_command(self.tool.get(), command))
+    }
+
+    /// Run a command of [`process_commands()`], see [`run_tool()`]
+    ///
+    /// [`process_commands()`]: #method.process_commands
+    /// [`run_tool()`]: #method.run_tool
+    fn run_process(&self, command: &mut process::Command) -> Result<process::Output, Error> {
+        let program = command.get_program().to_string_lossy().into_owned();
+        self.run_tool(&program, command)
+    }
+
+    /// The output directory, see [`set_output_directory()`]
+    ///
+    /// [`set_output_directory()`]: #method.set_output_directory
+    fn output_dir(&self) -> Result<PathBuf, Error> {
+        self.output_directory.clone().ok_or(Error::OutDirNotSet)
+    }
+
+    /// Whether the directives are printed for cargo, see [`set_standalone()`]
+    ///
+    /// [`set_standalone()`]: #method.set_standalone
+    fn in_build_script(&self) -> bool {
+        !self.standalone && self.env.var("OUT_DIR").is_some()
+    }
+
+    /// Compile `input` with the resource compiler of `toolchain`
+    ///
+    /// Returns the resource compiler that was run.
+    fn compile_with_toolkit(&self, toolchain: Toolchain, input: &Path, output_dir: &Path, library: &str,
+                            directives: &mut Vec<String>) -> Result<PathBuf, Error> {
+        match toolchain {
+            Toolchain::Msvc => self.compile_with_msvc(input, output_dir, library, directives),
+            Toolchain::Gnu => self.compile_with_gnu(input, output_dir, library),
+            Toolchain::LlvmRc => self.compile_with_llvm_rc(input, library),
+        }
+    }
+
+    /// The tool run by `command`, i.e. the program or the argument after the tool wrapper
+    fn wrapped_program(&self, command: &process::Command) -> PathBuf {
+        match self.tool_wrapper.len() {
+            0 => PathBuf::from(command.get_program()),
+            n => command.get_args().nth(n - 1).map(PathBuf::from).unwrap_or_default(),
+        }
+    }
+
+    fn compile_with_llvm_rc(&self, input: &Path, library: &str) -> Result<PathBuf, Error> {
+        let mut commands = self.process_commands(Toolchain::LlvmRc, input, library)?;
+        self.run_process(&mut commands[0])?;
+        Ok(self.wrapped_program(&commands[0]))
+    }
+
+    fn compile_with_gnu(&self, input: &Path, output_dir: &Path, library: &str) -> Result<PathBuf, Error> {
+        let mut commands = self.process_commands(Toolchain::Gnu, input, library)?;
+        self.run_process(&mut commands[0])?;
+        if let Some(ar) = commands.get_mut(1) {
+            self.run_process(ar)?;
+        } else if self.link_mode() == LinkMode::Library {
+            let object_name = format!("{}.o", library);
+            let mut object = vec![];
+            fs::File::open(output_dir.join(&object_name))?.read_to_end(&mut object)?;
+            let mut archive = fs::File::create(output_dir.join(format!("lib{}.a", library)))?;
+            archive::write_archive(&mut archive, &[(&object_name, &object)])?;
+        }
+        Ok(self.wrapped_program(&commands[0]))
+    }
+
+    /// Set how the compiled resource is passed to the linker
+    ///
+    /// By default it is linked as library named `resource`, see [`library_name()`]. With
+    /// [`LinkMode::LinkArg`], the compiled `resource.res`, or `resource.o` for GNU, is passed
+    /// to the linker directly, which avoids name clashes with other libraries.
+    ///
+    /// [`library_name()`]: #method.library_name
+    /// [`LinkMode::LinkArg`]: enum.LinkMode.html#variant.LinkArg
+    pub fn set_link_mode(&mut self, mode: LinkMode) -> &mut Self {
+        self.link_mode = mode;
+        self
+    }
+
+    /// How the compiled resource is passed to the linker, see [`set_link_mode()`]
+    ///
+    /// This is [`LinkMode::LinkArg`] if the resource is only linked into some kinds of
+    /// targets, see [`apply_to()`].
+    ///
+    /// [`set_link_mode()`]: #method.set_link_mode
+    /// [`apply_to()`]: #method.apply_to
+    /// [`LinkMode::LinkArg`]: enum.LinkMode.html#variant.LinkArg
+    pub fn link_mode(&self) -> LinkMode {
+        if self.target_kinds.is_empty() { self.link_mode } else { LinkMode::LinkArg }
+    }
+
+    /// Only link the resource into targets of `kind`, can be called for several kinds
+    ///
+    /// By default the resource is linked as library into everything cargo links for the
+    /// package, including test binaries and examples, which may get a manifest or icon
+    /// they were not meant to have. With `apply_to()` the compiled resource is passed as
+    /// linker argument only to the chosen kinds of targets, e.g. with
+    /// `cargo:rustc-link-arg-bins`, which implies [`LinkMode::LinkArg`]. This needs cargo
+    /// 1.56 or newer. [`compile_for_bin()`] still links only into its binary.
+    ///
+    /// ```rust,no_run
+    /// use winres::TargetKind;
+    ///
+    /// let mut res = winres::WindowsResource::new();
+    /// res.apply_to(TargetKind::Bins).apply_to(TargetKind::Examples);
+    /// res.compile().unwrap();
+    /// ```
+    ///
+    /// [`LinkMode::LinkArg`]: enum.LinkMode.html#variant.LinkArg
+    /// [`compile_for_bin()`]: #method.compile_for_bin
+    pub fn apply_to(&mut self, kind: TargetKind) -> &mut Self {
+        if !self.target_kinds.contains(&kind) {
+            self.target_kinds.push(kind);
+        }
+        self
+    }
+
+    /// Set the name of the output files and the linked library, e.g. `"myapp_res"`
+    ///
+    /// By default it is [`library_name()`]. Characters that are not valid in a library
+    /// name are replaced by `_`.
+    ///
+    /// [`library_name()`]: #method.library_name
+    pub fn set_output_name(&mut self, name: &str) -> &mut Self {
+        let name = name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+        self.output_name = Some(name);
+        self
+    }
+
+    /// Name of the library the compiled resource is linked as, unless [`set_output_name()`] is used
+    ///
+    /// This is `resource`, unless the package or a dependency with a `links` key already
+    /// uses that name, i.e. the package is named `resource`, declares `links = "resource"`,
+    /// or cargo passes `DEP_RESOURCE_*` variables to the build script. Linking two libraries
+    /// named `resource` results in duplicate symbol or link order failures, so the name
+    /// `resource_<package>` is used instead.
+    ///
+    /// [`set_output_name()`]: #method.set_output_name
+    pub fn library_name(&self) -> String {
+        let package = self.env.var("CARGO_PKG_NAME").unwrap_or_default().replace('-', "_");
+        let clash = package == "resource"
+            || self.env.var("CARGO_MANIFEST_LINKS").as_deref() == Some("resource")
+            || self.env.vars().iter().any(|(k, _)| k.starts_with("DEP_RESOURCE_"));
+        if clash {
+            format!("resource_{}", package)
+        } else {
+            "resource".to_string()
+        }
+    }
+
+    /// The name of the output files, see [`set_output_name()`]
+    ///
+    /// [`set_output_name()`]: #method.set_output_name
+    fn output_name(&self) -> String {
+        self.output_name.clone().unwrap_or_else(|| self.library_name())
+    }
+
+    /// The file the resource compiler writes for `library`
+    ///
+    /// `rc.exe` writes a `.res` file, which is named `.lib` if it is linked as library.
+    fn compiled_file(&self, toolchain: Toolchain, output_dir: &Path, library: &str) -> PathBuf {
+        match toolchain {
+            Toolchain::Msvc | Toolchain::LlvmRc if self.link_mode() == LinkMode::LinkArg =>
+                output_dir.join(format!("{}.res", library)),
+            Toolchain::Msvc | Toolchain::LlvmRc => output_dir.join(format!("{}.lib", library)),
+            Toolchain::Gnu => output_dir.join(format!("{}.o", library)),
+        }
+    }
+
+    /// The cargo instructions linking the compiled resource `library`
+    ///
+    /// The resource is linked into every binary of the package, into the targets of
+    /// [`apply_to()`], or with `bin` only into that binary as linker argument.
+    ///
+    /// [`apply_to()`]: #method.apply_to
+    fn link_directives(&self, toolchain: Toolchain, output_dir: &Path, library: &str, bin: Option<&str>)
+                       -> Vec<String> {
+        let compiled = self.compiled_file(toolchain, output_dir, library);
+        match bin {
+            Some(bin) => vec![format!("cargo:rustc-link-arg-bin={}={}", bin, compiled.display())],
+            None if !self.target_kinds.is_empty() => self.target_kinds.iter()
+                .map(|kind| format!("cargo:rustc-link-arg-{}={}", kind.directive_suffix(), compiled.display()))
+                .collect(),
+            None if self.link_mode == LinkMode::LinkArg =>
+                vec![format!("cargo:rustc-link-arg={}", compiled.display())],
+            None => {
+                let kind = if toolchain == Toolchain::Gnu { "static" } else { "dylib" };
+                vec![format!("cargo:rustc-link-search=native={}", output_dir.display()),
+                     format!("cargo:rustc-link-lib={}={}", kind, library)]
+            }
+        }
+    }
+
+    /// Render the command lines used to compile the resource file `input` with `toolchain`
+    ///
+    /// Each command line consists of the program followed by its arguments, in the order
+    /// the commands are run by [`compile()`]. Nothing is executed, so this can be used
+    /// to regression-test a resource setup without an installed toolkit.
+    ///
+    /// For GNU, `ar` is only run if [`set_ar_path()`] is set and the resource is linked as library.
+    ///
+    /// Arguments that are not valid Unicode are converted lossily.
+    ///
+    /// [`compile()`]: #method.compile
+    /// [`set_ar_path()`]: #method.set_ar_path
+    pub fn command_lines<P: AsRef<Path>>(&self, toolchain: Toolchain, input: P) -> Result<Vec<Vec<String>>, Error> {
+        Ok(self.commands(toolchain, input.as_ref(), &self.output_name())?.iter()
+            .map(|command| command.iter().map(|arg| arg.to_string_lossy().into_owned()).collect())
+            .collect())
+    }
+
+    /// The commands of [`command_lines()`] prepared to run, to tweak or wrap the invocation
+    /// of the resource compiler
+    ///
+    /// These are the commands [`compile()`] runs: the resource compiler found by [`tool()`]
+    /// is set up by [`sdk::Tool::command()`], and the commands run in the package directory.
+    ///
+    /// ```rust,no_run
+    /// let res = winres::WindowsResource::new();
+    /// let mut commands = res.prepared_commands(winres::Toolchain::Msvc, "resource.rc").unwrap();
+    /// commands[0].env("RC_EXTRA", "1");
+    /// let status = commands[0].status().unwrap();
+    /// ```
+    ///
+    /// [`command_lines()`]: #method.command_lines
+    /// [`compile()`]: #method.compile
+    /// [`tool()`]: #method.tool
+    /// [`sdk::Tool::command()`]: sdk/struct.Tool.html#method.command
+    pub fn prepared_commands<P: AsRef<Path>>(&self, toolchain: Toolchain, input: P)
+                                             -> Result<Vec<process::Command>, Error> {
+        self.process_commands(toolchain, input.as_ref(), &self.output_name())
+    }
+
+    fn process_commands(&self, toolchain: Toolchain, input: &Path, library: &str)
+                        -> Result<Vec<process::Command>, Error> {
+        let tool = match toolchain {
+            Toolchain::Msvc => Some(self.tool()?),
+            Toolchain::Gnu if self.windres_path.is_none() => Some(self.tool()?),
+            _ => None,
+        };
+        Ok(self.commands(toolchain, input, library)?.iter().enumerate()
+            .map(|(index, args)| self.prepare_command(if index == 0 { tool } else { None }, args))
+            .collect())
+    }
+
+    /// The command running `args`, with the environment of `tool` if it runs the tool,
+    /// possibly through the [`set_tool_wrapper()`] wrapper
+    ///
+    /// [`set_tool_wrapper()`]: #method.set_tool_wrapper
+    fn prepare_command(&self, tool: Option<&sdk::Tool>, args: &[OsString]) -> process::Command {
+        let mut command = process::Command::new(&args[0]);
+        let program = args.get(self.tool_wrapper.len()).map(OsString::as_os_str);
+        if let Some(tool) = tool.filter(|tool| program == Some(tool.path.as_os_str())) {
+            for (name, value) in tool.command().get_envs() {
+                if let Some(value) = value {
+                    command.env(name, value);
+                }
+            }
+        }
+        command.args(&args[1..]);
+        if self.manifest_dir.is_dir() {
+            command.current_dir(&self.manifest_dir);
+        }
+        command
+    }
+
+    /// The commands of [`command_lines()`], run by [`compile()`]
+    ///
+    /// Every argument is passed to the program as is, so paths with spaces need no quoting.
+    ///
+    /// [`command_lines()`]: #method.command_lines
+    /// [`compile()`]: #method.compile
+    fn commands(&self, toolchain: Toolchain, input: &Path, library: &str) -> Result<Vec<Vec<OsString>>, Error> {
+        let output_dir = self.output_dir()?;
+        Ok(match toolchain {
+            Toolchain::Msvc | Toolchain::LlvmRc => {
+                vec![self.compiler_command_line(toolchain, input, &self.compiled_file(toolchain, &output_dir, library))?]
+            }
+            Toolchain::Gnu => {
+                let output = self.compiled_file(toolchain, &output_dir, library);
+                let windres = self.compiler_command_line(toolchain, input, &output)?;
+                match self.ar_path {
+                    Some(ref ar_path) if self.link_mode() == LinkMode::Library => {
+                        let ar = vec![
+                            ar_path.clone().into_os_string(),
+                            OsString::from("rsc"),
+                            output_dir.join(format!("lib{}.a", library)).into_os_string(),
+                            output.into_os_string(),
+                        ];
+                        vec![windres, ar]
+                    }
+                    _ => vec![windres],
+                }
+            }
+        })
+    }
+
+    /// Pass the package directory as include path to the resource compiler
+    ///
+    /// This is enabled by default, so a custom resource file can include headers relative
+    /// to the package root. Disable it for generated resource scripts or hermetic builds
+    /// that only use explicit include paths.
+    pub fn set_include_package_dir(&mut self, include: bool) -> &mut Self {
+        self.package_include = include;
+        self
+    }
+
+    /// Replace the package directory include path by `path`
+    ///
+    /// This also enables the include path, see [`set_include_package_dir()`].
+    ///
+    /// [`set_include_package_dir()`]: #method.set_include_package_dir
+    pub fn set_package_include_dir<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.package_include = true;
+        self.package_include_dir = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Include paths of the resource compiler besides the SDK: the package directory,
+    /// if enabled, the directories of [`add_include_dir()`] and the directories of the
+    /// included resource scripts
+    ///
+    /// [`add_include_dir()`]: #method.add_include_dir
+    fn include_paths(&self) -> Vec<PathBuf> {
+        let mut paths = self.package_include_path().map(Path::to_path_buf).into_iter().collect::<Vec<_>>();
+        let dirs = self.include_dirs.iter()
+            .map(|dir| self.manifest_dir.join(dir))
+            .chain(self.include_rcs.iter().filter_map(|rc| self.manifest_dir.join(rc).parent().map(Path::to_path_buf)));
+        for dir in dirs {
+            if !paths.contains(&dir) {
+                paths.push(dir);
+            }
+        }
+        paths
+    }
+
+    /// The include path replacing the package directory, if enabled
+    fn package_include_path(&self) -> Option<&Path> {
+        if self.package_include {
+            Some(self.package_include_dir.as_deref().unwrap_or(&self.manifest_dir))
+        } else {
+            None
+        }
+    }
+
+    /// Command line of the resource compiler, compiling `input` to `output`
+    ///
+    /// windres picks the output format from the file extension of `output`.
+    fn compiler_command_line(&self, toolchain: Toolchain, input: &Path, output: &Path) -> Result<Vec<OsString>, Error> {
+        let command = match toolchain {
+            Toolchain::Msvc => self.rc_command_line(self.tool()?, input, output),
+            Toolchain::Gnu => {
+                let mut windres = vec![match self.windres_path {
+                    Some(ref path) => path.clone().into_os_string(),
+                    None => self.tool()?.path.clone().into_os_string(),
+                }];
+                for dir in self.include_paths() {
+                    windres.push(prefixed_arg("-I", self.resolve_path_os(dir)));
+                }
+                for define in self.define_args() {
+                    windres.push(OsString::from("-D"));
+                    windres.push(OsString::from(define));
+                }
+                windres.extend(self.toolkit_args.iter().map(OsString::from));
+                let has_arg = |names: &[&str]| self.toolkit_args.iter()
+                    .any(|arg| names.iter().any(|name| arg == name || arg.starts_with(&format!("{}=", name))));
+                // without it, windres reads the script as Latin-1 and mangles non-ASCII strings
+                if let Some(code_page) = declared_code_page(input) {
+                    if !has_arg(&["-c", "--codepage"]) {
+                        windres.push(OsString::from(format!("--codepage={}", code_page)));
+                    }
+                }
+                // a windres for one architecture may default to another
+                if let Some(target) = self.target.as_deref().and_then(windres_target) {
+                    if !has_arg(&["-F", "--target"]) {
+                        windres.push(OsString::from(format!("--target={}", target)));
+                    }
+                }
+                windres.push(self.resolve_path_os(input));
+                windres.push(self.resolve_path_os(output));
+                // windres runs the preprocessor through the shell, which splits paths
+                // with spaces unless it reads the input from a temporary file
+                let use_temp_file = OsString::from("--use-temp-file");
+                if windres[1..].iter().any(|arg| arg.to_string_lossy().contains(char::is_whitespace))
+                    && !windres.contains(&use_temp_file) {
+                    windres.insert(1, use_temp_file);
+                }
+                windres
+            }
+            Toolchain::LlvmRc => {
+                let mut llvm_rc = vec![self.llvm_rc_path.as_ref()
+                    .map_or_else(|| OsString::from("llvm-rc"), |path| path.clone().into_os_string())];
+                for dir in self.include_paths() {
+                    llvm_rc.push(prefixed_arg("-I", self.resolve_path_os(dir)));
+                }
+                for define in self.define_args() {
+                    llvm_rc.push(OsString::from("-D"));
+                    llvm_rc.push(OsString::from(define));
+                }
+                llvm_rc.extend(self.toolkit_args.iter().map(OsString::from));
+                llvm_rc.push(prefixed_arg("-FO", self.resolve_path_os(output)));
+                llvm_rc.push(self.resolve_path_os(input));
+                llvm_rc
+            }
+        };
+        Ok(self.wrapped(command))
+    }
+
+    /// Command line of `rc.exe` from the SDK of `tool`
+    fn rc_command_line(&self, tool: &sdk::Tool, input: &Path, output: &Path) -> Vec<OsString> {
+        let mut rc = vec![tool.path.clone().into_os_string()];
+        for dir in self.include_paths() {
+            rc.push(prefixed_arg("/I", self.resolve_path_os(dir)));
+        }
+        for inc in sorted_include_dirs(tool) {
+            rc.push(prefixed_arg("/I", self.resolve_path_os(inc)));
+        }
+        for define in self.define_args() {
+            rc.push(OsString::from("/d"));
+            rc.push(OsString::from(define));
+        }
+        rc.extend(self.toolkit_args.iter().map(OsString::from));
+        rc.push(prefixed_arg("/fo", self.resolve_path_os(output)));
+        rc.push(self.resolve_path_os(input));
+        rc
+    }
+
+    /// Languages with a `StringFileInfo` block, i.e. the language of the file
+    /// followed by the languages of [`set_localized()`]
+    ///
+    /// [`set_localized()`]: #method.set_localized
+    fn string_file_info_languages(&self) -> Vec<u16> {
+        let mut languages = vec![self.language];
+        for &(lang, _) in self.localized.iter() {
+            if !languages.contains(&lang) {
+                languages.push(lang);
+            }
+        }
+        languages
+    }
+
+    /// Languages of the version info, in the order of [`translations()`]
+    ///
+    /// [`translations()`]: #method.translations
+    fn languages(&self) -> Vec<u16> {
+        let mut languages = vec![];
+        for (lang, _) in self.translations() {
+            if !languages.contains(&lang) {
+                languages.push(lang);
+            }
+        }
+        languages
+    }
+
+    /// The language and charset pairs of [`translations()`] for `language`
+    ///
+    /// [`translations()`]: #method.translations
+    fn translations_of(&self, language: u16) -> Vec<(u16, u16)> {
+        self.translations().into_iter().filter(|&(lang, _)| lang == language).collect()
+    }
+
+    /// Compile one resource file per language, without linking them
+    ///
+    /// The resources that do not depend on the language (icons, fonts, type libraries and
+    /// the manifest) are compiled to `resource.neutral.res`, and the version info of each
+    /// language to `resource.<language>.res`, e.g. `resource.0407.res`, all in the output
+    /// directory. No cargo directives are printed, so the files can be packaged separately,
+    /// e.g. as MUI satellite files picked by an installer.
+    ///
+    /// Returns the paths of the compiled files, starting with the neutral one.
+    pub fn compile_by_language(&self) -> Result<Vec<PathBuf>, Error> {
+        let output = self.output_dir()?;
+        let toolchain = self.toolchain();
+        let mut compiled = vec![];
+
+        let rc = output.join("resource.neutral.rc");
+        self.write_script(&rc, |f| {
+            writeln!(f, "#pragma code_page(65001)")?;
+            self.write_shared_resources(f, None)
+        })?;
+        compiled.push(self.compile_resource_file(toolchain, &rc)?);
+
+        for language in self.languages() {
+            let rc = output.join(format!("resource.{:04x}.rc", language));
+            self.write_script(&rc, |f| {
+                writeln!(f, "#pragma code_page(65001)")?;
+                writeln!(f, "LANGUAGE {:#x}, {:#x}", language & 0x3ff, language >> 10)?;
+                self.write_version_info(f, &self.translations_of(language))
+            })?;
+            compiled.push(self.compile_resource_file(toolchain, &rc)?);
+        }
+        Ok(compiled)
+    }
+
+    /// Link the version info and the strings of each language into a resource-only DLL
+    ///
+    /// For every language of the version info or of [`set_localized_string()`], the DLL
+    /// `<name>.<tag>.dll` is written to the output directory, e.g. `myapp.de-DE.dll`, or
+    /// `myapp.0407.dll` for languages without a known tag. Each has the version info and
+    /// the `STRINGTABLE` of its language, with the strings of [`add_string()`] that are not
+    /// localized. These are the satellite files of the Windows MUI pattern, loaded with
+    /// `LoadLibraryEx(.., LOAD_LIBRARY_AS_DATAFILE)`. Icons, bitmaps and the other files
+    /// do not depend on the language and stay in the resource of the package.
+    ///
+    /// The DLLs are linked without entry point by the linker of [`set_linker_path()`].
+    /// Nothing is linked into the package. Returns the paths of the DLLs.
+    ///
+    /// [`set_localized_string()`]: #method.set_localized_string
+    /// [`add_string()`]: #method.add_string
+    /// [`set_linker_path()`]: #method.set_linker_path
+    pub fn compile_satellite_dlls(&self, name: &str) -> Result<Vec<PathBuf>, Error> {
+        let output = self.output_dir()?;
+        let toolchain = self.toolchain();
+        let mut languages = self.languages();
+        for &(_, language, _) in self.localized_strings.iter() {
+            if !languages.contains(&language) {
+                languages.push(language);
+            }
+        }
+        let mut dlls = vec![];
+        for language in languages {
+            let tag = language::to_tag(language).map_or_else(|| format!("{:04x}", language), str::to_string);
+            let rc = output.join(format!("{}.{}.rc", name, tag));
+            self.write_script(&rc, |f| {
+                writeln!(f, "#pragma code_page(65001)")?;
+                writeln!(f, "LANGUAGE {:#x}, {:#x}", language & 0x3ff, language >> 10)?;
+                let mut translations = self.translations_of(language);
+                if translations.is_empty() {
+                    translations.push((language, self.charset));
+                }
+                self.write_version_info(f, &translations)?;
+                self.write_localized_strings(f, language)
+            })?;
+            // the GNU linker needs a COFF object instead of a .res file
+            let compiled = rc.with_extension(if toolchain == Toolchain::Gnu { "o" } else { "res" });
+            let dll = output.join(format!("{}.{}.dll", name, tag));
+            self.reported(|| {
+                self.run_command(&self.compiler_command_line(toolchain, &rc, &compiled)?)?;
+                self.run_command(&self.satellite_link_command(toolchain, &compiled, &dll)?)
+            })?;
+            dlls.push(dll);
+        }
+        Ok(dlls)
+    }
+
+    /// Write the `STRINGTABLE` of `language`, the strings of [`add_string()`] with the
+    /// values of [`set_localized_string()`]
+    ///
+    /// [`add_string()`]: #method.add_string
+    /// [`set_localized_string()`]: #method.set_localized_string
+    fn write_localized_strings<W: Write>(&self, f: &mut W, language: u16) -> Result<(), Error> {
+        let mut strings = self.strings.iter().map(|&(_, id, ref value)| (id, value)).collect::<Vec<_>>();
+        for &(id, _, ref value) in self.localized_strings.iter().filter(|&&(_, lang, _)| lang == language) {
+            match strings.iter_mut().find(|&&mut (known, _)| known == id) {
+                Some(string) => string.1 = value,
+                None => strings.push((id, value)),
+            }
+        }
+        if !strings.is_empty() {
+            writeln!(f, "STRINGTABLE")?;
+            writeln!(f, "{{")?;
+            for (id, value) in strings {
+                writeln!(f, "{} \"{}\"", id, self.escape(value))?;
+            }
+            writeln!(f, "}}")?;
+        }
+        Ok(())
+    }
+
+    /// Command line linking the compiled resource `input` into the resource-only DLL `dll`
+    fn satellite_link_command(&self, toolchain: Toolchain, input: &Path, dll: &Path) -> Result<Vec<OsString>, Error> {
+        let linker = match (self.linker_path.as_ref(), toolchain) {
+            (Some(path), _) => path.clone().into_os_string(),
+            (None, Toolchain::Msvc) => OsString::from("link.exe"),
+            (None, Toolchain::LlvmRc) => OsString::from("lld-link"),
+            // the linker of rustc, which cargo passes if one is configured for the target
+            (None, Toolchain::Gnu) => match self.env.var("RUSTC_LINKER") {
+                Some(linker) => OsString::from(linker),
+                None => OsString::from(self.gnu_linker()),
+            },
+        };
+        let mut command = vec![linker];
+        match toolchain {
+            Toolchain::Msvc | Toolchain::LlvmRc => {
+                command.extend(["/NOLOGO", "/DLL", "/NOENTRY"].iter().map(OsString::from));
+                if let Some(arch) = self.arch() {
+                    command.push(OsString::from(format!("/MACHINE:{}", arch.dirname().to_uppercase())));
+                }
+                command.push(prefixed_arg("/OUT:", self.resolve_path_os(dll)));
+            }
+            Toolchain::Gnu => {
+                // an entry point address of 0 means no entry point
+                command.extend(["-shared", "-nostdlib", "-e", "0", "-s", "-o"].iter().map(OsString::from));
+                command.push(self.resolve_path_os(dll));
+            }
+        }
+        command.push(self.resolve_path_os(input));
+        Ok(command)
+    }
+
+    /// The default linker of rustc for the `*-windows-gnu` targets, `gcc` on Windows and
+    /// the target prefixed `gcc` of MinGW-w64 for cross builds
+    fn gnu_linker(&self) -> String {
+        match self.arch() {
+            Some(arch) if !cfg!(windows) => format!("{}gcc", mingw::target_prefix(arch)),
+            _ => "gcc".to_string(),
+        }
+    }
+
+    /// Compile a resource with only the version info to `<name>.res` in the output directory
+    ///
+    /// Nothing is linked; the `.res` file is meant for companion binaries, see
+    /// [`write_version_info_file()`]. Returns the path of the compiled file.
+    ///
+    /// [`write_version_info_file()`]: #method.write_version_info_file
+    pub fn compile_version_info_file(&self, name: &str) -> Result<PathBuf, Error> {
+        let rc = self.output_dir()?.join(format!("{}.rc", name));
+        self.write_version_info_file(&rc)?;
+        self.compile_resource_file(self.toolchain(), &rc)
+    }
+
+    /// Export the version info to the application as compile-time environment variables
+    ///
+    /// With this option, [`compile()`] prints `cargo:rustc-env` instructions for the
+    /// `FileVersion`, `ProductVersion` and `ProductName` properties with the placeholders
+    /// resolved, so the application can show the same version strings as the resource:
+    ///
+    /// ```text
+    /// // main.rs
+    /// println!("{} {}", env!("WINRES_PRODUCT_NAME"), env!("WINRES_FILE_VERSION"));
+    /// ```
+    ///
+    /// Properties that are not set are not exported.
+    ///
+    /// [`compile()`]: #method.compile
+    pub fn export_build_env(&mut self, export: bool) -> &mut Self {
+        self.export_build_env = export;
+        self
+    }
+
+    /// The environment variables printed with [`export_build_env()`]
+    ///
+    /// [`export_build_env()`]: #method.export_build_env
+    fn build_env(&self) -> Result<Vec<(String, String)>, Error> {
+        let mut env = vec![];
+        for name in ["FileVersion", "ProductVersion", "ProductName"].iter() {
+            if let Some(value) = self.properties.get(*name) {
+                // cargo reads one instruction per line
+                let value = self.resolve_placeholders(value)?.replace(['\r', '\n'], " ");
+                env.push((format!("WINRES_{}", constant_name(&snake_case(name))), value));
+            }
+        }
+        Ok(env)
+    }
+
+    /// Write the version info as WiX include file, so an installer uses the same metadata
+    /// as the executable
+    ///
+    /// A relative `path` is relative to the output directory. The file defines the
+    /// preprocessor variables `ProductName`, `Manufacturer` (the `CompanyName` property),
+    /// `ProductVersion` and `FileVersion`, with the placeholders resolved. The versions are
+    /// the numeric `PRODUCTVERSION` and `FILEVERSION` fields, e.g. `1.2.3.0`, as MSI
+    /// requires. Properties that are not set are left out. Returns the path of the file.
+    ///
+    /// ```xml
+    /// <?include $(env.OUT_DIR)\product.wxi ?>
+    /// <Package Name="$(var.ProductName)" Manufacturer="$(var.Manufacturer)"
+    ///          Version="$(var.ProductVersion)" UpgradeCode="...">
+    /// ```
+    ///
+    /// Fails with [`Error::InvalidProperties`] if a value contains a `"`, which a WiX
+    /// `define` cannot hold, or if the major or minor version of `PRODUCTVERSION` is above
+    /// 255, the limit of MSI product versions.
+    ///
+    /// [`Error::InvalidProperties`]: enum.Error.html#variant.InvalidProperties
+    pub fn export_wix_fragment<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf, Error> {
+        let path = self.output_dir()?.join(path);
+        let mut defines = vec![];
+        for &(name, property) in [("ProductName", "ProductName"), ("Manufacturer", "CompanyName")].iter() {
+            if let Some(value) = self.properties.get(property) {
+                defines.push((name, self.resolve_placeholders(value)?.replace(['\r', '\n'], " ")));
+            }
+        }
+        for &(name, field) in [("ProductVersion", VersionInfo::PRODUCTVERSION),
+                               ("FileVersion", VersionInfo::FILEVERSION)].iter() {
+            if let Some(&v) = self.version_info.get(&field) {
+                defines.push((name, format!("{}.{}.{}.{}", (v >> 48) as u16, (v >> 32) as u16, (v >> 16) as u16, v as u16)));
+            }
+        }
+        let mut invalid = defines.iter()
+            .filter(|(_, value)| value.contains('"'))
+            .map(|&(name, _)| format!("{} contains a double quote, which a WiX define cannot hold", name))
+            .collect::<Vec<_>>();
+        if let Some(&v) = self.version_info.get(&VersionInfo::PRODUCTVERSION) {
+            if (v >> 48) as u16 > 255 || (v >> 32) as u16 > 255 {
+                invalid.push(format!("ProductVersion {}.{} is not an MSI version, the major and minor \
+                                      version must be at most 255", (v >> 48) as u16, (v >> 32) as u16));
+            }
+        }
+        if !invalid.is_empty() {
+            return Err(Error::InvalidProperties(invalid));
+        }
+
+        let mut wxi = String::new();
+        wxi.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<Include>\n");
+        for (name, value) in defines {
+            wxi.push_str(&format!("  <?define {} = \"{}\" ?>\n", name, value));
+        }
+        wxi.push_str("</Include>\n");
+        fs::write(&path, wxi)?;
+        Ok(path)
+    }
+
+    /// Compile the resource file `rc` to a `.res` file next to it
+    fn compile_resource_file(&self, toolchain: Toolchain, rc: &Path) -> Result<PathBuf, Error> {
+        let res = rc.with_extension("res");
+        let command = self.compiler_command_line(toolchain, rc, &res)?;
+        self.reported(|| self.run_command(&command))?;
+        Ok(res)
+    }
+
+    /// Export the path of the compiled resource to dependent packages
+    ///
+    /// When this crate is a `staticlib` linked into a C/C++ application, the resource has to
+    /// be linked into the final executable by the parent build system. With this option,
+    /// [`compile()`] prints `cargo:resource=<path>`, which cargo passes to the build scripts
+    /// of dependent packages as `DEP_<LINKS>_RESOURCE` if the package sets a `links` key:
+    ///
+    /// ```toml
+    /// [package]
+    /// links = "myapp"
+    /// ```
+    ///
+    /// The path is the compiled `.res` file (named `resource.lib`, unless linked with
+    /// [`LinkMode::LinkArg`]) for MSVC and llvm-rc and the COFF object `resource.o` for GNU,
+    /// see [`library_name()`] for other names.
+    ///
+    /// [`LinkMode::LinkArg`]: enum.LinkMode.html#variant.LinkArg
+    ///
+    /// [`library_name()`]: #method.library_name
+    ///
+    /// [`compile()`]: #method.compile
+    pub fn set_export_metadata(&mut self, export: bool) -> &mut Self {
+        self.export_metadata = export;
+        self
+    }
+
+    /// Run the resource compiler
+    ///
+    /// This function generates a resource file from the settings or
+    /// uses an existing resource file and passes it to the resource compiler
+    /// of your toolkit.
+    ///
+    /// Further more we will print the correct statements for
+    /// `cargo:rustc-link-lib=` and `cargo:rustc-link-search` on the console,
+    /// so that the cargo build script can link the compiled resource file.
+    /// The library is named `resource`, unless that name clashes, see [`library_name()`],
+    /// or a name is set with [`set_output_name()`]. If the build script already compiled
+    /// a resource with that name to the output directory, a number is appended, like
+    /// `resource_2`, so several resources can be linked into the package.
+    /// Use [`build()`] to get the directives instead of printing them.
+    ///
+    /// [`library_name()`]: #method.library_name
+    /// [`set_output_name()`]: #method.set_output_name
+    /// [`build()`]: #method.build
+    pub fn compile(&self) -> Result<(), Error> {
+        self.with_output_name(|library| self.compile_as(library, None)).map(|_| ())
+    }
+
+    /// Compile several independent resources and link all of them, e.g. a generated
+    /// resource and a legacy resource file
+    ///
+    /// Each resource is compiled with [`compile()`], so they get unique output names.
+    /// The resource ids must not clash, or the linker reports duplicate resources.
+    ///
+    /// ```rust,no_run
+    /// let mut generated = winres::WindowsResource::new();
+    /// generated.set_icon("app.ico");
+    /// let mut legacy = winres::WindowsResource::new();
+    /// legacy.set_resource_file("legacy/dialogs.rc");
+    /// winres::WindowsResource::compile_all(&[&generated, &legacy]).unwrap();
+    /// ```
+    ///
+    /// [`compile()`]: #method.compile
+    pub fn compile_all(resources: &[&WindowsResource]) -> Result<(), Error> {
+        for res in resources {
+            res.compile()?;
+        }
+        Ok(())
+    }
+
+    /// Run the resource compiler for the binary `bin` of a package with several binaries
+    ///
+    /// The resource is only linked into `bin`, with `cargo:rustc-link-arg-bin`, so every
+    /// binary can get its own icon and version info from its own builder:
+    ///
+    /// ```rust,no_run
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_icon("server.ico").set("FileDescription", "My server");
+    /// res.compile_for_bin("my-server").unwrap();
+    ///
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_icon("client.ico").set("FileDescription", "My client");
+    /// res.compile_for_bin("my-client").unwrap();
+    /// ```
+    ///
+    /// The output files are named after the binary, so the builders do not overwrite
+    /// each other's resources. This needs cargo 1.56 or newer.
+    ///
+    /// A `package.metadata.winres.bin.<name>` section in `Cargo.toml` overrides the settings
+    /// of `package.metadata.winres` for the binary, only for this compilation:
+    ///
+    /// ```toml
+    /// [package.metadata.winres.bin.my-client]
+    /// FileDescription = "My client"
+    /// icon = "client.ico"
+    /// ```
+    ///
+    /// The overrides are only read with the `cargo-metadata` feature.
+    pub fn compile_for_bin(&mut self, bin: &str) -> Result<(), Error> {
+        let library = format!("{}_{}", self.output_name(), bin.replace('-', "_"));
+        let saved = self.bin_settings();
+        self.for_bin(bin);
+        let result = self.apply_bin_metadata(bin).and_then(|_| self.compile_as(&library, Some(bin)).map(|_| ()));
+        self.restore_bin_settings(saved);
+        result
+    }
+
+    /// A copy of the settings [`compile_for_bin()`] may change
+    ///
+    /// [`compile_for_bin()`]: #method.compile_for_bin
+    fn bin_settings(&self) -> BinSettings {
+        BinSettings {
+            properties: self.properties.clone(),
+            default_properties: self.default_properties.clone(),
+            version_info: self.version_info.clone(),
+            icon: self.icon.clone(),
+            icons: self.icons.clone(),
+            strings: self.strings.clone(),
+            language: self.language,
+            localized: self.localized.clone(),
+            manifest: self.manifest.clone(),
+            manifest_builder: self.manifest_builder.clone(),
+            manifest_file: self.manifest_file.clone(),
+            metadata_warnings: self.metadata_warnings.clone(),
+        }
+    }
+
+    fn restore_bin_settings(&mut self, saved: BinSettings) {
+        self.properties = saved.properties;
+        self.default_properties = saved.default_properties;
+        self.version_info = saved.version_info;
+        self.icon = saved.icon;
+        self.icons = saved.icons;
+        self.strings = saved.strings;
+        self.language = saved.language;
+        self.localized = saved.localized;
+        self.manifest = saved.manifest;
+        self.manifest_builder = saved.manifest_builder;
+        self.manifest_file = saved.manifest_file;
+        self.metadata_warnings = saved.metadata_warnings;
+    }
+
+    /// Apply the `package.metadata.winres.bin.<bin>` section for [`compile_for_bin()`]
+    ///
+    /// [`compile_for_bin()`]: #method.compile_for_bin
+    #[cfg(feature = "cargo-metadata")]
+    fn apply_bin_metadata(&mut self, bin: &str) -> Result<(), Error> {
+        let overrides = match self.package_metadata()? {
+            Some(winres) => winres.get("bin").and_then(|b| b.get(bin)).cloned(),
+            None => None,
+        };
+        match overrides {
+            Some(overrides) =>
+                self.apply_metadata_section(&overrides, &format!("package.metadata.winres.bin.{}", bin), Path::new(""), true),
+            None => Ok(()),
+        }
+    }
+
+    #[cfg(not(feature = "cargo-metadata"))]
+    fn apply_bin_metadata(&mut self, _bin: &str) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Run the resource compiler without linking the resource
+    ///
+    /// Unlike [`compile()`], no `cargo:rustc-link-*` or `cargo:resource` directives are
+    /// printed, so packaging tools and other build systems can link the resource themselves.
+    /// Returns the path of the compiled file in the output directory, `resource.res` for
+    /// MSVC and llvm-rc and the COFF object `resource.o` for GNU, see [`set_output_name()`]
+    /// for other names.
+    ///
+    /// [`compile()`]: #method.compile
+    /// [`set_output_name()`]: #method.set_output_name
+    pub fn compile_to_res(&self) -> Result<PathBuf, Error> {
+        let mut directives = vec![];
+        let result = self.compile_to_res_with(&mut directives);
+        self.take_messages(&mut directives);
+        self.emit_directives(&directives);
+        result
+    }
+
+    /// Compile like [`compile_to_res()`], adding the warnings to `directives`
+    ///
+    /// In a dry run, the command lines are added as warnings instead of running them.
+    ///
+    /// [`compile_to_res()`]: #method.compile_to_res
+    fn compile_to_res_with(&self, directives: &mut Vec<String>) -> Result<PathBuf, Error> {
+        let library = self.output_name();
+        let (toolchain, output, mut rc) = self.prepare_resource_file(&library, directives)?;
+        let compiled = match toolchain {
+            Toolchain::Gnu => output.join(format!("{}.o", library)),
+            Toolchain::Msvc | Toolchain::LlvmRc => output.join(format!("{}.res", library)),
+        };
+        if toolchain == Toolchain::Gnu && !self.res_files.is_empty() {
+            let res = output.join(format!("{}.res", library));
+            if self.dry_run {
+                self.plan_command(&self.compiler_command_line(toolchain, &rc, &res)?, directives);
+            } else {
+                self.merge_res_files_with_gnu(&rc, &res)?;
+            }
+            rc = res;
+        }
+        let command = self.compiler_command_line(toolchain, &rc, &compiled)?;
+        if self.dry_run {
+            self.plan_command(&command, directives);
+            return Ok(compiled);
+        }
+        self.run_command(&command)?;
+        if toolchain != Toolchain::Gnu {
+            self.append_res_files(&compiled)?;
+        }
+        Ok(compiled)
+    }
+
+    /// Compile the resource like [`compile()`], but return the `cargo:` directives instead
+    /// of printing them
+    ///
+    /// This separates the generation and compilation from the side effects of a build
+    /// script, so tools outside of cargo, like a packaging GUI, can compile resources and
+    /// decide themselves what to do with the link instructions and warnings:
+    ///
+    /// ```rust,no_run
+    /// let res = winres::WindowsResource::new();
+    /// let output = res.build().unwrap();
+    /// println!("compiled {}", output.compiled.display());
+    /// for warning in output.directives.iter().filter_map(|d| d.strip_prefix("cargo:warning=")) {
+    ///     eprintln!("warning: {}", warning);
+    /// }
+    /// ```
+    ///
+    /// [`compile()`]: #method.compile
+    pub fn build(&self) -> Result<CompilationOutput, Error> {
+        self.with_output_name(|library| self.build_as(library, None, &mut vec![]))
+    }
+
+    /// Compile the resource like [`compile()`] and return what was generated and run
+    ///
+    /// The directives are printed as with [`compile()`], and also returned, together with
+    /// the resource script, the compiled file and the resource compiler, so a build script
+    /// can log, cache or post-process the artifacts:
+    ///
+    /// ```rust,no_run
+    /// let res = winres::WindowsResource::new();
+    /// let output = res.compile_artifacts().unwrap();
+    /// eprintln!("{} compiled {} to {}", output.tool.display(), output.script.display(),
+    ///           output.compiled.display());
+    /// ```
+    ///
+    /// [`compile()`]: #method.compile
+    pub fn compile_artifacts(&self) -> Result<CompilationOutput, Error> {
+        self.with_output_name(|library| self.compile_as(library, None))
+    }
+
+    /// Run `compile` with the output name of [`compile()`], made unique by a number
+    ///
+    /// The name is released if the compilation fails, so a retry gets the same name.
+    ///
+    /// [`compile()`]: #method.compile
+    fn with_output_name<T, F>(&self, compile: F) -> Result<T, Error>
+        where F: FnOnce(&str) -> Result<T, Error> {
+        let output = self.output_dir()?;
+        let library = claim_output_name(&output, &self.output_name());
+        let result = compile(&library);
+        if result.is_err() {
+            release_output_name(&output, &library);
+        }
+        result
+    }
+
+    /// Compile the resource to `<library>.lib` or `<library>.o` and print the directives
+    /// linking it into all binaries or only `bin`
+    fn compile_as(&self, library: &str, bin: Option<&str>) -> Result<CompilationOutput, Error> {
+        let mut directives = vec![];
+        let result = self.build_as(library, bin, &mut directives);
+        // warnings are printed even if the compilation failed
+        self.emit_directives(&directives);
+        result
+    }
+
+    /// Print `directives` for cargo in a build script, otherwise only the warnings to stderr,
+    /// see [`set_standalone()`]
+    ///
+    /// [`set_standalone()`]: #method.set_standalone
+    fn emit_directives(&self, directives: &[String]) {
+        if self.in_build_script() {
+            print_directives(directives);
+        } else {
+            for warning in directives.iter().filter_map(|d| d.strip_prefix("cargo:warning=")) {
+                eprintln!("warning: {}", warning);
+            }
+        }
+    }
+
+    /// Compile the resource to `<library>.lib` or `<library>.o`, adding the directives
+    /// linking it into all binaries or only `bin` to `directives`
+    ///
+    /// Returns the artifacts with a copy of the directives. The messages of [`log()`] are
+    /// added to the directives, also if the compilation fails. In a dry run, nothing is
+    /// compiled, so all directives become warnings.
+    ///
+    /// [`log()`]: #method.log
+    fn build_as(&self, library: &str, bin: Option<&str>, directives: &mut Vec<String>)
+                -> Result<CompilationOutput, Error> {
+        let result = self.build_steps(library, bin, directives);
+        self.take_messages(directives);
+        if self.dry_run {
+            for directive in directives.iter_mut().filter(|d| !d.starts_with("cargo:warning=")) {
+                *directive = format!("cargo:warning=dry run: {}", directive);
+            }
+        }
+        result.map(|output| CompilationOutput { directives: directives.clone(), ..output })
+    }
+
+    /// The steps of [`build_as()`]
+    ///
+    /// [`build_as()`]: #method.build_as
+    fn build_steps(&self, library: &str, bin: Option<&str>, directives: &mut Vec<String>)
+                   -> Result<CompilationOutput, Error> {
+        let (toolchain, output, script) = self.prepare_resource_file(library, directives)?;
+        self.write_rc_copy(&script)?;
+        if self.dry_run {
+            return self.plan_as(toolchain, &output, script, library, bin, directives);
+        }
+        let mut rc = script.clone();
+        if toolchain == Toolchain::Gnu && !self.res_files.is_empty() {
+            let res = output.join(format!("{}.res", library));
+            self.merge_res_files_with_gnu(&rc, &res)?;
+            rc = res;
+        }
+        let tool = self.compile_with_toolkit(toolchain, &rc, &output, library, directives)?;
+        let compiled = self.compiled_file(toolchain, &output, library);
+        if toolchain != Toolchain::Gnu {
+            self.append_res_files(&compiled)?;
+        }
+        for hook in self.post_compile_hooks.iter() {
+            hook.after_compile(self, &compiled)?;
+        }
+        self.add_link_directives(toolchain, &output, library, bin, directives)?;
+        Ok(CompilationOutput { script, compiled, toolchain, tool, directives: directives.clone() })
+    }
+
+    /// The dry run of [`build_as()`], adding the command lines it would run as warnings
+    ///
+    /// [`build_as()`]: #method.build_as
+    fn plan_as(&self, toolchain: Toolchain, output: &Path, script: PathBuf, library: &str, bin: Option<&str>,
+               directives: &mut Vec<String>) -> Result<CompilationOutput, Error> {
+        let mut rc = script.clone();
+        let mut commands = vec![];
+        if toolchain == Toolchain::Gnu && !self.res_files.is_empty() {
+            let res = output.join(format!("{}.res", library));
+            commands.push(self.prepare_command(self.tool.get(), &self.compiler_command_line(toolchain, &rc, &res)?));
+            rc = res;
+        }
+        let compile = self.process_commands(toolchain, &rc, library)?;
+        let tool = self.wrapped_program(&compile[0]);
+        commands.extend(compile);
+        for command in commands.iter() {
+            directives.push(format!("cargo:warning=dry run: {}", command_line(command)));
+        }
+        let compiled = self.compiled_file(toolchain, output, library);
+        self.add_link_directives(toolchain, output, library, bin, directives)?;
+        Ok(CompilationOutput { script, compiled, toolchain, tool, directives: directives.clone() })
+    }
+
+    /// Add `command` of [`compiler_command_line()`] as warning of a dry run to `directives`
+    ///
+    /// [`compiler_command_line()`]: #method.compiler_command_line
+    fn plan_command(&self, command: &[OsString], directives: &mut Vec<String>) {
+        let command = self.prepare_command(self.tool.get(), command);
+        directives.push(format!("cargo:warning=dry run: {}", command_line(&command)));
+    }
+
+    /// Add the directives linking `<library>` into all binaries or only `bin` and the
+    /// exported metadata to `directives`
+    fn add_link_directives(&self, toolchain: Toolchain, output: &Path, library: &str, bin: Option<&str>,
+                           directives: &mut Vec<String>) -> Result<(), Error> {
+        directives.extend(self.link_directives(toolchain, output, library, bin));
+        if self.export_metadata {
+            let compiled = self.compiled_file(toolchain, output, library);
+            directives.push(format!("cargo:resource={}", compiled.display()));
+        }
+        if self.export_build_env {
+            for (name, value) in self.build_env()? {
+                directives.push(format!("cargo:rustc-env={}={}", name, value));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the settings and write `<library>.rc` to the output directory, unless a
+    /// resource file is set
+    ///
+    /// Returns the toolchain, the output directory and the resource file to compile.
+    fn prepare_resource_file(&self, library: &str, directives: &mut Vec<String>)
+                             -> Result<(Toolchain, PathBuf, PathBuf), Error> {
+        if self.init_failed {
+            WindowsResource::try_new()?;
+        }
+        // fail before anything is written without an output directory
+        let output = self.output_dir()?;
+        self.report_metadata_warnings(directives)?;
+        let tool_env = TOOL_ENV_VARS.iter().any(|name| self.env.var(name).is_some());
+        if tool_env {
+            directives.extend(TOOL_ENV_VARS.iter().map(|name| format!("cargo:rerun-if-env-changed={}", name)));
+        }
+        let placeholder_env = self.placeholder_env_vars();
+        directives.extend(placeholder_env.iter().map(|name| format!("cargo:rerun-if-env-changed={}", name)));
+        self.check_input_files(directives)?;
+        // any rerun-if directive turns off the rerun of cargo on changes of the package
+        if tool_env || !placeholder_env.is_empty() {
+            for file in self.input_files()? {
+                directives.push(format!("cargo:rerun-if-changed={}", file.display()));
+            }
+        }
+        if self.resources_rs {
+            self.write_resources_rs(output.join("resources.rs"))?;
+        }
+        let toolchain = self.toolchain.or_else(|| target_toolchain(&*self.env)).ok_or(Error::UnsupportedToolchain)?;
+        if self.rc_encoding == RcEncoding::Utf16Le && toolchain != Toolchain::Msvc {
+            self.warn(directives, &format!("the {:?} toolchain cannot read UTF-16 resource scripts", toolchain));
+        }
+        let rc = output.join(format!("{}.rc", library));
+        if self.rc_file.is_none() {
+            if self.merge_manifests.is_empty() || self.rc_template.is_some() {
+                self.write_resource_file(&rc)?;
+                self.check_size_budget(None, directives)?;
+                if self.manifest_mode != ManifestMode::Embedded {
+                    self.write_external_manifest(None)?;
+                }
+            } else {
+                let merged = self.merge_manifests()?;
+                self.write_resource_file_with_manifest(&rc, Some(&merged))?;
+                self.check_size_budget(Some(&merged), directives)?;
+                if self.manifest_mode != ManifestMode::Embedded {
+                    self.write_external_manifest(Some(&merged))?;
+                }
+            }
+            if self.rc_template.is_some() {
+                // the properties, version info and language are available as variables
+                let ignored = self.ignored_settings().into_iter()
+                    .filter(|s| !["properties", "version info", "language"].contains(s))
+                    .collect::<Vec<_>>();
+                if !ignored.is_empty() {
+                    self.warn(directives, &format!("a resource template is set, ignoring {}", ignored.join(", ")));
+                }
+            }
+        } else {
+            let ignored = self.ignored_settings();
+            if !ignored.is_empty() {
+                self.warn(directives, &format!("a custom resource file is set, ignoring {}", ignored.join(", ")));
+            }
+        }
+        let rc = self.rc_file.clone().unwrap_or(rc);
+        Ok((toolchain, output, rc))
+    }
+
+    /// The target triple the resource is compiled for, as set by cargo
+    pub fn target(&self) -> Option<&str> {
+        self.target.as_deref()
+    }
+
+    /// The architecture the resource is compiled for
+    pub fn arch(&self) -> Option<sdk::Arch> {
+        sdk::Arch::arch_for_target_with_env(&*self.env)
+    }
+
+    /// The toolchain used to compile the resource
+    pub fn backend(&self) -> Toolchain {
+        self.toolchain()
+    }
+
+    /// Override the toolchain used to compile the resource
+    ///
+    /// By default it is chosen from the target environment of cargo's target, i.e.
+    /// `rc.exe` for `*-windows-msvc` and `windres` for `*-windows-gnu`. [`Toolchain::LlvmRc`]
+    /// works for both and does not need a Windows SDK, which is useful for cross builds.
+    ///
+    /// [`Toolchain::LlvmRc`]: enum.Toolchain.html#variant.LlvmRc
+    pub fn set_toolchain(&mut self, toolchain: Toolchain) -> &mut Self {
+        self.toolchain = Some(toolchain);
+        self
+    }
+
+    /// The toolchain used to compile the resource, see [`set_toolchain()`]
+    ///
+    /// [`set_toolchain()`]: #method.set_toolchain
+    pub fn toolchain(&self) -> Toolchain {
+        self.toolchain.unwrap_or_else(|| default_toolchain(&*self.env))
+    }
+
+    /// Set the path of `llvm-rc` for [`Toolchain::LlvmRc`], which is looked up in the `PATH`
+    /// by default
+    ///
+    /// [`Toolchain::LlvmRc`]: enum.Toolchain.html#variant.LlvmRc
+    pub fn set_llvm_rc_path<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.llvm_rc_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Look for the Windows SDKs only in the installed root `path`, e.g.
+    /// `C:\Program Files (x86)\Windows Kits\10`
+    ///
+    /// By default the installed roots of a developer command prompt are used, otherwise the
+    /// ones registered for the machine or the current user. Call this before
+    /// [`set_sdk_version()`].
+    ///
+    /// [`set_sdk_version()`]: #method.set_sdk_version
+    pub fn set_kits_root<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.kits_root = Some(path.as_ref().to_path_buf());
+        self.tool = OnceCell::new();
+        self
+    }
+
+    /// Compile with the resource compiler of the Windows SDK `version`, e.g. `"10.0.22621.0"`
+    ///
+    /// By default the SDK of `WindowsSDKVersion` or the newest installed SDK is used. A prefix
+    /// like `"10.0.22621"` selects the newest matching SDK. This only applies to the MSVC
+    /// toolchain, an error is returned if the SDK version is not installed.
+    ///
+    /// ```rust,no_run
+    /// let mut res = winres::WindowsResource::new();
+    /// res.set_sdk_version("10.0.22621.0").unwrap();
+    /// ```
+    pub fn set_sdk_version(&mut self, version: &str) -> Result<&mut Self, Error> {
+        let tools = get_sdk_tools(&*self.env, self.kits_root.as_deref())?;
+        let position = parse_sdk_version(version).and_then(|requested| {
+            tools.iter().position(|tool| sdk_version_matches(&tool.sdk_version, &requested))
+        });
+        match position {
+            Some(index) => {
+                self.tool = OnceCell::from(tools[index].clone());
+                Ok(self)
+            }
+            None => Err(Error::SdkVersionNotFound {
+                version: version.to_string(),
+                installed: tools.iter().map(|tool| tool.sdk_version.clone()).collect(),
+            }),
+        }
+    }
+
+    /// The resource compiler of the toolchain, see [`set_tool()`]
+    ///
+    /// Unless it was set, it is looked up on the first call: the newest Windows SDK for
+    /// MSVC, `windres` for GNU.
+    ///
+    /// [`set_tool()`]: #method.set_tool
+    pub fn tool(&self) -> Result<&sdk::Tool, Error> {
+        if let Some(tool) = self.tool.get() {
+            return Ok(tool);
+        }
+        let tool = match (self.toolchain(), &self.rc_path) {
+            (Toolchain::Msvc, Some(path)) => {
+                let arch = self.arch()
+                    .ok_or_else(|| Error::UnsupportedArch(self.env.var("CARGO_CFG_TARGET_ARCH").unwrap_or_default()))?;
+                let tool = sdk::rc_tool(arch, path);
+                self.log(&format!("using {} of Windows SDK {}, set by set_rc_path()", tool.path.display(), tool.sdk_version));
+                tool
+            }
+            (Toolchain::Msvc, None) => {
+                let mut trace = vec![];
+                let result = get_sdk(&*self.env, self.kits_root.as_deref(), &mut trace);
+                for decision in trace {
+                    self.log(&decision);
+                }
+                let (tool, reason) = result?;
+                self.log(&format!("using {} of Windows SDK {}, {}", tool.path.display(), tool.sdk_version, reason));
+                tool
+            }
+            (Toolchain::Gnu, _) | (Toolchain::LlvmRc, _) => {
+                let tool = get_mingw_tool(&*self.env);
+                self.log(&format!("using {}", tool.path.display()));
+                tool
+            }
+        };
+        Ok(self.tool.get_or_init(|| tool))
+    }
+
+    pub fn tool_path(&self) -> Result<&Path, Error> {
+        Ok(&self.tool()?.path)
+    }
+
+    /// The include dirs of the Windows SDK, empty if none is found
+    pub fn include_dirs<'a>(&'a self) -> Vec<&Path> {
+        self.tool().map(sorted_include_dirs).unwrap_or_default()
+    }
+
+    /// The library dirs of the Windows SDK for the target arch, like `um` and `ucrt`
+    ///
+    /// Build scripts linking SDK libraries can pass these to cargo:
+    ///
+    /// ```rust,no_run
+    /// let res = winres::WindowsResource::new();
+    /// for dir in res.lib_dirs() {
+    ///     println!("cargo:rustc-link-search=native={}", dir.display());
+    /// }
+    /// ```
+    pub fn lib_dirs(&self) -> Vec<&Path> {
+        let tool = match self.tool() {
+            Ok(tool) => tool,
+            Err(_) => return vec![],
+        };
+        let mut dirs = tool.lib_dirs.iter().collect::<Vec<_>>();
+        dirs.sort();
+        dirs.into_iter().map(|(_, dir)| dir.as_path()).collect()
+    }
+
+    /// Retry with the other installed Windows SDKs if `rc.exe` crashes
+    ///
+    /// Broken partial SDK updates can leave an `rc.exe` that crashes or cannot be started.
+    /// With this option, the `rc.exe` of the other installed SDKs are tried, newest first,
+    /// until one does not crash, and a cargo warning reports which SDK was used. Ordinary
+    /// compile errors are not retried, and neither is a timeout of [`set_tool_timeout()`].
+    ///
+    /// [`set_tool_timeout()`]: #method.set_tool_timeout
+    pub fn set_sdk_fallback(&mut self, fallback: bool) -> &mut Self {
+        self.sdk_fallback = fallback;
+        self
+    }
+
+    fn compile_with_msvc(&self, input: &Path, output_dir: &Path, library: &str, directives: &mut Vec<String>)
+                         -> Result<PathBuf, Error> {
+        let mut commands = self.process_commands(Toolchain::Msvc, input, library)?;
+        if self.rc_path.is_none() {
+            let tool = self.tool()?;
+            if !sdk::SdkVersion::new(&tool.sdk_version).is_numeric() {
+                self.warn(directives, &format!("Windows SDK version {} is not numeric, so it may not be \
+                                                ordered correctly", tool.sdk_version));
+            }
+            if let Some(requested) = requested_sdk_version(&*self.env) {
+                if !requested.is_numeric() {
+                    self.warn(directives, &format!("WindowsSDKVersion {} is not a numeric version", requested));
+                }
+                if !sdk_version_matches(&tool.sdk_version, &requested) {
+                    self.warn(directives, &format!("WindowsSDKVersion is {}, but rc.exe of SDK {} is used",
+                                                   requested, tool.sdk_version));
+                }
+            }
+        }
+        let mut status = self.capture_output(&mut commands[0]);
+        let mut used = self.wrapped_program(&commands[0]);
+
+        if self.sdk_fallback && self.rc_path.is_none() && should_retry(&status) {
+            let output = self.compiled_file(Toolchain::Msvc, output_dir, library);
+            let current = self.tool()?;
+            for tool in get_sdk_tools(&*self.env, self.kits_root.as_deref())?.iter().filter(|tool| tool.path != current.path) {
+                let rc = self.wrapped(self.rc_command_line(tool, input, &output));
+                self.log(&format!("rc.exe crashed, retrying with SDK {}", tool.sdk_version));
+                status = self.capture_output(&mut self.prepare_command(Some(tool), &rc));
+                match status {
+                    // a hung rc.exe of another SDK would only take the timeout again
+                    Err(Error::ToolTimedOut { .. }) => break,
+                    Ok(ref s) if !is_crash(&s.status) => {
+                        self.warn(directives, &format!("rc.exe of SDK {} failed, used SDK {} instead",
+                                                       current.sdk_version, tool.sdk_version));
+                        used = tool.path.clone();
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        let status = status?;
+        self.log_tool_output("RC", &status);
+        if !status.status.success() {
+            // rc.exe reports syntax errors on stdout
+            let stdout = String::from_utf8_lossy(&status.stdout);
+            let stderr = String::from_utf8_lossy(&status.stderr);
+            let diagnostics = rc_errors::parse_rc_output(&stdout).into_iter()
+                .chain(rc_errors::parse_rc_output(&stderr))
+                .map(|diagnostic| diagnostic.to_string())
+                .collect::<Vec<_>>();
+            let message = if diagnostics.is_empty() {
+                format!("{}{}", stdout, stderr)
+            } else {
+                diagnostics.join("\n")
+            };
+            return Err(Error::ToolFailed {
+                tool: commands[0].get_program().to_string_lossy().into_owned(),
+                command: command_line(&commands[0]),
+                exit_code: status.status.code(),
+                stderr: message,
+            });
+        }
+        Ok(used)
+    }
+}
+
+/// Print `directives` for cargo, the only place the compilation writes them to stdout
+fn print_directives(directives: &[String]) {
+    for directive in directives {
+        println!("{}", directive);
+    }
+}
+
+/// The toolchain of the cargo target, from `CARGO_CFG_TARGET_ENV`
+///
+/// Outside of a build script the target environment of winres itself is used.
+/// Returns `None` for target environments without a resource compiler.
+fn target_toolchain(env: &dyn EnvSource) -> Option<Toolchain> {
+    let target_env = env.var("CARGO_CFG_TARGET_ENV").unwrap_or_else(|| {
+        if cfg!(target_env = "msvc") { "msvc" } else if cfg!(target_env = "gnu") { "gnu" } else { "" }.to_string()
+    });
+    match target_env.as_str() {
+        "msvc" => Some(Toolchain::Msvc),
+        "gnu" | "gnullvm" => Some(Toolchain::Gnu),
+        _ => None,
+    }
+}
+
+/// The toolchain of the cargo target, GNU if it has no resource compiler
+fn default_toolchain(env: &dyn EnvSource) -> Toolchain {
+    target_toolchain(env).unwrap_or(Toolchain::Gnu)
+}
+
+/// Include directories of `tool`, sorted by name so the include order is stable
+fn sorted_include_dirs(tool: &sdk::Tool) -> Vec<&Path> {
+    let mut dirs = tool.include_dirs.iter().collect::<Vec<_>>();
+    dirs.sort();
+    dirs.into_iter().map(|(_, dir)| dir.as_path()).collect()
+}
+
+/// An argument of `prefix` directly followed by `value`, like `/I<path>`
+fn prefixed_arg(prefix: &str, value: OsString) -> OsString {
+    let mut arg = OsString::from(prefix);
+    arg.push(value);
+    arg
+}
+
+/// Whether `rc.exe` crashed instead of reporting errors in the resource file
+fn is_crash(status: &process::ExitStatus) -> bool {
+    match status.code() {
+        Some(code) => code != 0 && code != 1 && code != 2,
+        None => true,
+    }
+}
+
+/// Whether `rc.exe` is retried with another SDK after `status`, see
+/// [`WindowsResource::set_sdk_fallback()`]
+///
+/// A crash or a failure to run it is retried, but not a timeout.
+///
+/// [`WindowsResource::set_sdk_fallback()`]: struct.WindowsResource.html#method.set_sdk_fallback
+fn should_retry(status: &Result<process::Output, Error>) -> bool {
+    match *status {
+        Ok(ref output) => is_crash(&output.status),
+        Err(Error::ToolTimedOut { .. }) => false,
+        Err(_) => true,
+    }
+}
+
+/// All `rc.exe` tools for the target arch, newest SDK first
+///
+/// Only the SDKs of `kits_root` are searched if it is given. Returns
+/// [`Error::SdkNotFound`] with the searched `bin` directories if there is none.
+fn get_sdk_tools(env: &dyn EnvSource, kits_root: Option<&Path>) -> Result<Vec<sdk::Tool>, Error> {
+    let (system, arch) = sdk_system(env, kits_root)?;
+    rc_tools(&system, arch)
+}
+
+/// The installed SDKs, only the ones of `kits_root` if it is given, and the target arch
+/// of `env`
+fn sdk_system(env: &dyn EnvSource, kits_root: Option<&Path>) -> Result<(sdk::System, sdk::Arch), Error> {
+    let system = match kits_root {
+        Some(kits_root) => sdk::System::from_installed_roots(sdk::InstalledRoots::scan(&[kits_root.to_path_buf()]))?,
+        // use the reg command, so we don't need a winapi dependency
+        None => sdk::System::new_with_env(env)?,
+    };
+    let arch = sdk::Arch::arch_for_target_with_env(env)
+        .ok_or_else(|| Error::UnsupportedArch(env.var("CARGO_CFG_TARGET_ARCH").unwrap_or_default()))?;
+    Ok((system, arch))
+}
+
+/// The `rc.exe` tools that run on this machine, for building for `arch`
+fn rc_tools(system: &sdk::System, arch: sdk::Arch) -> Result<Vec<sdk::Tool>, Error> {
+    let host = sdk::Arch::host().unwrap_or(arch);
+    let tools = system.find_host_tools("rc.exe", arch, host);
+    if tools.is_empty() {
+        return Err(Error::SdkNotFound { searched: runnable_bin_dirs(system, host) });
+    }
+    Ok(tools)
+}
+
+/// The `bin` dirs of the installed SDKs with tools that run on `host`
+fn runnable_bin_dirs(system: &sdk::System, host: sdk::Arch) -> Vec<PathBuf> {
+    system.sdks.iter()
+        .flat_map(|sdk| host.runnable_archs().iter()
+            .filter_map(move |bin_arch| sdk.sdk_archs.get(bin_arch).map(|sdk_arch| sdk_arch.bin_dir.clone())))
+        .collect()
+}
+
+/// Find `windres` for the target arch, see [`mingw::find_windres()`]
+///
+/// If none is found, `windres.exe` is run from the `PATH`, so the error of compiling
+/// names the missing tool.
+fn get_mingw_tool(env: &dyn EnvSource) -> sdk::Tool {
+    let arch = sdk::Arch::arch_for_target_with_env(env).unwrap_or(sdk::Arch::X64);
+    mingw::find_windres(arch).unwrap_or_else(|| mingw::windres_tool(arch, Path::new("windres.exe")))
+}
+
+/// Find the `rc.exe` of a Windows SDK, with the reason it was selected
+///
+/// The result is cached in `OUT_DIR`, so incremental builds skip the discovery, see
+/// [`sdk::read_cached_tool()`].
+///
+/// Every considered `rc.exe` and why it was rejected or chosen is added to `trace`, also if
+/// none is found.
+fn get_sdk(env: &dyn EnvSource, kits_root: Option<&Path>, trace: &mut Vec<String>)
+           -> Result<(sdk::Tool, String), Error> {
+    let cache = env.var("OUT_DIR").map(|dir| PathBuf::from(dir).join(SDK_CACHE_FILE));
+    let mut key = sdk::cache_key_with_env(env);
+    if let Some(kits_root) = kits_root {
+        key.push_str(&format!(";kits_root={}", kits_root.display()));
+    }
+    if let Some(cache) = cache.as_ref() {
+        if let Some(tool) = sdk::read_cached_tool(cache, &key) {
+            return Ok((tool, format!("cached in {}", cache.display())));
+        }
+    }
+
+    let env_version = requested_sdk_version(env);
+    let (system, arch) = sdk_system(env, kits_root)?;
+    let candidates = system.candidates("rc.exe", sdk::Arch::host().unwrap_or(arch));
+    let mut tools = match rc_tools(&system, arch) {
+        Ok(tools) => tools,
+        Err(e) => {
+            trace.extend(candidates.iter().map(|candidate| {
+                format!("rejected {}: {}", candidate.path.display(), candidate.rejected.as_deref().unwrap_or(""))
+            }));
+            return Err(e);
+        }
+    };
+
+    // prefer the SDK of a developer command prompt, otherwise take the newest
+    let position = env_version.as_ref().and_then(|ev| {
+        tools.iter().position(|tool| sdk_version_matches(&tool.sdk_version, ev))
+    });
+    let reason = match (position, env_version.as_ref()) {
+        (Some(_), _) => "selected by WindowsSDKVersion".to_string(),
+        (None, Some(ev)) => format!("WindowsSDKVersion {} is not installed, the newest of {} installed SDKs",
+                                    ev, tools.len()),
+        (None, None) => format!("the newest of {} installed SDKs", tools.len()),
+    };
+    let tool = tools.swap_remove(position.unwrap_or(0));
+    for candidate in candidates {
+        let decision = match candidate.rejected {
+            Some(rejected) => format!("rejected {}: {}", candidate.path.display(), rejected),
+            None if candidate.path == tool.path => format!("chose {}: {}", candidate.path.display(), reason),
+            None if position.is_some() => format!("skipped {}: WindowsSDKVersion is {}", candidate.path.display(),
+                                                  env_version.as_ref().map(|ev| ev.as_str()).unwrap_or("")),
+            None => format!("skipped {}: SDK {} is newer", candidate.path.display(), tool.sdk_version),
+        };
+        trace.push(decision);
+    }
+    if let Some(cache) = cache {
+        // the cache only saves time, a failure to write it is not an error
+        let _ = sdk::write_cached_tool(&cache, &key, &tool);
+    }
+    Ok((tool, reason))
+}
+
+/// The SDK version of a developer command prompt, `WindowsSDKVersion` without the trailing
+/// backslash that `vcvarsall` adds, e.g. `10.0.22621.0`
+fn requested_sdk_version(env: &dyn EnvSource) -> Option<sdk::SdkVersion> {
+    env.var("WindowsSDKVersion").and_then(|version| parse_sdk_version(&version))
+}
+
+fn parse_sdk_version(version: &str) -> Option<sdk::SdkVersion> {
+    let version = version.trim().trim_end_matches('\\');
+    if version.is_empty() {
+        None
+    } else {
+        Some(sdk::SdkVersion::new(version))
+    }
+}
+
+/// Whether the SDK `version` is `requested` or starts with it, e.g. `10.0.22621.0` for
+/// `10.0.22621`
+fn sdk_version_matches(version: &str, requested: &sdk::SdkVersion) -> bool {
+    sdk::SdkVersion::new(version).starts_with(requested)
+}
+
+/// The command line of `command` for messages, with arguments containing spaces quoted
+fn command_line(command: &process::Command) -> String {
+    Some(command.get_program()).into_iter().chain(command.get_args())
+        .map(|arg| quote_arg(&arg.to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn quote_arg(arg: &str) -> String {
+    if arg.is_empty() || arg.contains(char::is_whitespace) {
+        format!("\"{}\"", arg)
+    } else {
+        arg.to_string()
+    }
+}
+
+/// Read `reader` to the end in a new thread
+fn read_in_thread<R: Read + Send + 'static>(mut reader: R) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut data = vec![];
+        let _ = reader.read_to_end(&mut data);
+        data
+    })
+}
+
+/// File name of the SDK discovery cache in `OUT_DIR`
+const SDK_CACHE_FILE: &str = "winres-sdk-cache.txt";
+
+/// Error for a tool that exited unsuccessfully
+///
+/// Some tools report errors on stdout, which is used if nothing was written to stderr.
+fn tool_failed(tool: &str, command: &str, output: &process::Output) -> Error {
+    let stderr = if output.stderr.is_empty() { &output.stdout } else { &output.stderr };
+    Error::ToolFailed {
+        tool: tool.to_string(),
+        command: command.to_string(),
+        exit_code: output.status.code(),
+        stderr: String::from_utf8_lossy(stderr).into_owned(),
+    }
+}
+
+/// The output files of the resources compiled by the build script, without extension
+static COMPILED_OUTPUTS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// `name`, or `name_2`, `name_3` and so on if a resource was already compiled to
+/// `output_dir` with that name
+fn claim_output_name(output_dir: &Path, name: &str) -> String {
+    let mut compiled = COMPILED_OUTPUTS.lock().unwrap_or_else(|e| e.into_inner());
+    let unique = (1..).map(|n| if n == 1 { name.to_string() } else { format!("{}_{}", name, n) })
+        .find(|unique| !compiled.contains(&output_dir.join(unique)))
+        .unwrap_or_else(|| name.to_string());
+    compiled.push(output_dir.join(&unique));
+    unique
+}
+
+/// Release a name of [`claim_output_name()`] whose resource failed to compile
+///
+/// [`claim_output_name()`]: fn.claim_output_name.html
+fn release_output_name(output_dir: &Path, name: &str) {
+    let path = output_dir.join(name);
+    COMPILED_OUTPUTS.lock().unwrap_or_else(|e| e.into_inner()).retain(|compiled| *compiled != path);
+}
+
+/// Parsed `Cargo.toml` files, shared by all resources created by a build script
+#[cfg(feature = "cargo-metadata")]
+static CARGO_TOML_CACHE: Mutex<Option<HashMap<PathBuf, toml::Value>>> = Mutex::new(None);
+
+/// Read and parse the `Cargo.toml` in `manifest_dir`, or take it from the cache
+///
+/// A file that is not valid TOML results in an `Error::InvalidCargoToml`.
+#[cfg(feature = "cargo-metadata")]
+fn read_cargo_toml(manifest_dir: &Path) -> Result<toml::Value, Error> {
+    read_toml_file(&manifest_dir.join("Cargo.toml"))
+}
+
+/// Parse a JSON configuration file into the same tree as a TOML one
+#[cfg(feature = "json-config")]
+fn parse_json_config(source: &str) -> Result<toml::Value, String> {
+    serde_json::from_str(source).map_err(|e| e.to_string())
+}
+
+/// Parse a JSON configuration file into the same tree as a TOML one
+#[cfg(all(feature = "cargo-metadata", not(feature = "json-config")))]
+fn parse_json_config(_source: &str) -> Result<toml::Value, String> {
+    Err("reading JSON needs the `json-config` feature of winres".to_string())
+}
+
+/// Read and parse the manifest `cargo`, or take it from the cache
+#[cfg(feature = "cargo-metadata")]
+fn read_toml_file(cargo: &Path) -> Result<toml::Value, Error> {
+    let mut cache = CARGO_TOML_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    let cache = cache.get_or_insert_with(HashMap::new);
+    if let Some(ml) = cache.get(cargo) {
+        return Ok(ml.clone());
+    }
+
+    let mut cargo_toml = String::new();
+    fs::File::open(cargo)?.read_to_string(&mut cargo_toml)?;
+    let ml = cargo_toml.parse::<toml::Value>()
+        .map_err(|e| Error::InvalidCargoToml { path: cargo.to_path_buf(), message: e.to_string() })?;
+    cache.insert(cargo.to_path_buf(), ml.clone());
+    Ok(ml)
+}
+
+/// [`BinaryType::Dll`] if the package in `manifest_dir` has a `cdylib` or `dylib` and
+/// no binary target
+#[cfg(feature = "cargo-metadata")]
+fn detect_binary_type(manifest_dir: &Path) -> BinaryType {
+    let ml = match read_cargo_toml(manifest_dir) {
+        Ok(ml) => ml,
+        Err(_) => return BinaryType::Exe,
+    };
+    let dylib = ml.get("lib")
+        .and_then(|lib| lib.get("crate-type"))
+        .and_then(toml::Value::as_array)
+        .map(|types| types.iter().any(|t| t.as_str() == Some("cdylib") || t.as_str() == Some("dylib")))
+        .unwrap_or(false);
+    let has_bin = ml.get("bin").and_then(toml::Value::as_array).map(|bins| !bins.is_empty()).unwrap_or(false)
+        || manifest_dir.join("src").join("main.rs").exists();
+    if dylib && !has_bin {
+        BinaryType::Dll
+    } else {
+        BinaryType::Exe
+    }
+}
+
+/// Without the `cargo-metadata` feature, the crate types are not read
+#[cfg(not(feature = "cargo-metadata"))]
+fn detect_binary_type(_manifest_dir: &Path) -> BinaryType {
+    BinaryType::Exe
+}
+
+/// The standard names of the `StringFileInfo` block
+const KNOWN_PROPERTIES: [&str; 12] = ["Comments", "CompanyName", "FileDescription", "FileVersion", "InternalName",
+                                      "LegalCopyright", "LegalTrademarks", "OriginalFilename", "PrivateBuild",
+                                      "ProductName", "ProductVersion", "SpecialBuild"];
+
+/// The properties Windows requires in every `StringFileInfo` block
+const REQUIRED_PROPERTIES: [&str; 7] = ["CompanyName", "FileDescription", "FileVersion", "InternalName",
+                                        "OriginalFilename", "ProductName", "ProductVersion"];
+
+/// The standard name `name` is a misspelling of, if it differs in case or by at most two edits
+fn misspelled_property(name: &str) -> Option<&'static str> {
+    if KNOWN_PROPERTIES.contains(&name) {
+        return None;
+    }
+    KNOWN_PROPERTIES.iter().cloned()
+        .find(|known| known.eq_ignore_ascii_case(name) || edit_distance(known, name) <= 2)
+}
+
+/// The Levenshtein distance of `a` and `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == cb { previous } else { 1 + previous.min(current).min(row[j]) };
+            previous = current;
+        }
+    }
+    row[b.len()]
+}
+
+/// Keys of `package.metadata.winres` that are settings instead of string properties
+#[cfg(feature = "cargo-metadata")]
+const METADATA_SETTINGS: [&str; 9] = ["icon", "icons", "strings", "language", "manifest", "manifest-file",
+                                      "version-info", "lang", "bin"];
+
+/// Replace `${name}` placeholders in `value` using `lookup`
+///
+/// Names consist of ASCII letters, digits, `_`, `.` and `-`. `$${` is a literal `${`, and a
+/// `${` that does not start a placeholder is kept. Returns the name of the first
+/// placeholder without a value as error.
+fn expand_placeholders<F: Fn(&str) -> Option<String>>(value: &str, lookup: F) -> Result<String, String> {
+    let mut expanded = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            expanded.push_str(&rest[..start - 1]);
+            expanded.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start + 2..];
+        let name_len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || "_.-".contains(c))).unwrap_or(rest.len());
+        if name_len == 0 || !rest[name_len..].starts_with('}') {
+            expanded.push_str("${");
+            continue;
+        }
+        let key = &rest[..name_len];
+        expanded.push_str(&lookup(key).ok_or_else(|| key.to_string())?);
+        rest = &rest[name_len + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Replace the `@NAME@` variables in `template` with the values of `lookup`
+///
+/// An `@` that does not start a variable name is kept. Returns the name of the first
+/// variable without a value as error.
+fn expand_template_variables<F: Fn(&str) -> Option<String>>(template: &str, lookup: F) -> Result<String, String> {
+    let mut expanded = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('@') {
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+        let name_len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+        if name_len == 0 || !rest[name_len..].starts_with('@') {
+            expanded.push('@');
+            continue;
+        }
+        let name = &rest[..name_len];
+        expanded.push_str(&lookup(name).ok_or_else(|| name.to_string())?);
+        rest = &rest[name_len + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// `name` as Rust constant name, upper case with `_` for other characters than letters and digits
+fn constant_name(name: &str) -> String {
+    let mut constant = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect::<String>();
+    if constant.is_empty() || constant.starts_with(|c: char| c.is_ascii_digit()) {
+        constant.insert(0, '_');
+    }
+    constant
+}
+
+/// `name` in snake case, e.g. `Product_Name` for `ProductName`
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    let mut previous = None;
+    for c in name.chars() {
+        if c.is_uppercase() && previous.map(|p: char| p.is_lowercase() || p.is_ascii_digit()).unwrap_or(false) {
+            snake.push('_');
+        }
+        snake.push(c);
+        previous = Some(c);
+    }
+    snake
+}
+
+/// Whether cargo's `PROFILE` and `DEBUG` build script variables describe a debug build
+fn is_debug_build(profile: Option<&str>, debug: Option<&str>) -> bool {
+    match profile {
+        Some(profile) => profile != "release",
+        None => !matches!(debug, None | Some("false") | Some("0") | Some("none")),
+    }
+}
+
+/// The trailing number of a pre-release identifier, e.g. `4` for `rc.4` or `beta4`, and `0`
+/// without one
+fn prerelease_revision(pre: &str) -> Result<u16, String> {
+    let digits = pre.chars().rev().take_while(char::is_ascii_digit).count();
+    if digits == 0 {
+        return Ok(0);
+    }
+    pre[pre.len() - digits..].parse().map_err(|_| format!(
+        "pre-release {:?} does not end with a number between 0 and 65535", pre))
+}
+
+/// Pack a dotted version string with two to four components
+fn parse_version_string(version: &str) -> Result<u64, String> {
+    let parts = version.trim().split('.').collect::<Vec<_>>();
+    if parts.len() < 2 || parts.len() > 4 {
+        return Err(format!("version {:?} needs two to four components", version));
+    }
+    let mut packed = 0u64;
+    for (i, part) in parts.iter().enumerate() {
+        let component = part.parse::<u16>()
+            .map_err(|_| format!("version component {:?} of {:?} is not a number between 0 and 65535",
+                                 part, version))?;
+        packed |= (component as u64) << (48 - 16 * i);
+    }
+    Ok(packed)
+}
+
+/// Pack the `MAJOR.MINOR.PATCH` part of a cargo version into the
+/// four word version format of the version info struct
+#[cfg(feature = "cargo-metadata")]
+fn parse_semver(version: &str) -> Result<u64, String> {
+    let core = version.split(['-', '+']).next().unwrap_or("");
+    let mut packed = 0u64;
+    for (i, part) in core.split('.').take(3).enumerate() {
+        let component = part.parse::<u16>()
+            .map_err(|_| format!("version component {:?} of {:?} is not a number between 0 and 65535",
+                                 part, version))?;
+        packed |= u64::from(component) << (48 - 16 * i);
+    }
+    Ok(packed)
+}
+
+/// A component of the package version set by cargo, e.g. `CARGO_PKG_VERSION_MAJOR`
+///
+/// The version info stores each component in 16 bits, so larger numbers are an error
+/// instead of spilling into the next component.
+fn cargo_version_component(env: &dyn EnvSource, name: &str) -> Result<u16, Error> {
+    let value = cargo_env(env, name)?;
+    value.parse().map_err(|_| Error::InvalidVersionString(
+        format!("{} is {:?}, not a number between 0 and 65535", name, value)))
+}
+
+/// An environment variable cargo sets for build scripts
+fn cargo_env(env: &dyn EnvSource, name: &str) -> Result<String, Error> {
+    env.var(name).ok_or_else(|| Error::EnvVarNotSet(name.to_string()))
+}
+
+/// The package authors without email addresses, separated by commas
+fn cargo_authors(env: &dyn EnvSource) -> String {
+    env.var("CARGO_PKG_AUTHORS").unwrap_or_default()
+        .split(':')
+        .map(|author| author.split('<').next().unwrap_or_default().trim())
+        .filter(|author| !author.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The current year, or the year of `SOURCE_DATE_EPOCH` if it is set
+fn current_year(env: &dyn EnvSource) -> i64 {
+    let seconds = env.var("SOURCE_DATE_EPOCH").and_then(|epoch| epoch.trim().parse().ok())
+        .unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0));
+    year_of_day(seconds.div_euclid(86400))
+}
+
+/// The year of the day `days` after 1970-01-01, in the proleptic Gregorian calendar
+fn year_of_day(days: i64) -> i64 {
+    // shifted to start on 0000-03-01, so leap days are at the end of the 400 year eras
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let year = year_of_era + era * 400;
+    // January and February belong to the next year
+    if day_of_year >= 306 { year + 1 } else { year }
+}
+
+/// The file name of the binary of the package `name`
+fn original_filename(name: &str, binary_type: BinaryType) -> String {
+    match binary_type {
+        BinaryType::Exe => format!("{}.exe", name),
+        // cargo names libraries after the crate name
+        BinaryType::Dll => format!("{}.dll", name.replace('-', "_")),
+        BinaryType::Driver(_) => format!("{}.sys", name.replace('-', "_")),
+    }
+}
+
+/// Read the default language from `WINRES_LANG`, falling back to neutral
+///
+/// Returns the warning for an unknown language.
+fn default_language(env: &dyn EnvSource) -> Result<u16, String> {
+    match env.var("WINRES_LANG") {
+        Some(lang) => parse_language(&lang)
+            .ok_or_else(|| format!("WINRES_LANG={} is not a known language, using neutral", lang)),
+        None => Ok(0),
+    }
+}
+
+/// The code page declared by `#pragma code_page()` in the resource script `path`
+///
+/// Scripts that do not exist yet are written by winres, which declares UTF-8.
+fn declared_code_page(path: &Path) -> Option<u32> {
+    let script = match fs::read(path) {
+        Ok(script) => String::from_utf8_lossy(&script).into_owned(),
+        Err(_) => return Some(65001),
+    };
+    script.lines()
+        .filter_map(|line| line.trim().strip_prefix("#pragma"))
+        .filter_map(|pragma| pragma.trim().strip_prefix("code_page"))
+        .filter_map(|args| args.trim().strip_prefix('('))
+        .filter_map(|args| args.split(')').next())
+        .filter_map(|code_page| code_page.trim().parse().ok())
+        .next()
+}
+
+/// The BFD target windres writes for the target triple `target`
+fn windres_target(target: &str) -> Option<&'static str> {
+    match target.split('-').next() {
+        Some("x86_64") => Some("pe-x86-64"),
+        Some("i686") | Some("i586") | Some("i386") => Some("pe-i386"),
+        _ => None,
+    }
+}
+
+/// The `processorArchitecture` of an assembly identity for the target triple `target`
+fn processor_architecture(target: Option<&str>) -> &'static str {
+    match target.and_then(|target| target.split('-').next()) {
+        Some("x86_64") => "amd64",
+        Some("i686") | Some("i586") | Some("i386") => "x86",
+        Some("aarch64") => "arm64",
+        _ => "*",
+    }
+}
+
+/// Parse a language id, given either as a number (`0x0407`, `1031`)
+/// or as a language tag (`de-DE`)
+fn parse_language(lang: &str) -> Option<u16> {
+    let lang = lang.trim();
+    if lang.starts_with("0x") || lang.starts_with("0X") {
+        return u16::from_str_radix(&lang[2..], 16).ok();
+    }
+    if let Ok(id) = lang.parse::<u16>() {
+        return Some(id);
+    }
+    language::resolve_tag(lang)
+}
+
+/// Check that `name` can be written unquoted as resource name, or as resource type if
+/// `type_name` is set, which may also be a number
+fn check_resource_name(name: &str, type_name: bool) -> Result<(), Error> {
+    let numeric = type_name && name.parse::<u16>().is_ok();
+    let identifier = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if numeric || identifier {
+        Ok(())
+    } else {
+        Err(Error::InvalidResourceName(name.to_string()))
+    }
+}
+
+/// Escape `string` for a quoted string of a resource script compiled with `toolchain`
+pub(crate) fn escape_string(string: &str, toolchain: Toolchain) -> String {
+    let mut escaped = String::new();
+    for chr in string.chars() {
+        // In quoted RC strings, double-quotes are escaped by using two
+        // consecutive double-quotes.  Other characters are escaped in the
+        // usual C way using backslashes. `\"` is an error for llvm-rc and
+        // deprecated for windres, so it is never used.
+        match chr {
+            '"' => escaped.push_str("\"\""),
+            // windres and llvm-rc keep the backslash of unknown escapes
+            '\'' if toolchain == Toolchain::Msvc => escaped.push_str("\\'"),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            _ => escaped.push(chr),
+        };
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::escape_string;
+    use super::parse_language;
+    use super::{parse_sdk_version, sdk_version_matches};
+    use super::{declared_code_page, processor_architecture, windres_target};
+    use super::{original_filename, year_of_day};
+    #[cfg(feature = "cargo-metadata")]
+    use super::{cargo_version_component, parse_semver};
+    use super::expand_placeholders;
+    use super::parse_version_string;
+    use super::prerelease_revision;
+    use super::is_debug_build;
+    use super::misspelled_property;
+    use super::get_sdk;
+    use super::{BinaryType, DriverSubtype, Error, FileFlags, FileOs, LinkMode, ManifestMode, Preset, RcEncoding, ResourceId, TargetKind,
+                Toolchain, Verbosity, VersionInfo, WindowsResource};
+    use std::collections::HashMap;
+    use std::ops::Deref;
+    use std::path::{Path, PathBuf};
+    use std::{env, fs};
+
+    /// A directory `winres-<name>` in the temp dir, emptied when created and removed on drop
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> TempDir {
+            let path = env::temp_dir().join(format!("winres-{}", name));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).expect("create temp dir");
+            TempDir(path)
+        }
+    }
+
+    impl Deref for TempDir {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl AsRef<Path> for TempDir {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Compile `res` into `dir` with `true` as llvm-rc, so only the resource script is written
+    fn with_fake_llvm_rc<'a>(res: &'a mut WindowsResource, dir: &Path) -> &'a mut WindowsResource {
+        res.set_output_directory(dir).set_toolchain(Toolchain::LlvmRc).set_llvm_rc_path("true")
+    }
+
+    /// The variables cargo sets for the build script of the package `calc` 1.0.0, with `vars`
+    /// added or replaced
+    fn package_env(vars: &[(&str, &str)]) -> HashMap<String, String> {
+        [
+            ("CARGO_PKG_NAME", "calc"), ("CARGO_PKG_VERSION", "1.0.0"), ("CARGO_PKG_VERSION_MAJOR", "1"),
+            ("CARGO_PKG_VERSION_MINOR", "0"), ("CARGO_PKG_VERSION_PATCH", "0"), ("CARGO_PKG_DESCRIPTION", ""),
+            ("CARGO_MANIFEST_DIR", "."),
+        ].iter().chain(vars).map(|&(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn string_escaping() {
+        assert_eq!(&escape_string("", Toolchain::Msvc), "");
+        assert_eq!(&escape_string("foo", Toolchain::Msvc), "foo");
+        assert_eq!(&escape_string("\"Hello\"", Toolchain::Msvc), "\"\"Hello\"\"");
+        assert_eq!(&escape_string("C:\\Program Files\\Foobar", Toolchain::Msvc),
+                   "C:\\\\Program Files\\\\Foobar");
+        assert_eq!(&escape_string("it's", Toolchain::Msvc), "it\\'s");
+        assert_eq!(&escape_string("it's", Toolchain::Gnu), "it's");
+        assert_eq!(&escape_string("it's", Toolchain::LlvmRc), "it's");
+    }
+
+    #[test]
+    fn escaping_rc_output() {
+        // the quoted strings as they appear in the resource script, for rc.exe and for
+        // windres and llvm-rc, which keep the backslash of `\'`
+        let expected = [
+            ("\"quoted\" \"\"", r#"""quoted"" """""#, r#"""quoted"" """""#),
+            ("C:\\Program Files\\App\\", r"C:\\Program Files\\App\\", r"C:\\Program Files\\App\\"),
+            ("it's \\'", r"it\'s \\\'", r"it's \\'"),
+            ("© 2024 電卓 ü", "© 2024 電卓 ü", "© 2024 電卓 ü"),
+            ("line\nbreak\ttab\r", r"line\nbreak\ttab\r", r"line\nbreak\ttab\r"),
+        ];
+        for &(string, msvc, gnu) in expected.iter() {
+            assert_eq!(escape_string(string, Toolchain::Msvc), msvc);
+            assert_eq!(escape_string(string, Toolchain::Gnu), gnu);
+            assert_eq!(escape_string(string, Toolchain::LlvmRc), gnu);
+        }
+    }
+
+    #[test]
+    fn language_parsing() {
+        assert_eq!(parse_language("0x0407"), Some(0x0407));
+        assert_eq!(parse_language("1033"), Some(0x0409));
+        assert_eq!(parse_language("de-DE"), Some(0x0407));
+        assert_eq!(parse_language("en_gb"), Some(0x0809));
+        assert_eq!(parse_language("fr"), Some(0x040c));
+        assert_eq!(parse_language("xx-YY"), None);
+
+        let mut res = WindowsResource::new();
+        res.set_language_tag("pt").expect("known language");
+        assert_eq!((res.language().id, res.language().tag), (0x0416, Some("pt-BR")));
+        assert!(matches!(res.set_language_tag("xx"), Err(Error::UnknownLanguage(_))));
+        res.set_language(0x000c);
+        assert_eq!(res.language().tag, Some("fr"));
+    }
+
+    #[test]
+    fn windres_flags() {
+        assert_eq!(windres_target("x86_64-pc-windows-gnu"), Some("pe-x86-64"));
+        assert_eq!(windres_target("i686-pc-windows-gnu"), Some("pe-i386"));
+        assert_eq!(windres_target("aarch64-pc-windows-gnullvm"), None);
+
+        let path = env::temp_dir().join("winres-code-page.rc");
+        fs::write(&path, "// legacy\n#pragma code_page( 1252 )\n1 ICON \"app.ico\"\n").expect("write resource script");
+        assert_eq!(declared_code_page(&path), Some(1252));
+        fs::write(&path, "1 ICON \"app.ico\"\n").expect("write resource script");
+        assert_eq!(declared_code_page(&path), None);
+        let _ = fs::remove_file(&path);
+        assert_eq!(declared_code_page(&path), Some(65001));
+    }
+
+    #[test]
+    fn cargo_defaults() {
+        assert_eq!(year_of_day(0), 1970);
+        assert_eq!(year_of_day(59), 1970);
+        assert_eq!(year_of_day(364), 1970);
+        assert_eq!(year_of_day(365), 1971);
+        assert_eq!(year_of_day(19_722), 2023);
+        assert_eq!(year_of_day(19_723), 2024);
+        assert_eq!(year_of_day(20_088), 2024);
+        assert_eq!(year_of_day(-1), 1969);
+        assert_eq!(original_filename("my-app", BinaryType::Exe), "my-app.exe");
+        assert_eq!(original_filename("my-app", BinaryType::Dll), "my_app.dll");
+    }
+
+    #[test]
+    fn version_string_errors() {
+        let message = |result: Result<&mut WindowsResource, Error>| match result {
+            Err(Error::InvalidVersionString(message)) => message,
+            other => panic!("expected InvalidVersionString, got {:?}", other.map(|_| ())),
+        };
+        let mut res = WindowsResource::new();
+        assert_eq!(message(res.set_file_version_str("1.2.3.65536")),
+                   "version component \"65536\" of \"1.2.3.65536\" is not a number between 0 and 65535");
+        assert_eq!(message(res.set_product_version_str("7")), "version \"7\" needs two to four components");
+        assert_eq!(message(res.set_product_version_str("1.2.beta")),
+                   "version component \"beta\" of \"1.2.beta\" is not a number between 0 and 65535");
+        // a failed call changes nothing
+        assert_eq!(res.get("FileVersion"), Some(env!("CARGO_PKG_VERSION")));
+
+        let env = package_env(&[
+            ("CARGO_PKG_VERSION", "1.70000.0"), ("CARGO_PKG_VERSION_MINOR", "70000"),
+            ("CARGO_PKG_DESCRIPTION", "Calculator"),
+        ]);
+        match WindowsResource::new_with_env(env) {
+            Err(e @ Error::InvalidVersionString(_)) =>
+                assert_eq!(e.to_string(), "CARGO_PKG_VERSION_MINOR is \"70000\", not a number between 0 and 65535"),
+            other => panic!("expected InvalidVersionString, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "cargo-metadata")]
+    fn metadata_version_string_errors() {
+        let dir = TempDir::new("metadata-version-errors");
+        fs::write(dir.join("Cargo.toml"), r#"
+            [package]
+            name = "calc"
+            version = "1.0.0"
+
+            [package.metadata.winres.version-info]
+            FILEVERSION = "1.0.x"
+        "#).expect("write Cargo.toml");
+        let mut res = WindowsResource::new();
+        match res.set_manifest_dir(&dir) {
+            Err(Error::InvalidVersionString(message)) =>
+                assert_eq!(message, "version component \"x\" of \"1.0.x\" is not a number between 0 and 65535"),
+            other => panic!("expected InvalidVersionString, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "cargo-metadata")]
+    fn semver_packing() {
+        assert_eq!(parse_semver("1.2.3"), Ok(0x0001000200030000));
+        assert_eq!(parse_semver("0.1.11-rc.1+build"), Ok(0x00000001000b0000));
+        assert_eq!(parse_semver("2"), Ok(0x0002000000000000));
+        assert!(parse_semver("1.65536.0").is_err());
+        assert!(parse_semver("1.ventura.0").is_err());
+
+        let mut env = HashMap::new();
+        env.insert("CARGO_PKG_VERSION_MAJOR".to_string(), "65536".to_string());
+        env.insert("CARGO_PKG_VERSION_MINOR".to_string(), "12".to_string());
+        assert!(matches!(cargo_version_component(&env, "CARGO_PKG_VERSION_MAJOR"),
+                         Err(Error::InvalidVersionString(_))));
+        assert_eq!(cargo_version_component(&env, "CARGO_PKG_VERSION_MINOR").ok(), Some(12));
+        assert!(matches!(cargo_version_component(&env, "CARGO_PKG_VERSION_PATCH"), Err(Error::EnvVarNotSet(_))));
+
+        let mut res = WindowsResource::new();
+        res.set_version_components(1, 2, 3, 4512);
+        let rc = res.to_rc_string().expect("render resource script");
+        assert!(rc.contains("FILEVERSION 1, 2, 3, 4512\n"));
+        assert!(rc.contains("PRODUCTVERSION 1, 2, 3, 4512\n"));
+    }
+
+    #[test]
+    fn placeholder_expansion() {
+        let lookup = |key: &str| if key == "SHA" { Some("abc".to_string()) } else { None };
+        assert_eq!(expand_placeholders("no placeholders", lookup), Ok("no placeholders".to_string()));
+        assert_eq!(expand_placeholders("${SHA}-${SHA}", lookup), Ok("abc-abc".to_string()));
+        assert_eq!(expand_placeholders("open ${SHA", lookup), Ok("open ${SHA".to_string()));
+        assert_eq!(expand_placeholders("${MISSING}", lookup), Err("MISSING".to_string()));
+        // literal `${` sequences
+        assert_eq!(expand_placeholders("$${SHA} is ${SHA}", lookup), Ok("${SHA} is abc".to_string()));
+        assert_eq!(expand_placeholders("f(${ x }) and ${}", lookup), Ok("f(${ x }) and ${}".to_string()));
+        assert_eq!(expand_placeholders("${${SHA}}", lookup), Ok("${abc}".to_string()));
+    }
+
+    #[test]
+    fn version_string_parsing() {
+        assert_eq!(parse_version_string("1.2.3.4"), Ok(0x0001000200030004));
+        assert_eq!(parse_version_string("1.2"), Ok(0x0001000200000000));
+        assert!(parse_version_string("1").is_err());
+        assert!(parse_version_string("1.2.3.4.5").is_err());
+        assert!(parse_version_string("1.65536").is_err());
+        assert!(parse_version_string("1.x").is_err());
+    }
+
+    #[test]
+    fn prerelease_revisions() {
+        assert_eq!(prerelease_revision("rc.4"), Ok(4));
+        assert_eq!(prerelease_revision("beta12"), Ok(12));
+        assert_eq!(prerelease_revision("alpha"), Ok(0));
+        assert_eq!(prerelease_revision("rc.99999"),
+                   Err("pre-release \"rc.99999\" does not end with a number between 0 and 65535".to_string()));
+    }
+
+    #[test]
+    fn debug_profile() {
+        assert!(is_debug_build(Some("debug"), Some("true")));
+        assert!(!is_debug_build(Some("release"), Some("true")));
+        assert!(is_debug_build(None, Some("2")));
+        assert!(!is_debug_build(None, Some("false")));
+        assert!(!is_debug_build(None, None));
+
+        let env = package_env(&[("PROFILE", "release")]);
+        let mut res = WindowsResource::new_with_env(env).expect("resource from env");
+        res.set_version_info(VersionInfo::FILEFLAGS, FileFlags::DEBUG | FileFlags::PRERELEASE)
+            .set_fileflags_from_profile(true);
+        // release builds only clear the debug flag
+        assert_eq!(res.version_info[&VersionInfo::FILEFLAGS], FileFlags::PRERELEASE);
+    }
+
+    #[test]
+    fn deterministic_output() {
+        let mut res = WindowsResource::new();
+        res.set("ProductName", "winres")
+            .set("CompanyName", "winres contributors")
+            .set("LegalCopyright", "MIT")
+            .set("Comments", "reproducible");
+        let rc = res.to_rc_string().expect("render resource script");
+        assert_eq!(rc, res.to_rc_string().expect("render resource script"));
+
+        let keys = rc.lines()
+            .filter(|line| line.starts_with("VALUE \"") && !line.contains("Translation"))
+            .map(|line| line.split('"').nth(1).unwrap_or_default())
+            .collect::<Vec<_>>();
+        let mut sorted = keys.clone();
+        sorted.sort();
+        assert_eq!(keys, sorted);
+        let fields = rc.lines()
+            .filter_map(|line| line.split(' ').next())
+            .filter(|word| word.starts_with("FILE") || word.starts_with("PRODUCT"))
+            .collect::<Vec<_>>();
+        assert_eq!(fields, ["FILEVERSION", "PRODUCTVERSION", "FILEOS", "FILETYPE",
+                            "FILESUBTYPE", "FILEFLAGSMASK", "FILEFLAGS"]);
+    }
+
+    #[test]
+    fn skipped_version_info() {
+        let mut res = WindowsResource::new();
+        res.set_manifest("<assembly/>");
+        assert!(res.to_rc_string().expect("render resource script").contains("1 VERSIONINFO\n"));
+        res.skip_version_info(true);
+        let rc = res.to_rc_string().expect("render resource script");
+        assert!(!rc.contains("VERSIONINFO"));
+        assert!(!rc.contains("StringFileInfo"));
+        assert!(rc.contains(" 24\n"));
+    }
+
+    #[test]
+    fn translations_match_blocks() {
+        let mut res = WindowsResource::new();
+        res.set_language(0x0409)
+            .set_localized("ProductName", 0x0407, "Rechner")
+            .set_localized("ProductName", 0x0411, "電卓")
+            .add_translation(0x0411, 0x03a4)
+            .add_translation(0x040c, 0x04b0)
+            .add_translation(0x0411, 0x03a4);
+        assert_eq!(res.translations(), [(0x0411, 0x03a4), (0x040c, 0x04b0), (0x0409, 0x04b0), (0x0407, 0x04b0)]);
+        let rc = res.to_rc_string().expect("render resource script");
+        let blocks = rc.lines()
+            .filter(|line| line.starts_with("BLOCK \"0"))
+            .map(|line| line.trim_start_matches("BLOCK \"").trim_end_matches('"'))
+            .collect::<Vec<_>>();
+        assert_eq!(blocks, ["041103a4", "040c04b0", "040904b0", "040704b0"]);
+        assert!(rc.contains("VALUE \"Translation\", 0x411, 0x03a4, 0x40c, 0x04b0, 0x409, 0x04b0, 0x407, 0x04b0\n"));
+
+        res.set_translation(&[]);
+        assert_eq!(res.translations(), [(0x0409, 0x04b0), (0x0407, 0x04b0), (0x0411, 0x04b0)]);
+    }
+
+    #[test]
+    fn dll_binary_type() {
+        let mut res = WindowsResource::new();
+        res.set_manifest("<assembly/>").set_binary_type(BinaryType::Dll);
+        let rc = res.to_rc_string().expect("render resource script");
+        assert!(rc.contains("FILETYPE 0x2\n"));
+        assert!(rc.contains("FILESUBTYPE 0x0\n"));
+        assert!(rc.contains("\n2 24\n"));
+        res.set_binary_type(BinaryType::Driver(DriverSubtype::System));
+        let rc = res.to_rc_string().expect("render resource script");
+        assert!(rc.contains("FILETYPE 0x3\n"));
+        assert!(rc.contains("FILESUBTYPE 0x7\n"));
+        res.set_file_os(FileOs::DOS_WINDOWS32)
+            .set_binary_type(BinaryType::Driver(DriverSubtype::Network));
+        let rc = res.to_rc_string().expect("render resource script");
+        // the FILEOS set by the user is kept
+        assert!(rc.contains("FILEOS 0x10004\n"));
+        assert!(rc.contains("FILESUBTYPE 0x6\n"));
+        res.set_file_os(FileOs::NT);
+        assert!(res.to_rc_string().expect("render resource script").contains("FILEOS 0x40000\n"));
+        res.set_version_info(VersionInfo::FILETYPE, 1).set_manifest_resource_id(3);
+        let rc = res.to_rc_string().expect("render resource script");
+        assert!(rc.contains("\n3 24\n"));
+    }
+
+    #[test]
+    fn assembly_identity() {
+        let mut res = WindowsResource::new();
+        res.set_version_info(VersionInfo::FILEVERSION, 0x0001_0002_0003_0004).enable_visual_styles();
+        res.target = Some("x86_64-pc-windows-msvc".to_string());
+        let xml = res.manifest_xml().expect("manifest");
+        assert!(xml.contains(&format!("<assemblyIdentity type=\"win32\" name=\"{}\" version=\"1.2.3.4\" \
+                                       processorArchitecture=\"amd64\" />", env!("CARGO_PKG_NAME"))));
+        res.set_assembly_identity(::manifest::AssemblyIdentity::new("Contoso.Calculator", "2.0.0.0"));
+        let xml = res.manifest_xml().expect("manifest");
+        assert!(xml.contains("name=\"Contoso.Calculator\" version=\"2.0.0.0\" />"));
+        assert_eq!(xml.matches("<assemblyIdentity").count(), 2);
+
+        res.set_manifest("<assembly/>");
+        assert_eq!(res.manifest_xml(), Some("<assembly/>".to_string()));
+        assert!(res.metadata_warnings().is_empty());
+        res.set_long_path_aware(true);
+        assert!(res.manifest_xml().expect("manifest").contains("longPathAware"));
+        res.set_manifest_file("app.manifest").set_supported_os(&[::manifest::SupportedOs::Windows10]);
+        assert_eq!(res.metadata_warnings(), ["the manifest of set_manifest() is replaced by the manifest settings",
+                                             "the manifest file app.manifest is replaced by the manifest settings"]);
+        assert_eq!(processor_architecture(Some("i686-pc-windows-gnu")), "x86");
+        assert_eq!(processor_architecture(Some("aarch64-pc-windows-msvc")), "arm64");
+        assert_eq!(processor_architecture(None), "*");
+    }
+
+    #[test]
+    fn external_manifest() {
+        let dir = TempDir::new("external-manifest");
+        let mut res = WindowsResource::new();
+        with_fake_llvm_rc(&mut res, &dir)
+            .set_external_manifest_path(dir.join("app.exe.manifest"))
+            .enable_visual_styles()
+            .set_manifest_mode(ManifestMode::External);
+        res.compile().expect("compile resource");
+        let rc = fs::read_to_string(dir.join("resource.rc")).expect("read resource script");
+        assert!(!rc.contains(" 24\n"));
+        let manifest = fs::read_to_string(dir.join("app.exe.manifest")).expect("read external manifest");
+        assert!(manifest.contains("Microsoft.Windows.Common-Controls"));
+
+        fs::remove_file(dir.join("app.exe.manifest")).expect("remove external manifest");
+        res.set_manifest_mode(ManifestMode::Both);
+        res.compile().expect("compile resource");
+        let rc = fs::read_to_string(dir.join("resource_2.rc")).expect("read resource script");
+        assert!(rc.contains("\n1 24\n"));
+        assert!(dir.join("app.exe.manifest").exists());
+    }
+
+    #[test]
+    fn by_value_builder() {
+        let res = WindowsResource::default()
+            .with(|res| res.set("FileDescription", "Calculator").set_language(0x0407))
+            .property("ProductName", "Rechner")
+            .icon("calc.ico")
+            .manifest_file("calc.manifest");
+        assert_eq!(res.get("FileDescription"), Some("Calculator"));
+        assert_eq!(res.get("ProductName"), Some("Rechner"));
+        let rc = res.to_rc_string().expect("render resource script");
+        assert_eq!(res.language().id, 0x0407);
+        assert!(rc.contains("calc.ico"));
+        assert!(rc.contains("calc.manifest"));
+    }
+
+    #[test]
+    fn env_source() {
+        let dir = TempDir::new("env-source");
+        fs::write(dir.join("Cargo.toml"), "[package]\nname = \"calc\"\n\n[package.metadata.winres]\nLegalTrademarks = \"TM\"\n")
+            .expect("write Cargo.toml");
+        let env = package_env(&[
+            ("CARGO_PKG_VERSION", "1.2.3-rc.4"), ("CARGO_PKG_VERSION_MINOR", "2"), ("CARGO_PKG_VERSION_PATCH", "3"),
+            ("CARGO_PKG_VERSION_PRE", "rc.4"), ("CARGO_PKG_DESCRIPTION", "Calculator"),
+            ("CARGO_PKG_AUTHORS", "Jane Doe <jane@example.com>"), ("SOURCE_DATE_EPOCH", "1700000000"),
+            ("TARGET", "i686-pc-windows-msvc"), ("CARGO_CFG_TARGET_ENV", "msvc"),
+            ("CARGO_MANIFEST_DIR", dir.to_str().expect("temp dir is UTF-8")), ("CARGO_CFG_TARGET_ARCH", "x86"),
+            ("DEP_RESOURCE_LIB", "resource"), ("WindowsSDKVersion", "10.0.22621.0\\"),
+        ]);
+        let mut res = WindowsResource::new_with_env(env).expect("resource from env");
+        assert_eq!(res.get("ProductName"), Some("calc"));
+        assert_eq!(res.get("LegalCopyright"), Some("Copyright © 2023 Jane Doe"));
+        // Cargo.toml is only read with the cargo-metadata feature
+        assert_eq!(res.get("LegalTrademarks"), if cfg!(feature = "cargo-metadata") { Some("TM") } else { None });
+        assert_eq!(res.target(), Some("i686-pc-windows-msvc"));
+        assert_eq!(res.toolchain(), Toolchain::Msvc);
+        // the SDK discovery and the library name use the source too
+        assert_eq!(res.arch(), Some(::sdk::Arch::X86));
+        assert_eq!(res.output_name(), "resource_calc");
+        assert_eq!(super::requested_sdk_version(&*res.env), Some(::sdk::SdkVersion::new("10.0.22621.0")));
+        assert!(::sdk::cache_key_with_env(&*res.env).contains(";CARGO_CFG_TARGET_ARCH=x86;"));
+        res.set_prerelease_policy(::PrereleasePolicy::Revision).expect("pre-release number");
+        assert_eq!(res.get_version_info(VersionInfo::FILEVERSION), Some(0x0001_0002_0003_0004));
+        assert_eq!(res.get("FileVersion"), Some("1.2.3.4"));
+        assert_eq!(res.get("ProductVersion"), Some("1.2.3.4"));
+        assert!(matches!(res.set("Comments", "${OUT_DIR}").to_rc_string(), Err(Error::UndefinedPlaceholder { .. })));
+
+        assert!(matches!(WindowsResource::new_with_env(HashMap::new()), Err(Error::EnvVarNotSet(_))));
+    }
+
+    #[test]
+    fn tool_env_vars() {
+        let dir = TempDir::new("tool-env-vars");
+        let mut env = package_env(&[
+            ("WINRES_TOOLKIT_PATH", dir.to_str().expect("temp dir is UTF-8")), ("WINRES_AR_PATH", "/opt/ar"),
+        ]);
+        let res = WindowsResource::new_with_env(env.clone()).expect("resource from env");
+        assert_eq!(res.rc_path, Some(dir.join("rc.exe")));
+        assert_eq!(res.windres_path.as_ref().and_then(|path| path.parent()), Some(&*dir));
+        assert_eq!(res.ar_path, Some(::std::path::PathBuf::from("/opt/ar")));
+
+        env.insert("OUT_DIR".to_string(), dir.to_str().expect("temp dir is UTF-8").to_string());
+        let mut res = WindowsResource::new_with_env(env).expect("resource from env");
+        with_fake_llvm_rc(&mut res, &dir);
+        let output = res.build().expect("build resource");
+        assert_eq!(output.directives[..4], ::TOOL_ENV_VARS.iter()
+            .map(|name| format!("cargo:rerun-if-env-changed={}", name)).collect::<Vec<_>>()[..]);
+        // the empty description of the package is not reported
+        assert!(!output.directives.iter().any(|d| d.starts_with("cargo:warning=")));
+        // cargo no longer reruns the build script on every change of the package
+        assert!(output.directives.contains(&"cargo:rerun-if-changed=./Cargo.toml".to_string()));
+        assert!(!output.directives.iter().any(|d| d.ends_with("build.rs")));
+    }
+
+    #[test]
+    fn placeholder_env_vars() {
+        let dir = TempDir::new("placeholder-env-vars");
+        let env = package_env(&[("OUT_DIR", dir.to_str().expect("temp dir is UTF-8")), ("BUILD_YEAR", "2024")]);
+        let mut git = HashMap::new();
+        git.insert("GIT_SHA".to_string(), "4f3a2b1".to_string());
+        let mut res = WindowsResource::new_with_env(env).expect("resource from env");
+        with_fake_llvm_rc(&mut res, &dir)
+            .set("LegalCopyright", "© ${BUILD_YEAR} ACME")
+            .set("Comments", "built from ${GIT_SHA}")
+            .add_metadata_provider(git);
+        let output = res.build().expect("build resource");
+        let rerun = output.directives.iter().filter(|d| d.starts_with("cargo:rerun-if-")).collect::<Vec<_>>();
+        // the provider value does not come from the environment
+        assert_eq!(rerun, ["cargo:rerun-if-env-changed=BUILD_YEAR", "cargo:rerun-if-changed=./Cargo.toml"]);
+    }
+
+    #[test]
+    fn custom_resource_file_ignored_settings() {
+        let env = package_env(&[("CARGO_PKG_DESCRIPTION", "Calculator"), ("WINRES_LANG", "de-DE")]);
+        let mut res = WindowsResource::new_with_env(env).expect("resource from env");
+        res.set_resource_file("custom.rc");
+        assert!(res.ignored_settings().is_empty());
+
+        res.set("ProductName", "Calculator Pro")
+            .set_version_info(VersionInfo::FILEFLAGS, 0x2)
+            .set_language(0x0409);
+        assert_eq!(res.ignored_settings(), ["properties", "version info", "language"]);
+    }
+
+    #[test]
+    fn unknown_winres_lang() {
+        let mut env = package_env(&[("CARGO_PKG_DESCRIPTION", "Calculator"), ("WINRES_LANG", "de-DE")]);
+        let res = WindowsResource::new_with_env(env.clone()).expect("resource from env");
+        assert_eq!(res.language, 0x0407);
+        assert!(res.metadata_warnings().is_empty());
+
+        env.insert("WINRES_LANG".to_string(), "klingon".to_string());
+        let mut res = WindowsResource::new_with_env(env).expect("resource from env");
+        assert_eq!(res.language, 0);
+        assert_eq!(res.metadata_warnings(), ["WINRES_LANG=klingon is not a known language, using neutral"]);
+        let mut directives = vec![];
+        res.report_metadata_warnings(&mut directives).expect("report warnings");
+        assert_eq!(directives, ["cargo:warning=WINRES_LANG=klingon is not a known language, using neutral"]);
+        res.set_strict(true);
+        assert!(matches!(res.report_metadata_warnings(&mut directives), Err(Error::InvalidMetadata(_))));
+    }
+
+    #[test]
+    fn out_dir_not_set() {
+        let env = package_env(&[]);
+        let mut res = WindowsResource::new_with_env(env).expect("resource from env");
+        res.set_toolchain(Toolchain::LlvmRc).set_llvm_rc_path("true").set_resources_rs(true);
+        assert!(!res.in_build_script());
+        assert!(matches!(res.compile_for_bin("calc"), Err(Error::OutDirNotSet)));
+        res.set_standalone(true);
+        assert!(matches!(res.compile(), Err(Error::OutDirNotSet)));
+        assert!(!Path::new("resources.rs").exists() && !Path::new("resource.rc").exists());
+    }
+
+    #[test]
+    fn presets() {
+        let mut res = WindowsResource::new();
+        res.preset(Preset::GuiApp);
+        let rc = res.to_rc_string().expect("render resource script");
+        assert!(rc.contains("FILETYPE 0x1\n"));
+        assert!(rc.contains("\n1 24\n"));
+        let xml = res.manifest_xml().expect("manifest");
+        assert!(xml.contains("asInvoker"));
+        assert!(xml.contains("permonitorv2"));
+        assert!(xml.contains("Microsoft.Windows.Common-Controls"));
+
+        let mut res = WindowsResource::new();
+        res.preset(Preset::Service);
+        let xml = res.manifest_xml().expect("manifest");
+        assert!(xml.contains("uiAccess=\"false\""));
+        assert!(!xml.contains("Common-Controls"));
+        assert!(xml.contains("{8e0f7a12-bfb3-4fe8-b9a5-48fd50a15a9a}"));
+        assert!(xml.contains("longPathAware"));
+        let mut console = WindowsResource::new();
+        console.preset(Preset::ConsoleApp);
+        assert_ne!(console.manifest_xml(), res.manifest_xml());
+
+        let mut res = WindowsResource::new();
+        res.set_manifest("<assembly/>").preset(Preset::Dll);
+        let rc = res.to_rc_string().expect("render resource script");
+        assert!(rc.contains("FILETYPE 0x2\n"));
+        assert!(rc.contains("\n2 24\n"));
+    }
+
+    #[test]
+    #[cfg(feature = "cargo-metadata")]
+    fn package_metadata_settings() {
+        let dir = TempDir::new("package-metadata");
+        fs::write(dir.join("Cargo.toml"), r#"
+            [package]
+            name = "metadata"
+            version = "1.2.3"
+
+            [package.metadata.winres]
+            LegalCopyright = "MIT"
+            icon = "app.ico"
+            language = "de-DE"
+            manifest-file = "app.manifest"
+
+            [package.metadata.winres.version-info]
+            FILEFLAGS = 0x8
+            FILEVERSION = "1.2.3.4"
+
+            [package.metadata.winres.lang.0x0411]
+            LegalCopyright = "著作権"
+        "#).expect("write Cargo.toml");
+        let mut res = WindowsResource::new();
+        res.set_manifest_dir(&dir).expect("read package metadata");
+        let rc = res.to_rc_string().expect("render resource script");
+        assert!(rc.contains("FILEVERSION 1, 2, 3, 4\n"));
+        assert!(rc.contains("FILEFLAGS 0x8\n"));
+        assert!(rc.contains("VALUE \"LegalCopyright\", \"MIT\"\n"));
+        assert!(rc.contains("app.ico"));
+        assert!(rc.contains("app.manifest"));
+        assert!(rc.contains("040704b0"));
+        assert!(!rc.contains("\"icon\""));
+        assert!(rc.contains("041104b0"));
+        assert!(rc.contains("VALUE \"LegalCopyright\", \"著作権\"\n"));
+    }
+
+    #[test]
+    #[cfg(feature = "cargo-metadata")]
+    fn manifest_dir_package_identity() {
+        let dir = TempDir::new("manifest-dir-identity");
+        fs::write(dir.join("Cargo.toml"), r#"
+            [package]
+            name = "other-plugin"
+            version = "2.1.0"
+
+            [lib]
+            crate-type = ["cdylib"]
+        "#).expect("write Cargo.toml");
+        let mut res = WindowsResource::new();
+        res.for_bin("builder").enable_visual_styles();
+        res.set_manifest_dir(&dir).expect("read package metadata");
+        assert_eq!(res.get("ProductName"), Some("other-plugin"));
+        assert_eq!(res.get("OriginalFilename"), Some("other_plugin.dll"));
+        assert_eq!(res.get("InternalName"), None);
+        assert_eq!(res.version_info[&VersionInfo::FILETYPE], BinaryType::Dll.file_type());
+        let xml = res.manifest_xml().expect("manifest");
+        assert!(xml.contains("name=\"other-plugin\" version=\"2.1.0.0\""));
+        res.set_output_directory(&dir);
+        assert!(res.external_manifest_path().expect("manifest path").ends_with("other_plugin.dll.manifest"));
+        res.set_resource_file("custom.rc");
+        assert!(res.ignored_settings().iter().all(|s| *s != "properties" && *s != "version info"));
+
+        let mut res = WindowsResource::new();
+        res.set("OriginalFilename", "plugin.dll").set_manifest_dir(&dir).expect("read package metadata");
+        assert_eq!(res.get("OriginalFilename"), Some("plugin.dll"));
+    }
+
+    #[test]
+    fn property_validation() {
+        assert_eq!(misspelled_property("LegalTrademark"), Some("LegalTrademarks"));
+        assert_eq!(misspelled_property("companyname"), Some("CompanyName"));
+        assert_eq!(misspelled_property("CompanyName"), None);
+        assert_eq!(misspelled_property("GitCommit"), None);
+
+        let mut res = WindowsResource::new();
+        res.set("FileDescription", "").set("PrivateBuild", "local").set("LegalTrademark", "winres");
+        assert_eq!(res.validate_properties(), [
+            "property \"FileDescription\" is required but empty",
+            "property \"LegalTrademark\" looks like a misspelling of \"LegalTrademarks\"",
+            "property \"PrivateBuild\" is set without VS_FF_PRIVATEBUILD in FILEFLAGS",
+        ]);
+        res.set("FileDescription", "winres").set("LegalTrademarks", "winres")
+            .set_version_info(VersionInfo::FILEFLAGS, super::FileFlags::PRIVATEBUILD);
+        assert_eq!(res.remove("LegalTrademark").as_deref(), Some("winres"));
+        assert!(res.validate_properties().is_empty());
+        res.remove("ProductName");
+        assert_eq!(res.validate_properties(), ["property \"ProductName\" is required but missing"]);
+
+        // without a description or authors the defaults are incomplete, but not wrong
+        let env = package_env(&[]);
+        let mut res = WindowsResource::new_with_env(env).expect("resource from env");
+        res.set_strict(true);
+        assert!(res.validate_properties().is_empty());
+        assert!(res.report_metadata_warnings(&mut vec![]).is_ok());
+    }
+
+    #[test]
+    fn property_accessors() {
+        let mut res = WindowsResource::new();
+        res.set("CompanyName", "winres").set_version_info(VersionInfo::FILEFLAGS, 0x8);
+        assert_eq!(res.get("CompanyName"), Some("winres"));
+        assert!(res.properties().any(|p| p == ("CompanyName", "winres")));
+        assert_eq!(res.remove("CompanyName"), Some("winres".to_string()));
+        assert_eq!(res.get("CompanyName"), None);
+        assert_eq!(res.get_version_info(VersionInfo::FILEFLAGS), Some(0x8));
+        assert_eq!(res.remove_version_info(VersionInfo::FILEFLAGS), Some(0x8));
+        assert!(res.version_info().all(|(field, _)| field != VersionInfo::FILEFLAGS));
+        let rc = res.to_rc_string().expect("render resource script");
+        assert!(!rc.contains("FILEFLAGS "));
+    }
+
+    #[test]
+    #[cfg(feature = "cargo-metadata")]
+    fn strict_metadata() {
+        let dir = TempDir::new("strict-metadata");
+        fs::write(dir.join("Cargo.toml"), r#"
+            [package]
+            name = "strict"
+            version = "1.0.0"
+
+            [package.metadata.winres]
+            icon = 1
+            manifest_file = "app.manifest"
+            ProductName = "Strict"
+        "#).expect("write Cargo.toml");
+        let mut res = WindowsResource::new();
+        res.set_manifest_dir(&dir).expect("read package metadata");
+        assert_eq!(res.metadata_warnings(), ["package.metadata.winres.manifest_file is not a known setting, \
+                                              it is written as a string property",
+                                             "package.metadata.winres.icon is not a string"]);
+        with_fake_llvm_rc(&mut res, &dir).set_strict(true);
+        assert!(res.compile().is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "cargo-metadata")]
+    fn metadata_from_file() {
+        let dir = TempDir::new("metadata-from");
+        fs::write(dir.join("winres.toml"), r#"
+            [package.metadata.winres]
+            CompanyName = "Example"
+            language = 0x0407
+        "#).expect("write metadata");
+        let mut res = WindowsResource::new_without_metadata();
+        res.load_metadata_from(dir.join("winres.toml")).expect("load metadata");
+        let rc = res.to_rc_string().expect("render resource script");
+        assert!(rc.contains("VALUE \"CompanyName\", \"Example\"\n"));
+        assert!(rc.contains("040704b0"));
+        assert!(res.load_metadata_from(dir.join("missing.toml")).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "cargo-metadata")]
+    fn bin_metadata_overrides() {
+        let dir = TempDir::new("bin-metadata");
+        fs::write(dir.join("Cargo.toml"), r#"
+            [package]
+            name = "tools"
+            version = "1.0.0"
+
+            [package.metadata.winres]
+            FileDescription = "Tools"
+
+            [package.metadata.winres.bin.gui]
+            FileDescription = "Tools GUI"
+            icon = "gui.ico"
+        "#).expect("write Cargo.toml");
+        fs::write(dir.join("gui.ico"), b"").expect("write icon");
+        let mut res = WindowsResource::new();
+        res.set_manifest_dir(&dir).expect("read package metadata");
+        with_fake_llvm_rc(&mut res, &dir).set_validate_icons(false);
+        res.compile_for_bin("gui").expect("compile for bin");
+        let rc = fs::read_to_string(dir.join("resource_gui.rc")).expect("read resource script");
+        assert!(rc.contains("VALUE \"FileDescription\", \"Tools GUI\"\n"));
+        assert!(rc.contains("VALUE \"OriginalFilename\", \"gui.exe\"\n"));
+        assert!(rc.contains("VALUE \"InternalName\", \"gui\"\n"));
+        assert!(rc.contains("gui.ico"));
+        let rc = res.to_rc_string().expect("render resource script");
+        assert!(rc.contains("VALUE \"FileDescription\", \"Tools\"\n"));
+        assert!(!rc.contains("gui.ico"));
+        assert!(!rc.contains("InternalName"));
+    }
+
+    #[test]
+    #[cfg(feature = "cargo-metadata")]
+    fn bin_metadata_isolated() {
+        let dir = TempDir::new("bin-metadata-isolated");
+        fs::write(dir.join("Cargo.toml"), r#"
+            [package]
+            name = "tools"
+            version = "1.0.0"
+
+            [package.metadata.winres.bin.gui]
+            icons = { 2 = "gui.ico" }
+            strings = { greeting = { id = 1, value = "Hello" } }
+            lang = { de = { FileDescription = "Werkzeuge" } }
+            Comments = 1
+        "#).expect("write Cargo.toml");
+        fs::write(dir.join("gui.ico"), b"").expect("write icon");
+        let mut res = WindowsResource::new();
+        res.set_manifest_dir(&dir).expect("read package metadata");
+        with_fake_llvm_rc(&mut res, &dir).set_validate_icons(false);
+        res.compile_for_bin("gui").expect("compile gui");
+        let rc = fs::read_to_string(dir.join("resource_gui.rc")).expect("read gui script");
+        assert!(rc.contains("gui.ico") && rc.contains("\"Hello\"") && rc.contains("Werkzeuge"));
+
+        res.compile_for_bin("cli").expect("compile cli");
+        let rc = fs::read_to_string(dir.join("resource_cli.rc")).expect("read cli script");
+        assert!(!rc.contains("gui.ico") && !rc.contains("Hello") && !rc.contains("Werkzeuge"));
+        assert!(res.metadata_warnings().is_empty());
+    }
+
+    #[test]
+    fn bin_name_defaults() {
+        let mut res = WindowsResource::new();
+        res.set("InternalName", "TOOL").for_bin("my-tool");
+        assert_eq!(res.get("OriginalFilename"), Some("my-tool.exe"));
+        assert_eq!(res.get("InternalName"), Some("TOOL"));
+        res.for_bin("other");
+        assert_eq!(res.get("OriginalFilename"), Some("other.exe"));
+        res.set("OriginalFilename", "custom.exe").for_bin("third");
+        assert_eq!(res.get("OriginalFilename"), Some("custom.exe"));
+    }
+
+    #[test]
+    fn missing_input_files() {
+        let mut res = WindowsResource::new();
+        with_fake_llvm_rc(&mut res, &env::temp_dir()).set_icon("missing.ico");
+        let icon = res.manifest_dir.join("missing.ico");
+        match res.compile() {
+            Err(Error::IconNotFound(path)) => assert_eq!(path, icon),
+            result => panic!("expected IconNotFound, got {:?}", result),
+        }
+        res.set_icon("Cargo.toml");
+        assert!(matches!(res.compile(), Err(Error::InvalidIcon { .. })));
+        res.set_validate_icons(false).set_manifest_file("missing.manifest");
+        assert!(matches!(res.compile(), Err(Error::ManifestNotFound(_))));
+        res.set_resource_file("missing.rc");
+        assert!(matches!(res.compile(), Err(Error::ResourceFileNotFound(_))));
+    }
+
+    #[test]
+    fn verbosity() {
+        let mut res = WindowsResource::new();
+        assert_eq!(res.verbosity, Verbosity::Normal);
+        res.set_verbose(true);
+        assert_eq!(res.verbosity, Verbosity::Verbose);
+        res.set_verbosity(Verbosity::Quiet)
+            .set_output_directory(env::temp_dir())
+            .set_toolchain(Toolchain::LlvmRc)
+            .set_llvm_rc_path("false");
+        match res.compile() {
+            Err(Error::ToolFailed { tool, .. }) => assert_eq!(tool, "false"),
+            result => panic!("expected ToolFailed, got {:?}", result),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn verbose_messages() {
+        let dir = TempDir::new("verbose-messages");
+        let mut res = WindowsResource::new();
+        res.set_verbosity(Verbosity::Verbose)
+            .set_output_directory(dir.to_str().expect("temp dir is UTF-8"))
+            .set_toolchain(Toolchain::LlvmRc)
+            .set_llvm_rc_path("echo");
+        let output = res.build().expect("build resource");
+        assert!(output.directives.iter().any(|d| d.starts_with("cargo:warning=winres: running echo ")));
+        assert!(output.directives.iter().any(|d| d.starts_with("cargo:warning=echo: ")));
+        // the messages are returned once
+        assert!(res.messages.borrow().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn crash_classification() {
+        use std::os::unix::process::ExitStatusExt;
+        use std::io;
+        use std::process::{self, ExitStatus};
+        use std::time::Duration;
+        use super::{is_crash, should_retry};
+
+        // the raw wait status holds the exit code in the second byte, or the signal
+        let exited = |code: i32| ExitStatus::from_raw(code << 8);
+        assert!(!is_crash(&exited(0)));
+        assert!(!is_crash(&exited(1)));
+        assert!(!is_crash(&exited(2)));
+        assert!(is_crash(&exited(3)));
+        assert!(is_crash(&exited(255)));
+        assert!(is_crash(&ExitStatus::from_raw(9)));
+
+        // a crash or a tool that cannot be started is retried with another SDK, a timeout not
+        let output = |code: i32| process::Output { status: exited(code), stdout: vec![], stderr: vec![] };
+        assert!(should_retry(&Ok(output(3))));
+        assert!(!should_retry(&Ok(output(1))));
+        assert!(should_retry(&Err(Error::Io(io::Error::from(io::ErrorKind::NotFound)))));
+        let timeout = Duration::from_secs(1);
+        assert!(!should_retry(&Err(Error::ToolTimedOut { command: "rc.exe".to_string(), timeout })));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sdk_fallback() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = TempDir::new("sdk-fallback");
+        let arch = ::sdk::Arch::arch_for_target().expect("target arch");
+        let host = ::sdk::Arch::host().unwrap_or(arch);
+        for &(version, script) in [("10.0.19041.0", "#!/bin/sh\nexit 0\n"),
+                                   ("10.0.22621.0", "#!/bin/sh\necho crashed >&2\nexit 3\n")].iter() {
+            let bin_dir = root.join("bin").join(version).join(host.dirname());
+            fs::create_dir_all(&bin_dir).expect("create bin dir");
+            fs::write(bin_dir.join("rc.exe"), script).expect("write rc.exe");
+            fs::set_permissions(bin_dir.join("rc.exe"), fs::Permissions::from_mode(0o755)).expect("make rc.exe executable");
+            fs::create_dir_all(root.join("Include").join(version)).expect("create include dir");
+        }
+        fs::create_dir_all(root.join("out")).expect("create output dir");
+        let mut res = WindowsResource::new();
+        res.set_output_directory(root.join("out"))
+            .set_toolchain(Toolchain::Msvc)
+            .set_kits_root(&root);
+        match res.build() {
+            Err(Error::ToolFailed { exit_code, stderr, .. }) => {
+                assert_eq!(exit_code, Some(3));
+                assert_eq!(stderr.trim(), "crashed");
+            }
+            result => panic!("expected ToolFailed, got {:?}", result),
+        }
+
+        res.set_sdk_fallback(true);
+        let output = res.build().expect("compile with the older SDK");
+        assert!(output.tool.ends_with("10.0.19041.0/".to_string() + host.dirname() + "/rc.exe"));
+        assert!(output.directives.contains(&"cargo:warning=rc.exe of SDK 10.0.22621.0 failed, used SDK \
+                                            10.0.19041.0 instead".to_string()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn tool_timeout() {
+        use std::os::unix::fs::PermissionsExt;
+        use std::time::Duration;
+
+        let dir = TempDir::new("tool-timeout");
+        let hang = dir.join("hang.sh");
+        fs::write(&hang, "#!/bin/sh\nsleep 10\n").expect("write script");
+        fs::set_permissions(&hang, fs::Permissions::from_mode(0o755)).expect("make script executable");
+        let mut res = WindowsResource::new();
+        res.set_output_directory(&dir)
+            .set_toolchain(Toolchain::LlvmRc)
+            .set_llvm_rc_path(&hang)
+            .set_tool_timeout(Duration::from_millis(200));
+        match res.compile() {
+            Err(Error::ToolTimedOut { command, timeout }) => {
+                assert!(command.starts_with(&format!("{} ", hang.display())));
+                assert_eq!(timeout, Duration::from_millis(200));
+            }
+            result => panic!("expected ToolTimedOut, got {:?}", result),
+        }
+        res.set_llvm_rc_path("false");
+        match res.compile() {
+            Err(Error::ToolFailed { command, .. }) => assert!(command.starts_with("false ") && command.contains(" -FO")),
+            result => panic!("expected ToolFailed, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn compile_all() {
+        let dir = TempDir::new("compile-all");
+        let resource = |product: &str| {
+            let mut res = WindowsResource::new();
+            with_fake_llvm_rc(&mut res, &dir).set("ProductName", product);
+            res
+        };
+        let (generated, legacy) = (resource("Generated"), resource("Legacy"));
+        WindowsResource::compile_all(&[&generated, &legacy]).expect("compile resources");
+        let rc = fs::read_to_string(dir.join("resource.rc")).expect("read first resource script");
+        assert!(rc.contains("\"Generated\""));
+        let rc = fs::read_to_string(dir.join("resource_2.rc")).expect("read second resource script");
+        assert!(rc.contains("\"Legacy\""));
+
+        // a failed compilation does not use up its name
+        let mut failing = resource("Failing");
+        failing.set_llvm_rc_path("false");
+        assert!(failing.compile().is_err());
+        resource("Retried").compile().expect("compile resource");
+        let rc = fs::read_to_string(dir.join("resource_3.rc")).expect("read third resource script");
+        assert!(rc.contains("\"Retried\""));
+    }
+
+    #[test]
+    fn build_directives() {
+        let dir = TempDir::new("build-directives");
+        let mut res = WindowsResource::new();
+        with_fake_llvm_rc(&mut res, &dir)
+            .set_size_budget(1, ::SizeBudgetPolicy::Warn)
+            .add_rcdata(dir.join("data.bin"), 1);
+        fs::write(dir.join("data.bin"), [0; 16]).expect("write data");
+        let output = res.build().expect("build resource");
+        assert_eq!(output.compiled, dir.join("resource.lib"));
+        assert_eq!(output.script, dir.join("resource.rc"));
+        assert_eq!(output.toolchain, Toolchain::LlvmRc);
+        assert_eq!(output.tool, Path::new("true"));
+        assert!(output.directives[0].starts_with("cargo:warning=embedded resources are 16 bytes"));
+        assert_eq!(output.directives[1..], [format!("cargo:rustc-link-search=native={}", dir.display()),
+                                            "cargo:rustc-link-lib=dylib=resource".to_string()]);
+    }
+
+    #[test]
+    fn post_compile_hooks() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        #[derive(Debug)]
+        struct Record(Rc<RefCell<Vec<::std::path::PathBuf>>>);
+
+        impl ::PostCompileHook for Record {
+            fn after_compile(&self, _res: &WindowsResource, compiled: &Path) -> Result<(), Error> {
+                self.0.borrow_mut().push(compiled.to_path_buf());
+                Ok(())
+            }
+        }
+
+        #[derive(Debug)]
+        struct Fail;
+
+        impl ::PostCompileHook for Fail {
+            fn after_compile(&self, _res: &WindowsResource, _compiled: &Path) -> Result<(), Error> {
+                Err(Error::VerificationFailed(vec!["not signed".to_string()]))
+            }
+        }
+
+        let dir = TempDir::new("post-compile-hooks");
+        let compiled = Rc::new(RefCell::new(vec![]));
+        let mut res = WindowsResource::new();
+        with_fake_llvm_rc(&mut res, &dir).add_post_compile_hook(Record(compiled.clone()));
+        let output = res.compile_artifacts().expect("compile resource");
+        assert_eq!(*compiled.borrow(), [output.compiled]);
+
+        res.add_post_compile_hook(Fail);
+        assert!(matches!(res.build(), Err(Error::VerificationFailed(_))));
+    }
+
+    #[test]
+    fn rc_copy() {
+        let dir = TempDir::new("rc-copy");
+        fs::create_dir_all(dir.join("out")).expect("create output dir");
+        let copy = dir.join("review").join("app.rc");
+        let mut res = WindowsResource::new();
+        with_fake_llvm_rc(&mut res, &dir.join("out"))
+            .set("ProductName", "Reviewed")
+            .emit_rc_copy(&copy);
+        let output = res.compile_artifacts().expect("compile resource");
+        let script = fs::read_to_string(&output.script).expect("read script");
+        assert!(script.contains("\"Reviewed\""));
+        assert_eq!(fs::read_to_string(&copy).expect("read copy"), script);
+    }
+
+    #[test]
+    fn dry_run() {
+        let dir = TempDir::new("dry-run");
+        let mut res = WindowsResource::new();
+        res.set_output_directory(&dir)
+            .set_toolchain(Toolchain::LlvmRc)
+            .set_llvm_rc_path("false")
+            .set_dry_run(true);
+        let output = res.compile_artifacts().expect("dry run");
+        assert!(output.script.is_file());
+        assert_eq!(output.tool, Path::new("false"));
+        let command = output.directives.iter()
+            .find(|d| d.starts_with("cargo:warning=dry run: false "))
+            .expect("command line");
+        assert!(command.contains(&output.script.display().to_string()));
+        assert!(output.directives.iter().any(|d| d.starts_with("cargo:warning=dry run: cargo:rustc-link-lib=")));
+        assert!(output.directives.iter().all(|d| d.starts_with("cargo:warning=")));
+        let output = res.build().expect("dry run");
+        assert!(output.directives.iter().all(|d| d.starts_with("cargo:warning=")));
+
+        // compile_to_res() does not run the resource compiler either
+        let res_file = res.compile_to_res().expect("dry run");
+        assert_eq!(res_file, dir.join("resource.res"));
+        assert!(!res_file.exists());
+    }
+
+    #[test]
+    fn soft_warnings() {
+        let dir = TempDir::new("soft-warnings");
+        let mut res = WindowsResource::new();
+        with_fake_llvm_rc(&mut res, &dir)
+            .set("FileDescription", "")
+            .set("Comments", "")
+            .set_localized("Comments", 0x0407, "");
+        let output = res.build().expect("build resource");
+        let warnings = output.directives.iter().filter_map(|d| d.strip_prefix("cargo:warning=")).collect::<Vec<_>>();
+        assert_eq!(warnings, ["property \"FileDescription\" is required but empty",
+                              "property \"Comments\" is empty and left out of the version info"]);
+
+        res.set_warnings(false);
+        let output = res.build().expect("build resource");
+        assert!(!output.directives.iter().any(|d| d.starts_with("cargo:warning=")));
+    }
+
+    #[test]
+    fn compile_to_res() {
+        let dir = TempDir::new("compile-to-res");
+        let mut res = WindowsResource::new();
+        with_fake_llvm_rc(&mut res, &dir).set_output_name("app_res");
+        assert_eq!(res.compile_to_res().expect("compile resource"), dir.join("app_res.res"));
+        assert!(dir.join("app_res.rc").is_file());
+    }
+
+    #[test]
+    fn merge_res_files() {
+        let dir = TempDir::new("merge-res");
+        let header = |data_size: u32, kind: u16| {
+            let mut header = data_size.to_le_bytes().to_vec();
+            header.extend_from_slice(&32u32.to_le_bytes());
+            for &word in &[0xffff, kind, 0xffff, 1, 0, 0, 0x30, 0x0409, 0, 0, 0, 0] {
+                header.extend_from_slice(&u16::to_le_bytes(word));
+            }
+            header
+        };
+        // llvm-rc is replaced by `true`, so the compiled file is written by the test
+        fs::write(dir.join("resource.res"), header(0, 0)).expect("write compiled file");
+        let mut vendor = header(0, 0);
+        vendor.extend(header(4, 10));
+        vendor.extend_from_slice(b"data");
+        fs::write(dir.join("vendor.res"), vendor).expect("write vendor .res file");
+
+        let mut res = WindowsResource::new();
+        with_fake_llvm_rc(&mut res, &dir).add_res_file(dir.join("vendor.res"));
+        let compiled = res.compile_to_res().expect("compile resource");
+        let resources = ::res::parse(&compiled).expect("parse merged file");
+        assert_eq!(resources.len(), 1);
+        assert_eq!((resources[0].kind.clone(), &resources[0].data[..]), (::res::Id::Number(10), &b"data"[..]));
+
+        res.add_res_file(dir.join("missing.res"));
+        assert!(matches!(res.compile_to_res(), Err(Error::ResourceFileNotFound(_))));
+    }
+
+    #[test]
+    fn from_rc_file() {
+        let dir = TempDir::new("from-rc-file");
+        fs::write(dir.join("app.rc"), r#"
+            LANGUAGE 0x07, 0x01
+            1 ICON "app.ico"
+            2 ICON "doc.ico"
+            1 MENU
+            BEGIN
+                MENUITEM "&Open", 100
+            END
+            1 VERSIONINFO
+            FILEVERSION 1,2,3,4
+            BEGIN
+                BLOCK "StringFileInfo"
+                BEGIN
+                    BLOCK "040704b0"
+                    BEGIN
+                        VALUE "ProductName", "Rechner"
+                    END
+                    BLOCK "040904b0"
+                    BEGIN
+                        VALUE "ProductName", "Calculator"
+                    END
+                END
+            END
+        "#).expect("write resource script");
+        let res = WindowsResource::from_rc_file(dir.join("app.rc")).expect("read resource script");
+        assert_eq!(res.get("ProductName"), Some("Rechner"));
+        assert_eq!(res.get_version_info(VersionInfo::FILEVERSION), Some(0x0001_0002_0003_0004));
+        assert_eq!(res.icon, Some(dir.join("app.ico")));
+        assert_eq!(res.icons, [(ResourceId::Number(2), dir.join("doc.ico"))]);
+        assert_eq!(res.metadata_warnings().len(), 1);
+        let rc = res.to_rc_string().expect("render resource script");
+        assert!(rc.contains("BLOCK \"040704b0\""));
+        assert!(rc.contains("VALUE \"ProductName\", \"Calculator\"\n"));
+
+        fs::write(dir.join("broken.rc"), "1 ICON \"app.ico").expect("write resource script");
+        assert!(matches!(WindowsResource::from_rc_file(dir.join("broken.rc")), Err(Error::InvalidRcFile { .. })));
+        assert!(matches!(WindowsResource::from_rc_file(dir.join("missing.rc")), Err(Error::ResourceFileNotFound(_))));
+    }
+
+    #[test]
+    fn resource_template() {
+        let dir = TempDir::new("resource-template");
+        fs::write(dir.join("app.rc.in"), "LANGUAGE @LANGUAGE@, 0\n\
+                                          1 VERSIONINFO\n\
+                                          PRODUCTVERSION @PRODUCTVERSION@\n\
+                                          FILEFLAGS @FILEFLAGS@\n\
+                                          // support@example.com @ @@ @not a variable@\n\
+                                          VALUE \"ProductName\", \"@ProductName@\"\n").expect("write template");
+        fs::write(dir.join("broken.rc.in"), "VALUE \"Comments\", \"@Unknown@\"\n").expect("write template");
+
+        let mut res = WindowsResource::new();
+        res.set("ProductName", "Calc \"2\"")
+            .set_language(0x0407)
+            .set_version_info(VersionInfo::PRODUCTVERSION, 0x0001_0002_0003_0004)
+            .set_version_info(VersionInfo::FILEFLAGS, 0x8)
+            .set_resource_template(dir.join("app.rc.in"));
+        assert_eq!(res.to_rc_string().expect("render resource template"),
+                   "LANGUAGE 0x407, 0\n\
+                    1 VERSIONINFO\n\
+                    PRODUCTVERSION 1, 2, 3, 4\n\
+                    FILEFLAGS 0x8\n\
+                    // support@example.com @ @@ @not a variable@\n\
+                    VALUE \"ProductName\", \"Calc \"\"2\"\"\"\n");
+        assert!(matches!(res.set_resource_template(dir.join("broken.rc.in")).to_rc_string(),
+                         Err(Error::InvalidRcFile { .. })));
+        assert!(matches!(res.set_resource_template(dir.join("missing.rc.in")).to_rc_string(),
+                         Err(Error::ResourceFileNotFound(_))));
+        res.set_resource_file(dir.join("app.rc"));
+        assert!(res.to_rc_string().expect("render resource script").contains("1 VERSIONINFO"));
+    }
+
+    #[test]
+    fn wix_fragment() {
+        let dir = TempDir::new("wix-fragment");
+        let mut res = WindowsResource::new();
+        res.set_output_directory(&dir)
+            .set("ProductName", "Calc ${EDITION}")
+            .set("CompanyName", "ACME & Sons")
+            .set_version_info(VersionInfo::PRODUCTVERSION, 0x0001_0002_0003_0000)
+            .set_version_info(VersionInfo::FILEVERSION, 0x0001_0002_0003_0004);
+        let mut edition = HashMap::new();
+        edition.insert("EDITION".to_string(), "Pro".to_string());
+        res.add_metadata_provider(edition);
+        let path = res.export_wix_fragment("product.wxi").expect("export WiX fragment");
+        assert_eq!(path, dir.join("product.wxi"));
+        assert_eq!(fs::read_to_string(&path).expect("read WiX fragment"),
+                   "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<Include>\n\
+                    \x20 <?define ProductName = \"Calc Pro\" ?>\n\
+                    \x20 <?define Manufacturer = \"ACME & Sons\" ?>\n\
+                    \x20 <?define ProductVersion = \"1.2.3.0\" ?>\n\
+                    \x20 <?define FileVersion = \"1.2.3.4\" ?>\n\
+                    </Include>\n");
+
+        res.set("ProductName", "\"Calc\"");
+        assert!(matches!(res.export_wix_fragment("product.wxi"), Err(Error::InvalidProperties(_))));
+        res.set("ProductName", "Calc")
+            .set_version_info(VersionInfo::PRODUCTVERSION, 0x0001_0100_0000_0000);
+        match res.export_wix_fragment("product.wxi") {
+            Err(Error::InvalidProperties(invalid)) => assert_eq!(invalid, ["ProductVersion 1.256 is not an MSI version, \
+                                                                           the major and minor version must be at most 255"]),
+            result => panic!("expected InvalidProperties, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn satellite_dlls() {
+        let dir = TempDir::new("satellite-dlls");
+        let mut res = WindowsResource::new_without_metadata();
+        with_fake_llvm_rc(&mut res, &dir)
+            .set_language(::language::EN_US)
+            .set_localized("FileDescription", ::language::DE_DE, "Rechner")
+            .add_string("IDS_GREETING", 1, "Hello")
+            .add_string("IDS_QUIT", 2, "Quit")
+            .set_localized_string(1, ::language::DE_DE, "Hallo")
+            .set_localized_string(1, ::language::FR_FR, "Bonjour")
+            .set_linker_path("true");
+        let dlls = res.compile_satellite_dlls("calc").expect("link satellite DLLs");
+        assert_eq!(dlls, [dir.join("calc.en-US.dll"), dir.join("calc.de-DE.dll"), dir.join("calc.fr-FR.dll")]);
+        let rc = fs::read_to_string(dir.join("calc.de-DE.rc")).expect("read resource script");
+        assert!(rc.contains("LANGUAGE 0x7, 0x1\n"));
+        assert!(rc.contains("VALUE \"FileDescription\", \"Rechner\"\n"));
+        assert!(rc.contains("\nSTRINGTABLE\n{\n1 \"Hallo\"\n2 \"Quit\"\n}\n"));
+        let rc = fs::read_to_string(dir.join("calc.fr-FR.rc")).expect("read resource script");
+        assert!(rc.contains("BLOCK \"040c04b0\"\n"));
+        assert!(rc.contains("1 \"Bonjour\"\n"));
+
+        let command = res.satellite_link_command(Toolchain::Gnu, Path::new("calc.de-DE.o"), Path::new("calc.de-DE.dll"))
+            .expect("render link command");
+        assert_eq!(command[1..], ["-shared", "-nostdlib", "-e", "0", "-s", "-o", "calc.de-DE.dll", "calc.de-DE.o"]);
+    }
+
+    #[test]
+    fn named_resource_ids() {
+        let mut res = WindowsResource::new();
+        res.set_icon_with_id("icons/app.ico", "APP")
+            .add_icon("icons/document.ico", "DOCUMENT")
+            .add_bitmap("images/logo.bmp", "LOGO")
+            .add_rcdata("config.json", "201")
+            .set_manifest("<assembly/>")
+            .set_manifest_resource_id("MANIFEST");
+        let rc = res.to_rc_string().expect("render resource script");
+        assert!(rc.contains("\nAPP ICON \"icons/app.ico\"\n"));
+        assert!(rc.contains("\nDOCUMENT ICON \"icons/document.ico\"\n"));
+        assert!(rc.contains("\nLOGO BITMAP \"images/logo.bmp\"\n"));
+        assert!(rc.contains("\n201 RCDATA \"config.json\"\n"));
+        assert!(rc.contains("\nMANIFEST 24\n"));
+        assert_eq!(res.resource_ids().expect("resource ids"), [("RCDATA_CONFIG".to_string(), 201)]);
+
+        res.add_bitmap("images/logo.bmp", "LOGO \"2\"");
+        assert!(matches!(res.to_rc_string(), Err(Error::InvalidResourceName(_))));
+    }
+
+    #[test]
+    fn numeric_icon_ids() {
+        let mut res = WindowsResource::new();
+        res.set_icon("icons/app.ico")
+            .add_icon_with_numeric_id("icons/document.ico", 101)
+            .add_icon_with_numeric_id("other/document.ico", 102);
+        let rc = res.to_rc_string().expect("render resource script");
+        assert!(rc.contains("\n101 ICON \"icons/document.ico\"\n"));
+        assert!(rc.contains("\n102 ICON \"other/document.ico\"\n"));
+
+        let path = env::temp_dir().join("winres-resource-ids.rs");
+        res.write_resource_ids_rs(&path).expect("write resource ids");
+        let ids = fs::read_to_string(&path).expect("read resource ids");
+        assert_eq!(ids, "// Resource ids generated by winres\n\
+                         pub const ICON_APP: u16 = 1;\n\
+                         pub const ICON_DOCUMENT: u16 = 101;\n\
+                         pub const ICON_DOCUMENT_102: u16 = 102;\n");
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn resources_rs() {
+        let mut res = WindowsResource::new();
+        res.set("ProductName", "winres \"rs\"")
+            .add_bitmap("images/logo.bmp", 201)
+            .add_rcdata("config.json", 301)
+            .add_string("IDS_GREETING", 1, "Hello");
+        let rc = res.to_rc_string().expect("render resource script");
+        assert!(rc.contains("\n201 BITMAP \"images/logo.bmp\"\n"));
+        assert!(rc.contains("\n301 RCDATA \"config.json\"\n"));
+        assert!(rc.contains("\nSTRINGTABLE\n{\n1 \"Hello\"\n}\n"));
+
+        let path = env::temp_dir().join("winres-resources.rs");
+        res.write_resources_rs(&path).expect("write resources.rs");
+        let module = fs::read_to_string(&path).expect("read resources.rs");
+        assert!(module.contains("pub const BITMAP_LOGO: u16 = 201;\n"));
+        assert!(module.contains("pub const RCDATA_CONFIG: u16 = 301;\n"));
+        assert!(module.contains("pub const IDS_GREETING: u16 = 1;\n"));
+        assert!(module.contains("pub const PRODUCT_NAME: &str = \"winres \\\"rs\\\"\";\n"));
+        assert!(module.contains("pub const FILE_VERSION: &str = "));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn utf16_scripts() {
+        let mut res = WindowsResource::new();
+        res.set("LegalCopyright", "© 電卓").set_rc_encoding(RcEncoding::Utf16Le);
+        let path = env::temp_dir().join("winres-utf16.rc");
+        res.write_resource_file(&path).expect("write resource script");
+        let script = fs::read(&path).expect("read resource script");
+        assert_eq!(script[..2], [0xff, 0xfe]);
+        let utf16: Vec<u16> = script[2..].chunks(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+        let script = String::from_utf16(&utf16).expect("valid UTF-16");
+        assert!(script.contains("VALUE \"LegalCopyright\", \"© 電卓\"\r\n"));
+        assert!(!script.contains("code_page"));
+        assert!(res.to_rc_string().expect("render resource script").starts_with("#pragma code_page(65001)\n"));
+        // a script that is not UTF-8 fails instead of panicking
+        let invalid = res.write_script(&path, |f| Ok(::std::io::Write::write_all(f, b"\xff\n")?));
+        assert!(matches!(invalid, Err(Error::Io(ref e)) if e.kind() == ::std::io::ErrorKind::InvalidData));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn build_env() {
+        let mut res = WindowsResource::new();
+        res.set("ProductName", "winres\nsuite")
+            .set("FileVersion", "1.2.3-${git}")
+            .set("ProductVersion", "1.2.3")
+            .add_metadata_provider(vec![("git".to_string(), "abc".to_string())].into_iter()
+                .collect::<::std::collections::HashMap<_, _>>());
+        assert_eq!(res.build_env().expect("resolve build env"), [
+            ("WINRES_FILE_VERSION".to_string(), "1.2.3-abc".to_string()),
+            ("WINRES_PRODUCT_VERSION".to_string(), "1.2.3".to_string()),
+            ("WINRES_PRODUCT_NAME".to_string(), "winres suite".to_string()),
+        ]);
+        res.remove("ProductName");
+        assert_eq!(res.build_env().expect("resolve build env").len(), 2);
+    }
+
+    #[test]
+    fn custom_resources() {
+        let mut res = WindowsResource::new();
+        res.add_custom_resource("PRESET", "DEFAULT", "presets/default.bin")
+            .add_custom_resource("300", 2, "presets/warm.bin");
+        let rc = res.to_rc_string().expect("render resource script");
+        assert!(rc.contains("\nDEFAULT PRESET \"presets/default.bin\"\n"));
+        assert!(rc.contains("\n2 300 \"presets/warm.bin\"\n"));
+        assert_eq!(res.resource_ids().expect("resource ids"), [("_300_WARM".to_string(), 2)]);
+
+        res.add_custom_resource("MY TYPE", 3, "data.bin");
+        assert!(matches!(res.to_rc_string(), Err(Error::InvalidResourceName(ref name)) if name == "MY TYPE"));
+        let mut res = WindowsResource::new();
+        res.add_custom_resource("PRESET", "1ST", "data.bin");
+        assert!(matches!(res.to_rc_string(), Err(Error::InvalidResourceName(_))));
+    }
+
+    #[test]
+    fn env_placeholders() {
+        env::set_var("WINRES_TEST_BUILD_YEAR", "2031");
+        let mut res = WindowsResource::new();
+        res.set("LegalCopyright", "© ${WINRES_TEST_BUILD_YEAR} ACME");
+        let rc = res.to_rc_string().expect("render resource script");
+        assert!(rc.contains("VALUE \"LegalCopyright\", \"© 2031 ACME\"\n"));
+        res.add_metadata_provider(vec![("WINRES_TEST_BUILD_YEAR".to_string(), "2032".to_string())].into_iter()
+            .collect::<::std::collections::HashMap<_, _>>());
+        assert!(res.to_rc_string().expect("render resource script").contains("© 2032 ACME"));
+
+        res.set("Comments", "${WINRES_TEST_UNDEFINED}");
+        match res.to_rc_string() {
+            Err(Error::UndefinedPlaceholder { ref name, .. }) => assert_eq!(name, "WINRES_TEST_UNDEFINED"),
+            result => panic!("expected UndefinedPlaceholder, got {:?}", result),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "cargo-metadata")]
+    fn config_file() {
+        let dir = TempDir::new("config-file");
+        let toml_path = dir.join("winres.toml");
+        fs::write(&toml_path, r#"
+FileDescription = "Calculator"
+icon = "calc.ico"
+unknown = 1
+
+[version-info]
+FILEVERSION = "1.2.3.4"
+
+[icons]
+101 = "document.ico"
+DOCUMENT = "other.ico"
+
+[strings]
+IDS_GREETING = { id = 1, value = "Hello" }
+IDS_BROKEN = { value = "no id" }
+
+[manifest]
+execution-level = "requireAdministrator"
+dpi-awareness = "permonitorv2"
+visual-styles = true
+long-path-aware = "yes"
+segment-heap = true
+"#).expect("write winres.toml");
+        let res = WindowsResource::from_config_file(&toml_path).expect("load winres.toml");
+        assert_eq!(res.get("FileDescription"), Some("Calculator"));
+        assert_eq!(res.version_info.get(&VersionInfo::FILEVERSION), Some(&0x0001_0002_0003_0004));
+        let rc = res.to_rc_string().expect("render resource script");
+        assert!(rc.contains(&format!("1 ICON \"{}\"", escape_string(&dir.join("calc.ico").display().to_string(),
+                                                                        res.toolchain()))));
+        assert!(rc.contains("101 ICON"));
+        assert!(rc.contains("DOCUMENT ICON"));
+        assert!(rc.contains("1 \"Hello\""));
+        let xml = res.manifest_xml().expect("manifest");
+        assert!(xml.contains("requireAdministrator"));
+        assert!(xml.contains("permonitorv2, permonitor"));
+        assert!(xml.contains("Microsoft.Windows.Common-Controls"));
+        assert!(xml.contains(">SegmentHeap</heapType>"));
+        let warnings = res.metadata_warnings().join("\n");
+        assert!(warnings.contains("unknown"));
+        assert!(warnings.contains("strings.IDS_BROKEN"));
+        assert!(warnings.contains("manifest.long-path-aware is not a boolean"));
+
+        let json_path = dir.join("winres.json");
+        fs::write(&json_path, r#"{"ProductName": "Rechner", "manifest": {"dpi-awareness": "system"}}"#)
+            .expect("write winres.json");
+        if cfg!(feature = "json-config") {
+            let res = WindowsResource::from_config_file(&json_path).expect("load winres.json");
+            assert_eq!(res.get("ProductName"), Some("Rechner"));
+            assert!(res.manifest_xml().expect("manifest").contains("<dpiAware"));
+        } else {
+            assert!(matches!(WindowsResource::from_config_file(&json_path), Err(Error::InvalidConfigFile { .. })));
+        }
+
+        fs::write(&json_path, "{\"ProductName\": null}").expect("write winres.json");
+        assert!(matches!(WindowsResource::from_config_file(&json_path), Err(Error::InvalidConfigFile { .. })));
+        assert!(matches!(WindowsResource::from_config_file(dir.join("missing.toml")),
+                         Err(Error::ConfigFileNotFound(_))));
+    }
+
+    #[test]
+    fn paths_with_spaces() {
+        let mut res = WindowsResource::new();
+        res.set_output_directory("/home/John Doe/out")
+            .set_package_include_dir("/home/John Doe/project")
+            .set_windres_path("windres");
+        let commands = res.command_lines(Toolchain::Gnu, "/home/John Doe/out/resource.rc").expect("command lines");
+        assert_eq!(commands[0], ["windres", "--use-temp-file", "-I/home/John Doe/project", "--codepage=65001",
+                                 "/home/John Doe/out/resource.rc", "/home/John Doe/out/resource.o"]);
+        let commands = res.command_lines(Toolchain::LlvmRc, "/home/John Doe/out/resource.rc").expect("command lines");
+        assert_eq!(commands[0][1..], ["-I/home/John Doe/project", "-FO/home/John Doe/out/resource.lib",
+                                      "/home/John Doe/out/resource.rc"]);
+    }
+
+    #[test]
+    fn link_arg_output() {
+        let mut res = WindowsResource::new();
+        res.set_output_directory("out").set_include_package_dir(false).set_link_mode(LinkMode::LinkArg);
+        let commands = res.command_lines(Toolchain::LlvmRc, "resource.rc").expect("command lines");
+        assert_eq!(commands[0][1..], ["-FOout/resource.res", "resource.rc"]);
+        res.set_windres_path("windres").set_ar_path("ar").set_output_name("my-app.res");
+        let commands = res.command_lines(Toolchain::Gnu, "resource.rc").expect("command lines");
+        assert_eq!(commands, [["windres", "--codepage=65001", "resource.rc", "out/my_app_res.o"]]);
+    }
+
+    #[test]
+    fn target_kinds() {
+        let mut res = WindowsResource::new();
+        res.set_output_directory("out").set_include_package_dir(false)
+            .apply_to(TargetKind::Bins)
+            .apply_to(TargetKind::Tests)
+            .apply_to(TargetKind::Bins);
+        assert_eq!(res.link_mode(), LinkMode::LinkArg);
+        let commands = res.command_lines(Toolchain::LlvmRc, "resource.rc").expect("command lines");
+        assert_eq!(commands[0][1..], ["-FOout/resource.res", "resource.rc"]);
+        assert_eq!(res.link_directives(Toolchain::LlvmRc, Path::new("out"), "resource", None),
+                   ["cargo:rustc-link-arg-bins=out/resource.res", "cargo:rustc-link-arg-tests=out/resource.res"]);
+        assert_eq!(res.link_directives(Toolchain::Gnu, Path::new("out"), "resource", Some("app")),
+                   ["cargo:rustc-link-arg-bin=app=out/resource.o"]);
+    }
+
+    #[test]
+    fn prepared_commands() {
+        let mut res = WindowsResource::new();
+        let tool = ::mingw::windres_tool(::sdk::Arch::X64, Path::new("/opt/mingw/bin/windres"));
+        res.set_output_directory("out").set_include_package_dir(false).set_tool(tool);
+        let commands = res.prepared_commands(Toolchain::Gnu, "resource.rc").expect("prepared commands");
+        assert_eq!(commands[0].get_program(), "/opt/mingw/bin/windres");
+        assert_eq!(commands[0].get_args().collect::<Vec<_>>(), ["--codepage=65001", "resource.rc", "out/resource.o"]);
+        assert_eq!(commands[0].get_current_dir(), Some(Path::new(env!("CARGO_MANIFEST_DIR"))));
+        let path = commands[0].get_envs().find(|&(name, _)| name == "PATH").and_then(|(_, value)| value);
+        assert_eq!(path.and_then(|path| env::split_paths(path).next()), Some(::std::path::PathBuf::from("/opt/mingw/bin")));
+
+        res.set_tool_wrapper(["wine", "--quiet"]);
+        assert_eq!(res.command_lines(Toolchain::Gnu, "resource.rc").expect("command lines")[0],
+                   ["wine", "--quiet", "/opt/mingw/bin/windres", "--codepage=65001", "resource.rc", "out/resource.o"]);
+        let commands = res.prepared_commands(Toolchain::Gnu, "resource.rc").expect("prepared commands");
+        assert_eq!(commands[0].get_program(), "wine");
+        assert!(commands[0].get_envs().any(|(name, _)| name == "PATH"));
+        res.set_llvm_rc_path("llvm-rc");
+        assert_eq!(res.command_lines(Toolchain::LlvmRc, "resource.rc").expect("command lines")[0][..3],
+                   ["wine", "--quiet", "llvm-rc"]);
+    }
+
+    #[test]
+    fn compiler_arguments() {
+        let mut res = WindowsResource::new();
+        res.set_toolchain(Toolchain::LlvmRc)
+            .set_output_directory("out")
+            .set_include_package_dir(false)
+            .add_include_dir("/opt/res")
+            .add_include_rc("/opt/res/dialogs.rc")
+            .add_include_rc("/opt/menus/menus.rc")
+            .define("DEBUG", "1")
+            .define("FLAG", "")
+            .add_toolkit_arg("-N");
+        let commands = res.command_lines(Toolchain::LlvmRc, "resource.rc").expect("command lines");
+        assert_eq!(commands[0][1..], ["-I/opt/res", "-I/opt/menus", "-D", "DEBUG=1", "-D", "FLAG", "-N",
+                                      "-FOout/resource.lib", "resource.rc"]);
+    }
+
+    #[test]
+    fn sdk_version_matching() {
+        let requested = parse_sdk_version("10.0.22621.0\\").expect("version");
+        assert_eq!(requested.as_str(), "10.0.22621.0");
+        assert!(sdk_version_matches("10.0.22621.0", &requested));
+        let prefix = parse_sdk_version(" 10.0.22621 ").expect("prefix");
+        assert!(sdk_version_matches("10.0.22621.0", &prefix));
+        assert!(!sdk_version_matches("10.0.22000.0", &prefix));
+        assert_eq!(parse_sdk_version("\\"), None);
     }
 
     #[cfg(target_env = "msvc")]
     #[test]
     fn test_get_sdk() {
-        let tool = get_sdk().expect("get_sdk");
-        println!("{:?}", tool);
+        let (tool, reason) = get_sdk(&::ProcessEnv, None, &mut vec![]).expect("get_sdk");
+        println!("{:?}, {}", tool, reason);
     }
 }
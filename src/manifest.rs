@@ -0,0 +1,686 @@
+//! Typed builder for application manifests
+//!
+//! Instead of writing the manifest XML by hand, the most common settings can be
+//! configured with a [`Manifest`] and embedded with
+//! [`WindowsResource::set_manifest_builder()`].
+//!
+//! ```rust
+//! use winres::manifest::{Manifest, ExecutionLevel, DpiAwareness, SupportedOs};
+//!
+//! let xml = Manifest::new()
+//!     .requested_execution_level(ExecutionLevel::RequireAdministrator)
+//!     .dpi_awareness(DpiAwareness::PerMonitorV2)
+//!     .supported_os(&[SupportedOs::Windows10, SupportedOs::Windows11])
+//!     .common_controls_v6()
+//!     .to_xml();
+//! assert!(xml.contains("requireAdministrator"));
+//! ```
+//!
+//! [`Manifest`]: struct.Manifest.html
+//! [`WindowsResource::set_manifest_builder()`]: ../struct.WindowsResource.html#method.set_manifest_builder
+
+use std::fmt::Write;
+
+/// Requested execution level of the `trustInfo` section
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum ExecutionLevel {
+    /// Run with the same token as the parent process
+    AsInvoker,
+    /// Run with the highest privileges the user can obtain
+    HighestAvailable,
+    /// Run as administrator, always shows an UAC prompt for non-admins
+    RequireAdministrator,
+}
+
+impl ExecutionLevel {
+    fn as_str(&self) -> &'static str {
+        match *self {
+            ExecutionLevel::AsInvoker => "asInvoker",
+            ExecutionLevel::HighestAvailable => "highestAvailable",
+            ExecutionLevel::RequireAdministrator => "requireAdministrator",
+        }
+    }
+}
+
+/// DPI awareness of the application
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum DpiAwareness {
+    /// The application is scaled by the system
+    Unaware,
+    /// The application scales itself to the DPI of the primary monitor
+    System,
+    /// The application scales itself to the DPI of each monitor
+    PerMonitor,
+    /// Per monitor awareness with child window and dialog scaling,
+    /// falls back to `PerMonitor` on older Windows versions
+    PerMonitorV2,
+}
+
+impl DpiAwareness {
+    /// Value of the legacy `dpiAware` element
+    fn dpi_aware(&self) -> &'static str {
+        match *self {
+            DpiAwareness::Unaware => "false",
+            DpiAwareness::System => "true",
+            DpiAwareness::PerMonitor | DpiAwareness::PerMonitorV2 => "true/pm",
+        }
+    }
+
+    /// Value of the `dpiAwareness` element (Windows 10 1607 and later)
+    fn dpi_awareness(&self) -> &'static str {
+        match *self {
+            DpiAwareness::Unaware => "unaware",
+            DpiAwareness::System => "system",
+            DpiAwareness::PerMonitor => "permonitor",
+            DpiAwareness::PerMonitorV2 => "permonitorv2, permonitor",
+        }
+    }
+}
+
+/// Operating system versions for the `compatibility` section
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum SupportedOs {
+    WindowsVista,
+    Windows7,
+    Windows8,
+    Windows8_1,
+    Windows10,
+    /// Windows 11 shares its GUID with Windows 10
+    Windows11,
+}
+
+impl SupportedOs {
+    /// The `supportedOS` GUID
+    pub fn guid(&self) -> &'static str {
+        match *self {
+            SupportedOs::WindowsVista => "{e2011457-1546-43c5-a5fe-008deee3d3f0}",
+            SupportedOs::Windows7 => "{35138b9a-5d96-4fbd-8e2d-a2440225f93a}",
+            SupportedOs::Windows8 => "{4a2f28e3-53b9-4441-ba9c-d69d4a4a6e38}",
+            SupportedOs::Windows8_1 => "{1f676c76-80e1-4239-95bb-83d0f6d0da78}",
+            SupportedOs::Windows10 | SupportedOs::Windows11 => "{8e0f7a12-bfb3-4fe8-b9a5-48fd50a15a9a}",
+        }
+    }
+}
+
+/// Place of a raw XML fragment in the manifest, see [`Manifest::raw_fragment()`]
+///
+/// [`Manifest::raw_fragment()`]: struct.Manifest.html#method.raw_fragment
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum ManifestSection {
+    /// Directly in the `assembly` element, after everything else
+    Assembly,
+    /// In the `application` element of the `compatibility` section, after the `supportedOS` entries
+    Compatibility,
+    /// In the `windowsSettings` element, after the other settings
+    WindowsSettings,
+}
+
+/// Identity of the application, the `assemblyIdentity` element
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct AssemblyIdentity {
+    /// The name, e.g. `"Contoso.Calculator"`
+    pub name: String,
+    /// The four part version, e.g. `"1.2.3.0"`
+    pub version: String,
+    /// `"x86"`, `"amd64"`, `"arm64"` or `"*"`, left out if `None`
+    pub processor_architecture: Option<String>,
+}
+
+impl AssemblyIdentity {
+    /// An identity with `name`, `version` and no processor architecture
+    pub fn new(name: &str, version: &str) -> Self {
+        AssemblyIdentity { name: name.to_string(), version: version.to_string(), processor_architecture: None }
+    }
+}
+
+/// A side-by-side assembly the application depends on, see
+/// [`Manifest::add_dependent_assembly()`]
+///
+/// [`Manifest::add_dependent_assembly()`]: struct.Manifest.html#method.add_dependent_assembly
+#[derive(PartialEq, Eq, Debug, Clone)]
+struct DependentAssembly {
+    name: String,
+    version: String,
+    public_key_token: Option<String>,
+    processor_architecture: String,
+}
+
+/// Threading model of a COM class, the `threadingModel` attribute of `comClass`
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum ThreadingModel {
+    /// Single threaded apartment
+    Apartment,
+    /// Multithreaded apartment
+    Free,
+    /// Both single and multithreaded apartments
+    Both,
+    /// Neutral apartment
+    Neutral,
+}
+
+impl ThreadingModel {
+    fn as_str(&self) -> &'static str {
+        match *self {
+            ThreadingModel::Apartment => "Apartment",
+            ThreadingModel::Free => "Free",
+            ThreadingModel::Both => "Both",
+            ThreadingModel::Neutral => "Neutral",
+        }
+    }
+}
+
+/// A COM class of a [`ComFile`], the `comClass` element
+///
+/// [`ComFile`]: struct.ComFile.html
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct ComClass {
+    /// The class id, e.g. `"{00000000-0000-0000-0000-000000000000}"`
+    pub clsid: String,
+    pub threading_model: Option<ThreadingModel>,
+    pub progid: Option<String>,
+    /// The id of the type library describing the class
+    pub tlbid: Option<String>,
+    pub description: Option<String>,
+}
+
+impl ComClass {
+    /// A class with the id `clsid` and no other attributes
+    pub fn new(clsid: &str) -> Self {
+        ComClass {
+            clsid: clsid.to_string(),
+            threading_model: None,
+            progid: None,
+            tlbid: None,
+            description: None,
+        }
+    }
+}
+
+/// A type library of a [`ComFile`], the `typelib` element
+///
+/// [`ComFile`]: struct.ComFile.html
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct TypeLib {
+    /// The type library id
+    pub tlbid: String,
+    /// The version, e.g. `"1.0"`
+    pub version: String,
+    /// The directory of the help files, relative to the file
+    pub helpdir: String,
+}
+
+impl TypeLib {
+    /// A type library with the id `tlbid`, `version` and no help directory
+    pub fn new(tlbid: &str, version: &str) -> Self {
+        TypeLib { tlbid: tlbid.to_string(), version: version.to_string(), helpdir: String::new() }
+    }
+}
+
+/// A file of the application with COM classes and type libraries, the `file` element
+///
+/// Declaring the classes of a DLL next to the executable activates them without
+/// registration, so `CoCreateInstance` finds them without entries in the registry.
+///
+/// ```rust
+/// use winres::manifest::{ComClass, ComFile, Manifest, ThreadingModel, TypeLib};
+///
+/// let mut class = ComClass::new("{c3c2f2b4-7c59-4d8c-9a28-7f0e4a6b8e11}");
+/// class.threading_model = Some(ThreadingModel::Apartment);
+/// class.progid = Some("Sidecar.Widget".to_string());
+/// let xml = Manifest::new()
+///     .com_file(ComFile::new("sidecar.dll")
+///         .com_class(class)
+///         .typelib(TypeLib::new("{0d6a4a9e-3b8f-4b8e-9c1e-5b2a7f4c1d22}", "1.0")))
+///     .to_xml();
+/// assert!(xml.contains(r#"<file name="sidecar.dll">"#));
+/// ```
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct ComFile {
+    name: String,
+    classes: Vec<ComClass>,
+    typelibs: Vec<TypeLib>,
+}
+
+impl ComFile {
+    /// A file named `name`, relative to the application
+    pub fn new(name: &str) -> Self {
+        ComFile { name: name.to_string(), classes: vec![], typelibs: vec![] }
+    }
+
+    /// Declare a COM class implemented by the file
+    pub fn com_class(&mut self, class: ComClass) -> &mut Self {
+        self.classes.push(class);
+        self
+    }
+
+    /// Declare a type library contained in the file
+    pub fn typelib(&mut self, typelib: TypeLib) -> &mut Self {
+        self.typelibs.push(typelib);
+        self
+    }
+
+    fn write_xml(&self, xml: &mut String) -> ::std::fmt::Result {
+        writeln!(xml, r#"<file name="{}">"#, escape_attribute(&self.name))?;
+        for class in self.classes.iter() {
+            write!(xml, r#"<comClass clsid="{}""#, escape_attribute(&class.clsid))?;
+            if let Some(model) = class.threading_model {
+                write!(xml, r#" threadingModel="{}""#, model.as_str())?;
+            }
+            let attributes = [("progid", &class.progid), ("tlbid", &class.tlbid), ("description", &class.description)];
+            for (name, value) in attributes.iter() {
+                if let Some(value) = value {
+                    write!(xml, r#" {}="{}""#, name, escape_attribute(value))?;
+                }
+            }
+            writeln!(xml, " />")?;
+        }
+        for typelib in self.typelibs.iter() {
+            writeln!(xml, r#"<typelib tlbid="{}" version="{}" helpdir="{}" />"#, escape_attribute(&typelib.tlbid),
+                     escape_attribute(&typelib.version), escape_attribute(&typelib.helpdir))?;
+        }
+        writeln!(xml, "</file>")
+    }
+}
+
+/// Escape `value` for an XML attribute in double quotes
+fn escape_attribute(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('"', "&quot;")
+}
+
+/// Application manifest
+///
+/// Only the settings that were configured are written, an empty manifest
+/// results in an empty `assembly` element.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Manifest {
+    execution_level: Option<ExecutionLevel>,
+    ui_access: bool,
+    dpi_awareness: Option<DpiAwareness>,
+    supported_os: Vec<SupportedOs>,
+    common_controls_v6: bool,
+    long_path_aware: bool,
+    active_code_page_utf8: bool,
+    segment_heap: bool,
+    gdi_scaling: bool,
+    disable_theming: bool,
+    printer_driver_isolation: bool,
+    raw_fragments: Vec<(ManifestSection, String)>,
+    com_files: Vec<ComFile>,
+    assembly_identity: Option<AssemblyIdentity>,
+    dependent_assemblies: Vec<DependentAssembly>,
+}
+
+impl Manifest {
+    /// Create an empty manifest
+    pub fn new() -> Self {
+        Manifest::default()
+    }
+
+    /// Set the identity of the application
+    pub fn assembly_identity(&mut self, identity: AssemblyIdentity) -> &mut Self {
+        self.assembly_identity = Some(identity);
+        self
+    }
+
+    /// The identity of the application, if set
+    pub fn get_assembly_identity(&self) -> Option<&AssemblyIdentity> {
+        self.assembly_identity.as_ref()
+    }
+
+    /// Set the requested execution level for UAC
+    pub fn requested_execution_level(&mut self, level: ExecutionLevel) -> &mut Self {
+        self.execution_level = Some(level);
+        self
+    }
+
+    /// Allow the application to bypass UI protection levels, e.g. for accessibility tools
+    ///
+    /// This is written as `uiAccess` attribute of the requested execution level, which
+    /// defaults to `asInvoker` if none is set. Such applications have to be signed.
+    pub fn ui_access(&mut self, ui_access: bool) -> &mut Self {
+        self.ui_access = ui_access;
+        self
+    }
+
+    /// Set the DPI awareness
+    pub fn dpi_awareness(&mut self, awareness: DpiAwareness) -> &mut Self {
+        self.dpi_awareness = Some(awareness);
+        self
+    }
+
+    /// Declare the operating systems the application was tested on
+    ///
+    /// The list is added to the already declared operating systems.
+    pub fn supported_os(&mut self, os: &[SupportedOs]) -> &mut Self {
+        for os in os {
+            if !self.supported_os.contains(os) {
+                self.supported_os.push(*os);
+            }
+        }
+        self
+    }
+
+    /// Depend on version 6 of the Common Controls to enable visual styles
+    pub fn common_controls_v6(&mut self) -> &mut Self {
+        self.common_controls_v6 = true;
+        self
+    }
+
+    /// Depend on the side-by-side assembly `name` with `version`, e.g. a private assembly
+    /// in the application directory
+    ///
+    /// Shared assemblies are signed and identified by their `public_key_token`, private
+    /// ones usually have none. `processor_architecture` is e.g. `"amd64"` or `"*"`.
+    ///
+    /// ```rust
+    /// use winres::manifest::Manifest;
+    ///
+    /// let xml = Manifest::new()
+    ///     .add_dependent_assembly("Contoso.Plugins", "1.0.0.0", None, "*")
+    ///     .to_xml();
+    /// assert!(xml.contains(r#"name="Contoso.Plugins" version="1.0.0.0""#));
+    /// ```
+    pub fn add_dependent_assembly(&mut self, name: &str, version: &str, public_key_token: Option<&str>,
+                                  processor_architecture: &str) -> &mut Self {
+        self.dependent_assemblies.push(DependentAssembly {
+            name: name.to_string(),
+            version: version.to_string(),
+            public_key_token: public_key_token.map(str::to_string),
+            processor_architecture: processor_architecture.to_string(),
+        });
+        self
+    }
+
+    /// Opt into paths longer than `MAX_PATH` (Windows 10 1607 and later)
+    pub fn long_path_aware(&mut self, enable: bool) -> &mut Self {
+        self.long_path_aware = enable;
+        self
+    }
+
+    /// Use UTF-8 as the active code page of the process (Windows 10 1903 and later)
+    pub fn active_code_page_utf8(&mut self, enable: bool) -> &mut Self {
+        self.active_code_page_utf8 = enable;
+        self
+    }
+
+    /// Declare a file with COM classes and type libraries for registration free activation
+    ///
+    /// See [`ComFile`] for an example.
+    ///
+    /// [`ComFile`]: struct.ComFile.html
+    pub fn com_file(&mut self, file: &ComFile) -> &mut Self {
+        self.com_files.push(file.clone());
+        self
+    }
+
+    /// Use the segment heap instead of the NT heap (Windows 10 2004 and later)
+    ///
+    /// This is written as `heapType` and can reduce the memory use of long running processes.
+    pub fn segment_heap(&mut self, enable: bool) -> &mut Self {
+        self.segment_heap = enable;
+        self
+    }
+
+    /// Scale GDI content sharply on high DPI monitors instead of stretching bitmaps
+    /// (Windows 10 1703 and later)
+    pub fn gdi_scaling(&mut self, enable: bool) -> &mut Self {
+        self.gdi_scaling = enable;
+        self
+    }
+
+    /// Turn off visual styles for the windows of the application, e.g. for legacy owner drawn UIs
+    pub fn disable_theming(&mut self, disable: bool) -> &mut Self {
+        self.disable_theming = disable;
+        self
+    }
+
+    /// Load printer drivers in a separate process, so that a crashing driver doesn't take
+    /// down the application (Windows 7 and later)
+    pub fn printer_driver_isolation(&mut self, enable: bool) -> &mut Self {
+        self.printer_driver_isolation = enable;
+        self
+    }
+
+    /// Insert a hand written XML fragment into `section`, for settings without a helper
+    ///
+    /// The fragment is written as is, after the generated content of the section. The
+    /// section is written even if nothing else would be in it. Fragments are not checked,
+    /// an invalid one makes the manifest invalid.
+    ///
+    /// ```rust
+    /// use winres::manifest::{Manifest, ManifestSection};
+    ///
+    /// let xml = Manifest::new()
+    ///     .raw_fragment(ManifestSection::WindowsSettings,
+    ///                   r#"<autoElevate xmlns="http://schemas.microsoft.com/SMI/2005/WindowsSettings">true</autoElevate>"#)
+    ///     .to_xml();
+    /// assert!(xml.contains("<windowsSettings>\n<autoElevate"));
+    /// ```
+    pub fn raw_fragment(&mut self, section: ManifestSection, xml: &str) -> &mut Self {
+        self.raw_fragments.push((section, xml.to_string()));
+        self
+    }
+
+    fn has_fragments(&self, section: ManifestSection) -> bool {
+        self.raw_fragments.iter().any(|&(s, _)| s == section)
+    }
+
+    fn write_fragments(&self, xml: &mut String, section: ManifestSection) -> ::std::fmt::Result {
+        for (_, fragment) in self.raw_fragments.iter().filter(|&&(s, _)| s == section) {
+            writeln!(xml, "{}", fragment)?;
+        }
+        Ok(())
+    }
+
+    fn has_windows_settings(&self) -> bool {
+        self.dpi_awareness.is_some() || self.long_path_aware || self.active_code_page_utf8 || self.segment_heap
+            || self.gdi_scaling || self.disable_theming || self.printer_driver_isolation
+            || self.has_fragments(ManifestSection::WindowsSettings)
+    }
+
+    /// Serialize the manifest to XML
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::new();
+        // writing to a String cannot fail
+        self.write_xml(&mut xml).expect("write xml");
+        xml
+    }
+
+    fn write_xml(&self, xml: &mut String) -> ::std::fmt::Result {
+        writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#)?;
+        writeln!(xml, r#"<assembly xmlns="urn:schemas-microsoft-com:asm.v1" manifestVersion="1.0">"#)?;
+        if let Some(ref identity) = self.assembly_identity {
+            write!(xml, r#"<assemblyIdentity type="win32" name="{}" version="{}""#,
+                   escape_attribute(&identity.name), escape_attribute(&identity.version))?;
+            if let Some(ref architecture) = identity.processor_architecture {
+                write!(xml, r#" processorArchitecture="{}""#, escape_attribute(architecture))?;
+            }
+            writeln!(xml, " />")?;
+        }
+        for file in self.com_files.iter() {
+            file.write_xml(xml)?;
+        }
+        if self.common_controls_v6 {
+            writeln!(xml, "<dependency>")?;
+            writeln!(xml, "<dependentAssembly>")?;
+            writeln!(xml, r#"<assemblyIdentity type="win32" name="Microsoft.Windows.Common-Controls" version="6.0.0.0" processorArchitecture="*" publicKeyToken="6595b64144ccf1df" language="*" />"#)?;
+            writeln!(xml, "</dependentAssembly>")?;
+            writeln!(xml, "</dependency>")?;
+        }
+        for assembly in self.dependent_assemblies.iter() {
+            writeln!(xml, "<dependency>")?;
+            writeln!(xml, "<dependentAssembly>")?;
+            write!(xml, r#"<assemblyIdentity type="win32" name="{}" version="{}" processorArchitecture="{}""#,
+                   escape_attribute(&assembly.name), escape_attribute(&assembly.version),
+                   escape_attribute(&assembly.processor_architecture))?;
+            if let Some(ref token) = assembly.public_key_token {
+                write!(xml, r#" publicKeyToken="{}""#, escape_attribute(token))?;
+            }
+            writeln!(xml, " />")?;
+            writeln!(xml, "</dependentAssembly>")?;
+            writeln!(xml, "</dependency>")?;
+        }
+        if self.execution_level.is_some() || self.ui_access {
+            let level = self.execution_level.unwrap_or(ExecutionLevel::AsInvoker);
+            writeln!(xml, r#"<trustInfo xmlns="urn:schemas-microsoft-com:asm.v3">"#)?;
+            writeln!(xml, "<security>")?;
+            writeln!(xml, "<requestedPrivileges>")?;
+            writeln!(xml, r#"<requestedExecutionLevel level="{}" uiAccess="{}" />"#,
+                     level.as_str(), self.ui_access)?;
+            writeln!(xml, "</requestedPrivileges>")?;
+            writeln!(xml, "</security>")?;
+            writeln!(xml, "</trustInfo>")?;
+        }
+        if !self.supported_os.is_empty() || self.has_fragments(ManifestSection::Compatibility) {
+            writeln!(xml, r#"<compatibility xmlns="urn:schemas-microsoft-com:compatibility.v1">"#)?;
+            writeln!(xml, "<application>")?;
+            let mut guids: Vec<&str> = vec![];
+            for os in self.supported_os.iter() {
+                if !guids.contains(&os.guid()) {
+                    guids.push(os.guid());
+                    writeln!(xml, r#"<supportedOS Id="{}" />"#, os.guid())?;
+                }
+            }
+            self.write_fragments(xml, ManifestSection::Compatibility)?;
+            writeln!(xml, "</application>")?;
+            writeln!(xml, "</compatibility>")?;
+        }
+        if self.has_windows_settings() {
+            writeln!(xml, r#"<application xmlns="urn:schemas-microsoft-com:asm.v3">"#)?;
+            writeln!(xml, "<windowsSettings>")?;
+            if let Some(awareness) = self.dpi_awareness {
+                writeln!(xml, r#"<dpiAware xmlns="http://schemas.microsoft.com/SMI/2005/WindowsSettings">{}</dpiAware>"#,
+                         awareness.dpi_aware())?;
+                writeln!(xml, r#"<dpiAwareness xmlns="http://schemas.microsoft.com/SMI/2016/WindowsSettings">{}</dpiAwareness>"#,
+                         awareness.dpi_awareness())?;
+            }
+            if self.long_path_aware {
+                writeln!(xml, r#"<longPathAware xmlns="http://schemas.microsoft.com/SMI/2016/WindowsSettings">true</longPathAware>"#)?;
+            }
+            if self.active_code_page_utf8 {
+                writeln!(xml, r#"<activeCodePage xmlns="http://schemas.microsoft.com/SMI/2019/WindowsSettings">UTF-8</activeCodePage>"#)?;
+            }
+            if self.segment_heap {
+                writeln!(xml, r#"<heapType xmlns="http://schemas.microsoft.com/SMI/2020/WindowsSettings">SegmentHeap</heapType>"#)?;
+            }
+            if self.gdi_scaling {
+                writeln!(xml, r#"<gdiScaling xmlns="http://schemas.microsoft.com/SMI/2017/WindowsSettings">true</gdiScaling>"#)?;
+            }
+            if self.disable_theming {
+                writeln!(xml, r#"<disableTheming xmlns="http://schemas.microsoft.com/SMI/2005/WindowsSettings">true</disableTheming>"#)?;
+            }
+            if self.printer_driver_isolation {
+                writeln!(xml, r#"<printerDriverIsolation xmlns="http://schemas.microsoft.com/SMI/2011/WindowsSettings">true</printerDriverIsolation>"#)?;
+            }
+            self.write_fragments(xml, ManifestSection::WindowsSettings)?;
+            writeln!(xml, "</windowsSettings>")?;
+            writeln!(xml, "</application>")?;
+        }
+        self.write_fragments(xml, ManifestSection::Assembly)?;
+        writeln!(xml, "</assembly>")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Manifest, ExecutionLevel, DpiAwareness, SupportedOs};
+    use super::{AssemblyIdentity, ComClass, ComFile, ManifestSection, ThreadingModel, TypeLib};
+
+    #[test]
+    fn empty_manifest() {
+        let xml = Manifest::new().to_xml();
+        assert!(xml.contains("<assembly"));
+        assert!(!xml.contains("trustInfo"));
+        assert!(!xml.contains("windowsSettings"));
+    }
+
+    #[test]
+    fn manifest_settings() {
+        let xml = Manifest::new()
+            .requested_execution_level(ExecutionLevel::HighestAvailable)
+            .dpi_awareness(DpiAwareness::PerMonitorV2)
+            .supported_os(&[SupportedOs::Windows10, SupportedOs::Windows11])
+            .common_controls_v6()
+            .to_xml();
+        assert!(xml.contains(r#"level="highestAvailable" uiAccess="false""#));
+        assert!(xml.contains(">true/pm</dpiAware>"));
+        assert!(xml.contains(">permonitorv2, permonitor</dpiAwareness>"));
+        assert!(xml.contains("Microsoft.Windows.Common-Controls"));
+        assert_eq!(xml.matches("<supportedOS").count(), 1);
+    }
+
+    #[test]
+    fn windows_settings() {
+        let xml = Manifest::new()
+            .long_path_aware(true)
+            .active_code_page_utf8(true)
+            .segment_heap(true)
+            .gdi_scaling(true)
+            .to_xml();
+        assert_eq!(xml.matches("<windowsSettings>").count(), 1);
+        assert!(xml.contains(">SegmentHeap</heapType>"));
+        assert!(xml.contains(">true</gdiScaling>"));
+        assert!(xml.contains(">true</longPathAware>"));
+        assert!(xml.contains(">UTF-8</activeCodePage>"));
+        assert!(!xml.contains("dpiAware"));
+    }
+
+    #[test]
+    fn theming_and_raw_fragments() {
+        let xml = Manifest::new()
+            .disable_theming(true)
+            .printer_driver_isolation(true)
+            .raw_fragment(ManifestSection::Assembly, "<!-- tail -->")
+            .raw_fragment(ManifestSection::Compatibility, r#"<maxversiontested Id="10.0.22621.0" />"#)
+            .to_xml();
+        assert!(xml.contains(">true</disableTheming>"));
+        assert!(xml.contains(">true</printerDriverIsolation>\n</windowsSettings>"));
+        assert!(xml.contains("<application>\n<maxversiontested Id=\"10.0.22621.0\" />\n</application>"));
+        assert!(xml.ends_with("<!-- tail -->\n</assembly>\n"));
+        assert!(!xml.contains("supportedOS"));
+
+        let xml = Manifest::new().raw_fragment(ManifestSection::WindowsSettings, "<custom />").to_xml();
+        assert!(xml.contains("<windowsSettings>\n<custom />\n</windowsSettings>"));
+    }
+
+    #[test]
+    fn assembly_identity() {
+        let mut identity = AssemblyIdentity::new("Contoso.Calculator", "1.2.3.0");
+        let xml = Manifest::new().assembly_identity(identity.clone()).to_xml();
+        assert!(xml.contains("manifestVersion=\"1.0\">\n<assemblyIdentity type=\"win32\" \
+                              name=\"Contoso.Calculator\" version=\"1.2.3.0\" />\n"));
+        identity.processor_architecture = Some("amd64".to_string());
+        let xml = Manifest::new().assembly_identity(identity).to_xml();
+        assert!(xml.contains("version=\"1.2.3.0\" processorArchitecture=\"amd64\" />"));
+    }
+
+    #[test]
+    fn dependent_assemblies() {
+        let xml = Manifest::new()
+            .common_controls_v6()
+            .add_dependent_assembly("Contoso.Plugins", "1.0.0.0", None, "*")
+            .add_dependent_assembly("Contoso.Shared", "2.1.0.0", Some("0123456789abcdef"), "amd64")
+            .to_xml();
+        assert_eq!(xml.matches("<dependency>").count(), 3);
+        assert!(xml.contains("<assemblyIdentity type=\"win32\" name=\"Contoso.Plugins\" version=\"1.0.0.0\" \
+                              processorArchitecture=\"*\" />\n</dependentAssembly>"));
+        assert!(xml.contains("processorArchitecture=\"amd64\" publicKeyToken=\"0123456789abcdef\" />"));
+    }
+
+    #[test]
+    fn com_files() {
+        let mut class = ComClass::new("{c3c2f2b4-7c59-4d8c-9a28-7f0e4a6b8e11}");
+        class.threading_model = Some(ThreadingModel::Both);
+        class.description = Some("Widget <&> \"Factory\"".to_string());
+        let xml = Manifest::new()
+            .com_file(ComFile::new("sidecar.dll")
+                .com_class(class)
+                .com_class(ComClass::new("{5a0e3c1d-2b4f-4e6a-8c7d-9f1b2a3c4d5e}"))
+                .typelib(TypeLib::new("{0d6a4a9e-3b8f-4b8e-9c1e-5b2a7f4c1d22}", "1.0")))
+            .to_xml();
+        assert!(xml.contains("<file name=\"sidecar.dll\">\n<comClass clsid=\"{c3c2f2b4-7c59-4d8c-9a28-7f0e4a6b8e11}\" \
+                              threadingModel=\"Both\" description=\"Widget &lt;&amp;> &quot;Factory&quot;\" />\n"));
+        assert!(xml.contains("<comClass clsid=\"{5a0e3c1d-2b4f-4e6a-8c7d-9f1b2a3c4d5e}\" />\n"));
+        assert!(xml.contains("<typelib tlbid=\"{0d6a4a9e-3b8f-4b8e-9c1e-5b2a7f4c1d22}\" version=\"1.0\" helpdir=\"\" />\n</file>\n"));
+    }
+}
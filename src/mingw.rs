@@ -0,0 +1,107 @@
+//! Discovery of the MinGW resource compiler
+//!
+//! `windres` is looked up in the `PATH` first, then in the install prefixes of the
+//! common MinGW distributions (MSYS2, Chocolatey, standalone MinGW-w64). For cross
+//! builds the target prefixed name like `x86_64-w64-mingw32-windres` is found as well.
+
+use std::collections::HashMap;
+use std::env;
+use std::path::{Path, PathBuf};
+
+use sdk::{Arch, Tool};
+
+/// The target prefix of the MinGW tools for `arch`, e.g. `x86_64-w64-mingw32-`
+pub fn target_prefix(arch: Arch) -> &'static str {
+    match arch {
+        Arch::X64 => "x86_64-w64-mingw32-",
+        Arch::X86 => "i686-w64-mingw32-",
+        Arch::Arm64 => "aarch64-w64-mingw32-",
+        Arch::Arm64Ec => "arm64ec-w64-mingw32-",
+        Arch::Arm => "armv7-w64-mingw32-",
+    }
+}
+
+/// `bin` directories of the common MinGW installations for `arch`
+pub fn install_prefixes(arch: Arch) -> Vec<PathBuf> {
+    let dirs: &[&str] = match arch {
+        Arch::X64 => &[
+            r"C:\msys64\ucrt64\bin",
+            r"C:\msys64\mingw64\bin",
+            r"C:\msys64\clang64\bin",
+            r"C:\ProgramData\chocolatey\lib\mingw\tools\install\mingw64\bin",
+            r"C:\mingw64\bin",
+            r"C:\TDM-GCC-64\bin",
+        ],
+        Arch::X86 => &[
+            r"C:\msys64\mingw32\bin",
+            r"C:\msys64\clang32\bin",
+            r"C:\mingw32\bin",
+            r"C:\MinGW\bin",
+        ],
+        Arch::Arm64 | Arch::Arm64Ec => &[
+            r"C:\msys64\clangarm64\bin",
+        ],
+        Arch::Arm => &[],
+    };
+    dirs.iter().map(PathBuf::from).collect()
+}
+
+/// Names of `windres` for `arch`, the target prefixed name first
+///
+/// A plain `windres` may be the one of the host binutils, which targets another platform.
+fn windres_names(arch: Arch) -> Vec<String> {
+    let exe = if cfg!(windows) { ".exe" } else { "" };
+    vec![format!("{}windres{}", target_prefix(arch), exe), format!("windres{}", exe)]
+}
+
+/// Find `windres` in the `PATH` or one of the [`install_prefixes()`]
+///
+/// The target prefixed name is searched in all directories before the plain one.
+///
+/// [`install_prefixes()`]: fn.install_prefixes.html
+pub fn find_windres(arch: Arch) -> Option<Tool> {
+    let path = env::var_os("PATH").map(|path| env::split_paths(&path).collect::<Vec<_>>()).unwrap_or_default();
+    let dirs = path.into_iter().chain(install_prefixes(arch)).collect::<Vec<_>>();
+    for name in windres_names(arch) {
+        for dir in dirs.iter() {
+            let windres = dir.join(&name);
+            if windres.is_file() {
+                return Some(windres_tool(arch, &windres));
+            }
+        }
+    }
+    None
+}
+
+/// The `windres` for `arch` in `dir`, the target prefixed name first
+pub fn windres_in(dir: &Path, arch: Arch) -> Option<PathBuf> {
+    windres_names(arch).into_iter().map(|name| dir.join(name)).find(|windres| windres.is_file())
+}
+
+/// A `Tool` for the `windres` at `path`
+pub fn windres_tool(arch: Arch, path: &Path) -> Tool {
+    let bin_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    Tool {
+        sdk_version: "mingw".to_string(),
+        installed_root: bin_dir.parent().map(Path::to_path_buf).unwrap_or_default(),
+        arch,
+        path: path.to_path_buf(),
+        include_dirs: HashMap::new(),
+        lib_dirs: HashMap::new(),
+        bin_dir,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{target_prefix, windres_names};
+    use sdk::Arch;
+
+    #[test]
+    fn windres_candidates() {
+        assert_eq!(target_prefix(Arch::X86), "i686-w64-mingw32-");
+        let names = windres_names(Arch::X64);
+        assert!(names[0].starts_with("x86_64-w64-mingw32-windres"));
+        assert!(names[1].starts_with("windres"));
+    }
+}
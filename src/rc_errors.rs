@@ -0,0 +1,174 @@
+//! Structured `rc.exe` diagnostics
+//!
+//! `rc.exe` reports problems as lines like
+//! `C:\project\resource.rc(12) : error RC2135 : file not found: icon.ico`.
+//! [`parse_rc_output()`] turns them into [`RcDiagnostic`] values; the common error codes
+//! are mapped to [`RcErrorCode`] variants with an explanation and a likely fix.
+//!
+//! [`parse_rc_output()`]: fn.parse_rc_output.html
+//! [`RcDiagnostic`]: struct.RcDiagnostic.html
+//! [`RcErrorCode`]: enum.RcErrorCode.html
+
+use std::fmt;
+
+/// Well known `rc.exe` error and warning codes
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum RcErrorCode {
+    /// `RC1004`: unexpected end of file found
+    UnexpectedEndOfFile,
+    /// `RC1015`: cannot open include file
+    IncludeNotFound,
+    /// `RC2001`: newline in constant
+    NewlineInConstant,
+    /// `RC2104`: undefined keyword or key name
+    UndefinedKeyword,
+    /// `RC2135`: file not found
+    FileNotFound,
+    /// `RC2144`: primary language id not a number
+    InvalidLanguage,
+    /// `RC2176`: old DIB in file, i.e. an unsupported icon or bitmap format
+    UnsupportedImageFormat,
+    /// `RC4005`: macro redefinition
+    MacroRedefinition,
+    /// `RC4011`: identifier truncated
+    IdentifierTruncated,
+    /// Any other code
+    Other(u32),
+}
+
+impl RcErrorCode {
+    /// Map the numeric part of an `RCxxxx` code
+    pub fn from_code(code: u32) -> RcErrorCode {
+        match code {
+            1004 => RcErrorCode::UnexpectedEndOfFile,
+            1015 => RcErrorCode::IncludeNotFound,
+            2001 => RcErrorCode::NewlineInConstant,
+            2104 => RcErrorCode::UndefinedKeyword,
+            2135 => RcErrorCode::FileNotFound,
+            2144 => RcErrorCode::InvalidLanguage,
+            2176 => RcErrorCode::UnsupportedImageFormat,
+            4005 => RcErrorCode::MacroRedefinition,
+            4011 => RcErrorCode::IdentifierTruncated,
+            code => RcErrorCode::Other(code),
+        }
+    }
+
+    /// The numeric part of the `RCxxxx` code
+    pub fn code(&self) -> u32 {
+        match *self {
+            RcErrorCode::UnexpectedEndOfFile => 1004,
+            RcErrorCode::IncludeNotFound => 1015,
+            RcErrorCode::NewlineInConstant => 2001,
+            RcErrorCode::UndefinedKeyword => 2104,
+            RcErrorCode::FileNotFound => 2135,
+            RcErrorCode::InvalidLanguage => 2144,
+            RcErrorCode::UnsupportedImageFormat => 2176,
+            RcErrorCode::MacroRedefinition => 4005,
+            RcErrorCode::IdentifierTruncated => 4011,
+            RcErrorCode::Other(code) => code,
+        }
+    }
+
+    /// A likely cause and fix, if the code is known
+    pub fn explanation(&self) -> Option<&'static str> {
+        match *self {
+            RcErrorCode::UnexpectedEndOfFile =>
+                Some("the resource script ends inside a block or string; check for unbalanced braces or quotes"),
+            RcErrorCode::IncludeNotFound =>
+                Some("an #include could not be resolved; add its directory as include path"),
+            RcErrorCode::NewlineInConstant =>
+                Some("a string contains a line break; escape it as \\n"),
+            RcErrorCode::UndefinedKeyword =>
+                Some("a macro or keyword is not defined; custom .rc files may need #include <winres.h> or a define"),
+            RcErrorCode::FileNotFound =>
+                Some("a referenced file (icon, manifest, ...) does not exist; paths are relative to the package root"),
+            RcErrorCode::InvalidLanguage =>
+                Some("the language id is not a number; use a numeric LANGID like 0x0409"),
+            RcErrorCode::UnsupportedImageFormat =>
+                Some("the icon or bitmap format is not supported by this rc.exe; PNG compressed icons need a recent Windows SDK"),
+            RcErrorCode::MacroRedefinition =>
+                Some("a macro is defined more than once with different values"),
+            RcErrorCode::IdentifierTruncated =>
+                Some("an identifier is longer than 31 characters and was truncated"),
+            RcErrorCode::Other(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for RcErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RC{:04}", self.code())
+    }
+}
+
+/// A single error or warning reported by `rc.exe`
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct RcDiagnostic {
+    /// The file and line, e.g. `resource.rc(12)`, if reported
+    pub location: Option<String>,
+    /// `true` for errors, `false` for warnings
+    pub is_error: bool,
+    pub code: RcErrorCode,
+    pub message: String,
+}
+
+impl fmt::Display for RcDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ref location) = self.location {
+            write!(f, "{} : ", location)?;
+        }
+        write!(f, "{} {} : {}", if self.is_error { "error" } else { "warning" }, self.code, self.message)?;
+        if let Some(explanation) = self.code.explanation() {
+            write!(f, " ({})", explanation)?;
+        }
+        Ok(())
+    }
+}
+
+/// Extract the diagnostics from the output of `rc.exe`
+///
+/// Lines that are not diagnostics are skipped.
+pub fn parse_rc_output(output: &str) -> Vec<RcDiagnostic> {
+    output.lines().filter_map(parse_line).collect()
+}
+
+fn parse_line(line: &str) -> Option<RcDiagnostic> {
+    let (is_error, pos) = match (line.find("error RC"), line.find("warning RC")) {
+        (Some(pos), _) => (true, pos),
+        (None, Some(pos)) => (false, pos),
+        (None, None) => return None,
+    };
+    let location = line[..pos].trim().trim_end_matches("fatal").trim().trim_end_matches(':').trim();
+    let rest = &line[pos..];
+    let rest = &rest[rest.find("RC")? + 2..];
+    let digits = rest.chars().take_while(char::is_ascii_digit).count();
+    let code = rest[..digits].parse().ok()?;
+    let message = rest[digits..].trim().trim_start_matches(':').trim();
+    Some(RcDiagnostic {
+        location: if location.is_empty() { None } else { Some(location.to_string()) },
+        is_error,
+        code: RcErrorCode::from_code(code),
+        message: message.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_rc_output, RcErrorCode};
+
+    #[test]
+    fn parse_diagnostics() {
+        let output = "Microsoft (R) Windows (R) Resource Compiler Version 10.0.10011.16384\n\
+                      C:\\project\\resource.rc(12) : error RC2135 : file not found: icon.ico\n\
+                      resource.rc(3) : warning RC4011 : identifier truncated to 'VERY_LONG'\n\
+                      fatal error RC1004: unexpected end of file found\n";
+        let diagnostics = parse_rc_output(output);
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0].location.as_deref(), Some("C:\\project\\resource.rc(12)"));
+        assert_eq!(diagnostics[0].code, RcErrorCode::FileNotFound);
+        assert_eq!(diagnostics[0].message, "file not found: icon.ico");
+        assert!(!diagnostics[1].is_error);
+        assert_eq!(diagnostics[2].code, RcErrorCode::UnexpectedEndOfFile);
+        assert_eq!(diagnostics[2].location, None);
+    }
+}
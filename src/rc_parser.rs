@@ -0,0 +1,553 @@
+//! A minimal parser for hand-written `.rc` files
+//!
+//! Only the statements the builder can represent are read: `LANGUAGE`, `VERSIONINFO`,
+//! `ICON`, `BITMAP`, `RCDATA`, the manifest and `STRINGTABLE`. Other resources, like
+//! dialogs and menus, are skipped and listed in [`RcScript::skipped`]. `#define`s with
+//! a number are substituted, all other preprocessor lines are ignored.
+//!
+//! [`RcScript::skipped`]: struct.RcScript.html#structfield.skipped
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use language;
+
+/// A `StringFileInfo` table as language, charset and values
+pub type StringTable = (u16, u16, Vec<(String, String)>);
+
+/// The statements of an `.rc` file the builder can represent
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct RcScript {
+    /// The `LANGUAGE` statement
+    pub language: Option<u16>,
+    /// The fixed statements of `VERSIONINFO`, like `FILEVERSION`
+    pub fixed: Vec<(String, u64)>,
+    /// The `StringFileInfo` tables
+    pub string_tables: Vec<StringTable>,
+    /// `ICON` statements with the name or number of the icon
+    pub icons: Vec<(String, PathBuf)>,
+    pub bitmaps: Vec<(u16, PathBuf)>,
+    pub rcdata: Vec<(u16, PathBuf)>,
+    /// The file of the `RT_MANIFEST` statement
+    pub manifest: Option<PathBuf>,
+    /// The entries of the `STRINGTABLE`s
+    pub strings: Vec<(u16, String)>,
+    /// Statements that were not read, as `line N: ...` messages
+    pub skipped: Vec<String>,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+enum Token {
+    Str(String),
+    Num(u64),
+    Ident(String),
+    Comma,
+    Open,
+    Close,
+    Op(char),
+    Other(char),
+}
+
+/// Resource types whose data is a block of statements instead of a file
+const BLOCK_TYPES: [&str; 10] = ["DIALOG", "DIALOGEX", "MENU", "MENUEX", "ACCELERATORS", "TOOLBAR", "DLGINIT",
+                                 "DESIGNINFO", "TEXTINCLUDE", "HTML"];
+
+/// Memory options that may precede the file name of a resource
+const MEMORY_OPTIONS: [&str; 9] = ["PRELOAD", "LOADONCALL", "FIXED", "MOVEABLE", "DISCARDABLE", "PURE",
+                                   "IMPURE", "SHARED", "NONSHARED"];
+
+/// Parse the `.rc` file `source`
+///
+/// Returns a `line N: ...` message for syntax errors.
+pub fn parse(source: &str) -> Result<RcScript, String> {
+    let mut parser = Parser { tokens: tokenize(source)?, position: 0 };
+    let mut script = RcScript::default();
+    while let Some((token, line)) = parser.next() {
+        let keyword = match token {
+            Token::Ident(ref ident) => ident.to_ascii_uppercase(),
+            _ => String::new(),
+        };
+        match keyword.as_str() {
+            "LANGUAGE" => {
+                let primary = parser.expression()?;
+                parser.expect(Token::Comma)?;
+                let sub = parser.expression()?;
+                script.language = Some(language::make_lang_id(primary as u16, sub as u16));
+            }
+            "STRINGTABLE" => {
+                parser.skip_to_open()?;
+                while !parser.accept(&Token::Close) {
+                    let id = parser.expression()?;
+                    parser.accept(&Token::Comma);
+                    let value = parser.string()?;
+                    script.strings.push((id as u16, value));
+                }
+            }
+            "VERSION" | "CHARACTERISTICS" => {
+                parser.expression()?;
+            }
+            _ => {
+                let name = match token {
+                    Token::Num(id) => id.to_string(),
+                    Token::Ident(ident) => ident,
+                    Token::Str(name) => name,
+                    _ => return Err(format!("line {}: unexpected {:?}", line, token)),
+                };
+                let kind = match parser.next() {
+                    Some((Token::Ident(kind), _)) => kind.to_ascii_uppercase(),
+                    Some((Token::Num(kind), _)) => kind.to_string(),
+                    Some((Token::Str(kind), _)) => kind,
+                    _ => return Err(format!("line {}: resource {} has no type", line, name)),
+                };
+                parser.statement(&mut script, line, &name, &kind)?;
+            }
+        }
+    }
+    Ok(script)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<(Token, usize)> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens.get(self.position).or_else(|| self.tokens.last()).map_or(0, |&(_, line)| line)
+    }
+
+    /// Consume the next token if it is `token`
+    fn accept(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), String> {
+        if self.accept(&token) {
+            Ok(())
+        } else {
+            Err(format!("line {}: expected {:?}", self.line(), token))
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        match self.next() {
+            Some((Token::Str(s), _)) => Ok(s),
+            _ => Err(format!("line {}: expected a string", self.line())),
+        }
+    }
+
+    /// A number or known symbol, or several combined with `|` or `+`
+    fn expression(&mut self) -> Result<u64, String> {
+        let mut value = 0;
+        let mut op = '|';
+        loop {
+            let line = self.line();
+            let term = match self.next() {
+                Some((Token::Num(n), _)) => n,
+                Some((Token::Ident(ref name), _)) => match symbol(name) {
+                    Some(n) => n,
+                    None => return Err(format!("line {}: unknown symbol {}", line, name)),
+                },
+                _ => return Err(format!("line {}: expected a number", line)),
+            };
+            value = if op == '+' { value + term } else { value | term };
+            match self.peek() {
+                Some(&Token::Op(next)) => {
+                    op = next;
+                    self.position += 1;
+                }
+                _ => return Ok(value),
+            }
+        }
+    }
+
+    /// Skip tokens up to and including the next `BEGIN` or `{`
+    fn skip_to_open(&mut self) -> Result<(), String> {
+        loop {
+            match self.next() {
+                Some((Token::Open, _)) => return Ok(()),
+                Some(_) => {}
+                None => return Err(format!("line {}: expected BEGIN", self.line())),
+            }
+        }
+    }
+
+    /// Skip tokens up to and including the `END` or `}` matching an opened block
+    fn skip_block(&mut self) -> Result<(), String> {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next() {
+                Some((Token::Open, _)) => depth += 1,
+                Some((Token::Close, _)) => depth -= 1,
+                Some(_) => {}
+                None => return Err(format!("line {}: expected END", self.line())),
+            }
+        }
+        Ok(())
+    }
+
+    /// The file name of a resource statement, `None` for inline data, which is skipped
+    fn file_name(&mut self) -> Result<Option<PathBuf>, String> {
+        loop {
+            match self.next() {
+                Some((Token::Str(file), _)) => return Ok(Some(PathBuf::from(file))),
+                Some((Token::Ident(ref option), _)) if MEMORY_OPTIONS.contains(&option.to_ascii_uppercase().as_str()) => {}
+                Some((Token::Ident(file), _)) => return Ok(Some(PathBuf::from(file))),
+                Some((Token::Open, _)) => {
+                    self.skip_block()?;
+                    return Ok(None);
+                }
+                _ => return Err(format!("line {}: expected a file name", self.line())),
+            }
+        }
+    }
+
+    /// A resource statement `<name> <kind> ...`
+    fn statement(&mut self, script: &mut RcScript, line: usize, name: &str, kind: &str) -> Result<(), String> {
+        let number = name.parse::<u16>().ok();
+        match kind {
+            "VERSIONINFO" => self.version_info(script),
+            "ICON" => {
+                match self.file_name()? {
+                    Some(file) => script.icons.push((name.to_string(), file)),
+                    None => script.skipped.push(format!("line {}: ICON {} has inline data", line, name)),
+                }
+                Ok(())
+            }
+            "BITMAP" | "RCDATA" => {
+                match (self.file_name()?, number) {
+                    (Some(file), Some(id)) if kind == "BITMAP" => script.bitmaps.push((id, file)),
+                    (Some(file), Some(id)) => script.rcdata.push((id, file)),
+                    _ => script.skipped.push(format!("line {}: {} {} has no numeric id or no file", line, kind, name)),
+                }
+                Ok(())
+            }
+            "RT_MANIFEST" | "24" => {
+                if let Some(file) = self.file_name()? {
+                    script.manifest = Some(file);
+                }
+                Ok(())
+            }
+            _ if BLOCK_TYPES.contains(&kind) => {
+                self.skip_to_open()?;
+                self.skip_block()?;
+                script.skipped.push(format!("line {}: {} {} is not supported", line, kind, name));
+                Ok(())
+            }
+            _ => {
+                self.file_name()?;
+                script.skipped.push(format!("line {}: {} {} is not supported", line, kind, name));
+                Ok(())
+            }
+        }
+    }
+
+    /// The fixed statements and blocks of a `VERSIONINFO` resource
+    fn version_info(&mut self, script: &mut RcScript) -> Result<(), String> {
+        loop {
+            match self.next() {
+                Some((Token::Open, _)) => break,
+                Some((Token::Ident(key), line)) => {
+                    let key = key.to_ascii_uppercase();
+                    let value = if key == "FILEVERSION" || key == "PRODUCTVERSION" {
+                        let mut version = 0;
+                        for i in 0..4 {
+                            if i > 0 && !self.accept(&Token::Comma) {
+                                return Err(format!("line {}: {} needs four numbers", line, key));
+                            }
+                            version = (version << 16) | (self.expression()? & 0xffff);
+                        }
+                        version
+                    } else {
+                        self.expression()?
+                    };
+                    script.fixed.push((key, value));
+                }
+                _ => return Err(format!("line {}: expected BEGIN", self.line())),
+            }
+        }
+        self.version_block(script, &[])
+    }
+
+    /// The `BLOCK` and `VALUE` statements of the version info block at `path`
+    fn version_block(&mut self, script: &mut RcScript, path: &[String]) -> Result<(), String> {
+        loop {
+            let keyword = match self.next() {
+                Some((Token::Close, _)) => return Ok(()),
+                Some((Token::Ident(keyword), _)) => keyword.to_ascii_uppercase(),
+                _ => return Err(format!("line {}: expected BLOCK or VALUE", self.line())),
+            };
+            let line = self.line();
+            let name = self.string()?;
+            if keyword == "BLOCK" {
+                self.expect(Token::Open)?;
+                let mut path = path.to_vec();
+                path.push(name);
+                if path.len() == 2 && path[0] == "StringFileInfo" {
+                    let table = u32::from_str_radix(&path[1], 16)
+                        .map_err(|_| format!("line {}: invalid string table {}", line, path[1]))?;
+                    script.string_tables.push(((table >> 16) as u16, table as u16, vec![]));
+                }
+                self.version_block(script, &path)?;
+            } else if path.len() == 2 && path[0] == "StringFileInfo" {
+                self.accept(&Token::Comma);
+                let mut value = self.string()?;
+                // adjacent strings are concatenated
+                while let Some(&Token::Str(_)) = self.peek() {
+                    value.push_str(&self.string()?);
+                }
+                let value = value.trim_end_matches('\0').to_string();
+                if let Some(table) = script.string_tables.last_mut() {
+                    table.2.push((name, value));
+                }
+            } else {
+                // VarFileInfo values are generated from the string tables
+                while let Some(&Token::Comma) = self.peek() {
+                    self.position += 1;
+                    self.expression()?;
+                }
+            }
+        }
+    }
+}
+
+/// The value of the symbols of `winver.h` and `winnt.h` used in version info and
+/// `LANGUAGE` statements
+fn symbol(name: &str) -> Option<u64> {
+    Some(match name {
+        "VOS_UNKNOWN" | "VFT_UNKNOWN" | "VFT2_UNKNOWN" | "LANG_NEUTRAL" | "SUBLANG_NEUTRAL" => 0,
+        "VOS_DOS" => 0x10000,
+        "VOS_NT" => 0x40000,
+        "VOS__WINDOWS32" => 0x4,
+        "VOS_DOS_WINDOWS32" => 0x10004,
+        "VOS_NT_WINDOWS32" => 0x40004,
+        "VFT_APP" | "VS_FF_DEBUG" | "SUBLANG_DEFAULT" | "SUBLANG_ENGLISH_US" | "SUBLANG_GERMAN" => 0x1,
+        "VFT_DLL" | "VS_FF_PRERELEASE" | "SUBLANG_ENGLISH_UK" | "SUBLANG_SYS_DEFAULT" => 0x2,
+        "VFT_DRV" => 0x3,
+        "VFT_FONT" | "VS_FF_PATCHED" => 0x4,
+        "VFT_VXD" => 0x5,
+        "VFT_STATIC_LIB" => 0x7,
+        "VS_FF_PRIVATEBUILD" => 0x8,
+        "VS_FF_INFOINFERRED" => 0x10,
+        "VS_FF_SPECIALBUILD" => 0x20,
+        "VS_FFI_FILEFLAGSMASK" => 0x3f,
+        "LANG_ENGLISH" => u64::from(language::LANG_ENGLISH),
+        "LANG_GERMAN" => u64::from(language::LANG_GERMAN),
+        "LANG_FRENCH" => u64::from(language::LANG_FRENCH),
+        "LANG_SPANISH" => u64::from(language::LANG_SPANISH),
+        "LANG_ITALIAN" => u64::from(language::LANG_ITALIAN),
+        "LANG_JAPANESE" => u64::from(language::LANG_JAPANESE),
+        "CREATEPROCESS_MANIFEST_RESOURCE_ID" => 1,
+        "ISOLATIONAWARE_MANIFEST_RESOURCE_ID" => 2,
+        _ => return None,
+    })
+}
+
+/// Split `source` into tokens with their line numbers
+///
+/// `BEGIN` and `END` are returned as braces, and identifiers with a `#define`d number
+/// as that number.
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, String> {
+    let mut defines = HashMap::new();
+    let mut tokens = vec![];
+    for (index, text) in strip_block_comments(source).iter().enumerate() {
+        let line = index + 1;
+        let trimmed = text.trim_start();
+        if let Some(directive) = trimmed.strip_prefix('#') {
+            let mut words = directive.split_whitespace();
+            if let (Some("define"), Some(name), Some(value)) = (words.next(), words.next(), words.next()) {
+                if let Some(value) = parse_number(value) {
+                    defines.insert(name.to_string(), value);
+                }
+            }
+            continue;
+        }
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            let token = match c {
+                c if c.is_whitespace() => continue,
+                '/' if chars.peek() == Some(&'/') => break,
+                '"' => Token::Str(read_string(&mut chars).ok_or_else(|| format!("line {}: unterminated string", line))?),
+                'L' if chars.peek() == Some(&'"') => continue,
+                ',' => Token::Comma,
+                '{' => Token::Open,
+                '}' => Token::Close,
+                '|' | '+' => Token::Op(c),
+                c if c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '\\' => {
+                    let mut word = c.to_string();
+                    while let Some(&next) = chars.peek() {
+                        if next.is_ascii_alphanumeric() || "_.\\/-".contains(next) {
+                            word.push(next);
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                    match parse_number(&word).or_else(|| defines.get(&word).cloned()) {
+                        Some(n) => Token::Num(n),
+                        None if word.eq_ignore_ascii_case("BEGIN") => Token::Open,
+                        None if word.eq_ignore_ascii_case("END") => Token::Close,
+                        None => Token::Ident(word),
+                    }
+                }
+                c => Token::Other(c),
+            };
+            tokens.push((token, line));
+        }
+    }
+    Ok(tokens)
+}
+
+/// The lines of `source` with `/* */` comments blanked out, keeping the line numbers
+fn strip_block_comments(source: &str) -> Vec<String> {
+    let mut lines = vec![];
+    let mut in_comment = false;
+    for text in source.lines() {
+        let mut line = String::new();
+        let mut rest = text;
+        loop {
+            if in_comment {
+                match rest.find("*/") {
+                    Some(end) => {
+                        in_comment = false;
+                        rest = &rest[end + 2..];
+                    }
+                    None => break,
+                }
+            } else {
+                match rest.find("/*").filter(|&start| !rest[..start].contains('"')) {
+                    Some(start) => {
+                        line.push_str(&rest[..start]);
+                        in_comment = true;
+                        rest = &rest[start + 2..];
+                    }
+                    None => {
+                        line.push_str(rest);
+                        break;
+                    }
+                }
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// A decimal or hexadecimal number with an optional `L` or `U` suffix
+fn parse_number(word: &str) -> Option<u64> {
+    let word = word.trim_end_matches(&['L', 'l', 'U', 'u'][..]);
+    if word.starts_with("0x") || word.starts_with("0X") {
+        u64::from_str_radix(&word[2..], 16).ok()
+    } else {
+        word.parse().ok()
+    }
+}
+
+/// Read a string literal after its opening quote, `None` if it is not terminated
+///
+/// A quote is escaped as `""`.
+fn read_string<I: Iterator<Item = char>>(chars: &mut ::std::iter::Peekable<I>) -> Option<String> {
+    let mut s = String::new();
+    loop {
+        match chars.next()? {
+            '"' if chars.peek() == Some(&'"') => {
+                chars.next();
+                s.push('"');
+            }
+            '"' => return Some(s),
+            '\\' => match chars.next()? {
+                'n' => s.push('\n'),
+                'r' => s.push('\r'),
+                't' => s.push('\t'),
+                '0' => s.push('\0'),
+                c => s.push(c),
+            },
+            c => s.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+    use std::path::PathBuf;
+
+    #[test]
+    fn parse_rc_script() {
+        let script = parse(r#"
+            #include "resource.h"
+            #define IDI_APP 101
+            /* generated
+               by the resource editor */
+            LANGUAGE LANG_ENGLISH, SUBLANG_ENGLISH_US
+
+            IDI_APP ICON DISCARDABLE "res\\app.ico"
+            CREATEPROCESS_MANIFEST_RESOURCE_ID RT_MANIFEST "app.manifest"
+            200 BITMAP "logo.bmp"
+            IDD_ABOUT DIALOGEX 0, 0, 100, 50
+            CAPTION "About"
+            BEGIN
+                LTEXT "Hello", -1, 10, 10, 80, 8
+            END
+
+            STRINGTABLE
+            BEGIN
+                1, "Hello ""world"""
+            END
+
+            1 VERSIONINFO
+            FILEVERSION 1,2,3,4
+            FILEFLAGSMASK VS_FFI_FILEFLAGSMASK
+            FILEOS VOS_NT_WINDOWS32
+            FILETYPE VFT_APP
+            BEGIN
+                BLOCK "StringFileInfo"
+                BEGIN
+                    BLOCK "040904b0"
+                    BEGIN
+                        VALUE "ProductName", "Calc\0"
+                        VALUE "CompanyName", "Example " "Inc."
+                    END
+                END
+                BLOCK "VarFileInfo"
+                BEGIN
+                    VALUE "Translation", 0x409, 1200
+                END
+            END
+        "#).expect("parse resource script");
+        assert_eq!(script.language, Some(0x0409));
+        assert_eq!(script.icons, [("101".to_string(), PathBuf::from("res\\app.ico"))]);
+        assert_eq!(script.manifest, Some(PathBuf::from("app.manifest")));
+        assert_eq!(script.bitmaps, [(200, PathBuf::from("logo.bmp"))]);
+        assert_eq!(script.strings, [(1, "Hello \"world\"".to_string())]);
+        assert_eq!(script.fixed, [
+            ("FILEVERSION".to_string(), 0x0001_0002_0003_0004),
+            ("FILEFLAGSMASK".to_string(), 0x3f),
+            ("FILEOS".to_string(), 0x40004),
+            ("FILETYPE".to_string(), 1),
+        ]);
+        assert_eq!(script.string_tables, [(0x0409, 0x04b0, vec![
+            ("ProductName".to_string(), "Calc".to_string()),
+            ("CompanyName".to_string(), "Example Inc.".to_string()),
+        ])]);
+        assert_eq!(script.skipped, ["line 11: DIALOGEX IDD_ABOUT is not supported"]);
+
+        assert!(parse("1 ICON \"app.ico").is_err());
+        assert!(parse("1 VERSIONINFO FILEVERSION 1,2 BEGIN END").is_err());
+    }
+}
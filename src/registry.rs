@@ -0,0 +1,121 @@
+//! Read registry keys with the Windows API
+//!
+//! Used for the installed Windows SDKs instead of parsing the output of `reg.exe`,
+//! which is localized and not available in every environment.
+
+use std::ffi::OsString;
+use std::io;
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+use std::ptr;
+
+use windows_sys::Win32::Foundation::{ERROR_MORE_DATA, ERROR_NO_MORE_ITEMS, ERROR_SUCCESS};
+use windows_sys::Win32::System::Registry::{
+    RegCloseKey, RegEnumKeyExW, RegEnumValueW, RegOpenKeyExW, HKEY, HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE, KEY_READ,
+    KEY_WOW64_32KEY, KEY_WOW64_64KEY, REG_EXPAND_SZ, REG_SZ,
+};
+
+/// Longest key and value name, in UTF-16 code units
+const MAX_NAME: usize = 16384;
+
+/// An open registry key, closed on drop
+struct Key(HKEY);
+
+impl Drop for Key {
+    fn drop(&mut self) {
+        unsafe {
+            RegCloseKey(self.0);
+        }
+    }
+}
+
+/// The string values and the subkeys of `key`, like `HKEY_LOCAL_MACHINE\<subkey>` or
+/// `HKEY_CURRENT_USER\<subkey>`
+///
+/// `view` selects the registry view like the `reg.exe` option, `"/reg:32"` or `"/reg:64"`.
+pub fn read_key(key: &str, view: &str) -> io::Result<(Vec<(String, String)>, Vec<String>)> {
+    let wow64 = if view == "/reg:64" { KEY_WOW64_64KEY } else { KEY_WOW64_32KEY };
+    let (root, subkey) = match key.find('\\') {
+        Some(index) => (&key[..index], &key[index + 1..]),
+        None => (key, ""),
+    };
+    let root = match root {
+        "HKEY_LOCAL_MACHINE" | "HKLM" => HKEY_LOCAL_MACHINE,
+        "HKEY_CURRENT_USER" | "HKCU" => HKEY_CURRENT_USER,
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("unsupported registry root {}", root))),
+    };
+    let name = wide(subkey);
+    let mut hkey: HKEY = ptr::null_mut();
+    let status = unsafe { RegOpenKeyExW(root, name.as_ptr(), 0, KEY_READ | wow64, &mut hkey) };
+    check(status)?;
+    let key = Key(hkey);
+    Ok((string_values(&key)?, subkeys(&key)?))
+}
+
+fn string_values(key: &Key) -> io::Result<Vec<(String, String)>> {
+    let mut values = vec![];
+    let mut name = vec![0u16; MAX_NAME];
+    let mut data = vec![0u8; 4096];
+    let mut index = 0;
+    loop {
+        let mut name_len = name.len() as u32;
+        let mut data_len = data.len() as u32;
+        let mut kind = 0;
+        let status = unsafe {
+            RegEnumValueW(key.0, index, name.as_mut_ptr(), &mut name_len, ptr::null(),
+                          &mut kind, data.as_mut_ptr(), &mut data_len)
+        };
+        if status == ERROR_NO_MORE_ITEMS {
+            return Ok(values);
+        }
+        if status == ERROR_MORE_DATA {
+            // data_len is the size of the value, read it again with a buffer that fits
+            let size = (data_len as usize).max(data.len() * 2);
+            data.resize(size, 0);
+            continue;
+        }
+        check(status)?;
+        if kind == REG_SZ || kind == REG_EXPAND_SZ {
+            let data = data[..data_len as usize].chunks(2)
+                .map(|c| u16::from_le_bytes([c[0], *c.get(1).unwrap_or(&0)]))
+                .take_while(|&c| c != 0)
+                .collect::<Vec<_>>();
+            values.push((string(&name[..name_len as usize]), string(&data)));
+        }
+        index += 1;
+    }
+}
+
+fn subkeys(key: &Key) -> io::Result<Vec<String>> {
+    let mut keys = vec![];
+    let mut name = vec![0u16; MAX_NAME];
+    let mut index = 0;
+    loop {
+        let mut name_len = name.len() as u32;
+        let status = unsafe {
+            RegEnumKeyExW(key.0, index, name.as_mut_ptr(), &mut name_len, ptr::null(),
+                          ptr::null_mut(), ptr::null_mut(), ptr::null_mut())
+        };
+        if status == ERROR_NO_MORE_ITEMS {
+            return Ok(keys);
+        }
+        check(status)?;
+        keys.push(string(&name[..name_len as usize]));
+        index += 1;
+    }
+}
+
+fn check(status: u32) -> io::Result<()> {
+    if status == ERROR_SUCCESS {
+        Ok(())
+    } else {
+        Err(io::Error::from_raw_os_error(status as i32))
+    }
+}
+
+fn wide(s: &str) -> Vec<u16> {
+    OsString::from(s).encode_wide().chain(Some(0)).collect()
+}
+
+fn string(s: &[u16]) -> String {
+    OsString::from_wide(s).to_string_lossy().into_owned()
+}
@@ -0,0 +1,206 @@
+//! Reading compiled `.res` files
+//!
+//! A `.res` file is what `rc.exe` and llvm-rc produce: a sequence of resources, each with
+//! a header naming its type, name and language, followed by its data. [`parse()`] lists
+//! them, so tests and tools can inspect what was actually compiled:
+//!
+//! ```rust,no_run
+//! use winres::res::{self, Id};
+//!
+//! for resource in res::parse("resource.res").unwrap() {
+//!     if resource.kind == Id::Number(res::RT_VERSION) {
+//!         println!("version info of language {:#06x}", resource.language);
+//!     }
+//! }
+//! ```
+//!
+//! [`parse()`]: fn.parse.html
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use Error;
+
+pub const RT_CURSOR: u16 = 1;
+pub const RT_BITMAP: u16 = 2;
+pub const RT_ICON: u16 = 3;
+pub const RT_MENU: u16 = 4;
+pub const RT_DIALOG: u16 = 5;
+pub const RT_STRING: u16 = 6;
+pub const RT_FONT: u16 = 8;
+pub const RT_RCDATA: u16 = 10;
+pub const RT_GROUP_CURSOR: u16 = 12;
+pub const RT_GROUP_ICON: u16 = 14;
+pub const RT_VERSION: u16 = 16;
+pub const RT_MANIFEST: u16 = 24;
+
+/// The type or name of a resource, a number or a string
+#[derive(PartialEq, Eq, Debug, Clone, Hash, PartialOrd, Ord)]
+pub enum Id {
+    Number(u16),
+    Name(String),
+}
+
+impl fmt::Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Id::Number(id) => write!(f, "{}", id),
+            Id::Name(ref name) => f.write_str(name),
+        }
+    }
+}
+
+/// A resource of a compiled file
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Resource {
+    /// The resource type, e.g. `Id::Number(RT_VERSION)`
+    pub kind: Id,
+    /// The name or numeric id of the resource
+    pub name: Id,
+    /// The `LANGID` of the resource
+    pub language: u16,
+    /// The compiled data
+    pub data: Vec<u8>,
+}
+
+/// The problem of a file that is not a valid `.res` file
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct InvalidRes(String);
+
+impl fmt::Display for InvalidRes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Read the resources of the `.res` file at `path`
+pub fn parse<P: AsRef<Path>>(path: P) -> Result<Vec<Resource>, Error> {
+    let data = fs::read(path.as_ref())?;
+    from_bytes(&data).map_err(|e| Error::InvalidResFile { path: path.as_ref().to_path_buf(), message: e.0 })
+}
+
+/// Read the resources of the `.res` file `data`
+///
+/// The empty resource at the start of every `.res` file, which marks the format, is skipped.
+pub fn from_bytes(data: &[u8]) -> Result<Vec<Resource>, InvalidRes> {
+    let invalid = |message: &str| InvalidRes(message.to_string());
+    let mut resources = vec![];
+    let mut offset = 0;
+    while offset < data.len() {
+        let header = (read_u32(data, offset), read_u32(data, offset + 4));
+        let (data_size, header_size) = match header {
+            (Some(data_size), Some(header_size)) => (data_size as usize, header_size as usize),
+            _ => return Err(invalid("a resource header is truncated")),
+        };
+        let start = offset + header_size;
+        let end = match start.checked_add(data_size) {
+            Some(end) if header_size >= 32 && end <= data.len() => end,
+            _ => return Err(invalid("a resource lies outside of the file")),
+        };
+        let (kind, name_start) = read_id(data, offset + 8).ok_or_else(|| invalid("a resource type is truncated"))?;
+        let (name, name_end) = read_id(data, name_start).ok_or_else(|| invalid("a resource name is truncated"))?;
+        // data version and memory flags precede the language
+        let language = read_u16(data, align(name_end) + 6).ok_or_else(|| invalid("a resource header is truncated"))?;
+        if kind != Id::Number(0) {
+            resources.push(Resource { kind, name, language, data: data[start..end].to_vec() });
+        }
+        offset = align(end);
+    }
+    Ok(resources)
+}
+
+/// The resources of the `.res` file `data` without the empty resource marking the format,
+/// to append them to another `.res` file
+pub(crate) fn without_marker(data: &[u8]) -> &[u8] {
+    match (read_u32(data, 0), read_u32(data, 4), read_id(data, 8)) {
+        (Some(data_size), Some(header_size), Some((Id::Number(0), _))) =>
+            data.get(align(header_size as usize + data_size as usize)..).unwrap_or(&[]),
+        _ => data,
+    }
+}
+
+/// Read the type or name of a resource header, a number or a zero terminated string
+///
+/// Returns the id and the offset after it.
+fn read_id(data: &[u8], offset: usize) -> Option<(Id, usize)> {
+    if read_u16(data, offset)? == 0xffff {
+        Some((Id::Number(read_u16(data, offset + 2)?), offset + 4))
+    } else {
+        let (name, end) = read_string(data, offset)?;
+        Some((Id::Name(name), end))
+    }
+}
+
+/// Read a zero terminated UTF-16 string, returns it and the offset after the zero
+pub(crate) fn read_string(data: &[u8], offset: usize) -> Option<(String, usize)> {
+    let mut chars = vec![];
+    let mut end = offset;
+    loop {
+        match read_u16(data, end)? {
+            0 => break,
+            c => chars.push(c),
+        }
+        end += 2;
+    }
+    Some((String::from_utf16_lossy(&chars), end + 2))
+}
+
+/// Round `offset` up to the next multiple of 4, the alignment of resources and their headers
+pub(crate) fn align(offset: usize) -> usize {
+    (offset + 3) & !3
+}
+
+pub(crate) fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+pub(crate) fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_bytes, parse, Id, Resource, RT_MANIFEST, RT_VERSION};
+    use std::{env, fs};
+
+    /// The header of a resource with a numeric type and a string name
+    fn header(data_size: usize, kind: u16, name: &str, language: u16) -> Vec<u8> {
+        let mut header = vec![];
+        header.extend_from_slice(&[0xff, 0xff]);
+        header.extend_from_slice(&kind.to_le_bytes());
+        header.extend(name.encode_utf16().chain(Some(0)).flat_map(|c| c.to_le_bytes()));
+        header.resize((header.len() + 3) & !3, 0);
+        header.extend_from_slice(&[0, 0, 0, 0, 0x30, 0]);
+        header.extend_from_slice(&language.to_le_bytes());
+        header.extend_from_slice(&[0; 8]);
+        let mut resource = (data_size as u32).to_le_bytes().to_vec();
+        resource.extend_from_slice(&(header.len() as u32 + 8).to_le_bytes());
+        resource.extend(header);
+        resource
+    }
+
+    #[test]
+    fn parse_res() {
+        let mut file = header(0, 0, "", 0);
+        file.extend(header(5, RT_MANIFEST, "APP", 0x0409));
+        file.extend_from_slice(b"<xml/>\0\0");
+        file.extend(header(2, RT_VERSION, "V", 0x0407));
+        file.extend_from_slice(&[1, 2, 0, 0]);
+        let resources = from_bytes(&file).expect("parse .res file");
+        assert_eq!(resources, [
+            Resource { kind: Id::Number(RT_MANIFEST), name: Id::Name("APP".to_string()), language: 0x0409,
+                       data: b"<xml/>"[..5].to_vec() },
+            Resource { kind: Id::Number(RT_VERSION), name: Id::Name("V".to_string()), language: 0x0407,
+                       data: vec![1, 2] },
+        ]);
+        assert_eq!(resources[0].name.to_string(), "APP");
+        assert!(from_bytes(&file[..file.len() - 8]).is_err());
+        assert!(from_bytes(&[0; 12]).is_err());
+
+        let path = env::temp_dir().join("winres-parse.res");
+        fs::write(&path, &file[..40]).expect("write .res file");
+        assert!(matches!(parse(&path), Err(::Error::InvalidResFile { .. })));
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -1,50 +1,216 @@
 use std::path::{Path, PathBuf};
 use std::fmt;
+use std::cmp::{Ordering, Reverse};
 use std::collections::HashMap;
+use std::ops::RangeBounds;
+use std::env;
 use std::io;
 use std::process;
-use std::error::Error;
+use std::fs;
+use std::time;
+
+use {EnvSource, ProcessEnv};
 
 pub const INSTALLED_ROOTS_KEY: &'static str = r"HKEY_LOCAL_MACHINE\SOFTWARE\Microsoft\Windows Kits\Installed Roots";
 
+/// The installed roots of per-user SDK installs
+pub const USER_INSTALLED_ROOTS_KEY: &str = r"HKEY_CURRENT_USER\SOFTWARE\Microsoft\Windows Kits\Installed Roots";
+
+/// Registry keys queried for the installed roots, machine-wide installs first
+pub const INSTALLED_ROOTS_KEYS: [&str; 2] = [INSTALLED_ROOTS_KEY, USER_INSTALLED_ROOTS_KEY];
+
 #[derive(PartialEq,Eq,Debug,Clone,Hash)]
 pub struct KitsRoot(String);
 
+/// Architecture of the target and of the SDK tools
+///
+/// More architectures may be added, like `Arm64Ec` was, so matches on it need a
+/// wildcard arm.
 #[derive(PartialEq,Eq,Debug,Copy,Clone,Hash)]
+#[non_exhaustive]
 pub enum Arch {
     Arm,
     Arm64,
+    /// ARM64EC, x64 compatible code for ARM64, built with the `arm64` SDK dirs
+    Arm64Ec,
     X64,
     X86,
 }
 
 impl fmt::Display for Arch {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.dirname())
+        match self {
+            Arch::Arm64Ec => write!(f, "arm64ec"),
+            _ => write!(f, "{}", self.dirname()),
+        }
     }
 }
 
 impl Arch {
+    /// The arch winres itself is compiled for, i.e. of the build script
     pub fn arch_for_cfg_target() -> Option<Arch> {
         if cfg!(target_arch = "x86_64") {
             Some(Arch::X64)
         } else if cfg!(target_arch = "x86") {
             Some(Arch::X86)
+        } else if cfg!(target_arch = "aarch64") {
+            Some(Arch::Arm64)
+        } else if cfg!(target_arch = "arm64ec") {
+            Some(Arch::Arm64Ec)
+        } else if cfg!(target_arch = "arm") {
+            Some(Arch::Arm)
         } else {
             None
         }
     }
 
+    /// The arch for a value of `target_arch`, like `"x86_64"`
+    pub fn from_target_arch(target_arch: &str) -> Option<Arch> {
+        match target_arch {
+            "x86_64" => Some(Arch::X64),
+            "x86" => Some(Arch::X86),
+            "aarch64" => Some(Arch::Arm64),
+            "arm64ec" => Some(Arch::Arm64Ec),
+            "arm" => Some(Arch::Arm),
+            _ => None,
+        }
+    }
+
+    /// The arch of the machine running the build script, which can run its tools
+    ///
+    /// This differs from [`arch_for_target()`] when cross-compiling.
+    ///
+    /// [`arch_for_target()`]: #method.arch_for_target
+    pub fn host() -> Option<Arch> {
+        Arch::arch_for_cfg_target().map(|arch| if arch == Arch::Arm64Ec { Arch::Arm64 } else { arch })
+    }
+
+    /// The archs of the `bin` dirs with tools that run on this arch as host, preferred first
+    ///
+    /// ARM64 runs x64 and x86 tools with emulation, x64 runs x86 tools.
+    pub fn runnable_archs(&self) -> &'static [Arch] {
+        match self {
+            Arch::X64 => &[Arch::X64, Arch::X86],
+            Arch::X86 => &[Arch::X86],
+            Arch::Arm64 | Arch::Arm64Ec => &[Arch::Arm64, Arch::X64, Arch::X86],
+            Arch::Arm => &[Arch::Arm],
+        }
+    }
+
+    /// The arch of the cargo target, from `CARGO_CFG_TARGET_ARCH`
+    ///
+    /// Unlike [`arch_for_cfg_target()`], this is the arch of the compiled crate when
+    /// cross-compiling, not the arch of the build script. Outside of a build script
+    /// it falls back to [`arch_for_cfg_target()`].
+    ///
+    /// [`arch_for_cfg_target()`]: #method.arch_for_cfg_target
+    pub fn arch_for_target() -> Option<Arch> {
+        Arch::arch_for_target_with_env(&ProcessEnv)
+    }
+
+    /// [`arch_for_target()`] with `CARGO_CFG_TARGET_ARCH` from `env`
+    ///
+    /// [`arch_for_target()`]: #method.arch_for_target
+    pub fn arch_for_target_with_env(env: &dyn EnvSource) -> Option<Arch> {
+        match env.var("CARGO_CFG_TARGET_ARCH") {
+            Some(target_arch) => Arch::from_target_arch(&target_arch),
+            None => Arch::arch_for_cfg_target(),
+        }
+    }
+
+    /// Name of the arch for [`from_target_arch()`]
+    ///
+    /// [`from_target_arch()`]: #method.from_target_arch
+    fn cache_name(&self) -> &'static str {
+        match self {
+            Arch::Arm => "arm",
+            Arch::Arm64 => "aarch64",
+            Arch::Arm64Ec => "arm64ec",
+            Arch::X64 => "x86_64",
+            Arch::X86 => "x86",
+        }
+    }
+
     pub fn dirname(&self) -> &'static str {
         match self  {
             Arch::Arm => "arm",
-            Arch::Arm64 => "arm64",
+            Arch::Arm64 | Arch::Arm64Ec => "arm64",
             Arch::X64 => "x64",
             Arch::X86 => "x86",
         }
     }
 }
 
+/// A Windows SDK version like `10.0.22621.0`, ordered by its numeric components
+///
+/// Comparing the version strings would order `10.0.9` after `10.0.10`. Components that
+/// are not numbers count as `0`.
+///
+/// ```rust
+/// use winres::sdk::SdkVersion;
+///
+/// assert!(SdkVersion::new("10.0.10.0") > SdkVersion::new("10.0.9.0"));
+/// ```
+#[derive(Debug,Clone,PartialEq,Eq,Hash)]
+pub struct SdkVersion {
+    text: String,
+    components: Vec<u32>,
+}
+
+impl SdkVersion {
+    pub fn new(version: &str) -> SdkVersion {
+        let components = version.split('.')
+            .map(|component| component.trim().parse().unwrap_or(0))
+            .collect();
+        SdkVersion { text: version.to_string(), components }
+    }
+
+    /// The numeric components, e.g. `[10, 0, 22621, 0]`
+    pub fn components(&self) -> &[u32] {
+        &self.components
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Whether all components are numbers, otherwise the version may be ordered wrongly
+    pub fn is_numeric(&self) -> bool {
+        self.text.split('.').all(|component| component.trim().parse::<u32>().is_ok())
+    }
+
+    /// Whether the components of `prefix` are the first ones of this version, e.g. `10.0.22621`
+    /// of `10.0.22621.0`
+    pub fn starts_with(&self, prefix: &SdkVersion) -> bool {
+        self.components.starts_with(&prefix.components)
+    }
+}
+
+impl<'a> From<&'a str> for SdkVersion {
+    fn from(version: &'a str) -> Self {
+        SdkVersion::new(version)
+    }
+}
+
+impl fmt::Display for SdkVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl PartialOrd for SdkVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SdkVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // the text only breaks ties like "10.0" and "10.0.0"
+        self.components.cmp(&other.components).then_with(|| self.text.cmp(&other.text))
+    }
+}
+
 #[derive(Debug,Clone)]
 pub struct SdkArch {
     pub bin_dir: PathBuf,
@@ -85,6 +251,43 @@ pub struct Tool {
     pub bin_dir: PathBuf,
 }
 
+impl Tool {
+    /// A command running the tool, with its `bin_dir` first in `PATH` and the include dirs
+    /// of the SDK in `INCLUDE`
+    ///
+    /// `rc.exe` reads the include dirs from `INCLUDE`, and `windres` finds its preprocessor
+    /// in the `PATH`. The arguments and the working directory are left to the caller.
+    ///
+    /// ```rust,no_run
+    /// use winres::sdk::{Arch, System};
+    ///
+    /// let system = System::new().unwrap();
+    /// let rc = system.find_tool("rc.exe", Arch::X64).unwrap();
+    /// let status = rc.command().args(["/fo", "app.res", "app.rc"]).status().unwrap();
+    /// ```
+    pub fn command(&self) -> process::Command {
+        let mut command = process::Command::new(&self.path);
+        let inherited = |name: &str| env::var_os(name)
+            .map(|value| env::split_paths(&value).collect::<Vec<_>>())
+            .unwrap_or_default();
+        if !self.include_dirs.is_empty() {
+            let mut dirs = self.include_dirs.values().cloned().collect::<Vec<_>>();
+            dirs.sort();
+            dirs.extend(inherited("INCLUDE"));
+            if let Ok(include) = env::join_paths(dirs) {
+                command.env("INCLUDE", include);
+            }
+        }
+        if !self.bin_dir.as_os_str().is_empty() {
+            let dirs = Some(self.bin_dir.clone()).into_iter().chain(inherited("PATH"));
+            if let Ok(path) = env::join_paths(dirs) {
+                command.env("PATH", path);
+            }
+        }
+        command
+    }
+}
+
 impl Sdk {
     pub fn new(version: String, installed_root: PathBuf) -> io::Result<Sdk> {
         let mut sdk = Sdk {
@@ -96,6 +299,13 @@ impl Sdk {
         Ok(sdk)
     }
 
+    /// The version for ordering, see [`SdkVersion`]
+    ///
+    /// [`SdkVersion`]: struct.SdkVersion.html
+    pub fn sdk_version(&self) -> SdkVersion {
+        SdkVersion::new(&self.version)
+    }
+
     pub fn tool(&self, name: &str, arch: Arch) -> Option<Tool> {
         if let Some(sdk_arch) = self.sdk_archs.get(&arch) {
             let path = sdk_arch.bin_dir.join(name);
@@ -117,6 +327,24 @@ impl Sdk {
         }
     }
 
+    /// The tool `name` that runs on `host`, for building for `target`
+    ///
+    /// The tool is taken from the first `bin` dir of the [`runnable_archs()`] of `host` that
+    /// has it, e.g. the x64 `rc.exe` on an ARM64 machine without ARM64 tools. The include and
+    /// library dirs are the ones of `target`.
+    ///
+    /// [`runnable_archs()`]: enum.Arch.html#method.runnable_archs
+    pub fn host_tool(&self, name: &str, target: Arch, host: Arch) -> Option<Tool> {
+        let target_arch = self.sdk_archs.get(&target)?;
+        let tool = host.runnable_archs().iter().filter_map(|&bin_arch| self.tool(name, bin_arch)).next()?;
+        Some(Tool {
+            arch: target,
+            include_dirs: target_arch.include_dirs.clone(),
+            lib_dirs: target_arch.lib_dirs.clone(),
+            ..tool
+        })
+    }
+
     pub fn exists(version: &str, installed_root: &Path) -> io::Result<bool> {
         Ok(installed_root.join("bin").join(version).exists())
     }
@@ -142,6 +370,24 @@ impl Sdk {
         Ok(dirs)
     }
 
+    /// The library dirs of `arch`, i.e. `Lib\<version>\<component>\<arch>` for the
+    /// components like `um` and `ucrt` that have libraries for `arch`
+    fn load_lib_dirs(&self, arch: Arch) -> io::Result<HashMap<String,PathBuf>> {
+        let mut dirs = HashMap::new();
+        let lib_root = self.lib_root_dir();
+        if !lib_root.is_dir() {
+            return Ok(dirs);
+        }
+        for component in lib_root.read_dir()? {
+            let entry = component?;
+            let lib_dir = entry.path().join(arch.dirname());
+            if lib_dir.is_dir() {
+                dirs.insert(entry.file_name().to_string_lossy().into_owned(), lib_dir);
+            }
+        }
+        Ok(dirs)
+    }
+
     pub fn sdk_arch<'a>(&'a self, arch: &Arch) -> Option<&'a SdkArch> {
         self.sdk_archs.get(arch)
     }
@@ -156,6 +402,7 @@ impl Sdk {
     fn load_archs(&mut self) -> io::Result<()> {
         self.load_arch(Arch::Arm)?;
         self.load_arch(Arch::Arm64)?;
+        self.load_arch(Arch::Arm64Ec)?;
         self.load_arch(Arch::X86)?;
         self.load_arch(Arch::X64)?;
         Ok(())
@@ -165,11 +412,23 @@ impl Sdk {
         let bin_dir = self.bin_root_dir().join(arch.dirname());
         let mut sdk_arch = SdkArch::new(bin_dir);
         sdk_arch.include_dirs = self.load_include_dirs()?;
+        sdk_arch.lib_dirs = self.load_lib_dirs(arch)?;
         self.sdk_archs.insert(arch, sdk_arch);
         Ok(())
     }
 }
 
+/// An installed SDK's tool considered by [`System::candidates()`]
+///
+/// [`System::candidates()`]: struct.System.html#method.candidates
+#[derive(Debug,Clone,PartialEq)]
+pub struct Candidate {
+    pub sdk_version: String,
+    pub path: PathBuf,
+    /// Why the tool can't be used, `None` if it exists
+    pub rejected: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct System {
     pub installed_roots: InstalledRoots,
@@ -178,14 +437,140 @@ pub struct System {
 
 impl System {
     pub fn new() -> io::Result<Self> {
+        System::new_with_env(&ProcessEnv)
+    }
+
+    /// The installed SDKs like [`new()`], with the developer command prompt variables and
+    /// the Visual Studio installations from `env`
+    ///
+    /// The registry is read as with [`new()`], including the expansion of the environment
+    /// variables in its values by Windows.
+    ///
+    /// [`new()`]: #method.new
+    pub fn new_with_env(env: &dyn EnvSource) -> io::Result<Self> {
+        let installed_roots = match InstalledRoots::from_vars(|name| env.var(name)) {
+            Some(installed_roots) => installed_roots,
+            None => InstalledRoots::discover_with_env(env)?,
+        };
+        System::from_installed_roots(installed_roots)
+    }
+
+    /// The SDKs of `installed_roots`, e.g. of an explicit path with [`InstalledRoots::scan()`]
+    ///
+    /// [`InstalledRoots::scan()`]: struct.InstalledRoots.html#method.scan
+    pub fn from_installed_roots(installed_roots: InstalledRoots) -> io::Result<Self> {
         let mut system = System {
-            installed_roots: InstalledRoots::new()?,
+            installed_roots,
             sdks: vec![],
         };
         system.load_sdks()?;
         Ok(system)
     }
 
+    /// The installed SDK with `version`, e.g. `"10.0.22621.0"`
+    pub fn sdk(&self, version: &str) -> Option<&Sdk> {
+        self.sdks.iter().find(|sdk| sdk.version == version)
+    }
+
+    /// The newest installed SDK
+    pub fn latest_sdk(&self) -> Option<&Sdk> {
+        self.sdks.iter().max_by_key(|sdk| sdk.sdk_version())
+    }
+
+    /// The installed SDKs with a version in `range`, newest first
+    ///
+    /// ```rust,no_run
+    /// use winres::sdk::{SdkVersion, System};
+    ///
+    /// let system = System::new().unwrap();
+    /// // Windows 10 2004 and later
+    /// for sdk in system.sdks_matching(SdkVersion::new("10.0.19041.0")..) {
+    ///     println!("{}", sdk.version);
+    /// }
+    /// ```
+    pub fn sdks_matching<R: RangeBounds<SdkVersion>>(&self, range: R) -> Vec<&Sdk> {
+        let mut sdks = self.sdks.iter().filter(|sdk| range.contains(&sdk.sdk_version())).collect::<Vec<_>>();
+        sdks.sort_by_key(|sdk| Reverse(sdk.sdk_version()));
+        sdks
+    }
+
+    /// The tool `name` of all installed SDKs that have it for `arch`, newest SDK first
+    pub fn find_tools(&self, name: &str, arch: Arch) -> Vec<Tool> {
+        let mut tools = self.sdks.iter().filter_map(|sdk| sdk.tool(name, arch)).collect::<Vec<_>>();
+        tools.sort_by_key(|tool| Reverse(SdkVersion::new(&tool.sdk_version)));
+        tools
+    }
+
+    /// The tool `name` of all installed SDKs that run on `host`, for building for `target`,
+    /// newest SDK first, see [`Sdk::host_tool()`]
+    ///
+    /// [`Sdk::host_tool()`]: struct.Sdk.html#method.host_tool
+    pub fn find_host_tools(&self, name: &str, target: Arch, host: Arch) -> Vec<Tool> {
+        let mut tools = self.sdks.iter().filter_map(|sdk| sdk.host_tool(name, target, host)).collect::<Vec<_>>();
+        tools.sort_by_key(|tool| Reverse(SdkVersion::new(&tool.sdk_version)));
+        tools
+    }
+
+    /// The tool `name` of every installed SDK that runs on `host`, newest SDK first, with the
+    /// reason it can't be used, e.g. that the SDK only has it for other archs
+    ///
+    /// This explains the selection of [`find_host_tools()`].
+    ///
+    /// [`find_host_tools()`]: #method.find_host_tools
+    pub fn candidates(&self, name: &str, host: Arch) -> Vec<Candidate> {
+        let mut sdks = self.sdks.iter().collect::<Vec<_>>();
+        sdks.sort_by_key(|sdk| Reverse(sdk.sdk_version()));
+        let runnable = host.runnable_archs();
+        sdks.into_iter().map(|sdk| {
+            let paths = runnable.iter().map(|arch| sdk.bin_root_dir().join(arch.dirname()).join(name));
+            let found = paths.clone().find(|path| path.exists());
+            let rejected = if found.is_some() {
+                None
+            } else {
+                let others = [Arch::Arm, Arch::Arm64, Arch::X86, Arch::X64].iter()
+                    .filter(|other| !runnable.contains(other) && sdk.has_tool(other, name))
+                    .map(Arch::to_string)
+                    .collect::<Vec<_>>();
+                if others.is_empty() {
+                    Some("the file does not exist".to_string())
+                } else {
+                    Some(format!("SDK {} only has it for {}, which can't run on {}", sdk.version, others.join(", "), host))
+                }
+            };
+            let path = found.or_else(|| paths.clone().next()).unwrap_or_default();
+            Candidate { sdk_version: sdk.version.clone(), path, rejected }
+        }).collect()
+    }
+
+    /// The tool `name`, e.g. `"mt.exe"` or `"signtool.exe"`, of the newest SDK that has it
+    /// for `arch`
+    ///
+    /// ```rust,no_run
+    /// use winres::sdk::{Arch, System};
+    ///
+    /// let system = System::new().unwrap();
+    /// if let Some(signtool) = system.find_tool("signtool.exe", Arch::X64) {
+    ///     println!("{}", signtool.path.display());
+    /// }
+    /// ```
+    pub fn find_tool(&self, name: &str, arch: Arch) -> Option<Tool> {
+        self.find_tools(name, arch).into_iter().next()
+    }
+
+    /// `signtool.exe` of the newest SDK that runs on `host`, e.g. to sign or verify
+    /// binaries built next to the resource
+    ///
+    /// ```rust,no_run
+    /// use winres::sdk::{Arch, System};
+    ///
+    /// let system = System::new().unwrap();
+    /// let signtool = system.find_signtool(Arch::host().unwrap_or(Arch::X64)).expect("signtool.exe");
+    /// let status = signtool.command().args(&["verify", "/pa", "vendor.dll"]).status().unwrap();
+    /// ```
+    pub fn find_signtool(&self, host: Arch) -> Option<Tool> {
+        self.find_host_tools("signtool.exe", host, host).into_iter().next()
+    }
+
     fn load_sdks(&mut self) -> io::Result<()> {
         for (_kits_root, root_path) in self.installed_roots.kits_roots.iter() {
             for sdk_version in self.installed_roots.sdk_versions.iter() {
@@ -204,52 +589,476 @@ pub struct InstalledRoots {
     pub sdk_versions: Vec<String>,
 }
 
+/// Registry views queried for the installed roots
+///
+/// The key is usually registered in the 32-bit view, but some SDK installers only
+/// write the 64-bit view.
+pub const REGISTRY_VIEWS: [&str; 2] = ["/reg:32", "/reg:64"];
+
 impl InstalledRoots {
+    /// The installed roots in the registry, see [`INSTALLED_ROOTS_KEYS`] and [`REGISTRY_VIEWS`]
+    ///
+    /// [`INSTALLED_ROOTS_KEYS`]: constant.INSTALLED_ROOTS_KEYS.html
+    /// [`REGISTRY_VIEWS`]: constant.REGISTRY_VIEWS.html
     pub fn new() -> io::Result<InstalledRoots> {
+        let mut installed_roots = InstalledRoots {
+            kits_roots: vec![],
+            sdk_versions: vec![],
+        };
+        let mut last_error = None;
+        for key in INSTALLED_ROOTS_KEYS.iter() {
+            for view in REGISTRY_VIEWS.iter() {
+                if let Err(e) = installed_roots.load_view(key, view) {
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        if !installed_roots.kits_roots.is_empty() {
+            Ok(installed_roots)
+        } else {
+            Err(last_error.unwrap_or_else(|| io::Error::new(io::ErrorKind::Other, "no installed root found")))
+        }
+    }
+
+    /// Find the installed roots in the registry, or through the Visual Studio installations
+    /// if the registry has none
+    ///
+    /// The registry misses SDKs installed through the Visual Studio installer in some
+    /// configurations, and `reg.exe` is not usable in some containers. The developer command
+    /// prompt of each installation of [`vs_install_dirs()`] sets up the Windows SDK it uses,
+    /// which is then read like [`from_env()`].
+    ///
+    /// [`vs_install_dirs()`]: fn.vs_install_dirs.html
+    /// [`from_env()`]: #method.from_env
+    pub fn discover() -> io::Result<InstalledRoots> {
+        InstalledRoots::discover_with_env(&ProcessEnv)
+    }
+
+    /// [`discover()`] with the Visual Studio installations of [`vs_install_dirs_with_env()`]
+    ///
+    /// [`discover()`]: #method.discover
+    /// [`vs_install_dirs_with_env()`]: fn.vs_install_dirs_with_env.html
+    pub fn discover_with_env(env: &dyn EnvSource) -> io::Result<InstalledRoots> {
+        match InstalledRoots::new() {
+            Ok(installed_roots) => Ok(installed_roots),
+            Err(e) => {
+                let mut installed_roots = InstalledRoots {
+                    kits_roots: vec![],
+                    sdk_versions: vec![],
+                };
+                for vs_dir in vs_install_dirs_with_env(env) {
+                    let vars = match vs_dev_env(&vs_dir) {
+                        Some(vars) => vars,
+                        None => continue,
+                    };
+                    // the names of environment variables are case-insensitive on Windows
+                    let var = |name: &str| vars.iter()
+                        .find(|(known, _)| known.eq_ignore_ascii_case(name))
+                        .map(|(_, value)| value.clone());
+                    if let Some(vs_roots) = InstalledRoots::from_vars(var) {
+                        installed_roots.extend(vs_roots);
+                    }
+                }
+                if installed_roots.kits_roots.is_empty() {
+                    Err(e)
+                } else {
+                    Ok(installed_roots)
+                }
+            }
+        }
+    }
+
+    /// Installed roots from the environment of a developer command prompt (`vcvarsall`)
+    /// or an EWDK build environment, `None` if no SDK is set up
+    ///
+    /// `WindowsSdkVerBinPath` is preferred, then `WindowsSdkDir` with `WindowsSDKVersion`,
+    /// then the `WDKContentRoot` of the EWDK.
+    pub fn from_env() -> Option<InstalledRoots> {
+        InstalledRoots::from_vars(|name| env::var(name).ok())
+    }
+
+    fn from_vars<F: Fn(&str) -> Option<String>>(var: F) -> Option<InstalledRoots> {
+        let mut installed_roots = InstalledRoots {
+            kits_roots: vec![],
+            sdk_versions: vec![],
+        };
+        let trimmed = |name: &str| var(name)
+            .map(|value| value.trim().trim_end_matches('\\').to_string())
+            .filter(|value| !value.is_empty());
+
+        if let Some(bin_path) = trimmed("WindowsSdkVerBinPath").map(PathBuf::from) {
+            let version = bin_path.file_name().map(|name| name.to_string_lossy().into_owned());
+            let root = bin_path.parent().and_then(Path::parent).map(Path::to_path_buf);
+            if let (Some(version), Some(root)) = (version, root) {
+                installed_roots.add_kits_root(KitsRoot("WindowsSdkVerBinPath".to_string()), root);
+                installed_roots.add_sdk_version(version);
+            }
+        }
+        if let Some(root) = trimmed("WindowsSdkDir").map(PathBuf::from) {
+            match trimmed("WindowsSDKVersion") {
+                Some(version) => {
+                    installed_roots.add_kits_root(KitsRoot("WindowsSdkDir".to_string()), root);
+                    installed_roots.add_sdk_version(version);
+                }
+                None => installed_roots.add_scanned(&root),
+            }
+        }
+        if let Some(root) = trimmed("WDKContentRoot").map(PathBuf::from) {
+            installed_roots.add_scanned(&root);
+        }
+
+        installed_roots.kits_roots.retain(|(_, root)| root.join("bin").is_dir());
+        if installed_roots.kits_roots.is_empty() {
+            None
+        } else {
+            Some(installed_roots)
+        }
+    }
+
+    fn add_scanned(&mut self, root: &Path) {
+        self.extend(InstalledRoots::scan(&[root.to_path_buf()]));
+    }
+
+    fn extend(&mut self, other: InstalledRoots) {
+        for (kits_root, root) in other.kits_roots {
+            self.add_kits_root(kits_root, root);
+        }
+        for sdk_version in other.sdk_versions {
+            self.add_sdk_version(sdk_version);
+        }
+    }
+
+    /// Installed roots of the existing `roots`, with the SDK versions in their `bin` dir
+    pub fn scan(roots: &[PathBuf]) -> InstalledRoots {
+        let mut installed_roots = InstalledRoots {
+            kits_roots: vec![],
+            sdk_versions: vec![],
+        };
+        for root in roots.iter().filter(|root| root.join("bin").is_dir()) {
+            installed_roots.kits_roots.push((KitsRoot("KitsRoot10".to_string()), root.clone()));
+            let entries = match root.join("bin").read_dir() {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            let mut versions = entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .filter(|name| name.starts_with("10."))
+                .filter(|name| !installed_roots.sdk_versions.contains(name))
+                .collect::<Vec<_>>();
+            versions.sort_by_key(|version| SdkVersion::new(version));
+            installed_roots.sdk_versions.extend(versions);
+        }
+        installed_roots
+    }
+
+    /// Add the kits roots and SDK versions of `key` in the registry `view`
+    #[cfg(all(windows, feature = "registry"))]
+    fn load_view(&mut self, key: &str, view: &str) -> io::Result<()> {
+        let (values, subkeys) = ::registry::read_key(key, view)?;
+        for (name, data) in values {
+            if name.starts_with("KitsRoot") {
+                self.add_kits_root(KitsRoot(name), PathBuf::from(data));
+            }
+        }
+        for sdk_version in subkeys {
+            self.add_sdk_version(sdk_version);
+        }
+        Ok(())
+    }
+
+    /// Add the kits roots and SDK versions of `key` in the registry `view`, using `reg.exe`
+    #[cfg(not(all(windows, feature = "registry")))]
+    fn load_view(&mut self, key: &str, view: &str) -> io::Result<()> {
+        let lines = InstalledRoots::query(key, view)?;
+        self.merge(key, &lines)
+    }
+
+    fn add_kits_root(&mut self, kits_root: KitsRoot, root: PathBuf) {
+        if !self.kits_roots.iter().any(|(_, known)| known == &root) {
+            self.kits_roots.push((kits_root, root));
+        }
+    }
+
+    fn add_sdk_version(&mut self, sdk_version: String) {
+        if !sdk_version.is_empty() && !self.sdk_versions.contains(&sdk_version) {
+            self.sdk_versions.push(sdk_version);
+        }
+    }
+
+    /// Run `reg query` on `key` in the registry `view`
+    #[cfg(not(all(windows, feature = "registry")))]
+    fn query(key: &str, view: &str) -> io::Result<String> {
         let output = process::Command::new("reg")
             .arg("query")
-            .arg(INSTALLED_ROOTS_KEY)
-            .arg("/reg:32")
+            .arg(key)
+            .arg(view)
             .output()?;
 
-        let lines = String::from_utf8(output.stdout)
-            .or_else(|e| Err(io::Error::new(io::ErrorKind::Other, e.description())))?;
-
-        let mut roots = vec![];
-        let mut sdks = vec![];
+        // the output is in the OEM code page, which only matters for localized messages
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
 
+    /// Add the kits roots and SDK versions of a `reg query` output of `key` that are not
+    /// known yet
+    ///
+    /// Lines that are neither a value nor a subkey, like localized headers and messages,
+    /// are skipped. A kits root of another type than `REG_SZ` or `REG_EXPAND_SZ` is an error.
+    #[cfg_attr(all(windows, feature = "registry"), allow(dead_code))]
+    fn merge(&mut self, key: &str, lines: &str) -> io::Result<()> {
         for line in lines.lines() {
             let line = line.trim();
-            if line.starts_with("KitsRoot") {
-                let kits_root = KitsRoot(line.chars().take_while(|c| !c.is_whitespace()).collect());
-
-                let root = Path::new(
-                        &line.chars()
-                        .skip(line.find("REG_SZ").ok_or_else(||
-                            io::Error::new(io::ErrorKind::Other, "missing REG_SZ"))
-                            .expect("parse line") + 6)
-                        .skip_while(|c| c.is_whitespace())
-                        .collect::<String>()
-                    )
-                    .to_path_buf();
-                roots.push((kits_root, root));
-            } else if line.starts_with(INSTALLED_ROOTS_KEY) {
-                let sdk_version = line.chars().skip(INSTALLED_ROOTS_KEY.len() + 1).collect::<String>();
-                if !sdk_version.is_empty() {
-                    sdks.push(sdk_version);
+            if let Some((name, kind, data)) = parse_reg_value(line) {
+                if !name.starts_with("KitsRoot") || data.is_empty() {
+                    continue;
                 }
+                let root = match kind {
+                    "REG_SZ" => data.to_string(),
+                    "REG_EXPAND_SZ" => expand_env_vars(data, |name| env::var(name).ok()),
+                    _ => return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                                   format!("{} has the unexpected type {}", name, kind))),
+                };
+                self.add_kits_root(KitsRoot(name.to_string()), PathBuf::from(root));
+            } else if let Some(sdk_version) = subkey_name(key, line) {
+                self.add_sdk_version(sdk_version.to_string());
             }
+        }
+        Ok(())
+    }
+}
+
+/// The name, type and data of a value line of `reg query`, like
+/// `KitsRoot10    REG_SZ    C:\Program Files (x86)\Windows Kits\10\`
+///
+/// The columns are separated by spaces or tabs, and the name may contain spaces.
+fn parse_reg_value(line: &str) -> Option<(&str, &str, &str)> {
+    let start = line.match_indices("REG_")
+        .map(|(index, _)| index)
+        .find(|&index| index > 0 && line[..index].ends_with(char::is_whitespace))?;
+    let name = line[..start].trim();
+    let rest = &line[start..];
+    let kind_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+    if name.is_empty() {
+        return None;
+    }
+    Some((name, &rest[..kind_end], rest[kind_end..].trim()))
+}
+
+/// The name of the subkey in a `reg query` line `<key>\<name>`, compared case-insensitively
+fn subkey_name<'a>(key: &str, line: &'a str) -> Option<&'a str> {
+    let prefix = line.get(..key.len())?;
+    if !prefix.eq_ignore_ascii_case(key) {
+        return None;
+    }
+    line[key.len()..].strip_prefix('\\').filter(|name| !name.is_empty() && !name.contains('\\'))
+}
+
+/// Expand the `%NAME%` environment variables of a `REG_EXPAND_SZ` value, unknown ones are kept
+fn expand_env_vars<F: Fn(&str) -> Option<String>>(value: &str, var: F) -> String {
+    let mut expanded = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find('%') {
+        let end = match rest[start + 1..].find('%') {
+            Some(end) => start + 1 + end,
+            None => break,
         };
+        expanded.push_str(&rest[..start]);
+        match var(&rest[start + 1..end]) {
+            Some(value) => expanded.push_str(&value),
+            None => expanded.push_str(&rest[start..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
 
-        if !roots.is_empty() {
-            Ok(InstalledRoots {
-                kits_roots: roots,
-                sdk_versions: sdks,
-            })
-        } else {
-            Err(io::Error::new(io::ErrorKind::Other, format!("no installed root found")))
+/// Visual Studio installations, from the environment of a developer command prompt
+/// (`VSINSTALLDIR`, `VCToolsInstallDir`) or `vswhere.exe`
+pub fn vs_install_dirs() -> Vec<PathBuf> {
+    vs_install_dirs_with_env(&ProcessEnv)
+}
+
+/// [`vs_install_dirs()`] with the variables of `env`
+///
+/// [`vs_install_dirs()`]: fn.vs_install_dirs.html
+pub fn vs_install_dirs_with_env(env: &dyn EnvSource) -> Vec<PathBuf> {
+    let mut dirs = ["VSINSTALLDIR", "VCToolsInstallDir"].iter()
+        .filter_map(|name| env.var(name))
+        .map(PathBuf::from)
+        .collect::<Vec<_>>();
+    let vswhere = env.var("ProgramFiles(x86)")
+        .map(|dir| PathBuf::from(dir).join(r"Microsoft Visual Studio\Installer\vswhere.exe"));
+    if let Some(vswhere) = vswhere.filter(|vswhere| vswhere.is_file()) {
+        let output = process::Command::new(vswhere)
+            .args(["-all", "-products", "*", "-property", "installationPath"])
+            .output();
+        if let Ok(output) = output {
+            dirs.extend(parse_vswhere(&String::from_utf8_lossy(&output.stdout)));
         }
     }
+    dirs
+}
+
+/// The environment of the developer command prompt of the Visual Studio installation
+/// `vs_dir`, `None` if it has no `VsDevCmd.bat` or the script fails
+///
+/// `vs_dir` can also be a directory inside the installation, like `VCToolsInstallDir`.
+fn vs_dev_env(vs_dir: &Path) -> Option<Vec<(String, String)>> {
+    let script = vs_dir.ancestors()
+        .map(|dir| dir.join(r"Common7\Tools\VsDevCmd.bat"))
+        .find(|script| script.is_file())?;
+    let output = process::Command::new("cmd")
+        .args(["/d", "/c", "call"])
+        .arg(&script)
+        .args(["-no_logo", "&&", "set"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(parse_set_output(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// The variables of a `set` output, one `NAME=value` per line
+fn parse_set_output(output: &str) -> Vec<(String, String)> {
+    output.lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(name, value)| (name.to_string(), value.trim_end().to_string()))
+        .collect()
+}
+
+/// The installation paths of a `vswhere -property installationPath` output
+fn parse_vswhere(output: &str) -> Vec<PathBuf> {
+    output.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Environment variables that change the result of the SDK discovery
+const CACHE_KEY_VARS: [&str; 6] = ["CARGO_CFG_TARGET_ARCH", "WindowsSdkVerBinPath", "WindowsSdkDir",
+                                   "WindowsSDKVersion", "WDKContentRoot", "ProgramFiles(x86)"];
+
+/// Key of the discovered SDK tool in a cache, derived from the environment
+pub fn cache_key() -> String {
+    cache_key_with_env(&ProcessEnv)
+}
+
+/// [`cache_key()`] derived from the variables of `env`
+///
+/// [`cache_key()`]: fn.cache_key.html
+pub fn cache_key_with_env(env: &dyn EnvSource) -> String {
+    let mut key = format!("winres {}", env!("CARGO_PKG_VERSION"));
+    for var in CACHE_KEY_VARS.iter() {
+        key.push_str(&format!(";{}={}", var, env.var(var).unwrap_or_default()));
+    }
+    key
+}
+
+/// Modification time of the `bin` dir of an installed root, which changes when an
+/// SDK version is installed or removed
+fn cache_stamp(installed_root: &Path) -> Option<String> {
+    let modified = installed_root.join("bin").metadata().and_then(|m| m.modified()).ok()?;
+    let since_epoch = modified.duration_since(time::UNIX_EPOCH).ok()?;
+    Some(since_epoch.as_nanos().to_string())
+}
+
+/// Read the tool cached by [`write_cached_tool()`] for `key`
+///
+/// `None` if there is no cache, the key differs, or the installed SDKs changed.
+///
+/// [`write_cached_tool()`]: fn.write_cached_tool.html
+pub fn read_cached_tool(path: &Path, key: &str) -> Option<Tool> {
+    let content = fs::read_to_string(path).ok()?;
+    let mut lines = content.lines();
+    if lines.next()? != key {
+        return None;
+    }
+    let mut tool = Tool {
+        sdk_version: String::new(),
+        installed_root: PathBuf::new(),
+        arch: Arch::X64,
+        path: PathBuf::new(),
+        include_dirs: HashMap::new(),
+        lib_dirs: HashMap::new(),
+        bin_dir: PathBuf::new(),
+    };
+    let mut stamp = None;
+    for line in lines {
+        let mut fields = line.splitn(3, '\t');
+        match (fields.next()?, fields.next()?, fields.next()) {
+            ("stamp", value, None) => stamp = Some(value.to_string()),
+            ("sdk_version", value, None) => tool.sdk_version = value.to_string(),
+            ("installed_root", value, None) => tool.installed_root = PathBuf::from(value),
+            ("arch", value, None) => tool.arch = Arch::from_target_arch(value)?,
+            ("path", value, None) => tool.path = PathBuf::from(value),
+            ("bin_dir", value, None) => tool.bin_dir = PathBuf::from(value),
+            ("include", name, Some(dir)) => { tool.include_dirs.insert(name.to_string(), PathBuf::from(dir)); }
+            ("lib", name, Some(dir)) => { tool.lib_dirs.insert(name.to_string(), PathBuf::from(dir)); }
+            _ => return None,
+        }
+    }
+    if stamp.is_none() || stamp != cache_stamp(&tool.installed_root) || !tool.path.is_file() {
+        return None;
+    }
+    Some(tool)
+}
+
+/// Cache `tool` at `path` for [`read_cached_tool()`]
+///
+/// [`read_cached_tool()`]: fn.read_cached_tool.html
+pub fn write_cached_tool(path: &Path, key: &str, tool: &Tool) -> io::Result<()> {
+    let stamp = cache_stamp(&tool.installed_root)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "installed root has no bin dir"))?;
+    let mut content = format!("{}\nstamp\t{}\nsdk_version\t{}\ninstalled_root\t{}\narch\t{}\npath\t{}\nbin_dir\t{}\n",
+                              key, stamp, tool.sdk_version, tool.installed_root.display(),
+                              tool.arch.cache_name(), tool.path.display(), tool.bin_dir.display());
+    for (name, dir) in tool.include_dirs.iter() {
+        content.push_str(&format!("include\t{}\t{}\n", name, dir.display()));
+    }
+    for (name, dir) in tool.lib_dirs.iter() {
+        content.push_str(&format!("lib\t{}\t{}\n", name, dir.display()));
+    }
+    fs::write(path, content)
+}
+
+/// A `Tool` for the `rc.exe` at `path`
+///
+/// If `path` is in the layout of an installed SDK, `<root>\bin\<version>\<arch>\rc.exe`,
+/// the include and library dirs of that SDK for `arch` are used. Otherwise there are none
+/// and the SDK version is `"custom"`.
+pub fn rc_tool(arch: Arch, path: &Path) -> Tool {
+    let bin_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let version = bin_dir.parent().and_then(Path::file_name).map(|name| name.to_string_lossy().into_owned());
+    let root = bin_dir.parent().and_then(Path::parent)
+        .filter(|bin| bin.file_name().and_then(|name| name.to_str()).map(str::to_ascii_lowercase).as_deref() == Some("bin"))
+        .and_then(Path::parent);
+    let sdk = match (version, root) {
+        (Some(version), Some(root)) => Sdk::new(version, root.to_path_buf()).ok(),
+        _ => None,
+    };
+    let mut tool = Tool {
+        sdk_version: "custom".to_string(),
+        installed_root: PathBuf::new(),
+        arch,
+        path: path.to_path_buf(),
+        include_dirs: HashMap::new(),
+        lib_dirs: HashMap::new(),
+        bin_dir,
+    };
+    if let Some(sdk) = sdk {
+        if let Some(sdk_arch) = sdk.sdk_arch(&arch) {
+            tool.include_dirs = sdk_arch.include_dirs.clone();
+            tool.lib_dirs = sdk_arch.lib_dirs.clone();
+            tool.sdk_version = sdk.version.clone();
+            tool.installed_root = sdk.installed_root.clone();
+        }
+    }
+    tool
 }
 
 /// Find a Windows SDK
@@ -265,7 +1074,6 @@ pub fn get_sdk() -> io::Result<Vec<PathBuf>> {
         };
 
         if rc.exists() {
-            println!("{:?}", rc);
             kits.push(rc.parent().unwrap().to_owned());
         }
 
@@ -278,7 +1086,6 @@ pub fn get_sdk() -> io::Result<Vec<PathBuf>> {
             };
             
             if p.exists() {
-                println!("{:?}", p);
                 kits.push(p.parent().unwrap().to_owned());
             }
         }
@@ -289,7 +1096,195 @@ pub fn get_sdk() -> io::Result<Vec<PathBuf>> {
 
 #[cfg(test)]
 mod tests {
-    use super::{get_sdk, InstalledRoots, System};
+    use super::{expand_env_vars, parse_reg_value, parse_set_output, parse_vswhere, rc_tool, read_cached_tool, write_cached_tool, Arch, InstalledRoots, KitsRoot, Sdk, SdkVersion,
+                System, Tool, INSTALLED_ROOTS_KEY, USER_INSTALLED_ROOTS_KEY};
+    use std::collections::HashMap;
+    use std::env;
+    use std::fs;
+    #[cfg(target_env = "msvc")]
+    use super::get_sdk;
+    use std::path::PathBuf;
+
+    #[test]
+    fn merge_registry_views() {
+        let view32 = format!("\n{key}\n    KitsRoot10    REG_SZ    C:\\Program Files (x86)\\Windows Kits\\10\\\n\n\
+                              {key}\\10.0.19041.0\n", key = INSTALLED_ROOTS_KEY);
+        let view64 = format!("\n{key}\n    KitsRoot10    REG_SZ    C:\\Program Files (x86)\\Windows Kits\\10\\\n\n\
+                              {key}\\10.0.19041.0\n{key}\\10.0.22621.0\n", key = INSTALLED_ROOTS_KEY);
+        let mut roots = InstalledRoots { kits_roots: vec![], sdk_versions: vec![] };
+        roots.merge(INSTALLED_ROOTS_KEY, &view32).expect("merge 32-bit view");
+        roots.merge(INSTALLED_ROOTS_KEY, &view64).expect("merge 64-bit view");
+        assert_eq!(roots.kits_roots, vec![(KitsRoot("KitsRoot10".to_string()),
+                                           PathBuf::from("C:\\Program Files (x86)\\Windows Kits\\10\\"))]);
+        assert_eq!(roots.sdk_versions, vec!["10.0.19041.0", "10.0.22621.0"]);
+
+        let user = format!("\n{key}\n    KitsRoot10    REG_SZ    C:\\Users\\me\\Windows Kits\\10\\\n\n\
+                            {key}\\10.0.26100.0\n", key = USER_INSTALLED_ROOTS_KEY);
+        roots.merge(USER_INSTALLED_ROOTS_KEY, &user).expect("merge per-user key");
+        assert_eq!(roots.kits_roots[1].1, PathBuf::from("C:\\Users\\me\\Windows Kits\\10\\"));
+        assert_eq!(roots.sdk_versions, vec!["10.0.19041.0", "10.0.22621.0", "10.0.26100.0"]);
+    }
+
+    #[test]
+    fn merge_odd_reg_output() {
+        // German output with tabs, CRLF line ends, an expandable path and a lowercase key
+        let output = "\r\nHKEY_LOCAL_MACHINE\\SOFTWARE\\Microsoft\\Windows Kits\\Installed Roots\r\n\
+                      \tKitsRoot10\tREG_EXPAND_SZ\t%ProgramFiles(x86)%\\Windows Kits\\10\\\r\n\
+                      \tKitsRoot81    REG_SZ\r\n\
+                      \tWdkBinRootx86    REG_SZ    C:\\WDK\\bin\r\n\r\n\
+                      hkey_local_machine\\software\\microsoft\\windows kits\\installed roots\\10.0.22621.0\r\n\
+                      HKEY_LOCAL_MACHINE\\SOFTWARE\\Microsoft\\Windows Kits\\Installed Roots\\10.0.22621.0\\Installed Options\r\n\
+                      FEHLER: Der angegebene Registrierungsschlüssel bzw. Wert wurde nicht gefunden.\r\n";
+        let mut roots = InstalledRoots { kits_roots: vec![], sdk_versions: vec![] };
+        roots.merge(INSTALLED_ROOTS_KEY, output).expect("merge");
+        assert_eq!(roots.kits_roots.len(), 1);
+        assert_eq!(roots.kits_roots[0].0, KitsRoot("KitsRoot10".to_string()));
+        if env::var_os("ProgramFiles(x86)").is_none() {
+            assert_eq!(roots.kits_roots[0].1, PathBuf::from("%ProgramFiles(x86)%\\Windows Kits\\10\\"));
+        }
+        assert_eq!(roots.sdk_versions, vec!["10.0.22621.0"]);
+
+        let output = format!("{}\n    KitsRoot10    REG_DWORD    0x1\n", INSTALLED_ROOTS_KEY);
+        assert!(roots.merge(INSTALLED_ROOTS_KEY, &output).is_err());
+
+        assert_eq!(parse_reg_value("(Standard)    REG_SZ    (Wert nicht festgelegt)"),
+                   Some(("(Standard)", "REG_SZ", "(Wert nicht festgelegt)")));
+        assert_eq!(parse_reg_value("KitsRoot10 REG_SZ C:\\REG_SZ dir"), Some(("KitsRoot10", "REG_SZ", "C:\\REG_SZ dir")));
+        assert_eq!(parse_reg_value("REG_SZ"), None);
+        let vars = |name: &str| if name == "A" { Some("x".to_string()) } else { None };
+        assert_eq!(expand_env_vars("%A%\\%B%\\100%", vars), "x\\%B%\\100%");
+    }
+
+    #[test]
+    fn scan_kits_root() {
+        let root = env::temp_dir().join("winres-scan-kits-root");
+        let _ = fs::remove_dir_all(&root);
+        for dir in &["10.0.22621.0", "10.0.19041.0", "x64"] {
+            fs::create_dir_all(root.join("bin").join(dir)).expect("create bin dir");
+        }
+        let roots = InstalledRoots::scan(&[root.clone(), root.join("missing")]);
+        assert_eq!(roots.kits_roots, vec![(KitsRoot("KitsRoot10".to_string()), root.clone())]);
+        assert_eq!(roots.sdk_versions, vec!["10.0.19041.0", "10.0.22621.0"]);
+
+        let vars = |name: &str| match name {
+            "WindowsSdkDir" => Some(format!("{}\\", root.display())),
+            "WindowsSDKVersion" => Some("10.0.22621.0\\".to_string()),
+            _ => None,
+        };
+        let roots = InstalledRoots::from_vars(vars).expect("installed roots from env");
+        assert_eq!(roots.kits_roots, vec![(KitsRoot("WindowsSdkDir".to_string()), root.clone())]);
+        assert_eq!(roots.sdk_versions, vec!["10.0.22621.0"]);
+        assert_eq!(InstalledRoots::from_vars(|_| None), None);
+        let _ = fs::remove_dir_all(&root);
+
+        assert_eq!(parse_vswhere("C:\\VS\\2022\r\n\r\n"), vec![PathBuf::from("C:\\VS\\2022")]);
+        let vars = parse_set_output("Path=C:\\Windows\r\nWindowsSdkDir=C:\\Kits\\10\\\r\nVSCMD_ARG_app_plat=Desktop\r\n");
+        assert_eq!(vars[1], ("WindowsSdkDir".to_string(), "C:\\Kits\\10\\".to_string()));
+        assert_eq!(vars.len(), 3);
+    }
+
+    #[test]
+    fn sdk_version_ordering() {
+        assert!(SdkVersion::new("10.0.10.0") > SdkVersion::new("10.0.9.0"));
+        assert!(SdkVersion::new("10.0.22621.0") > SdkVersion::new("10.0.19041.0"));
+        assert_eq!(SdkVersion::new("10.0.22621.0").components(), [10, 0, 22621, 0]);
+        assert!(SdkVersion::new("10.0") < SdkVersion::new("10.0.0"));
+        assert!(SdkVersion::new("10.0.22621.0").starts_with(&SdkVersion::new("10.0.22621")));
+        assert!(!SdkVersion::new("10.0.22621.0").starts_with(&SdkVersion::new("10.0.22")));
+
+        let root = env::temp_dir().join("winres-sdk-version-ordering");
+        let _ = fs::remove_dir_all(&root);
+        let versions = ["10.0.9.0", "10.0.10.0", "10.0.22621.0"];
+        for version in versions.iter() {
+            let bin_dir = root.join("bin").join(version).join("x64");
+            fs::create_dir_all(&bin_dir).expect("create bin dir");
+            fs::write(bin_dir.join("rc.exe"), b"").expect("write rc.exe");
+            fs::create_dir_all(root.join("Include").join(version)).expect("create include dir");
+        }
+        let installed_roots = InstalledRoots::scan(::std::slice::from_ref(&root));
+        assert_eq!(installed_roots.sdk_versions, versions);
+        let sdks = versions.iter()
+            .map(|version| Sdk::new(version.to_string(), root.clone()).expect("load sdk"))
+            .collect();
+        let system = System { installed_roots, sdks };
+        assert_eq!(system.latest_sdk().map(|sdk| sdk.version.as_str()), Some("10.0.22621.0"));
+        let matching = system.sdks_matching(SdkVersion::new("10.0.10.0")..);
+        assert_eq!(matching.iter().map(|sdk| sdk.version.as_str()).collect::<Vec<_>>(),
+                   ["10.0.22621.0", "10.0.10.0"]);
+        let tools = system.find_tools("rc.exe", Arch::X64);
+        assert_eq!(tools.iter().map(|tool| tool.sdk_version.as_str()).collect::<Vec<_>>(),
+                   ["10.0.22621.0", "10.0.10.0", "10.0.9.0"]);
+
+        let candidates = system.candidates("rc.exe", Arch::X86);
+        assert_eq!(candidates.len(), 3);
+        assert_eq!(candidates[0].sdk_version, "10.0.22621.0");
+        assert_eq!(candidates[0].rejected.as_deref(), Some("SDK 10.0.22621.0 only has it for x64, which can't run on x86"));
+        assert_eq!(system.candidates("mt.exe", Arch::X64)[0].rejected.as_deref(), Some("the file does not exist"));
+        assert_eq!(system.candidates("rc.exe", Arch::Arm64)[2].rejected, None);
+
+        // the x64 rc.exe runs on ARM64, with the arm64 libraries for ARM64EC
+        let tools = system.find_host_tools("rc.exe", Arch::Arm64Ec, Arch::Arm64);
+        assert_eq!(tools.len(), 3);
+        assert_eq!(tools[0].bin_dir, root.join("bin").join("10.0.22621.0").join("x64"));
+        assert_eq!(tools[0].arch, Arch::Arm64Ec);
+        assert!(system.find_host_tools("rc.exe", Arch::X64, Arch::X86).is_empty());
+        assert_eq!(Arch::from_target_arch("arm64ec").map(|arch| arch.dirname()), Some("arm64"));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn load_lib_dirs() {
+        let root = env::temp_dir().join("winres-load-lib-dirs");
+        let _ = fs::remove_dir_all(&root);
+        let version = "10.0.22621.0";
+        for dir in &["bin/10.0.22621.0/x64", "Include/10.0.22621.0/um",
+                     "Lib/10.0.22621.0/um/x64", "Lib/10.0.22621.0/ucrt/x64", "Lib/10.0.22621.0/ucrt/arm64"] {
+            fs::create_dir_all(root.join(dir)).expect("create sdk dir");
+        }
+        let sdk = Sdk::new(version.to_string(), root.clone()).expect("load sdk");
+        let x64 = sdk.sdk_arch(&Arch::X64).expect("x64");
+        assert_eq!(x64.lib_dirs.len(), 2);
+        assert_eq!(x64.lib_dirs["ucrt"], root.join("Lib").join(version).join("ucrt").join("x64"));
+        assert_eq!(sdk.sdk_arch(&Arch::Arm64).expect("arm64").lib_dirs.len(), 1);
+        assert!(sdk.sdk_arch(&Arch::X86).expect("x86").lib_dirs.is_empty());
+
+        let tool = rc_tool(Arch::X64, &root.join("bin").join(version).join("x64").join("rc.exe"));
+        assert_eq!(tool.sdk_version, version);
+        assert_eq!(tool.include_dirs["um"], root.join("Include").join(version).join("um"));
+        assert_eq!(tool.lib_dirs.len(), 2);
+        let tool = rc_tool(Arch::X64, &root.join("rc.exe"));
+        assert_eq!(tool.sdk_version, "custom");
+        assert_eq!(tool.bin_dir, root);
+        assert!(tool.include_dirs.is_empty());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn cached_tool() {
+        let root = env::temp_dir().join("winres-cached-tool");
+        let _ = fs::remove_dir_all(&root);
+        let bin_dir = root.join("bin").join("10.0.22621.0").join("arm64");
+        fs::create_dir_all(&bin_dir).expect("create bin dir");
+        fs::write(bin_dir.join("rc.exe"), b"").expect("write rc.exe");
+        let mut include_dirs = HashMap::new();
+        include_dirs.insert("um".to_string(), root.join("Include").join("um"));
+        let tool = Tool {
+            sdk_version: "10.0.22621.0".to_string(),
+            installed_root: root.clone(),
+            arch: Arch::Arm64,
+            path: bin_dir.join("rc.exe"),
+            include_dirs,
+            lib_dirs: HashMap::new(),
+            bin_dir,
+        };
+        let cache = root.join("sdk-cache.txt");
+        write_cached_tool(&cache, "key", &tool).expect("write cache");
+        assert_eq!(read_cached_tool(&cache, "key"), Some(tool.clone()));
+        assert_eq!(read_cached_tool(&cache, "other key"), None);
+        fs::remove_file(&tool.path).expect("remove rc.exe");
+        assert_eq!(read_cached_tool(&cache, "key"), None);
+        let _ = fs::remove_dir_all(&root);
+    }
 
     #[cfg(target_env = "msvc")]
     #[test]
@@ -0,0 +1,264 @@
+//! Test harness building a fixture crate with each available backend
+//!
+//! A [`Fixture`] is a tiny binary crate with a build script using winres. It is built
+//! with `cargo` for the Windows target of a [`Toolchain`], and the resulting [`Binary`]
+//! can be inspected for the compiled resources. Besides the tests of this crate, the
+//! harness can be used for smoke tests of a downstream resource setup:
+//!
+//! ```rust,no_run
+//! use winres::testing::{available_toolchains, Fixture};
+//!
+//! let mut fixture = Fixture::new("target/winres-fixture");
+//! fixture.build_script(r#"
+//!     extern crate winres;
+//!     fn main() {
+//!         let mut res = winres::WindowsResource::new();
+//!         res.set("ProductName", "Smoke Test");
+//!         res.compile().unwrap();
+//!     }
+//! "#);
+//! for toolchain in available_toolchains() {
+//!     let binary = fixture.build(toolchain).unwrap();
+//!     assert!(binary.has_resource_section());
+//!     assert!(binary.contains_string("Smoke Test"));
+//! }
+//! ```
+//!
+//! [`Fixture`]: struct.Fixture.html
+//! [`Toolchain`]: ../enum.Toolchain.html
+//! [`Binary`]: struct.Binary.html
+
+use std::env;
+use std::fs;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use {command_line, get_sdk_tools, tool_failed, Error, ProcessEnv, Toolchain};
+use mingw;
+use sdk;
+
+/// Build script of a fixture if none is set
+pub const DEFAULT_BUILD_SCRIPT: &str = r#"
+extern crate winres;
+
+fn main() {
+    let mut res = winres::WindowsResource::new();
+    res.set("ProductName", "winres fixture");
+    res.compile().unwrap();
+}
+"#;
+
+/// A binary crate built with winres
+#[derive(Debug, Clone)]
+pub struct Fixture {
+    dir: PathBuf,
+    name: String,
+    build_script: String,
+    files: Vec<(PathBuf, Vec<u8>)>,
+}
+
+impl Fixture {
+    /// Create a fixture crate in `dir`
+    ///
+    /// The crate is only written when it is built, existing files are overwritten.
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Fixture {
+            dir: dir.as_ref().to_path_buf(),
+            name: "winres-fixture".to_string(),
+            build_script: DEFAULT_BUILD_SCRIPT.to_string(),
+            files: Vec::new(),
+        }
+    }
+
+    /// Set the package name, which is also the name of the binary
+    pub fn name(&mut self, name: &str) -> &mut Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Set the source of `build.rs`
+    pub fn build_script(&mut self, source: &str) -> &mut Self {
+        self.build_script = source.to_string();
+        self
+    }
+
+    /// Add a file the build script uses, e.g. an icon; `path` is relative to the crate
+    pub fn file<P: AsRef<Path>>(&mut self, path: P, content: &[u8]) -> &mut Self {
+        self.files.push((path.as_ref().to_path_buf(), content.to_vec()));
+        self
+    }
+
+    /// The target triple the fixture is built for with `toolchain`
+    pub fn target(toolchain: Toolchain) -> String {
+        let arch = if cfg!(target_arch = "x86") {
+            "i686"
+        } else if cfg!(target_arch = "aarch64") {
+            "aarch64"
+        } else {
+            "x86_64"
+        };
+        let env = match toolchain {
+            Toolchain::Msvc | Toolchain::LlvmRc => "msvc",
+            Toolchain::Gnu => "gnu",
+        };
+        format!("{}-pc-windows-{}", arch, env)
+    }
+
+    fn write(&self) -> Result<(), Error> {
+        fs::create_dir_all(self.dir.join("src"))?;
+        let winres = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let manifest = format!("[package]\n\
+                                name = \"{}\"\n\
+                                version = \"0.1.0\"\n\
+                                build = \"build.rs\"\n\n\
+                                [workspace]\n\n\
+                                [build-dependencies]\n\
+                                winres = {{ path = {:?} }}\n",
+                               self.name, winres.display().to_string());
+        fs::File::create(self.dir.join("Cargo.toml"))?.write_all(manifest.as_bytes())?;
+        fs::File::create(self.dir.join("build.rs"))?.write_all(self.build_script.as_bytes())?;
+        fs::File::create(self.dir.join("src").join("main.rs"))?.write_all(b"fn main() {}\n")?;
+        for (path, content) in self.files.iter() {
+            let path = self.dir.join(path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::File::create(path)?.write_all(content)?;
+        }
+        Ok(())
+    }
+
+    /// Write the crate and build it for the Windows target of `toolchain`
+    pub fn build(&self, toolchain: Toolchain) -> Result<Binary, Error> {
+        self.write()?;
+        let target = Fixture::target(toolchain);
+        let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+        let mut command = process::Command::new(&cargo);
+        command.arg("build")
+            .arg("--quiet")
+            .arg("--target")
+            .arg(&target)
+            .current_dir(&self.dir);
+        let output = command.output()?;
+        if !output.status.success() {
+            return Err(tool_failed("cargo", &command_line(&command), &output));
+        }
+        let path = self.dir.join("target").join(&target).join("debug").join(format!("{}.exe", self.name));
+        Binary::open(path)
+    }
+}
+
+/// A built executable
+#[derive(Debug, Clone)]
+pub struct Binary {
+    pub path: PathBuf,
+    data: Vec<u8>,
+}
+
+impl Binary {
+    /// Read the executable at `path`
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Binary, Error> {
+        let mut data = vec![];
+        fs::File::open(path.as_ref())?.read_to_end(&mut data)?;
+        Ok(Binary { path: path.as_ref().to_path_buf(), data })
+    }
+
+    /// Whether the executable has a `.rsrc` section, i.e. a resource was linked
+    pub fn has_resource_section(&self) -> bool {
+        section_names(&self.data).iter().any(|name| name == ".rsrc")
+    }
+
+    /// Whether `s` is part of the executable as UTF-16 string, like the values of
+    /// the version info
+    pub fn contains_string(&self, s: &str) -> bool {
+        let needle = s.encode_utf16().flat_map(|c| c.to_le_bytes()).collect::<Vec<_>>();
+        !needle.is_empty() && self.data.windows(needle.len()).any(|window| window == &needle[..])
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// Names of the sections of a PE file, empty if `data` is not one
+fn section_names(data: &[u8]) -> Vec<String> {
+    let pe = match read_u32(data, 0x3c) {
+        Some(pe) if data.get(pe as usize..pe as usize + 4) == Some(&b"PE\0\0"[..]) => pe as usize,
+        _ => return vec![],
+    };
+    let count = read_u16(data, pe + 6).unwrap_or(0) as usize;
+    let optional_header_size = read_u16(data, pe + 20).unwrap_or(0) as usize;
+    let table = pe + 24 + optional_header_size;
+    (0..count)
+        .filter_map(|i| data.get(table + i * 40..table + i * 40 + 8))
+        .map(|name| String::from_utf8_lossy(name).trim_end_matches('\0').to_string())
+        .collect()
+}
+
+/// The toolchains whose resource compiler is installed
+///
+/// The MSVC toolchain needs a Windows SDK, the GNU toolchain `windres`, see
+/// [`mingw::find_windres()`].
+/// The Rust target of the toolchain has to be installed as well, which is not checked.
+///
+/// [`mingw::find_windres()`]: ../mingw/fn.find_windres.html
+pub fn available_toolchains() -> Vec<Toolchain> {
+    let mut toolchains = vec![];
+    if cfg!(windows) && get_sdk_tools(&ProcessEnv, None).is_ok() {
+        toolchains.push(Toolchain::Msvc);
+    }
+    if sdk::Arch::arch_for_target().and_then(mingw::find_windres).is_some() {
+        toolchains.push(Toolchain::Gnu);
+    }
+    toolchains
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{available_toolchains, section_names, Binary, Fixture};
+    use std::path::{Path, PathBuf};
+
+    /// Minimal PE header with the sections `.text` and `.rsrc`
+    fn pe_file() -> Vec<u8> {
+        let mut data = vec![0u8; 0x40];
+        data[0x3c] = 0x40;
+        data.extend_from_slice(b"PE\0\0");
+        let mut coff = [0u8; 20];
+        coff[2] = 2;
+        data.extend_from_slice(&coff);
+        for name in &[&b".text\0\0\0"[..], &b".rsrc\0\0\0"[..]] {
+            data.extend_from_slice(name);
+            data.extend_from_slice(&[0u8; 32]);
+        }
+        data
+    }
+
+    #[test]
+    fn pe_sections() {
+        assert_eq!(section_names(&pe_file()), vec![".text", ".rsrc"]);
+        assert!(section_names(b"not a PE file").is_empty());
+
+        let mut data = pe_file();
+        data.extend("ProductName".encode_utf16().flat_map(|c| c.to_le_bytes()));
+        let binary = Binary { path: PathBuf::new(), data };
+        assert!(binary.has_resource_section());
+        assert!(binary.contains_string("ProductName"));
+        assert!(!binary.contains_string("FileVersion"));
+    }
+
+    #[test]
+    fn build_fixture() {
+        for toolchain in available_toolchains() {
+            let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("target")
+                .join(format!("fixture-{}", Fixture::target(toolchain)));
+            let binary = Fixture::new(dir).build(toolchain).expect("build fixture");
+            assert!(binary.has_resource_section());
+            assert!(binary.contains_string("winres fixture"));
+        }
+    }
+}
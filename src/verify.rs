@@ -0,0 +1,560 @@
+//! Verification of the resources in a compiled `.res` file or a linked binary
+//!
+//! A linker that silently drops the compiled resource produces a binary without version
+//! info, which is easy to miss. [`read()`] reads the version info, icons and manifest
+//! from a `.res` file, the COFF object of `windres` or a PE binary (`.exe` or `.dll`), on
+//! any platform, and [`check()`] compares them to the settings of a [`WindowsResource`]:
+//!
+//! ```rust,no_run
+//! let mut res = winres::WindowsResource::new();
+//! res.set("ProductName", "My app");
+//! let compiled = res.compile_to_res().unwrap();
+//! winres::verify::check_file(&res, compiled).unwrap();
+//! ```
+//!
+//! [`read()`]: fn.read.html
+//! [`check()`]: fn.check.html
+//! [`WindowsResource`]: ../struct.WindowsResource.html
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use res::{self, align, read_string, read_u16, read_u32, Id, Resource, RT_GROUP_ICON, RT_MANIFEST, RT_VERSION};
+use {Error, VersionInfo, WindowsResource};
+
+const FIXED_FILE_INFO_SIGNATURE: u32 = 0xfeef_04bd;
+
+/// Machine types of COFF objects: x86, x64, ARM and ARM64
+const COFF_MACHINES: [u16; 4] = [0x14c, 0x8664, 0x1c4, 0xaa64];
+
+/// The resources found in a compiled file
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct Embedded {
+    /// `FILEVERSION` of the version info, `None` without version info
+    pub file_version: Option<u64>,
+    /// `PRODUCTVERSION` of the version info
+    pub product_version: Option<u64>,
+    /// The string tables of the version info by language and code page, e.g. `"040904b0"`
+    pub string_tables: BTreeMap<String, BTreeMap<String, String>>,
+    /// Number of icon groups, i.e. icons with all their images
+    pub icon_groups: usize,
+    /// Whether a manifest is embedded
+    pub manifest: bool,
+}
+
+impl Embedded {
+    /// The value of the string property `name` in the first string table that has it
+    pub fn string(&self, name: &str) -> Option<&str> {
+        self.string_tables.values().filter_map(|table| table.get(name)).map(String::as_str).next()
+    }
+}
+
+/// Read the resources of a `.res` file, a COFF object or a PE binary
+///
+/// The GNU toolchain compiles to a COFF object, see [`compile_to_res()`], which has the
+/// resources in its `.rsrc` section like a binary. A malformed file is reported as
+/// [`Error::InvalidResFile`].
+///
+/// [`compile_to_res()`]: ../struct.WindowsResource.html#method.compile_to_res
+/// [`Error::InvalidResFile`]: ../enum.Error.html#variant.InvalidResFile
+pub fn read<P: AsRef<Path>>(path: P) -> Result<Embedded, Error> {
+    let data = fs::read(path.as_ref())?;
+    let malformed = |kind: &str| Error::InvalidResFile {
+        path: path.as_ref().to_path_buf(),
+        message: format!("the resource directory of the {} is malformed", kind),
+    };
+    let resources = if data.starts_with(b"MZ") {
+        pe_resources(&data).ok_or_else(|| malformed("binary"))?
+    } else if read_u16(&data, 0).is_some_and(|machine| COFF_MACHINES.contains(&machine)) {
+        coff_resources(&data).ok_or_else(|| malformed("object file"))?
+    } else {
+        res::parse(path)?
+    };
+    let mut embedded = Embedded::default();
+    for resource in resources {
+        match resource.kind {
+            Id::Number(RT_VERSION) => read_version_info(&resource.data, &mut embedded),
+            Id::Number(RT_GROUP_ICON) => embedded.icon_groups += 1,
+            Id::Number(RT_MANIFEST) => embedded.manifest = true,
+            _ => {}
+        }
+    }
+    Ok(embedded)
+}
+
+/// Check that `embedded` has the version info, icon and manifest set in `res`
+///
+/// Returns [`Error::VerificationFailed`] with every missing or different setting.
+///
+/// [`Error::VerificationFailed`]: ../enum.Error.html#variant.VerificationFailed
+pub fn check(res: &WindowsResource, embedded: &Embedded) -> Result<(), Error> {
+    let mut problems = vec![];
+    if embedded.file_version.is_none() {
+        problems.push("the version info is missing".to_string());
+    } else {
+        let versions = [
+            (VersionInfo::FILEVERSION, embedded.file_version),
+            (VersionInfo::PRODUCTVERSION, embedded.product_version),
+        ];
+        for &(key, found) in versions.iter() {
+            let expected = res.version_info.get(&key).cloned();
+            if expected.is_some() && found != expected {
+                problems.push(format!("{:?} is {}, expected {}", key,
+                                      found.map_or_else(|| "missing".to_string(), version_string),
+                                      version_string(expected.unwrap_or(0))));
+            }
+        }
+        let table = format!("{:04x}{:04x}", res.language, res.charset);
+        for (name, value) in res.properties.iter().filter(|&(_, value)| !value.is_empty()) {
+            let expected = res.resolve_placeholders(value)?;
+            match embedded.string_tables.get(&table).and_then(|strings| strings.get(name)) {
+                Some(found) if *found == expected => {}
+                Some(found) => problems.push(format!("{} is \"{}\", expected \"{}\"", name, found, expected)),
+                None => problems.push(format!("{} is missing", name)),
+            }
+        }
+    }
+    if (res.selected_icon()?.is_some() || !res.icons.is_empty()) && embedded.icon_groups == 0 {
+        problems.push("the icon is missing".to_string());
+    }
+    if (res.manifest_xml().is_some() || res.manifest_file.is_some()) && !embedded.manifest {
+        problems.push("the manifest is missing".to_string());
+    }
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::VerificationFailed(problems))
+    }
+}
+
+/// [`read()`] the file at `path` and [`check()`] it against `res`
+///
+/// [`read()`]: fn.read.html
+/// [`check()`]: fn.check.html
+pub fn check_file<P: AsRef<Path>>(res: &WindowsResource, path: P) -> Result<(), Error> {
+    check(res, &read(path)?)
+}
+
+fn version_string(version: u64) -> String {
+    format!("{}.{}.{}.{}", version >> 48, (version >> 32) & 0xffff, (version >> 16) & 0xffff, version & 0xffff)
+}
+
+/// A block of the version info, like `StringFileInfo`, with its value and children
+struct Block<'a> {
+    key: String,
+    value: &'a [u8],
+    text: bool,
+    children: Vec<Block<'a>>,
+}
+
+impl<'a> Block<'a> {
+    /// The value of a text block, without the terminating zero
+    fn text(&self) -> String {
+        let chars = self.value.chunks(2).filter(|c| c.len() == 2).map(|c| u16::from_le_bytes([c[0], c[1]]));
+        String::from_utf16_lossy(&chars.take_while(|&c| c != 0).collect::<Vec<_>>())
+    }
+}
+
+/// Parse the version info block at `offset` of `data`
+///
+/// The blocks are aligned to 4 bytes relative to the start of the resource.
+fn parse_block(data: &[u8], offset: usize) -> Option<Block<'_>> {
+    let length = read_u16(data, offset)? as usize;
+    let value_length = read_u16(data, offset + 2)? as usize;
+    let text = read_u16(data, offset + 4)? == 1;
+    let end = (offset + length).min(data.len());
+    let (key, key_end) = read_string(data, offset + 6)?;
+    let value_start = align(key_end).min(end);
+    // the length of text values is counted in characters
+    let value_end = (value_start + if text { value_length * 2 } else { value_length }).min(end);
+    let mut children = vec![];
+    let mut child = align(value_end);
+    while child + 6 <= end {
+        let block = parse_block(&data[..end], child)?;
+        let length = read_u16(data, child)? as usize;
+        if length == 0 {
+            break;
+        }
+        children.push(block);
+        child = align(child + length);
+    }
+    Some(Block { key, value: &data[value_start..value_end], text, children })
+}
+
+/// Read the `VS_VERSIONINFO` resource `data` into `embedded`
+fn read_version_info(data: &[u8], embedded: &mut Embedded) {
+    let root = match parse_block(data, 0) {
+        Some(root) => root,
+        None => return,
+    };
+    if root.key != "VS_VERSION_INFO" || read_u32(root.value, 0) != Some(FIXED_FILE_INFO_SIGNATURE) {
+        return;
+    }
+    let version = |offset| Some((u64::from(read_u32(root.value, offset)?) << 32) | u64::from(read_u32(root.value, offset + 4)?));
+    embedded.file_version = version(8);
+    embedded.product_version = version(16);
+    for child in root.children.iter().filter(|child| child.key == "StringFileInfo") {
+        for table in child.children.iter() {
+            let strings = table.children.iter().map(|string| {
+                let value = if string.text { string.text() } else { String::from_utf8_lossy(string.value).into_owned() };
+                (string.key.clone(), value)
+            });
+            embedded.string_tables.entry(table.key.clone()).or_default().extend(strings);
+        }
+    }
+}
+
+/// The resources of the `.rsrc` section of a PE binary, `None` if it is malformed
+///
+/// The resource directory is a tree with the levels type, name and language.
+fn pe_resources(data: &[u8]) -> Option<Vec<Resource>> {
+    let pe = read_u32(data, 0x3c)? as usize;
+    if data.get(pe..pe + 4)? != b"PE\0\0" {
+        return None;
+    }
+    let sections = read_u16(data, pe + 6)? as usize;
+    let optional_header = pe + 24;
+    let optional_header_size = read_u16(data, pe + 20)? as usize;
+    let directories = match read_u16(data, optional_header)? {
+        0x10b => optional_header + 96,
+        0x20b => optional_header + 112,
+        _ => return None,
+    };
+    let directory_count = read_u32(data, directories - 4)?;
+    let rsrc_rva = read_u32(data, directories + 16)?;
+    if directory_count < 3 || rsrc_rva == 0 {
+        return Some(vec![]);
+    }
+    let section_table = optional_header + optional_header_size;
+    let offset_of = |rva: u32| -> Option<usize> {
+        (0..sections).map(|i| section_table + i * 40).find_map(|section| {
+            let size = read_u32(data, section + 8)?.max(read_u32(data, section + 16)?);
+            let address = read_u32(data, section + 12)?;
+            let raw = read_u32(data, section + 20)?;
+            // the fields of a corrupt file can overflow
+            rva.checked_sub(address).filter(|&offset| offset < size).and_then(|offset| raw.checked_add(offset))
+        }).map(|offset| offset as usize)
+    };
+    let root = offset_of(rsrc_rva)?;
+    rsrc_resources(data, root, |entry| offset_of(read_u32(data, entry)?))
+}
+
+/// The resources of the `.rsrc` section of a COFF object, `None` if it is malformed
+///
+/// `cvtres` splits the resource directory in `.rsrc$01` from the data in `.rsrc$02`, while
+/// `windres` writes a single `.rsrc` section. Either way the offsets of the data are
+/// relocated against the symbol of the section that holds the data.
+fn coff_resources(data: &[u8]) -> Option<Vec<Resource>> {
+    let sections = read_u16(data, 2)? as usize;
+    let symbols = read_u32(data, 8)? as usize;
+    let section_table = 20 + read_u16(data, 16)? as usize;
+    let section = |number: usize| section_table + number * 40;
+    let rsrc = (0..sections).map(section).find(|&s| data.get(s..s + 5) == Some(&b".rsrc"[..]))?;
+    let root = read_u32(data, rsrc + 20)? as usize;
+    let relocations = read_u32(data, rsrc + 24)? as usize;
+    let relocation_count = read_u16(data, rsrc + 32)? as usize;
+    rsrc_resources(data, root, |entry| {
+        let addend = read_u32(data, entry)? as usize;
+        let address = (entry - root) as u32;
+        let relocation = (0..relocation_count).map(|i| relocations + i * 10)
+            .find(|&relocation| read_u32(data, relocation) == Some(address));
+        let relocation = match relocation {
+            Some(relocation) => relocation,
+            None => return root.checked_add(addend),
+        };
+        let symbol = (read_u32(data, relocation + 4)? as usize).checked_mul(18)?.checked_add(symbols)?;
+        // section numbers start at 1, the special ones are negative
+        let number = read_u16(data, symbol + 12)? as usize;
+        if number == 0 || number > sections {
+            return None;
+        }
+        let start = read_u32(data, section(number - 1) + 20)? as usize;
+        start.checked_add(read_u32(data, symbol + 8)? as usize)?.checked_add(addend)
+    })
+}
+
+/// The resources of the resource directory at `root`
+///
+/// `offset_of` maps the position of a data entry in `data` to the offset of its data.
+fn rsrc_resources<F>(data: &[u8], root: usize, offset_of: F) -> Option<Vec<Resource>>
+    where F: Fn(usize) -> Option<usize>
+{
+    let mut resources = vec![];
+    for (kind, names) in directory_entries(data, root, 0)? {
+        for (name, languages) in directory_entries(data, root, names & 0x7fff_ffff)? {
+            for (language, entry) in directory_entries(data, root, languages & 0x7fff_ffff)? {
+                let entry = root + entry as usize;
+                let start = offset_of(entry)?;
+                let size = read_u32(data, entry + 4)? as usize;
+                let language = match language {
+                    Id::Number(language) => language,
+                    Id::Name(_) => return None,
+                };
+                resources.push(Resource {
+                    kind: kind.clone(),
+                    name: name.clone(),
+                    language,
+                    data: data.get(start..start.checked_add(size)?)?.to_vec(),
+                });
+            }
+        }
+    }
+    Some(resources)
+}
+
+/// The entries of the resource directory at `root + offset`, as id and offset of the
+/// subdirectory or data entry
+fn directory_entries(data: &[u8], root: usize, offset: u32) -> Option<Vec<(Id, u32)>> {
+    let directory = root + offset as usize;
+    let count = read_u16(data, directory + 12)? as usize + read_u16(data, directory + 14)? as usize;
+    (0..count).map(|i| {
+        let entry = directory + 16 + i * 8;
+        let name = read_u32(data, entry)?;
+        let id = if name & 0x8000_0000 != 0 {
+            // a counted UTF-16 string
+            let start = root + (name & 0x7fff_ffff) as usize;
+            let length = read_u16(data, start)? as usize;
+            let chars = (0..length).map(|i| read_u16(data, start + 2 + i * 2)).collect::<Option<Vec<_>>>()?;
+            Id::Name(String::from_utf16_lossy(&chars))
+        } else {
+            Id::Number(name as u16)
+        };
+        Some((id, read_u32(data, entry + 4)?))
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, coff_resources, pe_resources, read, Embedded};
+    use res::{self, align, Id};
+    use std::{env, fs};
+    use WindowsResource;
+
+    fn utf16(s: &str) -> Vec<u8> {
+        s.encode_utf16().chain(Some(0)).flat_map(|c| c.to_le_bytes()).collect()
+    }
+
+    fn pad(data: &mut Vec<u8>) {
+        data.resize(align(data.len()), 0);
+    }
+
+    /// A version info block with a text or binary value and children
+    fn block(key: &str, value: &[u8], text: bool, children: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = vec![0, 0];
+        let value_length = if text { value.len() / 2 } else { value.len() };
+        data.extend_from_slice(&(value_length as u16).to_le_bytes());
+        data.extend_from_slice(&(text as u16).to_le_bytes());
+        data.extend(utf16(key));
+        pad(&mut data);
+        data.extend_from_slice(value);
+        for child in children {
+            pad(&mut data);
+            data.extend_from_slice(child);
+        }
+        let length = data.len() as u16;
+        data[..2].copy_from_slice(&length.to_le_bytes());
+        data
+    }
+
+    fn version_info(version: u64, product_name: &str) -> Vec<u8> {
+        let mut fixed = vec![];
+        for &word in &[0xfeef_04bd, 0x1_0000, (version >> 32) as u32, version as u32,
+                       (version >> 32) as u32, version as u32] {
+            fixed.extend_from_slice(&u32::to_le_bytes(word));
+        }
+        fixed.resize(52, 0);
+        let string = block("ProductName", &utf16(product_name), true, &[]);
+        let table = block("040904b0", &[], true, &[string]);
+        let string_file_info = block("StringFileInfo", &[], true, &[table]);
+        block("VS_VERSION_INFO", &fixed, false, &[string_file_info])
+    }
+
+    /// A `.res` file with the resources `(type, data)`
+    fn res_file(resources: &[(u16, Vec<u8>)]) -> Vec<u8> {
+        let mut file = vec![];
+        for &(kind, ref data) in [(0, vec![])].iter().chain(resources) {
+            file.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            file.extend_from_slice(&32u32.to_le_bytes());
+            for &word in &[0xffff, kind, 0xffff, 1, 0, 0, 0x30, 0x0409, 0, 0, 0, 0] {
+                file.extend_from_slice(&u16::to_le_bytes(word));
+            }
+            file.extend_from_slice(data);
+            pad(&mut file);
+        }
+        file
+    }
+
+    /// A resource directory with the resources `(type, data)` at `address`, and the offsets
+    /// of its data entries
+    fn rsrc_section(resources: &[(u16, Vec<u8>)], address: u32) -> (Vec<u8>, Vec<u32>) {
+        let mut rsrc = vec![];
+        let directory = |rsrc: &mut Vec<u8>, entries: &[(u32, u32)]| {
+            rsrc.extend_from_slice(&[0; 14]);
+            rsrc.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+            for &(id, offset) in entries {
+                rsrc.extend_from_slice(&id.to_le_bytes());
+                rsrc.extend_from_slice(&offset.to_le_bytes());
+            }
+        };
+        // the type directory, then per type a name and a language directory, then the data
+        let types = resources.iter().enumerate()
+            .map(|(i, &(kind, _))| (u32::from(kind), 0x8000_0000 | (16 + resources.len() as u32 * 8 + i as u32 * 48)))
+            .collect::<Vec<_>>();
+        directory(&mut rsrc, &types);
+        let data_entries = rsrc.len() as u32 + resources.len() as u32 * 48;
+        for i in 0..resources.len() as u32 {
+            let languages = rsrc.len() as u32 + 24;
+            directory(&mut rsrc, &[(1, 0x8000_0000 | languages)]);
+            directory(&mut rsrc, &[(0x0409, data_entries + i * 16)]);
+        }
+        let mut data_offset = data_entries as usize + resources.len() * 16;
+        let mut entries = vec![];
+        for (_, data) in resources {
+            entries.push(rsrc.len() as u32);
+            rsrc.extend_from_slice(&(address + data_offset as u32).to_le_bytes());
+            rsrc.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            rsrc.extend_from_slice(&[0; 8]);
+            data_offset += data.len();
+        }
+        for (_, data) in resources {
+            rsrc.extend_from_slice(data);
+        }
+        (rsrc, entries)
+    }
+
+    /// A PE32+ file with one section holding the resources `(type, data)`
+    fn pe_file(resources: &[(u16, Vec<u8>)]) -> Vec<u8> {
+        const SECTION_RVA: u32 = 0x1000;
+        let (rsrc, _) = rsrc_section(resources, SECTION_RVA);
+
+        let mut file = vec![0u8; 0x40];
+        file[..2].copy_from_slice(b"MZ");
+        file[0x3c] = 0x40;
+        file.extend_from_slice(b"PE\0\0");
+        let mut coff = [0u8; 20];
+        coff[2] = 1;
+        coff[16] = 240;
+        file.extend_from_slice(&coff);
+        let mut optional = vec![0u8; 240];
+        optional[..2].copy_from_slice(&0x20bu16.to_le_bytes());
+        optional[108] = 16;
+        optional[128..132].copy_from_slice(&SECTION_RVA.to_le_bytes());
+        file.extend(optional);
+        let raw = file.len() as u32 + 40;
+        let mut section = b".rsrc\0\0\0".to_vec();
+        for &word in &[rsrc.len() as u32, SECTION_RVA, rsrc.len() as u32, raw, 0, 0, 0, 0] {
+            section.extend_from_slice(&word.to_le_bytes());
+        }
+        file.extend(section);
+        file.extend(rsrc);
+        file
+    }
+
+    /// An x64 COFF object like the output of `windres`, with a `.rsrc` section holding the
+    /// resources `(type, data)` and relocations of the data entries against the section
+    fn coff_file(resources: &[(u16, Vec<u8>)]) -> Vec<u8> {
+        let (rsrc, entries) = rsrc_section(resources, 0);
+        let raw = 20 + 40;
+        let relocations = raw + rsrc.len() as u32;
+        let symbols = relocations + entries.len() as u32 * 10;
+        let mut file = vec![];
+        for &word in &[0x8664, 1] {
+            file.extend_from_slice(&u16::to_le_bytes(word));
+        }
+        file.extend_from_slice(&[0; 4]);
+        file.extend_from_slice(&symbols.to_le_bytes());
+        file.extend_from_slice(&1u32.to_le_bytes());
+        file.extend_from_slice(&[0; 4]);
+        file.extend_from_slice(b".rsrc\0\0\0");
+        for &word in &[0, 0, rsrc.len() as u32, raw, relocations, 0] {
+            file.extend_from_slice(&u32::to_le_bytes(word));
+        }
+        file.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        file.extend_from_slice(&[0; 6]);
+        file.extend(rsrc);
+        for entry in entries {
+            // IMAGE_REL_AMD64_ADDR32NB against the section symbol
+            file.extend_from_slice(&entry.to_le_bytes());
+            file.extend_from_slice(&0u32.to_le_bytes());
+            file.extend_from_slice(&3u16.to_le_bytes());
+        }
+        file.extend_from_slice(b".rsrc\0\0\0");
+        file.extend_from_slice(&[0, 0, 0, 0, 1, 0, 0, 0, 3, 0]);
+        file
+    }
+
+    #[test]
+    fn parse_res_file() {
+        let file = res_file(&[(16, version_info(0x0001_0002_0003_0004, "Calc")), (14, vec![0; 20])]);
+        let resources = res::from_bytes(&file).expect("parse .res file");
+        assert_eq!(resources.iter().map(|r| r.kind.clone()).collect::<Vec<_>>(), [Id::Number(16), Id::Number(14)]);
+    }
+
+    #[test]
+    fn parse_pe_file() {
+        let file = pe_file(&[(16, version_info(0x0001_0002_0003_0004, "Calc")), (24, b"<assembly/>".to_vec())]);
+        let resources = pe_resources(&file).expect("parse PE file");
+        assert_eq!(resources.len(), 2);
+        assert_eq!(resources[1].kind, Id::Number(24));
+        assert_eq!((resources[1].name.clone(), resources[1].language), (Id::Number(1), 0x0409));
+        assert_eq!(resources[1].data, b"<assembly/>");
+    }
+
+    #[test]
+    fn parse_coff_file() {
+        let file = coff_file(&[(16, version_info(0x0001_0002_0003_0004, "Calc")), (24, b"<assembly/>".to_vec())]);
+        let resources = coff_resources(&file).expect("parse COFF object");
+        assert_eq!(resources.len(), 2);
+        assert_eq!(resources[1].kind, Id::Number(24));
+        assert_eq!(resources[1].data, b"<assembly/>");
+    }
+
+    #[test]
+    fn verify_resources() {
+        let dir = env::temp_dir().join("winres-verify");
+        fs::create_dir_all(&dir).expect("create temp dir");
+        let path = dir.join("resource.res");
+        fs::write(&path, res_file(&[(16, version_info(0x0001_0002_0003_0004, "Calc"))])).expect("write .res file");
+        let embedded = read(&path).expect("read .res file");
+        assert_eq!(embedded.file_version, Some(0x0001_0002_0003_0004));
+        assert_eq!(embedded.string("ProductName"), Some("Calc"));
+        assert_eq!(embedded.icon_groups, 0);
+
+        let mut res = WindowsResource::new_without_metadata();
+        let defaults = ["FileDescription", "FileVersion", "ProductVersion", "CompanyName", "LegalCopyright",
+                        "Comments", "OriginalFilename"];
+        for name in defaults.iter() {
+            res.remove(name);
+        }
+        res.set_version_info(::VersionInfo::FILEVERSION, 0x0001_0002_0003_0004)
+            .set_version_info(::VersionInfo::PRODUCTVERSION, 0x0001_0002_0003_0004)
+            .set_language(0x0409)
+            .set("ProductName", "Calc");
+        check(&res, &embedded).expect("matching version info");
+        res.set("ProductName", "Calculator").set("CompanyName", "Example").set_icon("calc.ico");
+        match check(&res, &embedded) {
+            Err(::Error::VerificationFailed(problems)) => assert_eq!(problems, [
+                "CompanyName is missing",
+                "ProductName is \"Calc\", expected \"Calculator\"",
+                "the icon is missing",
+            ]),
+            result => panic!("expected VerificationFailed, got {:?}", result),
+        }
+        assert!(check(&res, &Embedded::default()).is_err());
+
+        fs::write(&path, pe_file(&[(16, version_info(0x0001_0002_0003_0004, "Calc"))])).expect("write binary");
+        assert_eq!(read(&path).expect("read binary"), embedded);
+        fs::write(&path, coff_file(&[(16, version_info(0x0001_0002_0003_0004, "Calc"))])).expect("write object");
+        assert_eq!(read(&path).expect("read object"), embedded);
+        fs::write(&path, b"MZ").expect("write binary");
+        assert!(matches!(read(&path), Err(::Error::InvalidResFile { .. })));
+        // a raw offset of the section near the end of the address space
+        let mut binary = pe_file(&[(16, version_info(0x0001_0002_0003_0004, "Calc"))]);
+        binary[0x15c..0x160].copy_from_slice(&0xffff_ff00u32.to_le_bytes());
+        binary[0xd8..0xdc].copy_from_slice(&0x1100u32.to_le_bytes());
+        fs::write(&path, binary).expect("write binary");
+        assert!(matches!(read(&path), Err(::Error::InvalidResFile { .. })));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}